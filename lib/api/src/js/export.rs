@@ -1,10 +1,11 @@
 use crate::js::instance::Instance;
+use crate::js::trap::RuntimeError;
 use crate::js::wasm_bindgen_polyfill::Global;
 use crate::js::HostEnvInitError;
 use crate::js::WasmerEnv;
 use js_sys::Function;
 use js_sys::WebAssembly::{Memory, Table};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::sync::Arc;
 use wasm_bindgen::{JsCast, JsValue};
@@ -60,24 +61,107 @@ pub struct VMFunction {
     pub(crate) function: Function,
     pub(crate) ty: FunctionType,
     pub(crate) environment: Option<Arc<RefCell<Box<dyn WasmerEnv>>>>,
+    pub(crate) funcref: Arc<FuncRefHandle>,
+}
+
+/// Shared by the clones of a `VMFunction`: when the last one is
+/// dropped, the slot holding the binary form of the function, if it
+/// has one, is released.
+#[derive(Debug, Default)]
+pub(crate) struct FuncRefHandle {
+    pub(crate) slot: Cell<Option<usize>>,
+}
+
+impl Drop for FuncRefHandle {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.get() {
+            crate::js::externals::function::release_funcref_slot(slot);
+        }
+    }
 }
 
 unsafe impl Send for VMFunction {}
 unsafe impl Sync for VMFunction {}
 
+/// The property of a JS function holding the index of its
+/// signature in `FUNCTION_TYPES`.
+///
+/// JavaScript doesn't expose the signature of a WebAssembly function,
+/// so it is attached to every function created or exported by
+/// `wasmer`, in order to recover it when a function reference is read
+/// from a table or a global.
+const FUNCTION_TYPE_PROPERTY: &str = "__wasmer_function_type";
+
+thread_local! {
+    /// The signatures of the functions, interned.
+    static FUNCTION_TYPES: RefCell<Vec<FunctionType>> = RefCell::new(Vec::new());
+}
+
 impl VMFunction {
     pub(crate) fn new(
         function: Function,
         ty: FunctionType,
         environment: Option<Box<dyn WasmerEnv>>,
     ) -> Self {
+        let type_index = FUNCTION_TYPES.with(|types| {
+            let mut types = types.borrow_mut();
+            match types.iter().position(|known| *known == ty) {
+                Some(index) => index,
+                None => {
+                    types.push(ty.clone());
+                    types.len() - 1
+                }
+            }
+        });
+        // Frozen functions can't be annotated: their references
+        // can't be read back, see `from_funcref`.
+        let _ = js_sys::Reflect::set(
+            &function,
+            &FUNCTION_TYPE_PROPERTY.into(),
+            &JsValue::from_f64(type_index as f64),
+        );
+
         Self {
             function,
             ty,
             environment: environment.map(|env| Arc::new(RefCell::new(env))),
+            funcref: Arc::default(),
         }
     }
 
+    /// Creates a `VMFunction` from a function reference read from a
+    /// table or a global, or returns `None` for a null reference.
+    ///
+    /// The signature of a function which was neither created nor
+    /// exported by `wasmer` (like a function of a module which is
+    /// only reachable through a table) is unknown, so an error is
+    /// returned for it.
+    pub(crate) fn from_funcref(value: JsValue) -> Result<Option<Self>, RuntimeError> {
+        if value.is_null() || value.is_undefined() {
+            return Ok(None);
+        }
+
+        let ty = js_sys::Reflect::get(&value, &FUNCTION_TYPE_PROPERTY.into())
+            .ok()
+            .and_then(|index| index.as_f64())
+            .and_then(|index| {
+                FUNCTION_TYPES.with(|types| types.borrow().get(index as usize).cloned())
+            })
+            .ok_or_else(|| {
+                RuntimeError::new(
+                    "the signature of the function reference is unknown: \
+                     it was neither created nor exported by Wasmer",
+                )
+            })?;
+
+        Ok(Some(Self {
+            function: value.unchecked_into(),
+            ty,
+            environment: None,
+            funcref: Arc::default(),
+        }))
+    }
+
     pub(crate) fn init_envs(&self, instance: &Instance) -> Result<(), HostEnvInitError> {
         if let Some(env) = &self.environment {
             let mut borrowed_env = env.borrow_mut();
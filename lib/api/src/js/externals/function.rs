@@ -5,6 +5,7 @@ use crate::js::types::{param_from_js, AsJs /* ValFuncRef */, Val};
 use crate::js::FunctionType;
 use crate::js::NativeFunc;
 use crate::js::RuntimeError;
+use crate::js::Type;
 use crate::js::WasmerEnv;
 pub use inner::{FromToNativeWasmType, HostFunction, WasmTypeList, WithEnv, WithoutEnv};
 use js_sys::{Array, Function as JSFunction};
@@ -12,24 +13,17 @@ use std::iter::FromIterator;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
-use crate::js::export::{Export, VMFunction};
+use crate::js::export::{Export, FuncRefHandle, VMFunction};
+use std::cell::RefCell;
 use std::fmt;
+use std::sync::{Arc, Weak};
 
 #[repr(C)]
 pub struct VMFunctionBody(u8);
 
 #[inline]
 fn result_to_js(val: &Val) -> JsValue {
-    match val {
-        Val::I32(i) => JsValue::from_f64(*i as _),
-        Val::I64(i) => JsValue::from_f64(*i as _),
-        Val::F32(f) => JsValue::from_f64(*f as _),
-        Val::F64(f) => JsValue::from_f64(*f),
-        val => unimplemented!(
-            "The value `{:?}` is not yet supported in the JS Function API",
-            val
-        ),
-    }
+    val.as_jsvalue()
 }
 
 #[inline]
@@ -48,12 +42,6 @@ fn results_to_js_array(values: &[Val]) -> Array {
 /// during execution of the function.
 ///
 /// Spec: <https://webassembly.github.io/spec/core/exec/runtime.html#function-instances>
-///
-/// # Panics
-/// - Closures (functions with captured environments) are not currently supported
-///   with native functions. Attempting to create a native `Function` with one will
-///   result in a panic.
-///   [Closures as host functions tracking issue](https://github.com/wasmerio/wasmer/issues/1840)
 #[derive(Clone, PartialEq)]
 pub struct Function {
     pub(crate) store: Store,
@@ -103,6 +91,7 @@ impl Function {
     {
         let ty = ty.into();
         let new_ty = ty.clone();
+        let new_store = store.clone();
 
         let wrapped_func: JsValue = match ty.results().len() {
             0 => Closure::wrap(Box::new(move |args: &Array| {
@@ -110,8 +99,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let _results = func(&wasm_arguments)?;
                 Ok(())
             })
@@ -122,8 +111,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let results = func(&wasm_arguments)?;
                 return Ok(result_to_js(&results[0]));
             })
@@ -134,8 +123,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let results = func(&wasm_arguments)?;
                 return Ok(results_to_js_array(&results));
             })
@@ -205,6 +194,7 @@ impl Function {
     {
         let ty = ty.into();
         let new_ty = ty.clone();
+        let new_store = store.clone();
 
         let wrapped_func: JsValue = match ty.results().len() {
             0 => Closure::wrap(Box::new(move |args: &Array| {
@@ -212,8 +202,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32 + 1)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32 + 1)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let env_ptr = args.get(0).as_f64().unwrap() as usize;
                 let env: &Env = unsafe { &*(env_ptr as *const u8 as *const Env) };
                 let _results = func(env, &wasm_arguments)?;
@@ -226,8 +216,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32 + 1)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32 + 1)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let env_ptr = args.get(0).as_f64().unwrap() as usize;
                 let env: &Env = unsafe { &*(env_ptr as *const u8 as *const Env) };
                 let results = func(env, &wasm_arguments)?;
@@ -240,8 +230,8 @@ impl Function {
                     .params()
                     .iter()
                     .enumerate()
-                    .map(|(i, param)| param_from_js(param, &args.get(i as u32 + 1)))
-                    .collect::<Vec<_>>();
+                    .map(|(i, param)| param_from_js(&new_store, param, &args.get(i as u32 + 1)))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let env_ptr = args.get(0).as_f64().unwrap() as usize;
                 let env: &Env = unsafe { &*(env_ptr as *const u8 as *const Env) };
                 let results = func(env, &wasm_arguments)?;
@@ -291,7 +281,8 @@ impl Function {
         Env: Sized + 'static,
     {
         if std::mem::size_of::<F>() != 0 {
-            Self::closures_unsupported_panic();
+            let ty = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+            return Self::new_closure(store, ty, None, 0, func.into_dynamic());
         }
         let function = inner::Function::<Args, Rets>::new(func);
         let address = function.address() as usize as u32;
@@ -338,7 +329,10 @@ impl Function {
         Env: Sized + WasmerEnv + 'static,
     {
         if std::mem::size_of::<F>() != 0 {
-            Self::closures_unsupported_panic();
+            let ty = FunctionType::new(Args::wasm_types(), Rets::wasm_types());
+            let environment = Box::new(env);
+            let env_ptr = &*environment as *const Env as *const u8 as usize;
+            return Self::new_closure(store, ty, Some(environment), env_ptr, func.into_dynamic());
         }
         let function = inner::Function::<Args, Rets>::new(func);
         let address = function.address() as usize as u32;
@@ -467,7 +461,7 @@ impl Function {
         match result_types.len() {
             0 => Ok(Box::new([])),
             1 => {
-                let value = param_from_js(&result_types[0], &result);
+                let value = param_from_js(&self.store, &result_types[0], &result)?;
                 Ok(vec![value].into_boxed_slice())
             }
             _n => {
@@ -475,8 +469,8 @@ impl Function {
                 Ok(result_array
                     .iter()
                     .enumerate()
-                    .map(|(i, js_val)| param_from_js(&result_types[i], &js_val))
-                    .collect::<Vec<_>>()
+                    .map(|(i, js_val)| param_from_js(&self.store, &result_types[i], &js_val))
+                    .collect::<Result<Vec<_>, _>>()?
                     .into_boxed_slice())
             }
         }
@@ -600,9 +594,63 @@ impl Function {
         Ok(NativeFunc::new(self.store.clone(), self.exported.clone()))
     }
 
-    #[track_caller]
-    fn closures_unsupported_panic() -> ! {
-        unimplemented!("Closures (functions with captured environments) are currently unsupported with native functions. See: https://github.com/wasmerio/wasmer/issues/1840")
+    /// Creates a host `Function` from a native closure capturing an
+    /// environment.
+    ///
+    /// Unlike a plain function, such a closure can't be called through
+    /// the function table, so it is boxed into a JS callback, which is
+    /// owned by the JS function and lives as long as it does.
+    fn new_closure(
+        store: &Store,
+        ty: FunctionType,
+        environment: Option<Box<dyn WasmerEnv>>,
+        env_ptr: usize,
+        func: inner::DynamicHostFunction,
+    ) -> Self {
+        let new_ty = ty.clone();
+        let new_store = store.clone();
+
+        let wrapped_func: JsValue = Closure::wrap(Box::new(move |args: &Array| {
+            let wasm_arguments = new_ty
+                .params()
+                .iter()
+                .enumerate()
+                .map(|(i, param)| {
+                    let mut binary = 0;
+                    let value = param_from_js(&new_store, param, &args.get(i as u32))?;
+                    unsafe { value.write_value_to(&mut binary) };
+                    Ok(binary)
+                })
+                .collect::<Result<Vec<_>, RuntimeError>>()?;
+            let results = func(env_ptr, &wasm_arguments)?
+                .iter()
+                .zip(new_ty.results())
+                .map(|(binary, ty)| {
+                    if *ty == Type::FuncRef
+                        && *binary != 0
+                        && Self::from_funcref_binary(*binary as usize).is_none()
+                    {
+                        return Err(RuntimeError::new("invalid function reference"));
+                    }
+                    Ok(unsafe { Val::read_value_from(&new_store, binary, *ty) })
+                })
+                .collect::<Result<Vec<_>, RuntimeError>>()?;
+            Ok(match results.len() {
+                0 => JsValue::UNDEFINED,
+                1 => result_to_js(&results[0]),
+                _n => results_to_js_array(&results).into(),
+            })
+        })
+            as Box<dyn FnMut(&Array) -> Result<JsValue, JsValue>>)
+        .into_js_value();
+
+        let dyn_func =
+            JSFunction::new_with_args("f", "return f(Array.prototype.slice.call(arguments, 1))");
+        let binded_func = dyn_func.bind1(&JsValue::UNDEFINED, &wrapped_func);
+        Self {
+            store: store.clone(),
+            exported: VMFunction::new(binded_func, ty, environment),
+        }
     }
 }
 
@@ -628,16 +676,122 @@ impl fmt::Debug for Function {
     }
 }
 
+/// The slot of a function reference written in its binary form.
+struct FuncRef {
+    /// The function, with a handle of its own, so that the slot
+    /// doesn't keep the handle of the written function alive.
+    function: Function,
+    /// The handle of the written function.
+    funcref: Weak<FuncRefHandle>,
+}
+
+/// The function references written in their binary form. A JS
+/// function can't be turned into an integer, so its binary form is the
+/// index of its slot, plus one.
+///
+/// A function only gets one slot, whichever handle it's written
+/// through, so its binary form can be read any number of times. The
+/// slot is released, and reused, when the handle which was first
+/// written is dropped.
+struct FuncRefs {
+    slots: Vec<Option<FuncRef>>,
+    free_slots: Vec<usize>,
+    /// The slot of each written function, keyed by the JS function.
+    indices: js_sys::Map,
+}
+
+thread_local! {
+    static FUNCREFS: RefCell<FuncRefs> = RefCell::new(FuncRefs {
+        slots: Vec::new(),
+        free_slots: Vec::new(),
+        indices: js_sys::Map::new(),
+    });
+}
+
+/// Releases the slot of a function reference whose handle is dropped.
+pub(crate) fn release_funcref_slot(slot: usize) {
+    let released = FUNCREFS
+        .try_with(|funcrefs| {
+            let mut funcrefs = funcrefs.borrow_mut();
+            let released = funcrefs.slots.get_mut(slot)?.take()?;
+            funcrefs
+                .indices
+                .delete(&released.function.exported.function);
+            funcrefs.free_slots.push(slot);
+
+            Some(released)
+        })
+        .ok()
+        .flatten();
+    // Dropped without `FUNCREFS` borrowed: its environment may own
+    // other functions.
+    drop(released);
+}
+
+impl Function {
+    /// Reads a function reference written in its binary form by
+    /// `write_value_to`, or returns `None` if `binary` is not one.
+    fn from_funcref_binary(binary: usize) -> Option<Self> {
+        let index = binary.checked_sub(1)?;
+        FUNCREFS.with(|funcrefs| {
+            let funcrefs = funcrefs.borrow();
+            let slot = funcrefs.slots.get(index)?.as_ref()?;
+            let mut function = slot.function.clone();
+            function.exported.funcref = slot.funcref.upgrade()?;
+
+            Some(function)
+        })
+    }
+}
+
 // This is needed for reference types
 impl wasmer_types::WasmValueType for Function {
     /// Write the value.
-    unsafe fn write_value_to(&self, _p: *mut i128) {
-        unimplemented!();
+    unsafe fn write_value_to(&self, p: *mut i128) {
+        let index = FUNCREFS.with(|funcrefs| {
+            let mut funcrefs = funcrefs.borrow_mut();
+            if let Some(index) = funcrefs.indices.get(&self.exported.function).as_f64() {
+                return index as usize;
+            }
+
+            let slot = FuncRef {
+                function: Self {
+                    store: self.store.clone(),
+                    exported: VMFunction {
+                        funcref: Arc::default(),
+                        ..self.exported.clone()
+                    },
+                },
+                funcref: Arc::downgrade(&self.exported.funcref),
+            };
+            let index = match funcrefs.free_slots.pop() {
+                Some(index) => {
+                    funcrefs.slots[index] = Some(slot);
+                    index
+                }
+                None => {
+                    funcrefs.slots.push(Some(slot));
+                    funcrefs.slots.len() - 1
+                }
+            };
+            funcrefs
+                .indices
+                .set(&self.exported.function, &JsValue::from_f64(index as f64));
+            self.exported.funcref.slot.set(Some(index));
+
+            index
+        });
+        std::ptr::write(p as *mut usize, index + 1);
     }
 
     /// Read the value.
-    unsafe fn read_value_from(_store: &dyn std::any::Any, _p: *const i128) -> Self {
-        unimplemented!();
+    ///
+    /// Host functions results are checked with `from_funcref_binary`
+    /// before being read, so an invalid function reference can only
+    /// come from a corrupted value.
+    unsafe fn read_value_from(_store: &dyn std::any::Any, p: *const i128) -> Self {
+        Self::from_funcref_binary(std::ptr::read(p as *const usize))
+            .expect("invalid function reference")
     }
 }
 
@@ -933,8 +1087,18 @@ mod inner {
     {
         /// Get the pointer to the function body.
         fn function_body_ptr(self) -> *const VMFunctionBody;
+
+        /// Turns the function into a `DynamicHostFunction`, to call a
+        /// closure capturing an environment, which has no function
+        /// body of its own.
+        fn into_dynamic(self) -> DynamicHostFunction;
     }
 
+    /// A host function taking the pointer to its environment (if any)
+    /// and its arguments in their binary form, and returning its
+    /// results in their binary form.
+    pub type DynamicHostFunction = Box<dyn Fn(usize, &[i128]) -> Result<Vec<i128>, RuntimeError>>;
+
     /// Empty trait to specify the kind of `HostFunction`: With or
     /// without an environment.
     ///
@@ -1135,6 +1299,19 @@ mod inner {
 
                     func_wrapper::< $( $x, )* Rets, RetsAsResult, Self > as *const VMFunctionBody
                 }
+
+                #[allow(non_snake_case)]
+                fn into_dynamic(self) -> DynamicHostFunction {
+                    Box::new(move |_: usize, args: &[i128]| {
+                        let ( $( $x ),* ) = <( $( $x ),* ) as WasmTypeList>::from_slice(args)
+                            .map_err(|_| RuntimeError::new("unexpected number of arguments"))?;
+                        let mut results = self( $( $x ),* )
+                            .into_result()
+                            .map_err(|error| RuntimeError::user(Box::new(error)))?
+                            .into_array();
+                        Ok(results.as_mut().to_vec())
+                    })
+                }
             }
 
             // Implement `HostFunction` for a function that has the same arity than the tuple.
@@ -1181,6 +1358,20 @@ mod inner {
 
                     func_wrapper::< $( $x, )* Rets, RetsAsResult, Env, Self > as *const VMFunctionBody
                 }
+
+                #[allow(non_snake_case)]
+                fn into_dynamic(self) -> DynamicHostFunction {
+                    Box::new(move |ptr: usize, args: &[i128]| {
+                        let env: &Env = unsafe { &*(ptr as *const u8 as *const Env) };
+                        let ( $( $x ),* ) = <( $( $x ),* ) as WasmTypeList>::from_slice(args)
+                            .map_err(|_| RuntimeError::new("unexpected number of arguments"))?;
+                        let mut results = self(env, $( $x ),* )
+                            .into_result()
+                            .map_err(|error| RuntimeError::user(Box::new(error)))?
+                            .into_array();
+                        Ok(results.as_mut().to_vec())
+                    })
+                }
             }
         };
     }
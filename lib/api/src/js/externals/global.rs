@@ -1,9 +1,9 @@
 use crate::js::export::Export;
-use crate::js::export::VMGlobal;
+use crate::js::export::{VMFunction, VMGlobal};
 use crate::js::exports::{ExportError, Exportable};
-use crate::js::externals::Extern;
+use crate::js::externals::{Extern, Function};
 use crate::js::store::Store;
-use crate::js::types::{Val, ValType};
+use crate::js::types::{AsJs, Val, ValType};
 use crate::js::wasm_bindgen_polyfill::Global as JSGlobal;
 use crate::js::GlobalType;
 use crate::js::Mutability;
@@ -69,6 +69,7 @@ impl Global {
             Val::I64(i) => ("i64", JsValue::from_f64(i as _)),
             Val::F32(f) => ("f32", JsValue::from_f64(f as _)),
             Val::F64(f) => ("f64", JsValue::from_f64(f)),
+            Val::FuncRef(_) => ("anyfunc", val.as_jsvalue()),
             _ => unimplemented!("The type is not yet supported in the JS Global API"),
         };
         // This is the value type as string, even though is incorrectly called "value"
@@ -135,12 +136,22 @@ impl Global {
     ///
     /// assert_eq!(g.get(), Value::I32(1));
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the global holds a function whose signature is
+    /// unknown because it was neither created nor exported by Wasmer.
     pub fn get(&self) -> Val {
         match self.vm_global.ty.ty {
             ValType::I32 => Val::I32(self.vm_global.global.value().as_f64().unwrap() as _),
             ValType::I64 => Val::I64(self.vm_global.global.value().as_f64().unwrap() as _),
             ValType::F32 => Val::F32(self.vm_global.global.value().as_f64().unwrap() as _),
             ValType::F64 => Val::F64(self.vm_global.global.value().as_f64().unwrap()),
+            ValType::FuncRef => Val::FuncRef(
+                VMFunction::from_funcref(self.vm_global.global.value())
+                    .unwrap_or_else(|error| panic!("{}", error.message()))
+                    .map(|func| Function::from_vm_export(&self.store, func)),
+            ),
             _ => unimplemented!("The type is not yet supported in the JS Global API"),
         }
     }
@@ -198,6 +209,7 @@ impl Global {
             Val::I64(i) => JsValue::from_f64(i as _),
             Val::F32(f) => JsValue::from_f64(f as _),
             Val::F64(f) => JsValue::from_f64(f),
+            Val::FuncRef(_) => val.as_jsvalue(),
             _ => unimplemented!("The type is not yet supported in the JS Global API"),
         };
         self.vm_global.global.set_value(&new_value);
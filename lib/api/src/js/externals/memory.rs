@@ -1,9 +1,12 @@
 use crate::js::export::{Export, VMMemory};
 use crate::js::exports::{ExportError, Exportable};
 use crate::js::externals::Extern;
+use crate::js::mem_access::MemoryAccessError;
 use crate::js::store::Store;
 use crate::js::{MemoryType, MemoryView};
 use std::convert::TryInto;
+use std::mem::MaybeUninit;
+use std::slice;
 use thiserror::Error;
 
 use wasm_bindgen::prelude::*;
@@ -153,6 +156,12 @@ impl Memory {
     /// Until the returned slice is dropped, it is undefined behaviour to
     /// modify the memory contents in any way including by calling a wasm
     /// function that writes to the memory or by resizing the memory.
+    ///
+    /// # Panics
+    ///
+    /// The memory contents live in a JavaScript `ArrayBuffer` which
+    /// can't be borrowed from Rust, so this always panics in the `js`
+    /// backend. Use [`Memory::read`] and [`Memory::write`] instead.
     pub unsafe fn data_unchecked(&self) -> &[u8] {
         unimplemented!("direct data pointer access is not possible in JavaScript");
     }
@@ -166,12 +175,20 @@ impl Memory {
     /// write to the pointed-to memory in any way except through this slice,
     /// including by calling a wasm function that reads the memory contents or
     /// by resizing this Memory.
+    ///
+    /// # Panics
+    ///
+    /// Always panics in the `js` backend, like [`Memory::data_unchecked`].
     #[allow(clippy::mut_from_ref)]
     pub unsafe fn data_unchecked_mut(&self) -> &mut [u8] {
         unimplemented!("direct data pointer access is not possible in JavaScript");
     }
 
     /// Returns the pointer to the raw bytes of the `Memory`.
+    ///
+    /// # Panics
+    ///
+    /// Always panics in the `js` backend, like [`Memory::data_unchecked`].
     pub fn data_ptr(&self) -> *mut u8 {
         unimplemented!("direct data pointer access is not possible in JavaScript");
     }
@@ -253,39 +270,27 @@ impl Memory {
         Ok(Pages(new_pages))
     }
 
-    /// Return a "view" of the currently accessible memory. By
-    /// default, the view is unsynchronized, using regular memory
-    /// accesses. You can force a memory view to use atomic accesses
-    /// by calling the [`MemoryView::atomically`] method.
+    /// Return a "view" of the currently accessible memory.
     ///
-    /// # Notes:
-    ///
-    /// This method is safe (as in, it won't cause the host to crash or have UB),
-    /// but it doesn't obey rust's rules involving data races, especially concurrent ones.
-    /// Therefore, if this memory is shared between multiple threads, a single memory
-    /// location can be mutated concurrently without synchronization.
+    /// The view is backed by a `Uint8Array` over the memory buffer:
+    /// values are copied in and out of the memory, with
+    /// [`MemoryView::copy_from`] and [`MemoryView::copy_to`].
     ///
     /// # Usage:
     ///
     /// ```
     /// # use wasmer::{Memory, MemoryView};
-    /// # use std::{cell::Cell, sync::atomic::Ordering};
     /// # fn view_memory(memory: Memory) {
-    /// // Without synchronization.
     /// let view: MemoryView<u8> = memory.view();
-    /// for byte in view[0x1000 .. 0x1010].iter().map(Cell::get) {
-    ///     println!("byte: {}", byte);
-    /// }
-    ///
-    /// // With synchronization.
-    /// let atomic_view = view.atomically();
-    /// for byte in atomic_view[0x1000 .. 0x1010].iter().map(|atom| atom.load(Ordering::SeqCst)) {
+    /// let mut bytes = [0; 0x10];
+    /// view.subarray(0x1000, 0x1010).copy_to(&mut bytes);
+    /// for byte in bytes.iter() {
     ///     println!("byte: {}", byte);
     /// }
     /// # }
     /// ```
     pub fn view<T: ValueType>(&self) -> MemoryView<T> {
-        unimplemented!("The view function is not yet implemented in Wasmer Javascript");
+        MemoryView::new(self)
     }
 
     /// A theoretical alais to `Self::view::<u8>` but it returns a `js::Uint8Array` in this case.
@@ -296,6 +301,74 @@ impl Memory {
         js_sys::Uint8Array::new(&self.vm_memory.memory.buffer())
     }
 
+    /// Safely reads bytes from the memory at the given offset.
+    ///
+    /// The full buffer will be filled, otherwise a `MemoryAccessError` is returned
+    /// to indicate an out-of-bounds access.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent writes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x10, b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// m.read(0x10, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    ///
+    /// assert!(m.read(65535, &mut buf).is_err());
+    /// ```
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        let array = self.uint8view();
+        let (start, end) = checked_range(&array, offset, buf.len())?;
+        array.subarray(start, end).copy_to(buf);
+        Ok(())
+    }
+
+    /// Safely reads bytes from the memory at the given offset.
+    ///
+    /// This method is similar to `read` but allows reading into an
+    /// uninitialized buffer. An initialized view of the buffer is returned.
+    ///
+    /// The full buffer will be filled, otherwise a `MemoryAccessError` is returned
+    /// to indicate an out-of-bounds access.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent writes.
+    pub fn read_uninit<'a>(
+        &self,
+        offset: u64,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], MemoryAccessError> {
+        let array = self.uint8view();
+        let (start, end) = checked_range(&array, offset, buf.len())?;
+        let buf_ptr = buf.as_mut_ptr() as *mut u8;
+        unsafe {
+            array.subarray(start, end).raw_copy_to_ptr(buf_ptr);
+            Ok(slice::from_raw_parts_mut(buf_ptr, buf.len()))
+        }
+    }
+
+    /// Safely writes bytes to the memory at the given offset.
+    ///
+    /// If the write exceeds the bounds of the memory then a `MemoryAccessError` is
+    /// returned, and the memory is left unmodified.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent reads/writes.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        let array = self.uint8view();
+        let (start, end) = checked_range(&array, offset, data.len())?;
+        array.subarray(start, end).copy_from(data);
+        Ok(())
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_memory: VMMemory) -> Self {
         Self {
             store: store.clone(),
@@ -332,3 +405,19 @@ impl<'a> Exportable<'a> for Memory {
         }
     }
 }
+
+/// Returns the range of an access of `len` bytes at `offset` in `array`.
+fn checked_range(
+    array: &js_sys::Uint8Array,
+    offset: u64,
+    len: usize,
+) -> Result<(u32, u32), MemoryAccessError> {
+    let end = offset
+        .checked_add(len as u64)
+        .ok_or(MemoryAccessError::Overflow)?;
+    if end > array.length() as u64 {
+        return Err(MemoryAccessError::HeapOutOfBounds);
+    }
+    // Both fit in a `u32` since they are bounded by the array length.
+    Ok((offset as u32, end as u32))
+}
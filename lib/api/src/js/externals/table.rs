@@ -7,7 +7,7 @@ use crate::js::types::Val;
 use crate::js::RuntimeError;
 use crate::js::TableType;
 use js_sys::Function;
-use wasmer_types::FunctionType;
+use wasm_bindgen::{JsCast, JsValue};
 
 /// A WebAssembly `table` instance.
 ///
@@ -30,7 +30,9 @@ fn set_table_item(table: &VMTable, item_index: u32, item: &Function) -> Result<(
 
 fn get_function(val: Val) -> Result<Function, RuntimeError> {
     match val {
-        Val::FuncRef(func) => Ok(func.as_ref().unwrap().exported.function.clone().into()),
+        Val::FuncRef(Some(func)) => Ok(func.exported.function.clone().into()),
+        // A null reference.
+        Val::FuncRef(None) => Ok(JsValue::NULL.unchecked_into()),
         // Only funcrefs is supported by the spec atm
         _ => unimplemented!(),
     }
@@ -77,13 +79,16 @@ impl Table {
     }
 
     /// Retrieves an element of the table at the provided `index`.
+    ///
+    /// Returns `None` if the index is out of bounds, or if the element
+    /// is a function whose signature is unknown because it was neither
+    /// created nor exported by Wasmer.
     pub fn get(&self, index: u32) -> Option<Val> {
         let func = self.vm_table.table.get(index).ok()?;
-        let ty = FunctionType::new(vec![], vec![]);
-        Some(Val::FuncRef(Some(WasmerFunction::from_vm_export(
-            &self.store,
-            VMFunction::new(func, ty, None),
-        ))))
+        let func = VMFunction::from_funcref(func.into()).ok()?;
+        Some(Val::FuncRef(func.map(|func| {
+            WasmerFunction::from_vm_export(&self.store, func)
+        })))
     }
 
     /// Sets an element `val` in the Table at the provided `index`.
@@ -2,12 +2,18 @@
 //! manipulate and access a wasm module's imports including memories, tables, globals, and
 //! functions.
 use crate::js::export::Export;
+use crate::js::exports::Exportable;
 use crate::js::resolver::NamedResolver;
+use crate::js::{
+    Extern, Function, ImportType, Instance, InstantiationError, Module, RuntimeError, Store, Type,
+    Val,
+};
 use std::borrow::{Borrow, BorrowMut};
 use std::collections::VecDeque;
 use std::collections::{hash_map::Entry, HashMap};
 use std::fmt;
 use std::sync::{Arc, Mutex};
+use wasmer_types::{ExternType, FunctionType};
 
 /// The `LikeNamespace` trait represents objects that act as a namespace for imports.
 /// For example, an `Instance` or `Namespace` could be
@@ -43,6 +49,16 @@ pub trait LikeNamespace {
 #[derive(Clone, Default)]
 pub struct ImportObject {
     map: Arc<Mutex<HashMap<String, Box<dyn LikeNamespace + Send + Sync>>>>,
+    aliases: Arc<Mutex<HashMap<(String, String), Alias>>>,
+}
+
+/// An import defined on top of the namespaces of an `ImportObject`.
+#[derive(Clone)]
+enum Alias {
+    /// Another name for an export of a namespace.
+    Name(String, String),
+    /// A function adapting the signature of an export of a namespace.
+    Adapter(Function),
 }
 
 impl ImportObject {
@@ -60,6 +76,21 @@ impl ImportObject {
     /// import_object.get_export("module", "name");
     /// ```
     pub fn get_export(&self, module: &str, name: &str) -> Option<Export> {
+        match self.get_alias(module, name) {
+            Some(Alias::Name(module, name)) => self.get_registered_export(&module, &name),
+            Some(Alias::Adapter(function)) => Some(function.to_export()),
+            None => self.get_registered_export(module, name),
+        }
+    }
+
+    fn get_alias(&self, module: &str, name: &str) -> Option<Alias> {
+        let aliases = self.aliases.lock().unwrap();
+        aliases
+            .get(&(module.to_string(), name.to_string()))
+            .cloned()
+    }
+
+    fn get_registered_export(&self, module: &str, name: &str) -> Option<Export> {
         let guard = self.map.lock().unwrap();
         let map_ref = guard.borrow();
         if map_ref.contains_key(module) {
@@ -72,6 +103,12 @@ impl ImportObject {
     /// Returns true if the ImportObject contains namespace with the provided name.
     pub fn contains_namespace(&self, name: &str) -> bool {
         self.map.lock().unwrap().borrow().contains_key(name)
+            || self
+                .aliases
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|(module, _)| module == name)
     }
 
     /// Register anything that implements `LikeNamespace` as a namespace.
@@ -102,6 +139,156 @@ impl ImportObject {
         }
     }
 
+    /// Register the exports of an [`Instance`] as a namespace.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ImportObject, Instance};
+    /// let mut import_object = ImportObject::new();
+    ///
+    /// import_object.register_instance("shim", &shim_instance);
+    /// // ...
+    /// ```
+    pub fn register_instance<S>(
+        &mut self,
+        name: S,
+        instance: &Instance,
+    ) -> Option<Box<dyn LikeNamespace>>
+    where
+        S: Into<String>,
+    {
+        self.register(name, instance.exports.clone())
+    }
+
+    /// Makes the export `name` of the namespace `module` available
+    /// under another name, `alias_name` in the namespace
+    /// `alias_module`. The namespace of the alias doesn't need to be
+    /// registered.
+    ///
+    /// The export must be compatible with the type `ty`, as it would be
+    /// checked for an import of this type.
+    ///
+    /// Aliases take precedence over the exports of the namespaces, and
+    /// aliasing an alias makes another name for the same export.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ExternType, FunctionType, ImportObject, Type};
+    /// let mut import_object = ImportObject::new();
+    /// // ...
+    /// let ty = ExternType::Function(FunctionType::new([Type::I32], []));
+    /// import_object.alias("env", "print", "wasi_unstable", "print", &ty)?;
+    /// ```
+    pub fn alias<S1, S2>(
+        &mut self,
+        module: &str,
+        name: &str,
+        alias_module: S1,
+        alias_name: S2,
+        ty: &ExternType,
+    ) -> Result<(), InstantiationError>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let export = self.get_export(module, name).ok_or_else(|| {
+            InstantiationError::Link(format!(
+                "unknown import \"{}\".\"{}\". Expected {:?}",
+                module, name, ty
+            ))
+        })?;
+        let export_ty = export_type(&export);
+
+        if !export_ty.is_compatible_with(ty) {
+            return Err(InstantiationError::Link(format!(
+                "incompatible import type for \"{}\".\"{}\". Expected {:?} but received {:?}",
+                module, name, ty, export_ty
+            )));
+        }
+
+        let alias = self
+            .get_alias(module, name)
+            .unwrap_or_else(|| Alias::Name(module.to_string(), name.to_string()));
+        self.aliases
+            .lock()
+            .unwrap()
+            .insert((alias_module.into(), alias_name.into()), alias);
+
+        Ok(())
+    }
+
+    /// Adapts the function imports of `module` whose signature differs
+    /// from the function provided by this `ImportObject` only by the
+    /// width of some integers, `i32` instead of `i64` or the other way
+    /// around, like pointers of a 32-bit and a 64-bit module.
+    ///
+    /// The adapted imports resolve to a function which converts its
+    /// arguments and results: an `i32` is zero-extended to an `i64`,
+    /// like a pointer, and an `i64` is truncated to an `i32`. The call
+    /// traps if an `i64` doesn't fit in 32 bits, neither as a signed
+    /// nor as an unsigned integer.
+    ///
+    /// Returns the number of adapted imports. The other mismatches are
+    /// left as they are, to be reported when instantiating the module.
+    pub fn adapt_pointer_widths(&mut self, module: &Module) -> usize {
+        self.adapt_pointer_widths_with(module, |_, _| IntegerExtension::Zero)
+    }
+
+    /// Like [`ImportObject::adapt_pointer_widths`], but the `i32`
+    /// results which are converted to `i64`s are extended as returned
+    /// by `extension`, called with the import and the index of the
+    /// result. The arguments are still zero-extended.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ImportObject, IntegerExtension};
+    /// let mut import_object = ImportObject::new();
+    /// // ...
+    /// // `errno` returns a signed error code, not a pointer.
+    /// import_object.adapt_pointer_widths_with(&module, |import, _| {
+    ///     if import.name() == "errno" {
+    ///         IntegerExtension::Sign
+    ///     } else {
+    ///         IntegerExtension::Zero
+    ///     }
+    /// });
+    /// ```
+    pub fn adapt_pointer_widths_with<F>(&mut self, module: &Module, mut extension: F) -> usize
+    where
+        F: FnMut(&ImportType<FunctionType>, usize) -> IntegerExtension,
+    {
+        let mut adapted = 0;
+
+        for import in module.imports().functions() {
+            let function = match self.get_export(import.module(), import.name()) {
+                Some(export @ Export::Function(_)) => {
+                    match Extern::from_vm_export(module.store(), export) {
+                        Extern::Function(function) => function,
+                        _ => unreachable!(),
+                    }
+                }
+                _ => continue,
+            };
+
+            if !is_pointer_width_adaptable(import.ty(), function.ty()) {
+                continue;
+            }
+
+            let extensions = (0..import.ty().results().len())
+                .map(|index| extension(&import, index))
+                .collect();
+            let adapter =
+                pointer_width_adapter(module.store(), import.ty().clone(), function, extensions);
+            self.aliases.lock().unwrap().insert(
+                (import.module().to_string(), import.name().to_string()),
+                Alias::Adapter(adapter),
+            );
+            adapted += 1;
+        }
+
+        adapted
+    }
+
     fn get_objects(&self) -> VecDeque<((String, String), Export)> {
         let mut out = VecDeque::new();
         let guard = self.map.lock().unwrap();
@@ -111,21 +298,30 @@ impl ImportObject {
                 out.push_back(((name.clone(), id), exp));
             }
         }
+        drop(guard);
+        for ((module, name), alias) in self.aliases.lock().unwrap().iter() {
+            let export = match alias {
+                Alias::Name(module, name) => self.get_registered_export(module, name),
+                Alias::Adapter(function) => Some(function.to_export()),
+            };
+            if let Some(export) = export {
+                out.push_back(((module.clone(), name.clone()), export));
+            }
+        }
         out
     }
 
     /// Returns the `ImportObject` as a Javascript `Object`
     pub fn as_jsobject(&self) -> js_sys::Object {
-        let guard = self.map.lock().expect("Can't get the map");
-        let map = guard.borrow();
+        let mut namespaces: HashMap<String, js_sys::Object> = HashMap::new();
+        for ((module, name), exp) in self.get_objects() {
+            let import_namespace = namespaces.entry(module).or_insert_with(js_sys::Object::new);
+            js_sys::Reflect::set(import_namespace, &name.into(), exp.as_jsvalue())
+                .expect("Error while setting into the js namespace object");
+        }
 
         let imports = js_sys::Object::new();
-        for (module, ns) in map.iter() {
-            let import_namespace = js_sys::Object::new();
-            for (name, exp) in ns.get_namespace_exports() {
-                js_sys::Reflect::set(&import_namespace, &name.into(), exp.as_jsvalue())
-                    .expect("Error while setting into the js namespace object");
-            }
+        for (module, import_namespace) in namespaces {
             js_sys::Reflect::set(&imports, &module.into(), &import_namespace.into())
                 .expect("Error while setting into the js imports object");
         }
@@ -133,10 +329,90 @@ impl ImportObject {
     }
 }
 
-impl Into<js_sys::Object> for ImportObject {
-    fn into(self) -> js_sys::Object {
-        self.as_jsobject()
+/// Get the `ExternType` of an `Export`.
+fn export_type(export: &Export) -> ExternType {
+    match export {
+        Export::Function(f) => ExternType::Function(f.ty.clone()),
+        Export::Table(t) => ExternType::Table(t.ty),
+        Export::Memory(m) => ExternType::Memory(m.ty),
+        Export::Global(g) => ExternType::Global(g.ty),
+    }
+}
+
+/// Whether the function type `provided` differs from `expected` only
+/// by `i32`s in place of `i64`s, or the other way around.
+fn is_pointer_width_adaptable(expected: &FunctionType, provided: &FunctionType) -> bool {
+    fn adaptable(expected: &[Type], provided: &[Type]) -> bool {
+        expected.len() == provided.len()
+            && expected.iter().zip(provided).all(|types| match types {
+                (Type::I32, Type::I64) | (Type::I64, Type::I32) => true,
+                (expected, provided) => expected == provided,
+            })
+    }
+
+    expected != provided
+        && adaptable(expected.params(), provided.params())
+        && adaptable(expected.results(), provided.results())
+}
+
+/// How an `i32` is converted to an `i64` by the adapters of
+/// [`ImportObject::adapt_pointer_widths_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegerExtension {
+    /// The `i32` is read as unsigned, like a pointer: `-1` becomes
+    /// `0xFFFF_FFFF`.
+    Zero,
+    /// The `i32` is read as signed, like an error code: `-1` stays
+    /// `-1`.
+    Sign,
+}
+
+/// Creates a function of type `ty` calling `function`, converting the
+/// integers from one width to the other. The `i32` results are
+/// extended as given by `extensions`.
+fn pointer_width_adapter(
+    store: &Store,
+    ty: FunctionType,
+    function: Function,
+    extensions: Vec<IntegerExtension>,
+) -> Function {
+    fn convert(value: &Val, ty: &Type, extension: IntegerExtension) -> Result<Val, RuntimeError> {
+        Ok(match (value, ty) {
+            (Val::I32(value), Type::I64) => match extension {
+                IntegerExtension::Zero => Val::I64(*value as u32 as i64),
+                IntegerExtension::Sign => Val::I64(*value as i64),
+            },
+            (Val::I64(value), Type::I32) => {
+                if *value < i32::MIN as i64 || *value > u32::MAX as i64 {
+                    return Err(RuntimeError::new(format!(
+                        "the integer {} doesn't fit in 32 bits",
+                        value
+                    )));
+                }
+
+                Val::I32(*value as i32)
+            }
+            (value, _) => value.clone(),
+        })
     }
+
+    let results = ty.results().to_vec();
+
+    Function::new(store, ty, move |args| {
+        let args = args
+            .iter()
+            .zip(function.ty().params())
+            .map(|(arg, ty)| convert(arg, ty, IntegerExtension::Zero))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        function
+            .call(&args)?
+            .iter()
+            .zip(&results)
+            .zip(&extensions)
+            .map(|((result, ty), extension)| convert(result, ty, *extension))
+            .collect()
+    })
 }
 
 impl NamedResolver for ImportObject {
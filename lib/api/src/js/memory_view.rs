@@ -0,0 +1,107 @@
+use crate::js::externals::Memory;
+use js_sys::Uint8Array;
+use std::marker::PhantomData;
+use std::{mem, slice};
+use wasmer_types::ValueType;
+
+/// A view into a memory.
+///
+/// In JavaScript, the memory contents can't be borrowed directly, so
+/// the view is backed by a `Uint8Array` over the memory buffer, and
+/// values are copied in and out of it.
+///
+/// Like in the `sys` backend, the view must not be used once the
+/// memory has grown: the underlying buffer is detached by the
+/// JavaScript engine and the view becomes empty.
+///
+/// Unlike the `sys` view, this one can't be indexed nor dereferenced
+/// to a `[Cell<T>]`, and it has no `atomically` method, since the
+/// memory contents can't be referenced from Rust. Use
+/// [`MemoryView::copy_from`] and [`MemoryView::copy_to`], or
+/// [`Memory::read`] and [`Memory::write`], instead.
+#[derive(Clone, Debug)]
+pub struct MemoryView<'a, T: 'a> {
+    array: Uint8Array,
+    // Note: the length is in the terms of `size::<T>()`.
+    // The total length in memory is `size::<T>() * length`.
+    length: usize,
+    _phantom: PhantomData<&'a [T]>,
+}
+
+impl<'a, T> MemoryView<'a, T>
+where
+    T: ValueType,
+{
+    /// Creates a new `MemoryView` over the whole memory.
+    pub(crate) fn new(memory: &'a Memory) -> Self {
+        let array = memory.uint8view();
+        let length = array.length() as usize / mem::size_of::<T>();
+        Self {
+            array,
+            length,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a subarray view from this `MemoryView`.
+    pub fn subarray(&self, start: u32, end: u32) -> Self {
+        assert!(
+            (start as usize) <= self.length,
+            "The range start is bigger than current length"
+        );
+        assert!(
+            (end as usize) <= self.length,
+            "The range end is bigger than current length"
+        );
+
+        let size = mem::size_of::<T>() as u32;
+        Self {
+            array: self.array.subarray(start * size, end * size),
+            length: (end - start) as usize,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the number of `T` in the view.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns whether the view is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Copy the contents of the source slice into this `MemoryView`.
+    ///
+    /// This function will efficiently copy the memory from within the wasm
+    /// module’s own linear memory to this typed array.
+    ///
+    /// # Safety
+    ///
+    /// This method is unsafe because the caller will need to make sure
+    /// there are no data races when copying memory into the view.
+    pub unsafe fn copy_from(&self, src: &[T]) {
+        // We cap at a max length
+        let sliced_src = &src[..self.length];
+        let bytes = slice::from_raw_parts(
+            sliced_src.as_ptr() as *const u8,
+            sliced_src.len() * mem::size_of::<T>(),
+        );
+        self.array.copy_from(bytes);
+    }
+
+    /// Copy the contents of this `MemoryView` into the destination
+    /// slice, which must be at least as long as the view.
+    pub fn copy_to(&self, dst: &mut [T]) {
+        let sliced_dst = &mut dst[..self.length];
+        // Safe because `T: ValueType` is valid for any bit pattern.
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(
+                sliced_dst.as_mut_ptr() as *mut u8,
+                sliced_dst.len() * mem::size_of::<T>(),
+            )
+        };
+        self.array.copy_to(bytes);
+    }
+}
@@ -32,6 +32,8 @@ mod externals;
 mod import_object;
 mod instance;
 mod js_import_object;
+mod mem_access;
+mod memory_view;
 mod module;
 #[cfg(feature = "wasm-types-polyfill")]
 mod module_info_polyfill;
@@ -49,6 +51,11 @@ mod wasm_bindgen_polyfill;
 /// See the [`WasmerEnv`] trait for more information.
 pub use wasmer_derive::WasmerEnv;
 
+/// Implement [`ValueType`] for your `#[repr(C)]` struct with `#[derive(ValueType)]`.
+///
+/// See the [`WasmPtr`] documentation for an example.
+pub use wasmer_derive::ValueType;
+
 pub use crate::js::cell::WasmCell;
 pub use crate::js::env::{HostEnvInitError, LazyInit, WasmerEnv};
 pub use crate::js::export::Export;
@@ -57,9 +64,13 @@ pub use crate::js::externals::{
     Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, MemoryError, Table,
     WasmTypeList,
 };
-pub use crate::js::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
+pub use crate::js::import_object::{
+    ImportObject, ImportObjectIterator, IntegerExtension, LikeNamespace,
+};
 pub use crate::js::instance::{Instance, InstantiationError};
 pub use crate::js::js_import_object::JsImportObject;
+pub use crate::js::mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
+pub use crate::js::memory_view::MemoryView;
 pub use crate::js::module::{Module, ModuleTypeHints};
 pub use crate::js::native::NativeFunc;
 pub use crate::js::ptr::{Array, Item, WasmPtr};
@@ -77,8 +88,8 @@ pub use crate::js::types::{Val as Value, ValType as Type};
 pub use crate::js::utils::is_wasm;
 
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, GlobalInit, LittleEndianConvert, LocalFunctionIndex, Pages,
+    ValueType, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 #[cfg(feature = "wat")]
@@ -87,7 +87,7 @@ macro_rules! impl_native_traits {
                     0 => {},
                     1 => unsafe {
                         let ty = Rets::wasm_types()[0];
-                        let val = param_from_js(&ty, &results);
+                        let val = param_from_js(&self.store, &ty, &results)?;
                         val.write_value_to(mut_rets);
                     }
                     _n => {
@@ -95,7 +95,7 @@ macro_rules! impl_native_traits {
                         for (i, ret_type) in Rets::wasm_types().iter().enumerate() {
                             let ret = results.get(i as u32);
                             unsafe {
-                                let val = param_from_js(&ret_type, &ret);
+                                let val = param_from_js(&self.store, &ret_type, &ret)?;
                                 val.write_value_to(mut_rets.add(i));
                             }
                         }
@@ -7,9 +7,10 @@
 //! related bugs when implementing an ABI.
 
 use crate::js::cell::WasmCell;
+use crate::js::mem_access::{MemoryAccessError, WasmRef, WasmSlice};
 use crate::js::{externals::Memory, FromToNativeWasmType};
 use std::{fmt, marker::PhantomData, mem};
-use wasmer_types::ValueType;
+use wasmer_types::{LittleEndianConvert, ValueType};
 
 /// The `Array` marker type. This type can be used like `WasmPtr<T, Array>`
 /// to get access to methods
@@ -41,24 +42,23 @@ pub struct Item;
 /// # use wasmer::WasmPtr;
 /// # use wasmer::ValueType;
 ///
-/// #[derive(Copy, Clone, Debug)]
+/// // The derive checks that the 12 bytes represented by this struct
+/// // are valid for all bit combinations.
+/// #[derive(Copy, Clone, Debug, ValueType)]
 /// #[repr(C)]
 /// struct V3 {
 ///     x: f32,
 ///     y: f32,
 ///     z: f32
 /// }
-/// // This is safe as the 12 bytes represented by this struct
-/// // are valid for all bit combinations.
-/// unsafe impl ValueType for V3 {
-/// }
 ///
 /// fn update_vector_3(memory: Memory, ptr: WasmPtr<V3>) {
 ///     let derefed_ptr = ptr.deref(&memory).expect("pointer in bounds");
 ///     let mut inner_val: V3 = derefed_ptr.get();
 ///     println!("Got {:?} from Wasm memory address 0x{:X}", inner_val, ptr.offset());
-///     // update the value being pointed to
-///     inner_val.x = 10.4;
+///     // update the value being pointed to, the accessors take care of
+///     // the byte order of Wasm memory
+///     inner_val.set_x(inner_val.get_x() + 10.4);
 ///     derefed_ptr.set(inner_val);
 /// }
 /// ```
@@ -194,6 +194,101 @@ impl<T: Copy + ValueType> WasmPtr<T, Array> {
     }
 }
 
+/// Bounds-checked accessors for `WasmPtr`s to data that implement
+/// [`ValueType`].
+///
+/// Unlike [`WasmPtr::deref`], these methods copy the values in and out of
+/// the memory and check every access against its current size, so they
+/// can't be invalidated by a grow of the memory.
+impl<T: Copy + ValueType, Ty> WasmPtr<T, Ty> {
+    /// Reads the value pointed to by this `WasmPtr`.
+    #[inline]
+    pub fn read(self, memory: &Memory) -> Result<T, MemoryAccessError> {
+        WasmRef::new(memory, self.offset as u64).read()
+    }
+
+    /// Writes to the location pointed to by this `WasmPtr`.
+    #[inline]
+    pub fn write(self, memory: &Memory, val: T) -> Result<(), MemoryAccessError> {
+        WasmRef::new(memory, self.offset as u64).write(val)
+    }
+
+    /// Creates a `WasmSlice` of `len` values starting at this `WasmPtr`.
+    ///
+    /// Returns a `MemoryAccessError::Overflow` if the end of the slice
+    /// overflows.
+    #[inline]
+    pub fn slice(self, memory: &Memory, len: u32) -> Result<WasmSlice<'_, T>, MemoryAccessError> {
+        WasmSlice::new(memory, self.offset as u64, len as u64)
+    }
+}
+
+impl<Ty> WasmPtr<u8, Ty> {
+    /// Reads a UTF-8 `String` of `len` bytes from the `WasmPtr`.
+    #[inline]
+    pub fn read_utf8_string(self, memory: &Memory, len: u32) -> Result<String, MemoryAccessError> {
+        let vec = self.slice(memory, len)?.read_to_vec()?;
+        Ok(String::from_utf8(vec)?)
+    }
+
+    /// Reads a nul-terminated UTF-8 `String` from the `WasmPtr`.
+    ///
+    /// The terminating nul byte is not included in the string.
+    #[inline]
+    pub fn read_utf8_string_with_nul(self, memory: &Memory) -> Result<String, MemoryAccessError> {
+        let vec = read_until_nul::<u8>(memory, self.offset as u64)?;
+        Ok(String::from_utf8(vec)?)
+    }
+}
+
+impl<Ty> WasmPtr<u16, Ty> {
+    /// Reads a UTF-16 `String` of `len` code units from the `WasmPtr`.
+    #[inline]
+    pub fn read_utf16_string(self, memory: &Memory, len: u32) -> Result<String, MemoryAccessError> {
+        let vec = self.slice(memory, len)?.read_to_vec()?;
+        String::from_utf16(&vec).map_err(|_| MemoryAccessError::NonUtf16String)
+    }
+
+    /// Reads a nul-terminated UTF-16 `String` from the `WasmPtr`.
+    ///
+    /// The terminating nul code unit is not included in the string.
+    #[inline]
+    pub fn read_utf16_string_with_nul(self, memory: &Memory) -> Result<String, MemoryAccessError> {
+        let vec = read_until_nul::<u16>(memory, self.offset as u64)?;
+        String::from_utf16(&vec).map_err(|_| MemoryAccessError::NonUtf16String)
+    }
+}
+
+/// Reads the values starting at `offset` up to the first zero, which is
+/// not included, a chunk at a time.
+fn read_until_nul<T>(memory: &Memory, mut offset: u64) -> Result<Vec<T>, MemoryAccessError>
+where
+    T: ValueType + Default + PartialEq,
+{
+    const CHUNK_LEN: u64 = 256;
+    let size = mem::size_of::<T>() as u64;
+    let mut vec = Vec::new();
+    loop {
+        // Don't read past the end of the memory, where a string without
+        // terminator is out of bounds.
+        let remaining = memory.data_size().saturating_sub(offset) / size;
+        if remaining == 0 {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        let chunk = WasmSlice::<T>::new(memory, offset, remaining.min(CHUNK_LEN))?.read_to_vec()?;
+        match chunk.iter().position(|value| *value == T::default()) {
+            Some(nul) => {
+                vec.extend_from_slice(&chunk[..nul]);
+                return Ok(vec);
+            }
+            None => {
+                vec.extend_from_slice(&chunk);
+                offset += chunk.len() as u64 * size;
+            }
+        }
+    }
+}
+
 unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
     type Native = i32;
 
@@ -210,6 +305,16 @@ unsafe impl<T: Copy, Ty> FromToNativeWasmType for WasmPtr<T, Ty> {
 
 unsafe impl<T: Copy, Ty> ValueType for WasmPtr<T, Ty> {}
 
+impl<T: Copy, Ty> LittleEndianConvert for WasmPtr<T, Ty> {
+    fn little_endian_to_native(self) -> Self {
+        Self::new(self.offset.little_endian_to_native())
+    }
+
+    fn native_to_little_endian(self) -> Self {
+        Self::new(self.offset.native_to_little_endian())
+    }
+}
+
 impl<T: Copy, Ty> Clone for WasmPtr<T, Ty> {
     fn clone(&self) -> Self {
         Self {
@@ -346,4 +451,84 @@ mod test {
             assert!(oob_end_array_ptr.deref(&memory, 1, 0).is_none());
         }
     }
+
+    /// Ensure that the bounds-checked accessors catch out of bounds
+    /// accesses, and see the new bounds of a grown memory.
+    #[wasm_bindgen_test]
+    fn wasm_ptr_read_write_bounds_checks_hold() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(2), false)).unwrap();
+        let page_size = memory.data_size();
+
+        let ptr: WasmPtr<u32> = WasmPtr::new(page_size as u32 - 4);
+        ptr.write(&memory, 42).unwrap();
+        assert_eq!(ptr.read(&memory), Ok(42));
+
+        let oob_ptr: WasmPtr<u32> = WasmPtr::new(page_size as u32 - 3);
+        assert_eq!(
+            oob_ptr.write(&memory, 1),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            oob_ptr.read(&memory),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+
+        let slice = ptr.slice(&memory, 2).unwrap();
+        assert_eq!(slice.read_to_vec(), Err(MemoryAccessError::HeapOutOfBounds));
+        assert_eq!(
+            WasmPtr::<u32>::new(u32::MAX)
+                .slice(&memory, u32::MAX)
+                .map(|_| ()),
+            Ok(())
+        );
+
+        memory.grow(1).unwrap();
+        oob_ptr.write(&memory, 1).unwrap();
+        assert_eq!(slice.read(1), Ok(0));
+        slice.copy_from_slice(&[1, 2]).unwrap();
+        assert_eq!(slice.read_to_vec(), Ok(vec![1, 2]));
+        assert_eq!(
+            slice
+                .iter()
+                .map(|r| r.read().unwrap())
+                .rev()
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn wasm_ptr_read_strings() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(1), false)).unwrap();
+
+        memory.write(0, b"hello\0").unwrap();
+        let ptr: WasmPtr<u8> = WasmPtr::new(0);
+        assert_eq!(ptr.read_utf8_string(&memory, 5).unwrap(), "hello");
+        assert_eq!(ptr.read_utf8_string_with_nul(&memory).unwrap(), "hello");
+
+        memory.write(0, &[0xff, 0xfe]).unwrap();
+        assert_eq!(
+            ptr.read_utf8_string(&memory, 2),
+            Err(MemoryAccessError::NonUtf8String)
+        );
+
+        let utf16 = "héllo\0".encode_utf16().collect::<Vec<_>>();
+        let ptr: WasmPtr<u16> = WasmPtr::new(16);
+        ptr.slice(&memory, utf16.len() as u32)
+            .unwrap()
+            .copy_from_slice(&utf16)
+            .unwrap();
+        assert_eq!(ptr.read_utf16_string(&memory, 5).unwrap(), "héllo");
+        assert_eq!(ptr.read_utf16_string_with_nul(&memory).unwrap(), "héllo");
+
+        // A string without terminator running to the end of the memory.
+        let end = memory.data_size() as u32 - 2;
+        memory.write(end as u64, b"ab").unwrap();
+        assert_eq!(
+            WasmPtr::<u8>::new(end).read_utf8_string_with_nul(&memory),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+    }
 }
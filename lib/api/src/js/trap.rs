@@ -108,6 +108,15 @@ impl RuntimeError {
             _ => false,
         }
     }
+
+    /// Returns a reference to the user error if it is of type `T`,
+    /// without consuming the `RuntimeError` like `downcast` does.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match self.inner.as_ref() {
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for RuntimeError {
@@ -1,6 +1,7 @@
+use crate::js::export::VMFunction;
 use crate::js::externals::Function;
-// use crate::js::store::{Store, StoreObject};
-// use crate::js::RuntimeError;
+use crate::js::store::Store;
+use crate::js::RuntimeError;
 use wasm_bindgen::JsValue;
 use wasmer_types::Value;
 pub use wasmer_types::{
@@ -21,18 +22,26 @@ pub trait AsJs {
     fn as_jsvalue(&self) -> JsValue;
 }
 
+/// Converts a JS value to a `Val` of the given type.
+///
+/// It fails for a function reference whose signature is unknown, see
+/// `VMFunction::from_funcref`.
 #[inline]
-pub fn param_from_js(ty: &ValType, js_val: &JsValue) -> Val {
-    match ty {
+pub fn param_from_js(store: &Store, ty: &ValType, js_val: &JsValue) -> Result<Val, RuntimeError> {
+    Ok(match ty {
         ValType::I32 => Val::I32(js_val.as_f64().unwrap() as _),
         ValType::I64 => Val::I64(js_val.as_f64().unwrap() as _),
         ValType::F32 => Val::F32(js_val.as_f64().unwrap() as _),
         ValType::F64 => Val::F64(js_val.as_f64().unwrap()),
+        ValType::FuncRef => Val::FuncRef(
+            VMFunction::from_funcref(js_val.clone())?
+                .map(|function| Function::from_vm_export(store, function)),
+        ),
         t => unimplemented!(
             "The type `{:?}` is not yet supported in the JS Function API",
             t
         ),
-    }
+    })
 }
 
 impl AsJs for Val {
@@ -42,7 +51,8 @@ impl AsJs for Val {
             Self::I64(i) => JsValue::from_f64(*i as f64),
             Self::F32(f) => JsValue::from_f64(*f as f64),
             Self::F64(f) => JsValue::from_f64(*f),
-            Self::FuncRef(func) => func.as_ref().unwrap().exported.function.clone().into(),
+            Self::FuncRef(Some(func)) => func.exported.function.clone().into(),
+            Self::FuncRef(None) => JsValue::NULL,
             v => unimplemented!(
                 "The value `{:?}` is not yet supported in the JS Function API",
                 v
@@ -847,6 +847,7 @@ mod inner {
     use std::marker::PhantomData;
     use std::panic::{self, AssertUnwindSafe};
 
+    use crate::sys::RuntimeError;
     #[cfg(feature = "experimental-reference-types-extern-ref")]
     pub use wasmer_types::{ExternRef, VMExternRef};
     use wasmer_types::{FunctionType, NativeWasmType, Type};
@@ -1189,6 +1190,21 @@ mod inner {
         }
     }
 
+    /// Raises the error returned by a host function as a trap.
+    ///
+    /// The `RuntimeError` is built here, while the Wasm frames are
+    /// still on the stack. Building it once the trap has been caught
+    /// would capture a backtrace where the Wasm frames have already
+    /// been unwound, leaving the trace of the error empty.
+    ///
+    /// # Safety
+    ///
+    /// Same as `wasmer_vm::raise_user_trap`: it must only be called
+    /// from a host function invoked by Wasm code.
+    unsafe fn raise_host_error(error: Box<dyn Error + Send + Sync>) -> ! {
+        raise_user_trap(Box::new(RuntimeError::user(error)))
+    }
+
     macro_rules! impl_host_function {
         ( [$c_struct_representation:ident]
            $c_struct_name:ident,
@@ -1315,7 +1331,7 @@ mod inner {
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
-                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Ok(Err(trap)) => unsafe { raise_host_error(Box::new(trap)) },
                             Err(panic) => unsafe { resume_panic(panic) },
                         }
                     }
@@ -1359,7 +1375,7 @@ mod inner {
 
                         match result {
                             Ok(Ok(result)) => return result.into_c_struct(),
-                            Ok(Err(trap)) => unsafe { raise_user_trap(Box::new(trap)) },
+                            Ok(Err(trap)) => unsafe { raise_host_error(Box::new(trap)) },
                             Err(panic) => unsafe { resume_panic(panic) },
                         }
                     }
@@ -1,9 +1,11 @@
 use crate::sys::exports::{ExportError, Exportable};
 use crate::sys::externals::Extern;
+use crate::sys::mem_access::MemoryAccessError;
 use crate::sys::store::Store;
 use crate::sys::{MemoryType, MemoryView};
 use loupe::MemoryUsage;
 use std::convert::TryInto;
+use std::mem::{self, MaybeUninit};
 use std::slice;
 use std::sync::Arc;
 use wasmer_engine::Export;
@@ -234,6 +236,92 @@ impl Memory {
         self.view()
     }
 
+    /// Safely reads bytes from the memory at the given offset.
+    ///
+    /// The full buffer will be filled, otherwise a `MemoryAccessError` is returned
+    /// to indicate an out-of-bounds access.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent writes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wasmer::{Memory, MemoryType, Store};
+    /// # let store = Store::default();
+    /// #
+    /// let m = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+    /// m.write(0x10, b"hello").unwrap();
+    ///
+    /// let mut buf = [0; 5];
+    /// m.read(0x10, &mut buf).unwrap();
+    /// assert_eq!(&buf, b"hello");
+    ///
+    /// assert!(m.read(65535, &mut buf).is_err());
+    /// ```
+    pub fn read(&self, offset: u64, buf: &mut [u8]) -> Result<(), MemoryAccessError> {
+        let end = checked_end(offset, buf.len())?;
+        if end > self.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        unsafe {
+            volatile_memcpy_read(
+                self.data_ptr().add(offset as usize),
+                buf.as_mut_ptr(),
+                buf.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Safely reads bytes from the memory at the given offset.
+    ///
+    /// This method is similar to `read` but allows reading into an
+    /// uninitialized buffer. An initialized view of the buffer is returned.
+    ///
+    /// The full buffer will be filled, otherwise a `MemoryAccessError` is returned
+    /// to indicate an out-of-bounds access.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent writes.
+    pub fn read_uninit<'a>(
+        &self,
+        offset: u64,
+        buf: &'a mut [MaybeUninit<u8>],
+    ) -> Result<&'a mut [u8], MemoryAccessError> {
+        let end = checked_end(offset, buf.len())?;
+        if end > self.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        let buf_ptr = buf.as_mut_ptr() as *mut u8;
+        unsafe {
+            volatile_memcpy_read(self.data_ptr().add(offset as usize), buf_ptr, buf.len());
+            Ok(slice::from_raw_parts_mut(buf_ptr, buf.len()))
+        }
+    }
+
+    /// Safely writes bytes to the memory at the given offset.
+    ///
+    /// If the write exceeds the bounds of the memory then a `MemoryAccessError` is
+    /// returned, and the memory is left unmodified.
+    ///
+    /// This method is guaranteed to be safe (from the host side) in the face of
+    /// concurrent reads/writes.
+    pub fn write(&self, offset: u64, data: &[u8]) -> Result<(), MemoryAccessError> {
+        let end = checked_end(offset, data.len())?;
+        if end > self.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        unsafe {
+            volatile_memcpy_write(
+                data.as_ptr(),
+                self.data_ptr().add(offset as usize),
+                data.len(),
+            );
+        }
+        Ok(())
+    }
+
     pub(crate) fn from_vm_export(store: &Store, vm_memory: VMMemory) -> Self {
         Self {
             store: store.clone(),
@@ -301,3 +389,72 @@ impl<'a> Exportable<'a> for Memory {
             .map(|v| *v = v.downgrade());
     }
 }
+
+/// Returns the end of an access of `len` bytes at `offset`.
+fn checked_end(offset: u64, len: usize) -> Result<u64, MemoryAccessError> {
+    offset
+        .checked_add(len as u64)
+        .ok_or(MemoryAccessError::Overflow)
+}
+
+/// Copies `len` bytes from the Wasm memory at `src` to `dst`.
+///
+/// The Wasm memory is read with volatile accesses, so that the compiler
+/// doesn't assume that it isn't concurrently modified. The bytes are
+/// copied in the largest chunks possible, which don't need to be
+/// aligned.
+unsafe fn volatile_memcpy_read(mut src: *const u8, mut dst: *mut u8, mut len: usize) {
+    #[inline]
+    unsafe fn copy_one<T>(src: &mut *const u8, dst: &mut *mut u8, len: &mut usize) {
+        #[repr(packed)]
+        struct Unaligned<T>(T);
+        let val = (*src as *const Unaligned<T>).read_volatile();
+        (*dst as *mut Unaligned<T>).write(val);
+        *src = src.add(mem::size_of::<T>());
+        *dst = dst.add(mem::size_of::<T>());
+        *len -= mem::size_of::<T>();
+    }
+
+    while len >= 8 {
+        copy_one::<u64>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 4 {
+        copy_one::<u32>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 2 {
+        copy_one::<u16>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 1 {
+        copy_one::<u8>(&mut src, &mut dst, &mut len);
+    }
+}
+
+/// Copies `len` bytes from `src` to the Wasm memory at `dst`.
+///
+/// Like `volatile_memcpy_read`, but the Wasm memory is written to with
+/// volatile accesses.
+unsafe fn volatile_memcpy_write(mut src: *const u8, mut dst: *mut u8, mut len: usize) {
+    #[inline]
+    unsafe fn copy_one<T>(src: &mut *const u8, dst: &mut *mut u8, len: &mut usize) {
+        #[repr(packed)]
+        struct Unaligned<T>(T);
+        let val = (*src as *const Unaligned<T>).read();
+        (*dst as *mut Unaligned<T>).write_volatile(val);
+        *src = src.add(mem::size_of::<T>());
+        *dst = dst.add(mem::size_of::<T>());
+        *len -= mem::size_of::<T>();
+    }
+
+    while len >= 8 {
+        copy_one::<u64>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 4 {
+        copy_one::<u32>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 2 {
+        copy_one::<u16>(&mut src, &mut dst, &mut len);
+    }
+    if len >= 1 {
+        copy_one::<u8>(&mut src, &mut dst, &mut len);
+    }
+}
@@ -1,6 +1,8 @@
 //! The import module contains the implementation data structures and helper functions used to
 //! manipulate and access a wasm module's imports including memories, tables, globals, and
 //! functions.
+use crate::sys::exports::Exportable;
+use crate::sys::{Function, ImportType, Instance, Module, RuntimeError, Store, Type, Val};
 use crate::Exports;
 use crate::Extern;
 use std::borrow::{Borrow, BorrowMut};
@@ -8,7 +10,9 @@ use std::collections::VecDeque;
 use std::collections::{hash_map::Entry, HashMap};
 use std::fmt;
 use std::sync::{Arc, Mutex};
-use wasmer_engine::{Export, NamedResolver};
+use wasmer_engine::{Export, ImportError, LinkError, NamedResolver};
+use wasmer_types::{ExternType, FunctionType};
+use wasmer_vm::VMFunctionKind;
 
 /// The `LikeNamespace` trait represents objects that act as a namespace for imports.
 /// For example, an `Instance` or `Namespace` could be
@@ -50,6 +54,16 @@ pub trait LikeNamespace {
 #[derive(Clone, Default)]
 pub struct ImportObject {
     map: Arc<Mutex<HashMap<String, Box<dyn LikeNamespace + Send + Sync>>>>,
+    aliases: Arc<Mutex<HashMap<(String, String), Alias>>>,
+}
+
+/// An import defined on top of the namespaces of an `ImportObject`.
+#[derive(Clone)]
+enum Alias {
+    /// Another name for an export of a namespace.
+    Name(String, String),
+    /// A function adapting the signature of an export of a namespace.
+    Adapter(Function),
 }
 
 impl ImportObject {
@@ -67,6 +81,21 @@ impl ImportObject {
     /// import_object.get_export("module", "name");
     /// ```
     pub fn get_export(&self, module: &str, name: &str) -> Option<Export> {
+        match self.get_alias(module, name) {
+            Some(Alias::Name(module, name)) => self.get_registered_export(&module, &name),
+            Some(Alias::Adapter(function)) => Some(function.to_export()),
+            None => self.get_registered_export(module, name),
+        }
+    }
+
+    fn get_alias(&self, module: &str, name: &str) -> Option<Alias> {
+        let aliases = self.aliases.lock().unwrap();
+        aliases
+            .get(&(module.to_string(), name.to_string()))
+            .cloned()
+    }
+
+    fn get_registered_export(&self, module: &str, name: &str) -> Option<Export> {
         let guard = self.map.lock().unwrap();
         let map_ref = guard.borrow();
         if map_ref.contains_key(module) {
@@ -79,6 +108,12 @@ impl ImportObject {
     /// Returns true if the ImportObject contains namespace with the provided name.
     pub fn contains_namespace(&self, name: &str) -> bool {
         self.map.lock().unwrap().borrow().contains_key(name)
+            || self
+                .aliases
+                .lock()
+                .unwrap()
+                .keys()
+                .any(|(module, _)| module == name)
     }
 
     /// Register anything that implements `LikeNamespace` as a namespace.
@@ -109,6 +144,168 @@ impl ImportObject {
         }
     }
 
+    /// Register the exports of an [`Instance`] as a namespace.
+    ///
+    /// The exports are looked up in the instance when the imports are
+    /// resolved, so there is no need to copy them into an `Exports`.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ImportObject, Instance};
+    /// let mut import_object = ImportObject::new();
+    ///
+    /// import_object.register_instance("shim", &shim_instance);
+    /// // ...
+    /// ```
+    pub fn register_instance<S>(
+        &mut self,
+        name: S,
+        instance: &Instance,
+    ) -> Option<Box<dyn LikeNamespace>>
+    where
+        S: Into<String>,
+    {
+        self.register(name, instance.clone())
+    }
+
+    /// Makes the export `name` of the namespace `module` available
+    /// under another name, `alias_name` in the namespace
+    /// `alias_module`. The namespace of the alias doesn't need to be
+    /// registered.
+    ///
+    /// The export must be compatible with the type `ty`, as it would be
+    /// checked for an import of this type.
+    ///
+    /// Aliases take precedence over the exports of the namespaces, and
+    /// aliasing an alias makes another name for the same export.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ExternType, FunctionType, ImportObject, Type};
+    /// let mut import_object = ImportObject::new();
+    /// // ...
+    /// let ty = ExternType::Function(FunctionType::new([Type::I32], []));
+    /// import_object.alias("env", "print", "wasi_unstable", "print", &ty)?;
+    /// ```
+    pub fn alias<S1, S2>(
+        &mut self,
+        module: &str,
+        name: &str,
+        alias_module: S1,
+        alias_name: S2,
+        ty: &ExternType,
+    ) -> Result<(), LinkError>
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        let import_error = |error| LinkError::Import(module.to_string(), name.to_string(), error);
+        let export = self
+            .get_export(module, name)
+            .ok_or_else(|| import_error(ImportError::UnknownImport(ty.clone())))?;
+        let export_ty = export_type(&export);
+
+        if !export_ty.is_compatible_with(ty) {
+            return Err(import_error(ImportError::IncompatibleType(
+                ty.clone(),
+                export_ty,
+            )));
+        }
+
+        let alias = self
+            .get_alias(module, name)
+            .unwrap_or_else(|| Alias::Name(module.to_string(), name.to_string()));
+        self.aliases
+            .lock()
+            .unwrap()
+            .insert((alias_module.into(), alias_name.into()), alias);
+
+        Ok(())
+    }
+
+    /// Adapts the function imports of `module` whose signature differs
+    /// from the function provided by this `ImportObject` only by the
+    /// width of some integers, `i32` instead of `i64` or the other way
+    /// around, like pointers of a 32-bit and a 64-bit module.
+    ///
+    /// The adapted imports resolve to a function which converts its
+    /// arguments and results: an `i32` is zero-extended to an `i64`,
+    /// like a pointer, and an `i64` is truncated to an `i32`. The call
+    /// traps if an `i64` doesn't fit in 32 bits, neither as a signed
+    /// nor as an unsigned integer.
+    ///
+    /// Only WebAssembly functions and dynamic host functions, created
+    /// with `Function::new` or `Function::new_with_env`, can be
+    /// adapted.
+    ///
+    /// Returns the number of adapted imports. The other mismatches are
+    /// left as they are, to be reported when instantiating the module.
+    pub fn adapt_pointer_widths(&mut self, module: &Module) -> usize {
+        self.adapt_pointer_widths_with(module, |_, _| IntegerExtension::Zero)
+    }
+
+    /// Like [`ImportObject::adapt_pointer_widths`], but the `i32`
+    /// results which are converted to `i64`s are extended as returned
+    /// by `extension`, called with the import and the index of the
+    /// result. The arguments are still zero-extended.
+    ///
+    /// # Usage:
+    /// ```ignore
+    /// # use wasmer::{ImportObject, IntegerExtension};
+    /// let mut import_object = ImportObject::new();
+    /// // ...
+    /// // `errno` returns a signed error code, not a pointer.
+    /// import_object.adapt_pointer_widths_with(&module, |import, _| {
+    ///     if import.name() == "errno" {
+    ///         IntegerExtension::Sign
+    ///     } else {
+    ///         IntegerExtension::Zero
+    ///     }
+    /// });
+    /// ```
+    pub fn adapt_pointer_widths_with<F>(&mut self, module: &Module, mut extension: F) -> usize
+    where
+        F: FnMut(&ImportType<FunctionType>, usize) -> IntegerExtension,
+    {
+        let mut adapted = 0;
+
+        for import in module.imports().functions() {
+            let function = match self.get_export(import.module(), import.name()) {
+                // Native host functions can't be called from the host.
+                Some(Export::Function(ref f))
+                    if f.vm_function.call_trampoline.is_none()
+                        && matches!(f.vm_function.kind, VMFunctionKind::Static) =>
+                {
+                    continue
+                }
+                Some(export @ Export::Function(_)) => {
+                    match Extern::from_vm_export(module.store(), export) {
+                        Extern::Function(function) => function,
+                        _ => unreachable!(),
+                    }
+                }
+                _ => continue,
+            };
+
+            if !is_pointer_width_adaptable(import.ty(), function.ty()) {
+                continue;
+            }
+
+            let extensions = (0..import.ty().results().len())
+                .map(|index| extension(&import, index))
+                .collect();
+            let adapter =
+                pointer_width_adapter(module.store(), import.ty().clone(), function, extensions);
+            self.aliases.lock().unwrap().insert(
+                (import.module().to_string(), import.name().to_string()),
+                Alias::Adapter(adapter),
+            );
+            adapted += 1;
+        }
+
+        adapted
+    }
+
     /// Returns the contents of a namespace as an `Exports`.
     ///
     /// Returns `None` if the namespace doesn't exist or doesn't implement the
@@ -133,6 +330,18 @@ impl ImportObject {
                 None => {}
             }
         }
+        drop(guard);
+        for ((namespace, name), alias) in self.aliases.lock().unwrap().iter() {
+            let extern_ = match alias {
+                Alias::Name(module, name) => self
+                    .get_namespace_exports(module)
+                    .and_then(|exports| exports.get_extern(name).cloned()),
+                Alias::Adapter(function) => Some(Extern::Function(function.clone())),
+            };
+            if let Some(extern_) = extern_ {
+                out.push((namespace.clone(), name.clone(), extern_));
+            }
+        }
         out
     }
 
@@ -145,10 +354,106 @@ impl ImportObject {
                 out.push_back(((name.clone(), id), exp));
             }
         }
+        drop(guard);
+        for ((module, name), alias) in self.aliases.lock().unwrap().iter() {
+            let export = match alias {
+                Alias::Name(module, name) => self.get_registered_export(module, name),
+                Alias::Adapter(function) => Some(function.to_export()),
+            };
+            if let Some(export) = export {
+                out.push_back(((module.clone(), name.clone()), export));
+            }
+        }
         out
     }
 }
 
+/// Get the `ExternType` of an `Export`.
+fn export_type(export: &Export) -> ExternType {
+    match export {
+        Export::Function(ref f) => ExternType::Function(f.vm_function.signature.clone()),
+        Export::Table(ref t) => ExternType::Table(*t.ty()),
+        Export::Memory(ref m) => ExternType::Memory(m.ty()),
+        Export::Global(ref g) => ExternType::Global(*g.from.ty()),
+    }
+}
+
+/// Whether the function type `provided` differs from `expected` only
+/// by `i32`s in place of `i64`s, or the other way around.
+fn is_pointer_width_adaptable(expected: &FunctionType, provided: &FunctionType) -> bool {
+    fn adaptable(expected: &[Type], provided: &[Type]) -> bool {
+        expected.len() == provided.len()
+            && expected.iter().zip(provided).all(|types| match types {
+                (Type::I32, Type::I64) | (Type::I64, Type::I32) => true,
+                (expected, provided) => expected == provided,
+            })
+    }
+
+    expected != provided
+        && adaptable(expected.params(), provided.params())
+        && adaptable(expected.results(), provided.results())
+}
+
+/// How an `i32` is converted to an `i64` by the adapters of
+/// [`ImportObject::adapt_pointer_widths_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegerExtension {
+    /// The `i32` is read as unsigned, like a pointer: `-1` becomes
+    /// `0xFFFF_FFFF`.
+    Zero,
+    /// The `i32` is read as signed, like an error code: `-1` stays
+    /// `-1`.
+    Sign,
+}
+
+/// Creates a function of type `ty` calling `function`, converting the
+/// integers from one width to the other. The `i32` results are
+/// extended as given by `extensions`.
+fn pointer_width_adapter(
+    store: &Store,
+    ty: FunctionType,
+    function: Function,
+    extensions: Vec<IntegerExtension>,
+) -> Function {
+    fn convert(value: &Val, ty: &Type, extension: IntegerExtension) -> Result<Val, RuntimeError> {
+        Ok(match (value, ty) {
+            (Val::I32(value), Type::I64) => match extension {
+                IntegerExtension::Zero => Val::I64(*value as u32 as i64),
+                IntegerExtension::Sign => Val::I64(*value as i64),
+            },
+            (Val::I64(value), Type::I32) => {
+                if *value < i32::MIN as i64 || *value > u32::MAX as i64 {
+                    return Err(RuntimeError::new(format!(
+                        "the integer {} doesn't fit in 32 bits",
+                        value
+                    )));
+                }
+
+                Val::I32(*value as i32)
+            }
+            (value, _) => value.clone(),
+        })
+    }
+
+    let results = ty.results().to_vec();
+
+    Function::new(store, ty, move |args| {
+        let args = args
+            .iter()
+            .zip(function.ty().params())
+            .map(|(arg, ty)| convert(arg, ty, IntegerExtension::Zero))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        function
+            .call(&args)?
+            .iter()
+            .zip(&results)
+            .zip(&extensions)
+            .map(|((result, ty), extension)| convert(result, ty, *extension))
+            .collect()
+    })
+}
+
 impl NamedResolver for ImportObject {
     fn resolve_by_name(&self, module: &str, name: &str) -> Option<Export> {
         self.get_export(module, name)
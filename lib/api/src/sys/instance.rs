@@ -1,5 +1,6 @@
 use crate::sys::exports::Exports;
 use crate::sys::externals::Extern;
+use crate::sys::import_object::LikeNamespace;
 use crate::sys::module::Module;
 use crate::sys::store::Store;
 use crate::sys::{HostEnvInitError, LinkError, RuntimeError};
@@ -7,7 +8,7 @@ use loupe::MemoryUsage;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
-use wasmer_engine::Resolver;
+use wasmer_engine::{Export, Resolver};
 use wasmer_vm::{InstanceHandle, VMContext};
 
 /// A WebAssembly Instance is a stateful, executable
@@ -183,6 +184,20 @@ impl fmt::Debug for Instance {
     }
 }
 
+impl LikeNamespace for Instance {
+    fn get_namespace_export(&self, name: &str) -> Option<Export> {
+        self.exports.get_namespace_export(name)
+    }
+
+    fn get_namespace_exports(&self) -> Vec<(String, Export)> {
+        self.exports.get_namespace_exports()
+    }
+
+    fn as_exports(&self) -> Option<Exports> {
+        Some(self.exports.clone())
+    }
+}
+
 impl Instance {
     /// Resets the [`Globals`] and [`Memories`] for an [`Instance`].
     pub fn reset(&self) -> Result<(), String> {
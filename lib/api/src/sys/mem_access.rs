@@ -0,0 +1,356 @@
+//! Safe, bounds-checked accessors to values and slices in Wasm linear
+//! memory.
+//!
+//! Unlike [`WasmCell`](crate::sys::WasmCell), these accessors don't borrow
+//! the memory contents: every access is checked against the current size
+//! of the memory, so they stay valid when the memory grows.
+
+use crate::sys::externals::Memory;
+use crate::sys::RuntimeError;
+use std::convert::TryInto;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ops::Range;
+use std::slice;
+use std::string::FromUtf8Error;
+use thiserror::Error;
+use wasmer_types::ValueType;
+
+/// Error for invalid [`Memory`] access.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemoryAccessError {
+    /// Memory access is outside heap bounds.
+    #[error("memory access out of bounds")]
+    HeapOutOfBounds,
+    /// Address calculation overflow.
+    #[error("address calculation overflow")]
+    Overflow,
+    /// String is not valid UTF-8.
+    #[error("string is not valid utf-8")]
+    NonUtf8String,
+    /// String is not valid UTF-16.
+    #[error("string is not valid utf-16")]
+    NonUtf16String,
+}
+
+impl From<MemoryAccessError> for RuntimeError {
+    fn from(err: MemoryAccessError) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
+impl From<FromUtf8Error> for MemoryAccessError {
+    fn from(_err: FromUtf8Error) -> Self {
+        Self::NonUtf8String
+    }
+}
+
+/// A reference to a value of type `T` in Wasm linear memory.
+///
+/// The value is copied in and out of the memory on every access, which
+/// is checked against the current size of the memory.
+pub struct WasmRef<'a, T: ValueType> {
+    memory: &'a Memory,
+    offset: u64,
+    marker: PhantomData<*mut T>,
+}
+
+impl<'a, T: ValueType> WasmRef<'a, T> {
+    /// Creates a new `WasmRef` at the given offset in a memory.
+    #[inline]
+    pub fn new(memory: &'a Memory, offset: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            marker: PhantomData,
+        }
+    }
+
+    /// Get the offset into Wasm linear memory for this `WasmRef`.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get a reference to the Wasm memory backing this reference.
+    #[inline]
+    pub fn memory(&self) -> &'a Memory {
+        self.memory
+    }
+
+    /// Reads the location pointed to by this `WasmRef`.
+    #[inline]
+    pub fn read(&self) -> Result<T, MemoryAccessError> {
+        let mut out = MaybeUninit::<T>::zeroed();
+        // Safe because `T: ValueType` is valid for any bit pattern,
+        // including all zeroes.
+        let buf =
+            unsafe { slice::from_raw_parts_mut(out.as_mut_ptr() as *mut u8, mem::size_of::<T>()) };
+        self.memory.read(self.offset, buf)?;
+        Ok(unsafe { out.assume_init() })
+    }
+
+    /// Writes to the location pointed to by this `WasmRef`.
+    #[inline]
+    pub fn write(&self, val: T) -> Result<(), MemoryAccessError> {
+        let buf =
+            unsafe { slice::from_raw_parts(&val as *const T as *const u8, mem::size_of::<T>()) };
+        self.memory.write(self.offset, buf)
+    }
+}
+
+impl<'a, T: ValueType> Clone for WasmRef<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ValueType> Copy for WasmRef<'a, T> {}
+
+impl<'a, T: ValueType> fmt::Debug for WasmRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WasmRef(offset: {}, pointer: {:#x})",
+            self.offset, self.offset
+        )
+    }
+}
+
+/// A slice of values of type `T` in Wasm linear memory.
+///
+/// Creating the slice only checks that its end doesn't overflow: the
+/// bounds are checked against the current size of the memory on every
+/// access.
+pub struct WasmSlice<'a, T: ValueType> {
+    memory: &'a Memory,
+    offset: u64,
+    len: u64,
+    marker: PhantomData<*mut T>,
+}
+
+impl<'a, T: ValueType> WasmSlice<'a, T> {
+    /// Creates a new `WasmSlice` of `len` values at the given offset in a
+    /// memory.
+    ///
+    /// Returns a `MemoryAccessError::Overflow` if the end of the slice
+    /// doesn't fit in a `u64`.
+    #[inline]
+    pub fn new(memory: &'a Memory, offset: u64, len: u64) -> Result<Self, MemoryAccessError> {
+        let total_len = len
+            .checked_mul(mem::size_of::<T>() as u64)
+            .ok_or(MemoryAccessError::Overflow)?;
+        offset
+            .checked_add(total_len)
+            .ok_or(MemoryAccessError::Overflow)?;
+        Ok(Self {
+            memory,
+            offset,
+            len,
+            marker: PhantomData,
+        })
+    }
+
+    /// Get the offset into Wasm linear memory for this `WasmSlice`.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the number of elements in this slice.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the number of elements is 0.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get a reference to the Wasm memory backing this slice.
+    #[inline]
+    pub fn memory(&self) -> &'a Memory {
+        self.memory
+    }
+
+    /// Get a `WasmRef` to an element in the slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of the slice bounds.
+    #[inline]
+    pub fn index(&self, idx: u64) -> WasmRef<'a, T> {
+        assert!(idx < self.len, "WasmSlice out of bounds");
+        WasmRef::new(self.memory, self.offset + idx * mem::size_of::<T>() as u64)
+    }
+
+    /// Get a `WasmSlice` for a subslice of this slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of the slice bounds.
+    #[inline]
+    pub fn subslice(&self, range: Range<u64>) -> WasmSlice<'a, T> {
+        assert!(range.start <= range.end, "WasmSlice out of bounds");
+        assert!(range.end <= self.len, "WasmSlice out of bounds");
+        Self {
+            memory: self.memory,
+            offset: self.offset + range.start * mem::size_of::<T>() as u64,
+            len: range.end - range.start,
+            marker: PhantomData,
+        }
+    }
+
+    /// Get an iterator over the elements in this slice.
+    #[inline]
+    pub fn iter(&self) -> WasmSliceIter<'a, T> {
+        WasmSliceIter { slice: *self }
+    }
+
+    /// Reads an element of this slice.
+    #[inline]
+    pub fn read(&self, idx: u64) -> Result<T, MemoryAccessError> {
+        self.index(idx).read()
+    }
+
+    /// Writes to an element of this slice.
+    #[inline]
+    pub fn write(&self, idx: u64, val: T) -> Result<(), MemoryAccessError> {
+        self.index(idx).write(val)
+    }
+
+    /// Reads the entire slice into the given buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the length of
+    /// the slice.
+    #[inline]
+    pub fn read_slice(&self, buf: &mut [T]) -> Result<(), MemoryAccessError> {
+        assert_eq!(
+            buf.len() as u64,
+            self.len,
+            "slice length doesn't match WasmSlice length"
+        );
+        // Safe because `T: ValueType` is valid for any bit pattern.
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * mem::size_of::<T>())
+        };
+        self.memory.read(self.offset, bytes)
+    }
+
+    /// Reads this `WasmSlice` into a `Vec`.
+    #[inline]
+    pub fn read_to_vec(&self) -> Result<Vec<T>, MemoryAccessError> {
+        let len: usize = self
+            .len
+            .try_into()
+            .map_err(|_| MemoryAccessError::Overflow)?;
+        // Check the bounds before allocating, so that a bogus length
+        // coming from the guest doesn't exhaust the host memory.
+        self.check_bounds()?;
+        let mut vec = Vec::with_capacity(len);
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(
+                vec.as_mut_ptr() as *mut MaybeUninit<u8>,
+                len * mem::size_of::<T>(),
+            )
+        };
+        self.memory.read_uninit(self.offset, bytes)?;
+        // Safe because `T: ValueType` is valid for any bit pattern, and
+        // all the bytes have been initialized by `read_uninit`.
+        unsafe {
+            vec.set_len(len);
+        }
+        Ok(vec)
+    }
+
+    /// Writes the entire slice from the given buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the buffer doesn't match the length of
+    /// the slice.
+    #[inline]
+    pub fn copy_from_slice(&self, src: &[T]) -> Result<(), MemoryAccessError> {
+        assert_eq!(
+            src.len() as u64,
+            self.len,
+            "slice length doesn't match WasmSlice length"
+        );
+        let bytes = unsafe {
+            slice::from_raw_parts(src.as_ptr() as *const u8, src.len() * mem::size_of::<T>())
+        };
+        self.memory.write(self.offset, bytes)
+    }
+
+    fn check_bounds(&self) -> Result<(), MemoryAccessError> {
+        // Can't overflow, see `WasmSlice::new`.
+        let end = self.offset + self.len * mem::size_of::<T>() as u64;
+        if end > self.memory.data_size() {
+            return Err(MemoryAccessError::HeapOutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: ValueType> Clone for WasmSlice<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ValueType> Copy for WasmSlice<'a, T> {}
+
+impl<'a, T: ValueType> fmt::Debug for WasmSlice<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "WasmSlice(offset: {}, len: {}, pointer: {:#x})",
+            self.offset, self.len, self.offset
+        )
+    }
+}
+
+/// Iterator over the elements of a `WasmSlice`.
+#[derive(Debug)]
+pub struct WasmSliceIter<'a, T: ValueType> {
+    slice: WasmSlice<'a, T>,
+}
+
+impl<'a, T: ValueType> Iterator for WasmSliceIter<'a, T> {
+    type Item = WasmRef<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.slice.is_empty() {
+            let elem = self.slice.index(0);
+            self.slice = self.slice.subslice(1..self.slice.len());
+            Some(elem)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.slice.len() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: ValueType> DoubleEndedIterator for WasmSliceIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if !self.slice.is_empty() {
+            let elem = self.slice.index(self.slice.len() - 1);
+            self.slice = self.slice.subslice(0..self.slice.len() - 1);
+            Some(elem)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T: ValueType> ExactSizeIterator for WasmSliceIter<'a, T> {}
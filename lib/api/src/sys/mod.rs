@@ -4,6 +4,7 @@ mod exports;
 mod externals;
 mod import_object;
 mod instance;
+mod mem_access;
 mod module;
 mod native;
 mod ptr;
@@ -17,6 +18,11 @@ mod utils;
 /// See the [`WasmerEnv`] trait for more information.
 pub use wasmer_derive::WasmerEnv;
 
+/// Implement [`ValueType`] for your `#[repr(C)]` struct with `#[derive(ValueType)]`.
+///
+/// See the [`WasmPtr`] documentation for an example.
+pub use wasmer_derive::ValueType;
+
 #[doc(hidden)]
 pub mod internals {
     //! We use the internals module for exporting types that are only
@@ -33,8 +39,11 @@ pub use crate::sys::exports::{ExportError, Exportable, Exports, ExportsIterator}
 pub use crate::sys::externals::{
     Extern, FromToNativeWasmType, Function, Global, HostFunction, Memory, Table, WasmTypeList,
 };
-pub use crate::sys::import_object::{ImportObject, ImportObjectIterator, LikeNamespace};
+pub use crate::sys::import_object::{
+    ImportObject, ImportObjectIterator, IntegerExtension, LikeNamespace,
+};
 pub use crate::sys::instance::{Instance, InstantiationError};
+pub use crate::sys::mem_access::{MemoryAccessError, WasmRef, WasmSlice, WasmSliceIter};
 pub use crate::sys::module::Module;
 pub use crate::sys::native::NativeFunc;
 pub use crate::sys::ptr::{Array, Item, WasmPtr};
@@ -56,14 +65,14 @@ pub use wasmer_compiler::{
     CompileError, CpuFeature, Features, ParseCpuFeatureError, Target, WasmError, WasmResult,
 };
 pub use wasmer_engine::{
-    ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, LinkError, NamedResolver,
-    NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
+    ChainableNamedResolver, DeserializeError, Engine, Export, FrameInfo, ImportError, LinkError,
+    NamedResolver, NamedResolverChain, Resolver, RuntimeError, SerializeError, Tunables,
 };
 #[cfg(feature = "experimental-reference-types-extern-ref")]
 pub use wasmer_types::ExternRef;
 pub use wasmer_types::{
-    Atomically, Bytes, ExportIndex, GlobalInit, LocalFunctionIndex, MemoryView, Pages, ValueType,
-    WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
+    Atomically, Bytes, ExportIndex, GlobalInit, LittleEndianConvert, LocalFunctionIndex,
+    MemoryView, Pages, ValueType, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
 
 // TODO: should those be moved into wasmer::vm as well?
@@ -171,6 +171,61 @@ mod js {
         );
     }
 
+    #[wasm_bindgen_test]
+    fn memory_view() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+
+        let view: MemoryView<u8> = memory.view();
+        assert_eq!(view.len(), 65536);
+        unsafe { view.subarray(0x10, 0x14).copy_from(&[1, 2, 3, 4]) };
+
+        let mut bytes = [0; 6];
+        view.subarray(0xf, 0x15).copy_to(&mut bytes);
+        assert_eq!(bytes, [0, 1, 2, 3, 4, 0]);
+
+        // The end of the memory can be viewed.
+        let mut bytes = [1; 2];
+        view.subarray(65534, 65536).copy_to(&mut bytes);
+        assert_eq!(bytes, [0, 0]);
+
+        let view: MemoryView<u32> = memory.view();
+        assert_eq!(view.len(), 16384);
+        let mut values = [0; 1];
+        view.subarray(4, 5).copy_to(&mut values);
+        assert_eq!(values, [u32::from_le_bytes([1, 2, 3, 4])]);
+    }
+
+    #[wasm_bindgen_test]
+    fn memory_read_write() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(2), false)).unwrap();
+
+        memory.write(65532, &[1, 2, 3, 4]).unwrap();
+        let mut bytes = [0; 4];
+        memory.read(65532, &mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4]);
+
+        let mut bytes = [0; 5];
+        assert_eq!(
+            memory.read(65532, &mut bytes),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.write(65533, &[0; 4]),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.read(u64::MAX, &mut bytes),
+            Err(MemoryAccessError::Overflow)
+        );
+
+        // The accesses are checked against the grown memory.
+        memory.grow(1).unwrap();
+        memory.read(65532, &mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4, 0]);
+    }
+
     #[wasm_bindgen_test]
     fn function_new() {
         let store = Store::default();
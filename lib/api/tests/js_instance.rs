@@ -341,6 +341,67 @@ mod js {
         assert_eq!(exported.call(&[Val::I32(4)]), Ok(expected));
     }
 
+    #[wasm_bindgen_test]
+    fn test_imported_function_native_closure() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (func $imported (import "env" "imported") (param i32) (result i32))
+        (func $imported_with_env (import "env" "imported_with_env") (param i32) (result i32))
+        (func (export "exported") (param i32) (result i32)
+            (call $imported_with_env (call $imported (local.get 0)))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![
+                    ExternType::Function(FunctionType::new(vec![Type::I32], vec![Type::I32])),
+                    ExternType::Function(FunctionType::new(vec![Type::I32], vec![Type::I32])),
+                ],
+                exports: vec![ExternType::Function(FunctionType::new(
+                    vec![Type::I32],
+                    vec![Type::I32],
+                ))],
+            })
+            .unwrap();
+
+        #[derive(WasmerEnv, Clone)]
+        struct Env {
+            multiplier: u32,
+        }
+
+        let offset = 2u32;
+        let imported = Function::new_native(&store, move |arg: u32| arg + offset);
+        let divisor = 2u32;
+        let imported_with_env = Function::new_native_with_env(
+            &store,
+            Env { multiplier: 3 },
+            move |env: &Env, arg: u32| env.multiplier * arg / divisor,
+        );
+        assert_eq!(
+            imported.ty().clone(),
+            FunctionType::new(vec![Type::I32], vec![Type::I32])
+        );
+
+        let import_object = imports! {
+            "env" => {
+                "imported" => imported,
+                "imported_with_env" => imported_with_env,
+            }
+        };
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        let exported = instance.exports.get_function("exported").unwrap();
+
+        let expected = vec![Val::I32(9)].into_boxed_slice();
+        assert_eq!(exported.call(&[Val::I32(4)]), Ok(expected));
+    }
+
     #[wasm_bindgen_test]
     fn test_imported_function_native_with_wasmer_env() {
         let store = Store::default();
@@ -584,6 +645,158 @@ mod js {
         assert_eq!(global.get(), Val::I32(43));
     }
 
+    #[wasm_bindgen_test]
+    fn test_funcref_table_and_global() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (table (export "table") 2 funcref)
+        (elem (i32.const 0) $get_magic)
+        (func $get_magic (export "get_magic") (result i32)
+          (i32.const 42)
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![],
+                exports: vec![
+                    ExternType::Table(TableType::new(Type::FuncRef, 2, None)),
+                    ExternType::Function(FunctionType::new(vec![], vec![Type::I32])),
+                ],
+            })
+            .unwrap();
+
+        let import_object = imports! {};
+        let instance = Instance::new(&module, &import_object).unwrap();
+        let table = instance.exports.get_table("table").unwrap();
+
+        // The function keeps its signature through the table.
+        let get_magic = match table.get(0) {
+            Some(Val::FuncRef(Some(function))) => function,
+            value => panic!("unexpected table element: {:?}", value),
+        };
+        assert_eq!(
+            get_magic.ty().clone(),
+            FunctionType::new(vec![], vec![Type::I32])
+        );
+        assert_eq!(
+            get_magic.call(&[]),
+            Ok(vec![Val::I32(42)].into_boxed_slice())
+        );
+
+        assert_eq!(table.get(1), Some(Val::FuncRef(None)));
+        table.set(1, Val::FuncRef(Some(get_magic.clone()))).unwrap();
+        assert!(matches!(table.get(1), Some(Val::FuncRef(Some(_)))));
+        table.set(0, Val::FuncRef(None)).unwrap();
+        assert_eq!(table.get(0), Some(Val::FuncRef(None)));
+
+        // And through a global.
+        let global = Global::new_mut(&store, Val::FuncRef(None));
+        assert_eq!(global.get(), Val::FuncRef(None));
+        global.set(Val::FuncRef(Some(get_magic))).unwrap();
+        match global.get() {
+            Val::FuncRef(Some(function)) => assert_eq!(
+                function.call(&[]),
+                Ok(vec![Val::I32(42)].into_boxed_slice())
+            ),
+            value => panic!("unexpected global value: {:?}", value),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_funcref_binary_can_be_read_many_times() {
+        let store = Store::default();
+        let function = Function::new_native(&store, || {});
+
+        let mut binary = 0;
+        unsafe { Val::FuncRef(Some(function.clone())).write_value_to(&mut binary) };
+        for _ in 0..2 {
+            match unsafe { Val::read_value_from(&store, &binary, Type::FuncRef) } {
+                Val::FuncRef(Some(read)) => assert_eq!(read.ty(), function.ty()),
+                value => panic!("unexpected value: {:?}", value),
+            }
+        }
+
+        // Writing the same function again reuses its binary form.
+        let mut other_binary = 0;
+        unsafe { Val::FuncRef(Some(function)).write_value_to(&mut other_binary) };
+        assert_eq!(other_binary, binary);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_funcref_binary_is_released_with_the_function() {
+        let store = Store::default();
+        let function = Function::new_native(&store, || {});
+
+        let mut binary = 0;
+        unsafe { Val::FuncRef(Some(function.clone())).write_value_to(&mut binary) };
+        // Read through any handle, the binary form stays the same.
+        let read = match unsafe { Val::read_value_from(&store, &binary, Type::FuncRef) } {
+            Val::FuncRef(Some(read)) => read,
+            value => panic!("unexpected value: {:?}", value),
+        };
+        let mut read_binary = 0;
+        unsafe { Val::FuncRef(Some(read.clone())).write_value_to(&mut read_binary) };
+        assert_eq!(read_binary, binary);
+
+        // Once all the handles are dropped, the slot of the binary form
+        // is given to the next function.
+        drop(function);
+        drop(read);
+        let other = Function::new_native(&store, |_: i32| {});
+        let mut other_binary = 0;
+        unsafe { Val::FuncRef(Some(other.clone())).write_value_to(&mut other_binary) };
+        assert_eq!(other_binary, binary);
+        match unsafe { Val::read_value_from(&store, &other_binary, Type::FuncRef) } {
+            Val::FuncRef(Some(read)) => assert_eq!(read.ty(), other.ty()),
+            value => panic!("unexpected value: {:?}", value),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_funcref_with_unknown_signature() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (table (export "table") 1 funcref)
+        (elem (i32.const 0) $hidden)
+        (func $hidden (result i32)
+          (i32.const 42)
+        )
+        (func (export "get_hidden") (result funcref)
+          (table.get 0 (i32.const 0))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![],
+                exports: vec![
+                    ExternType::Table(TableType::new(Type::FuncRef, 1, None)),
+                    ExternType::Function(FunctionType::new(vec![], vec![Type::FuncRef])),
+                ],
+            })
+            .unwrap();
+
+        let import_object = imports! {};
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        // `$hidden` is not exported, so its signature is unknown.
+        let table = instance.exports.get_table("table").unwrap();
+        assert_eq!(table.get(0), None);
+        let get_hidden = instance.exports.get_function("get_hidden").unwrap();
+        assert!(get_hidden.call(&[]).is_err());
+    }
+
     #[wasm_bindgen_test]
     fn test_native_function() {
         let store = Store::default();
@@ -757,4 +970,69 @@ mod js {
         let err = result.unwrap_err();
         assert!(format!("{:?}", err).contains("zero"))
     }
+
+    #[wasm_bindgen_test]
+    fn test_import_object_alias() {
+        let store = Store::default();
+        let mut module = Module::new(
+            &store,
+            br#"
+    (module
+        (func $twice (import "env" "twice") (param i32) (result i32))
+        (func (export "exported") (param i32) (result i32)
+            (call $twice (local.get 0))
+        )
+    )
+    "#,
+        )
+        .unwrap();
+        module
+            .set_type_hints(ModuleTypeHints {
+                imports: vec![ExternType::Function(FunctionType::new(
+                    vec![Type::I32],
+                    vec![Type::I32],
+                ))],
+                exports: vec![ExternType::Function(FunctionType::new(
+                    vec![Type::I32],
+                    vec![Type::I32],
+                ))],
+            })
+            .unwrap();
+
+        let signature = FunctionType::new(vec![Type::I32], vec![Type::I32]);
+        let double = Function::new(&store, &signature, |args| {
+            Ok(vec![Value::I32(args[0].unwrap_i32() * 2)])
+        });
+
+        let mut import_object = imports! {
+            "shim" => {
+                "double" => double,
+            }
+        };
+        import_object
+            .alias(
+                "shim",
+                "double",
+                "env",
+                "twice",
+                &ExternType::Function(signature),
+            )
+            .unwrap();
+        assert!(import_object
+            .alias(
+                "shim",
+                "double",
+                "env",
+                "other",
+                &ExternType::Function(FunctionType::new(vec![Type::I64], vec![Type::I32])),
+            )
+            .is_err());
+
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        let exported = instance.exports.get_function("exported").unwrap();
+
+        let expected = vec![Val::I32(6)].into_boxed_slice();
+        assert_eq!(exported.call(&[Val::I32(3)]), Ok(expected));
+    }
 }
@@ -179,6 +179,103 @@ mod sys {
         Ok(())
     }
 
+    #[test]
+    fn memory_view() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+
+        let view: MemoryView<u8> = memory.view();
+        assert_eq!(view.len(), 65536);
+        unsafe { view.subarray(0x10, 0x14).copy_from(&[1, 2, 3, 4]) };
+
+        let mut bytes = [0; 6];
+        view.subarray(0xf, 0x15).copy_to(&mut bytes);
+        assert_eq!(bytes, [0, 1, 2, 3, 4, 0]);
+
+        let view: MemoryView<u32> = memory.view();
+        assert_eq!(view.len(), 16384);
+        let mut values = [0; 1];
+        view.subarray(4, 5).copy_to(&mut values);
+        assert_eq!(values, [u32::from_le_bytes([1, 2, 3, 4])]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_read_write() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, Some(2), false))?;
+
+        memory.write(65532, &[1, 2, 3, 4])?;
+        let mut bytes = [0; 4];
+        memory.read(65532, &mut bytes)?;
+        assert_eq!(bytes, [1, 2, 3, 4]);
+
+        let mut bytes = [0; 5];
+        assert_eq!(
+            memory.read(65532, &mut bytes),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.write(65533, &[0; 4]),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+        assert_eq!(
+            memory.read(u64::MAX, &mut bytes),
+            Err(MemoryAccessError::Overflow)
+        );
+
+        // The accesses are checked against the grown memory.
+        memory.grow(1)?;
+        memory.read(65532, &mut bytes)?;
+        assert_eq!(bytes, [1, 2, 3, 4, 0]);
+
+        // Unaligned accesses of every length are copied in chunks.
+        let data = (0..=255).collect::<Vec<u8>>();
+        for len in 0..20 {
+            memory.write(3, &data[..len])?;
+            let mut bytes = vec![0; len];
+            memory.read(3, &mut bytes)?;
+            assert_eq!(bytes, &data[..len]);
+            let mut uninit = vec![std::mem::MaybeUninit::uninit(); len];
+            assert_eq!(memory.read_uninit(3, &mut uninit)?, &data[..len]);
+        }
+        let mut uninit = [std::mem::MaybeUninit::uninit(); 5];
+        assert_eq!(
+            memory.read_uninit(131068, &mut uninit),
+            Err(MemoryAccessError::HeapOutOfBounds)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_view_subarray_bounds() -> Result<()> {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false))?;
+        let view: MemoryView<u8> = memory.view();
+
+        // `end` is exclusive, so it may be equal to the length of the
+        // view, to reach the last byte of the memory or to view all of it.
+        unsafe { view.subarray(65534, 65536).copy_from(&[1, 2]) };
+        let mut bytes = [0; 2];
+        view.subarray(65534, 65536).copy_to(&mut bytes);
+        assert_eq!(bytes, [1, 2]);
+        assert_eq!(view.subarray(0, 65536).len(), 65536);
+        assert!(view.subarray(65536, 65536).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "The range end is bigger than current length")]
+    fn memory_view_subarray_out_of_bounds() {
+        let store = Store::default();
+        let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+        let view: MemoryView<u8> = memory.view();
+        view.subarray(65535, 65537);
+    }
+
     #[test]
     fn function_new() -> Result<()> {
         let store = Store::default();
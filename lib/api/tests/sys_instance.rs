@@ -67,4 +67,157 @@ mod sys {
 
         Ok(())
     }
+
+    #[test]
+    fn instance_as_namespace_with_aliases() -> Result<()> {
+        let store = Store::default();
+        let shim = Module::new(
+            &store,
+            "
+    (module
+      (func (export \"double\") (param i32) (result i32)
+        local.get 0
+        i32.const 2
+        i32.mul))
+",
+        )?;
+        let shim = Instance::new(&shim, &ImportObject::new())?;
+
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (import \"shim\" \"double\" (func $double (param i32) (result i32)))
+      (import \"env\" \"twice\" (func $twice (param i32) (result i32)))
+      (func (export \"run\") (param i32) (result i32)
+        local.get 0
+        call $double
+        call $twice))
+",
+        )?;
+
+        let mut import_object = ImportObject::new();
+        import_object.register_instance("shim", &shim);
+
+        let ty = ExternType::Function(FunctionType::new([Type::I32], [Type::I32]));
+        import_object.alias("shim", "double", "env", "twice", &ty)?;
+        assert!(import_object.contains_namespace("env"));
+
+        let wrong_ty = ExternType::Function(FunctionType::new([Type::I64], [Type::I32]));
+        assert!(matches!(
+            import_object.alias("shim", "double", "env", "other", &wrong_ty),
+            Err(LinkError::Import(_, _, ImportError::IncompatibleType(..)))
+        ));
+        assert!(matches!(
+            import_object.alias("shim", "missing", "env", "other", &ty),
+            Err(LinkError::Import(_, _, ImportError::UnknownImport(_)))
+        ));
+
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.exports.get_function("run")?;
+        assert_eq!(run.call(&[Value::I32(3)])?.into_vec(), vec![Value::I32(12)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pointer_width_adapters() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (import \"env\" \"offset\" (func $offset (param i64) (result i64)))
+      (func (export \"run\") (param i64) (result i64)
+        local.get 0
+        call $offset))
+",
+        )?;
+
+        let import_object = imports! {
+            "env" => {
+                "offset" => Function::new(
+                    &store,
+                    FunctionType::new([Type::I32], [Type::I32]),
+                    |args| Ok(vec![Value::I32(args[0].unwrap_i32() + 8)]),
+                ),
+            },
+        };
+
+        assert!(Instance::new(&module, &import_object).is_err());
+
+        let mut import_object = import_object;
+        assert_eq!(import_object.adapt_pointer_widths(&module), 1);
+
+        let instance = Instance::new(&module, &import_object)?;
+        let run = instance.exports.get_function("run")?;
+        assert_eq!(
+            run.call(&[Value::I64(0x8000_0000)])?.into_vec(),
+            vec![Value::I64(0x8000_0008)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pointer_width_adapters_extend_and_check_integers() -> Result<()> {
+        let store = Store::default();
+        let module = Module::new(
+            &store,
+            "
+    (module
+      (import \"env\" \"pointer\" (func $pointer (param i64) (result i64)))
+      (import \"env\" \"errno\" (func $errno (param i64) (result i64)))
+      (func (export \"pointer\") (param i64) (result i64)
+        local.get 0
+        call $pointer)
+      (func (export \"errno\") (param i64) (result i64)
+        local.get 0
+        call $errno))
+",
+        )?;
+
+        let identity = |store| {
+            Function::new(store, FunctionType::new([Type::I32], [Type::I32]), |args| {
+                Ok(vec![args[0].clone()])
+            })
+        };
+        let mut import_object = imports! {
+            "env" => {
+                "pointer" => identity(&store),
+                "errno" => identity(&store),
+            },
+        };
+
+        assert_eq!(
+            import_object.adapt_pointer_widths_with(&module, |import, _| {
+                if import.name() == "errno" {
+                    IntegerExtension::Sign
+                } else {
+                    IntegerExtension::Zero
+                }
+            }),
+            2
+        );
+
+        let instance = Instance::new(&module, &import_object)?;
+        let pointer = instance.exports.get_function("pointer")?;
+        let errno = instance.exports.get_function("errno")?;
+
+        assert_eq!(
+            pointer.call(&[Value::I64(-1)])?.into_vec(),
+            vec![Value::I64(0xFFFF_FFFF)]
+        );
+        assert_eq!(
+            errno.call(&[Value::I64(-1)])?.into_vec(),
+            vec![Value::I64(-1)]
+        );
+
+        // Integers which don't fit in 32 bits trap instead of being
+        // truncated.
+        let error = pointer.call(&[Value::I64(0x1_0000_0000)]).unwrap_err();
+        assert!(error.message().contains("doesn't fit in 32 bits"));
+
+        Ok(())
+    }
 }
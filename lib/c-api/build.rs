@@ -49,6 +49,9 @@ const WASI_FEATURE_AS_C_DEFINE: &'static str = "WASMER_WASI_ENABLED";
 #[allow(unused)]
 const MIDDLEWARES_FEATURE_AS_C_DEFINE: &'static str = "WASMER_MIDDLEWARES_ENABLED";
 
+#[allow(unused)]
+const CACHE_FEATURE_AS_C_DEFINE: &'static str = "WASMER_CACHE_ENABLED";
+
 #[allow(unused)]
 const EMSCRIPTEN_FEATURE_AS_C_DEFINE: &'static str = "WASMER_EMSCRIPTEN_ENABLED";
 
@@ -144,6 +147,7 @@ fn build_wasm_c_api_headers(crate_dir: &str, out_dir: &str) {
     map_feature_as_c_define!("compiler", COMPILER_FEATURE_AS_C_DEFINE, pre_header);
     map_feature_as_c_define!("wasi", WASI_FEATURE_AS_C_DEFINE, pre_header);
     map_feature_as_c_define!("middlewares", MIDDLEWARES_FEATURE_AS_C_DEFINE, pre_header);
+    map_feature_as_c_define!("cache", CACHE_FEATURE_AS_C_DEFINE, pre_header);
     map_feature_as_c_define!("emscripten", EMSCRIPTEN_FEATURE_AS_C_DEFINE, pre_header);
 
     add_wasmer_version(&mut pre_header);
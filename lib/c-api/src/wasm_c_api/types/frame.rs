@@ -4,7 +4,7 @@ use wasmer_api::FrameInfo;
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone)]
 pub struct wasm_frame_t {
-    info: FrameInfo,
+    pub(crate) info: FrameInfo,
 }
 
 impl<'a> From<&'a FrameInfo> for wasm_frame_t {
@@ -25,6 +25,15 @@ pub type wasm_byte_t = u8;
 
 wasm_declare_vec!(byte);
 
+impl Default for wasm_byte_vec_t {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            data: std::ptr::null_mut(),
+        }
+    }
+}
+
 #[allow(non_camel_case_types)]
 pub type wasm_name_t = wasm_byte_vec_t;
 
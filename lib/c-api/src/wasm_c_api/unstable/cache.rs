@@ -0,0 +1,237 @@
+//! Unstable non-standard Wasmer-specific API to cache compiled
+//! modules on the file system.
+//!
+//! A cache is a directory where modules are stored, serialized, under
+//! a [hash][wasmer_hash_t] of their WebAssembly bytes. Loading a
+//! module from the cache skips its compilation, and works with a
+//! headless engine.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use inline_c::assert_c;
+//! # fn main() {
+//! #    (assert_c! {
+//! # #include "tests/wasmer.h"
+//! #
+//! int main() {
+//!     // Create the engine and the store.
+//!     wasm_engine_t* engine = wasm_engine_new();
+//!     wasm_store_t* store = wasm_store_new(engine);
+//!
+//!     // Create a WebAssembly module from a WAT definition.
+//!     wasm_byte_vec_t wat;
+//!     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"f\")))");
+//!     wasm_byte_vec_t wasm;
+//!     wat2wasm(&wat, &wasm);
+//!
+//!     // Open the cache, and hash the module.
+//!     wasmer_cache_t* cache = wasmer_cache_open("target/c-api-cache-example");
+//!     assert(cache);
+//!
+//!     wasmer_hash_t* hash = wasmer_hash_generate(&wasm);
+//!
+//!     // The module is not in the cache yet, so let's compile it,
+//!     // and store it.
+//!     wasm_module_t* module = wasmer_cache_load(cache, store, hash);
+//!
+//!     if (!module) {
+//!         module = wasm_module_new(store, &wasm);
+//!         assert(module);
+//!         assert(wasmer_cache_store(cache, hash, module));
+//!     }
+//!
+//!     wasm_module_delete(module);
+//!
+//!     // Now, the module can be loaded without being compiled.
+//!     module = wasmer_cache_load(cache, store, hash);
+//!     assert(module);
+//!
+//!     // Free everything.
+//!     wasm_module_delete(module);
+//!     wasmer_hash_delete(hash);
+//!     wasmer_cache_delete(cache);
+//!     wasm_byte_vec_delete(&wasm);
+//!     wasm_byte_vec_delete(&wat);
+//!     wasm_store_delete(store);
+//!     wasm_engine_delete(engine);
+//!
+//!     return 0;
+//! }
+//! #    })
+//! #    .success();
+//! # }
+//! ```
+
+use super::super::module::wasm_module_t;
+use super::super::store::wasm_store_t;
+use super::super::types::{wasm_byte_vec_t, wasm_name_t};
+use crate::error::update_last_error;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::str::{self, FromStr};
+use std::sync::Arc;
+use wasmer_cache::{Cache, FileSystemCache, Hash};
+
+/// Opaque type representing the hash of a module, used as a key in a
+/// [cache][wasmer_cache_t].
+///
+/// # Example
+///
+/// See the module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_hash_t {
+    inner: Hash,
+}
+
+/// Hashes WebAssembly bytes, to use them as a key in a cache.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_hash_generate(bytes: &wasm_byte_vec_t) -> Box<wasmer_hash_t> {
+    Box::new(wasmer_hash_t {
+        inner: Hash::generate(bytes.as_slice()),
+    })
+}
+
+/// Parses a hash from its hexadecimal representation, as written by
+/// [`wasmer_hash_to_string`].
+///
+/// It returns `NULL` if the string isn't a valid hash; the error can
+/// be read with `wasmer_last_error_message`.
+#[no_mangle]
+pub extern "C" fn wasmer_hash_from_string(string: &wasm_name_t) -> Option<Box<wasmer_hash_t>> {
+    let string = c_try!(str::from_utf8(string.as_slice()));
+    let hash = c_try!(Hash::from_str(string));
+
+    Some(Box::new(wasmer_hash_t { inner: hash }))
+}
+
+/// Writes the hexadecimal representation of a hash to `out`. It's
+/// also the name of the file of the module in the cache.
+#[no_mangle]
+pub extern "C" fn wasmer_hash_to_string(
+    hash: &wasmer_hash_t,
+    // own
+    out: &mut wasm_name_t,
+) {
+    out.set_buffer(hash.inner.to_string().into_bytes());
+}
+
+/// Deletes a hash.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_hash_delete(_hash: Option<Box<wasmer_hash_t>>) {}
+
+/// Opaque type representing a cache of modules in a directory.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_cache_t {
+    inner: FileSystemCache,
+}
+
+/// Opens a cache in the given directory, which is created if it
+/// doesn't exist.
+///
+/// It returns `NULL` if `path` is `NULL` or isn't valid UTF-8, or if
+/// the directory can't be created; the error can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_cache_open(path: *const c_char) -> Option<Box<wasmer_cache_t>> {
+    if path.is_null() {
+        update_last_error("`path` must not be `NULL`");
+
+        return None;
+    }
+
+    let path = c_try!(CStr::from_ptr(path).to_str());
+    let cache = c_try!(FileSystemCache::new(path));
+
+    Some(Box::new(wasmer_cache_t { inner: cache }))
+}
+
+/// Sets the extension of the files of the cache, e.g. `"wasmu"`.
+/// There is no extension by default. A `NULL` extension removes it.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_cache_set_extension(
+    cache: &mut wasmer_cache_t,
+    extension: *const c_char,
+) -> bool {
+    if extension.is_null() {
+        cache.inner.set_cache_extension(None::<String>);
+
+        return true;
+    }
+
+    let extension = c_try!(CStr::from_ptr(extension).to_str(); otherwise false);
+    cache.inner.set_cache_extension(Some(extension));
+
+    true
+}
+
+/// Loads a module from the cache, with the engine of the given store.
+///
+/// It returns `NULL` if the module isn't in the cache, or if it can't
+/// be deserialized, e.g. because it has been compiled for another
+/// engine or target; the error can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Safety
+///
+/// The module is deserialized without being validated: the cache
+/// directory must be trusted.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_cache_load(
+    cache: &wasmer_cache_t,
+    store: &wasm_store_t,
+    hash: &wasmer_hash_t,
+) -> Option<Box<wasm_module_t>> {
+    let module = c_try!(cache.inner.load(&store.inner, hash.inner));
+
+    Some(Box::new(wasm_module_t {
+        inner: Arc::new(module),
+    }))
+}
+
+/// Stores a module in the cache.
+///
+/// It returns `false` if the module can't be serialized or written;
+/// the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_cache_store(
+    cache: &mut wasmer_cache_t,
+    hash: &wasmer_hash_t,
+    module: &wasm_module_t,
+) -> bool {
+    c_try!(cache.inner.store(hash.inner, &module.inner); otherwise false);
+
+    true
+}
+
+/// Deletes a cache. The cached modules are kept in the directory.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_cache_delete(_cache: Option<Box<wasmer_cache_t>>) {}
@@ -0,0 +1,150 @@
+//! Unstable non-standard Wasmer-specific extensions to the Wasm C API
+//! to get more information about the frames of a trap.
+
+use super::super::types::{wasm_frame_t, wasm_name_t};
+use std::ptr;
+
+/// Writes `name` to `out`, otherwise `out->size` is set to `0` and
+/// `out->data` to `NULL`.
+fn set_optional_name(out: &mut wasm_name_t, name: Option<&str>) {
+    match name {
+        Some(name) => out.set_buffer(name.as_bytes().to_vec()),
+        None => {
+            out.data = ptr::null_mut();
+            out.size = 0;
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to get the name of the
+/// function of a frame, as found in the name section of the module,
+/// otherwise `out->size` is set to `0` and `out->data` to `NULL`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module $moduleName\n"
+///         "  (func $crash (export \"crash\")\n"
+///         "    unreachable))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module and instantiate it.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+///     assert(instance);
+///
+///     // Call the function, which traps.
+///     wasm_name_t export_name;
+///     wasmer_byte_vec_new_from_string(&export_name, "crash");
+///     wasm_func_t* crash = wasmer_instance_export_function(instance, &export_name);
+///     assert(crash);
+///
+///     wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results = WASM_EMPTY_VEC;
+///     wasm_trap_t* trap = wasm_func_call(crash, &arguments, &results);
+///     assert(trap);
+///
+///     // Read the names of the origin frame.
+///     wasm_frame_t* frame = wasm_trap_origin(trap);
+///     assert(frame);
+///
+///     wasm_name_t function_name;
+///     wasmer_frame_function_name(frame, &function_name);
+///     wasmer_assert_name(&function_name, "crash");
+///
+///     wasm_name_t module_name;
+///     wasmer_frame_module_name(frame, &module_name);
+///     wasmer_assert_name(&module_name, "moduleName");
+///
+///     // Free everything.
+///     wasm_byte_vec_delete(&module_name);
+///     wasm_byte_vec_delete(&function_name);
+///     wasm_frame_delete(frame);
+///     wasm_trap_delete(trap);
+///     wasm_func_delete(crash);
+///     wasm_byte_vec_delete(&export_name);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_function_name(
+    frame: &wasm_frame_t,
+    // own
+    out: &mut wasm_name_t,
+) {
+    set_optional_name(out, frame.info.function_name());
+}
+
+/// Unstable non-standard Wasmer-specific API to get the name of the
+/// module of a frame.
+///
+/// If the module has no name, it's `<module>`.
+///
+/// # Example
+///
+/// See [`wasmer_frame_function_name`].
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_module_name(
+    frame: &wasm_frame_t,
+    // own
+    out: &mut wasm_name_t,
+) {
+    out.set_buffer(frame.info.module_name().as_bytes().to_vec());
+}
+
+/// Unstable non-standard Wasmer-specific API to get the source file
+/// of a frame, from the DWARF debug information of the module,
+/// otherwise `out->size` is set to `0` and `out->data` to `NULL`.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_source_file(
+    frame: &wasm_frame_t,
+    // own
+    out: &mut wasm_name_t,
+) {
+    set_optional_name(out, frame.info.source_file());
+}
+
+/// Unstable non-standard Wasmer-specific API to get the source line
+/// of a frame, from the DWARF debug information of the module.
+///
+/// Lines start at `1`, `0` means that the line is unknown.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_line(frame: &wasm_frame_t) -> u32 {
+    frame.info.line().unwrap_or(0)
+}
+
+/// Unstable non-standard Wasmer-specific API to get the source column
+/// of a frame, from the DWARF debug information of the module.
+///
+/// Columns start at `1`, `0` means that the column is unknown.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_frame_column(frame: &wasm_frame_t) -> u32 {
+    frame.info.column().unwrap_or(0)
+}
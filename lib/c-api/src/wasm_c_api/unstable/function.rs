@@ -0,0 +1,505 @@
+//! Unstable non-standard Wasmer-specific API to create host functions
+//! that can access the exports of the instance calling them, e.g. its
+//! memory, like `#[wasmer(export)]` fields of a `WasmerEnv` in Rust.
+//!
+//! A host function created with [`wasmer_func_new_with_caller`]
+//! receives a [`wasmer_caller_t`], representing the instance that
+//! imports the function. The exports of this instance are looked up
+//! lazily, when the host function asks for them, so no global state
+//! is needed to find the instance.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use inline_c::assert_c;
+//! # fn main() {
+//! #    (assert_c! {
+//! # #include "tests/wasmer.h"
+//! #
+//! // A host function reading a string from the memory of the
+//! // caller, and calling back one of its functions.
+//! wasm_trap_t* greet(const wasmer_caller_t* caller, void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+//!     int32_t offset = args->data[0].of.i32;
+//!     int32_t length = args->data[1].of.i32;
+//!
+//!     char buffer[16] = { 0 };
+//!     assert(length < 16);
+//!
+//!     if (!wasmer_caller_memory_read(caller, offset, (uint8_t*) buffer, length)) {
+//!         wasm_name_t message;
+//!         wasm_name_new_from_string_nt(&message, "out of bounds");
+//!         wasm_trap_t* trap = wasm_trap_new(NULL, &message);
+//!         wasm_name_delete(&message);
+//!
+//!         return trap;
+//!     }
+//!
+//!     assert(strcmp(buffer, "Hello") == 0);
+//!
+//!     // Write the result back, right after the string.
+//!     wasmer_caller_memory_write(caller, offset + length, (const uint8_t*) ", World!", 8);
+//!
+//!     // Call an exported function of the caller.
+//!     wasm_name_t name;
+//!     wasmer_byte_vec_new_from_string(&name, "seven");
+//!     wasm_func_t* seven = wasmer_caller_export_function(caller, &name);
+//!     wasm_name_delete(&name);
+//!     assert(seven);
+//!
+//!     wasm_val_t seven_results_val[1] = { WASM_INIT_VAL };
+//!     wasm_val_vec_t seven_arguments = WASM_EMPTY_VEC;
+//!     wasm_val_vec_t seven_results = WASM_ARRAY_VEC(seven_results_val);
+//!     assert(wasm_func_call(seven, &seven_arguments, &seven_results) == NULL);
+//!     wasm_func_delete(seven);
+//!
+//!     results->data[0].kind = WASM_I32;
+//!     results->data[0].of.i32 = seven_results_val[0].of.i32 + *((int32_t*) env);
+//!
+//!     return NULL;
+//! }
+//!
+//! int main() {
+//!     // Create the engine and the store.
+//!     wasm_engine_t* engine = wasm_engine_new();
+//!     wasm_store_t* store = wasm_store_new(engine);
+//!
+//!     // Create a WebAssembly module from a WAT definition.
+//!     wasm_byte_vec_t wat;
+//!     wasmer_byte_vec_new_from_string(
+//!         &wat,
+//!         "(module\n"
+//!         "  (import \"host\" \"greet\" (func $greet (param i32 i32) (result i32)))\n"
+//!         "  (memory (export \"memory\") 1)\n"
+//!         "  (data (i32.const 0) \"Hello\")\n"
+//!         "  (func (export \"seven\") (result i32)\n"
+//!         "    i32.const 7)\n"
+//!         "  (func (export \"run\") (result i32)\n"
+//!         "    (call $greet (i32.const 0) (i32.const 5))))"
+//!     );
+//!     wasm_byte_vec_t wasm;
+//!     wat2wasm(&wat, &wasm);
+//!
+//!     wasm_module_t* module = wasm_module_new(store, &wasm);
+//!     assert(module);
+//!
+//!     // Create the host function.
+//!     wasm_functype_t* greet_type = wasm_functype_new_2_1(
+//!         wasm_valtype_new_i32(),
+//!         wasm_valtype_new_i32(),
+//!         wasm_valtype_new_i32()
+//!     );
+//!     int32_t env = 35;
+//!     wasm_func_t* greet_function = wasmer_func_new_with_caller(store, greet_type, greet, &env, NULL);
+//!     assert(greet_function);
+//!     wasm_functype_delete(greet_type);
+//!
+//!     // Instantiate the module.
+//!     wasm_extern_t* externs[] = { wasm_func_as_extern(greet_function) };
+//!     wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+//!     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+//!     assert(instance);
+//!
+//!     // Run the module.
+//!     wasm_name_t run_name;
+//!     wasmer_byte_vec_new_from_string(&run_name, "run");
+//!     wasm_func_t* run = wasmer_instance_export_function(instance, &run_name);
+//!     assert(run);
+//!
+//!     wasm_val_t results_val[1] = { WASM_INIT_VAL };
+//!     wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+//!     wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+//!     assert(wasm_func_call(run, &arguments, &results) == NULL);
+//!     assert(results_val[0].of.i32 == 42);
+//!
+//!     // Free everything.
+//!     wasm_func_delete(run);
+//!     wasm_byte_vec_delete(&run_name);
+//!     wasm_instance_delete(instance);
+//!     wasm_func_delete(greet_function);
+//!     wasm_module_delete(module);
+//!     wasm_byte_vec_delete(&wasm);
+//!     wasm_byte_vec_delete(&wat);
+//!     wasm_store_delete(store);
+//!     wasm_engine_delete(engine);
+//!
+//!     return 0;
+//! }
+//! #    })
+//! #    .success();
+//! # }
+//! ```
+
+use super::super::externals::{wasm_env_finalizer_t, wasm_func_t, wasm_memory_t};
+use super::super::store::wasm_store_t;
+use super::super::trap::wasm_trap_t;
+use super::super::types::{wasm_functype_t, wasm_name_t, wasm_valkind_enum};
+use super::super::value::{wasm_val_inner, wasm_val_t, wasm_val_vec_t};
+use crate::error::update_last_error;
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::slice;
+use std::str;
+use std::sync::Arc;
+use wasmer_api::{
+    Exportable, Exports, Function, HostEnvInitError, Instance, LazyInit, Memory, RuntimeError, Val,
+    WasmerEnv,
+};
+
+/// Name of the memory accessed by [`wasmer_caller_memory_read`] and
+/// [`wasmer_caller_memory_write`], like in WASI.
+const MEMORY_EXPORT_NAME: &str = "memory";
+
+/// Opaque type representing the instance calling a host function
+/// created with [`wasmer_func_new_with_caller`].
+///
+/// It is only valid during the call of the host function.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[allow(non_camel_case_types)]
+#[derive(Clone)]
+pub struct wasmer_caller_t {
+    /// The exports of the instance, holding weak references to it, to
+    /// prevent a cycle between the instance and its imported function.
+    exports: LazyInit<Exports>,
+}
+
+impl wasmer_caller_t {
+    fn exports(&self) -> Result<&Exports, &'static str> {
+        self.exports
+            .get_ref()
+            .ok_or("the function has not been imported by an instance")
+    }
+
+    fn memory(&self) -> Result<&Memory, String> {
+        self.exports()?
+            .get_memory(MEMORY_EXPORT_NAME)
+            .map_err(|error| error.to_string())
+    }
+}
+
+/// Function type of the callback of a host function created with
+/// [`wasmer_func_new_with_caller`].
+///
+/// # Example
+///
+/// See the module's documentation.
+#[allow(non_camel_case_types)]
+pub type wasmer_func_callback_with_caller_t = unsafe extern "C" fn(
+    caller: &wasmer_caller_t,
+    env: *mut c_void,
+    args: &wasm_val_vec_t,
+    results: &mut wasm_val_vec_t,
+) -> Option<Box<wasm_trap_t>>;
+
+/// Calls the finalizer of the environment once, when the last clone
+/// of the function is dropped.
+struct EnvFinalizer {
+    env: *mut c_void,
+    env_finalizer: Option<wasm_env_finalizer_t>,
+}
+
+impl Drop for EnvFinalizer {
+    fn drop(&mut self) {
+        if let Some(env_finalizer) = self.env_finalizer {
+            unsafe { (env_finalizer)(self.env) }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CallerEnv {
+    env: *mut c_void,
+    _finalizer: Arc<EnvFinalizer>,
+    caller: wasmer_caller_t,
+}
+
+// Only relevant when using multiple threads in the C API;
+// Synchronization will be done via the C API / on the C side.
+unsafe impl Send for CallerEnv {}
+unsafe impl Sync for CallerEnv {}
+
+impl WasmerEnv for CallerEnv {
+    fn init_with_instance(&mut self, instance: &Instance) -> Result<(), HostEnvInitError> {
+        let mut exports = Exports::new();
+
+        for (name, export) in instance.exports.iter() {
+            let mut export = export.clone();
+            export.into_weak_instance_ref();
+            exports.insert(name.clone(), export);
+        }
+
+        self.caller.exports.initialize(exports);
+
+        Ok(())
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to create a host
+/// function like `wasm_func_new_with_env`, whose callback also
+/// receives a [`wasmer_caller_t`] to access the exports of the
+/// instance calling it.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_func_new_with_caller(
+    store: Option<&wasm_store_t>,
+    function_type: Option<&wasm_functype_t>,
+    callback: wasmer_func_callback_with_caller_t,
+    env: *mut c_void,
+    env_finalizer: Option<unsafe extern "C" fn(env: *mut c_void)>,
+) -> Option<Box<wasm_func_t>> {
+    let store = store?;
+    let function_type = function_type?;
+
+    let func_sig = &function_type.inner().function_type;
+    let num_rets = func_sig.results().len();
+
+    let trampoline = move |env: &CallerEnv, args: &[Val]| -> Result<Vec<Val>, RuntimeError> {
+        let processed_args: wasm_val_vec_t = args
+            .iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<wasm_val_t>, _>>()
+            .expect("Argument conversion failed")
+            .into();
+
+        let mut results: wasm_val_vec_t = vec![
+            wasm_val_t {
+                kind: wasm_valkind_enum::WASM_I64 as _,
+                of: wasm_val_inner { int64_t: 0 },
+            };
+            num_rets
+        ]
+        .into();
+
+        let trap = callback(&env.caller, env.env, &processed_args, &mut results);
+
+        if let Some(trap) = trap {
+            return Err(trap.inner);
+        }
+
+        let processed_results = results
+            .take()
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<Val>, _>>()
+            .expect("Result conversion failed");
+
+        Ok(processed_results)
+    };
+
+    let function = Function::new_with_env(
+        &store.inner,
+        func_sig,
+        CallerEnv {
+            env,
+            _finalizer: Arc::new(EnvFinalizer { env, env_finalizer }),
+            caller: wasmer_caller_t {
+                exports: LazyInit::new(),
+            },
+        },
+        trampoline,
+    );
+
+    Some(Box::new(wasm_func_t::new(function)))
+}
+
+/// Unstable non-standard Wasmer-specific API to get an exported
+/// function of the caller by its name.
+///
+/// It returns `NULL` if there is no function exported with this name;
+/// the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_export_function(
+    caller: &wasmer_caller_t,
+    name: &wasm_name_t,
+) -> Option<Box<wasm_func_t>> {
+    let name = c_try!(str::from_utf8(name.as_slice()));
+    let function = c_try!(c_try!(caller.exports()).get_function(name));
+
+    Some(Box::new(wasm_func_t::new(function.clone())))
+}
+
+/// Unstable non-standard Wasmer-specific API to get an exported
+/// memory of the caller by its name.
+///
+/// It returns `NULL` if there is no memory exported with this name;
+/// the error can be read with `wasmer_last_error_message`.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_export_memory(
+    caller: &wasmer_caller_t,
+    name: &wasm_name_t,
+) -> Option<Box<wasm_memory_t>> {
+    let name = c_try!(str::from_utf8(name.as_slice()));
+    let memory = c_try!(c_try!(caller.exports()).get_memory(name));
+
+    Some(Box::new(wasm_memory_t::new(memory.clone())))
+}
+
+/// Unstable non-standard Wasmer-specific API to copy `length` bytes
+/// at `offset` in the memory exported as `"memory"` by the caller
+/// into `buffer`.
+///
+/// It returns `false` if the caller has no such memory, if the bytes
+/// are out of its bounds, or if `buffer` is `NULL` while `length`
+/// isn't 0; the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_memory_read(
+    caller: &wasmer_caller_t,
+    offset: u64,
+    buffer: *mut u8,
+    length: usize,
+) -> bool {
+    let memory = c_try!(caller.memory(); otherwise false);
+
+    if length == 0 {
+        return true;
+    }
+
+    if buffer.is_null() {
+        update_last_error("`buffer` must not be `NULL`");
+
+        return false;
+    }
+
+    let buffer = slice::from_raw_parts_mut(buffer, length);
+    c_try!(memory.read(offset, buffer); otherwise false);
+
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to copy `length` bytes
+/// from `buffer` at `offset` in the memory exported as `"memory"` by
+/// the caller.
+///
+/// It returns `false` if the caller has no such memory, if the bytes
+/// are out of its bounds, or if `buffer` is `NULL` while `length`
+/// isn't 0; the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See the module's documentation.
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_caller_memory_write(
+    caller: &wasmer_caller_t,
+    offset: u64,
+    buffer: *const u8,
+    length: usize,
+) -> bool {
+    let memory = c_try!(caller.memory(); otherwise false);
+
+    if length == 0 {
+        return true;
+    }
+
+    if buffer.is_null() {
+        update_last_error("`buffer` must not be `NULL`");
+
+        return false;
+    }
+
+    let buffer = slice::from_raw_parts(buffer, length);
+    c_try!(memory.write(offset, buffer); otherwise false);
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use inline_c::assert_c;
+
+    #[test]
+    fn test_wasmer_caller_memory_null_buffer() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <string.h>
+
+            void assert_last_error(const char* expected) {
+                char message[64] = { 0 };
+                assert(wasmer_last_error_length() == (int) strlen(expected) + 1);
+                wasmer_last_error_message(message, 64);
+                assert(strcmp(message, expected) == 0);
+            }
+
+            wasm_trap_t* access(const wasmer_caller_t* caller, void* env, const wasm_val_vec_t* args, wasm_val_vec_t* results) {
+                (void) env;
+                (void) args;
+                (void) results;
+
+                assert(!wasmer_caller_memory_read(caller, 0, NULL, 5));
+                assert_last_error("`buffer` must not be `NULL`");
+                assert(!wasmer_caller_memory_write(caller, 0, NULL, 5));
+                assert_last_error("`buffer` must not be `NULL`");
+
+                // nothing is copied from or into an empty buffer
+                assert(wasmer_caller_memory_read(caller, 0, NULL, 0));
+                assert(wasmer_caller_memory_write(caller, 0, NULL, 0));
+
+                return NULL;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"host\" \"access\" (func $access))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (func (export \"run\")\n"
+                    "    (call $access)))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasm_functype_t* access_type = wasm_functype_new_0_0();
+                wasm_func_t* access_function = wasmer_func_new_with_caller(store, access_type, access, NULL, NULL);
+                assert(access_function);
+                wasm_functype_delete(access_type);
+
+                wasm_extern_t* externs[] = { wasm_func_as_extern(access_function) };
+                wasm_extern_vec_t imports = WASM_ARRAY_VEC(externs);
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_name_t run_name;
+                wasmer_byte_vec_new_from_string(&run_name, "run");
+                wasm_func_t* run = wasmer_instance_export_function(instance, &run_name);
+                assert(run);
+
+                wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                assert(wasm_func_call(run, &arguments, &results) == NULL);
+
+                wasm_func_delete(run);
+                wasm_byte_vec_delete(&run_name);
+                wasm_instance_delete(instance);
+                wasm_func_delete(access_function);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
+}
@@ -0,0 +1,91 @@
+//! Unstable non-standard Wasmer-specific extensions to the Wasm C API
+//! for instances.
+
+use super::super::externals::wasm_func_t;
+use super::super::instance::wasm_instance_t;
+use super::super::types::wasm_name_t;
+use std::str;
+use wasmer_api::Function;
+
+/// Unstable non-standard Wasmer-specific API to get an exported
+/// function of the instance by its name.
+///
+/// It returns `NULL` if there is no function exported with this name;
+/// the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (global (export \"global\") i32 (i32.const 7))\n"
+///         "  (func (export \"forty_two\") (result i32)\n"
+///         "    i32.const 42))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module and instantiate it.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+///     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+///     assert(instance);
+///
+///     // Look up the function by its name.
+///     wasm_name_t name;
+///     wasmer_byte_vec_new_from_string(&name, "forty_two");
+///     wasm_func_t* forty_two = wasmer_instance_export_function(instance, &name);
+///     assert(forty_two);
+///
+///     wasm_val_t results_val[1] = { WASM_INIT_VAL };
+///     wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+///     wasm_val_vec_t results = WASM_ARRAY_VEC(results_val);
+///     assert(wasm_func_call(forty_two, &arguments, &results) == NULL);
+///     assert(results_val[0].of.i32 == 42);
+///
+///     // A global is not a function.
+///     wasm_name_t global_name;
+///     wasmer_byte_vec_new_from_string(&global_name, "global");
+///     assert(wasmer_instance_export_function(instance, &global_name) == NULL);
+///     assert(wasmer_last_error_length() > 0);
+///
+///     // Free everything.
+///     wasm_byte_vec_delete(&global_name);
+///     wasm_func_delete(forty_two);
+///     wasm_byte_vec_delete(&name);
+///     wasm_instance_delete(instance);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_instance_export_function(
+    instance: &wasm_instance_t,
+    name: &wasm_name_t,
+) -> Option<Box<wasm_func_t>> {
+    let name = c_try!(str::from_utf8(name.as_slice()));
+    let function = c_try!(instance.inner.exports.get::<Function>(name));
+
+    Some(Box::new(wasm_func_t::new(function.clone())))
+}
@@ -0,0 +1,632 @@
+//! Unstable non-standard Wasmer-specific API to write custom
+//! middlewares in C, like a `ModuleMiddleware` in Rust.
+//!
+//! A custom middleware is made of two callbacks:
+//!
+//! * a _feed_ callback, called for every operator of every function
+//!   of the module, with a [`wasmer_middleware_state_t`]. The
+//!   operator is dropped unless the callback pushes it back with
+//!   [`wasmer_middleware_state_push_current`]. Other operators can be
+//!   injected before or after it, with the
+//!   `wasmer_middleware_state_push_*` functions,
+//! * an optional _transform module info_ callback, called once before
+//!   the functions are fed, with a [`wasmer_module_info_t`] to
+//!   declare new globals for instance.
+//!
+//! Operators are pushed by kind, with
+//! [`wasmer_middleware_state_push_operator`] for the operators
+//! without immediate, and
+//! [`wasmer_middleware_state_push_operator_with_index`] for the
+//! operators whose only immediate is an index or a depth. There is
+//! no way yet to push an operator with a `memarg` immediate, such as
+//! `I32Load`, nor an `F32Const` or an `F64Const`: only
+//! [`wasmer_middleware_state_push_i32_const`] and
+//! [`wasmer_middleware_state_push_i64_const`] exist. Such operators
+//! can still be pushed back unchanged with
+//! [`wasmer_middleware_state_push_current`].
+//!
+//! # Example
+//!
+//! ```rust
+//! # use inline_c::assert_c;
+//! # fn main() {
+//! #    (assert_c! {
+//! # #include "tests/wasmer.h"
+//! #
+//! // The environment of the middleware, shared by the callbacks.
+//! typedef struct {
+//!     uint32_t calls_global_index;
+//! } counter_t;
+//!
+//! // Declare a global counting the calls, and export it.
+//! void transform_module_info(void* env, wasmer_module_info_t* module_info) {
+//!     counter_t* counter = (counter_t*) env;
+//!
+//!     assert(wasmer_module_info_add_global(module_info, WASM_I32, true, 0, &counter->calls_global_index));
+//!
+//!     wasm_name_t name;
+//!     wasmer_byte_vec_new_from_string(&name, "calls");
+//!     assert(wasmer_module_info_export_global(module_info, &name, counter->calls_global_index));
+//!     wasm_name_delete(&name);
+//! }
+//!
+//! // Increment the global before every call, and drop the `nop`s.
+//! bool feed(void* env, uint32_t local_function_index, wasmer_parser_operator_t wasm_operator, wasmer_middleware_state_t* state) {
+//!     counter_t* counter = (counter_t*) env;
+//!
+//!     switch (wasm_operator) {
+//!         case Nop:
+//!             return true;
+//!
+//!         case Call:
+//!             wasmer_middleware_state_push_operator_with_index(state, GlobalGet, counter->calls_global_index);
+//!             wasmer_middleware_state_push_i32_const(state, 1);
+//!             wasmer_middleware_state_push_operator(state, I32Add);
+//!             wasmer_middleware_state_push_operator_with_index(state, GlobalSet, counter->calls_global_index);
+//!             break;
+//!
+//!         default:
+//!             break;
+//!     }
+//!
+//!     wasmer_middleware_state_push_current(state);
+//!
+//!     return true;
+//! }
+//!
+//! int main() {
+//!     counter_t counter = { 0 };
+//!
+//!     // Create the middleware, and turn it into a generic one.
+//!     wasmer_custom_middleware_t* custom_middleware = wasmer_custom_middleware_new(
+//!         feed,
+//!         transform_module_info,
+//!         &counter,
+//!         NULL
+//!     );
+//!     wasmer_middleware_t* middleware = wasmer_custom_middleware_as_middleware(custom_middleware);
+//!
+//!     // Create the configuration (which consumes `middleware`),
+//!     // the engine, and the store.
+//!     wasm_config_t* config = wasm_config_new();
+//!     wasm_config_push_middleware(config, middleware);
+//!
+//!     wasm_engine_t* engine = wasm_engine_new_with_config(config);
+//!     wasm_store_t* store = wasm_store_new(engine);
+//!
+//!     // Create the module and instantiate it.
+//!     wasm_byte_vec_t wat;
+//!     wasmer_byte_vec_new_from_string(
+//!         &wat,
+//!         "(module\n"
+//!         "  (func $nothing)\n"
+//!         "  (func (export \"run\")\n"
+//!         "    nop\n"
+//!         "    call $nothing\n"
+//!         "    call $nothing))"
+//!     );
+//!     wasm_byte_vec_t wasm;
+//!     wat2wasm(&wat, &wasm);
+//!
+//!     wasm_module_t* module = wasm_module_new(store, &wasm);
+//!     assert(module);
+//!
+//!     wasm_extern_vec_t imports = WASM_EMPTY_VEC;
+//!     wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+//!     assert(instance);
+//!
+//!     wasm_extern_vec_t exports;
+//!     wasm_instance_exports(instance, &exports);
+//!     assert(exports.size == 2);
+//!
+//!     // Call the function, and read the counter.
+//!     wasm_func_t* run = wasm_extern_as_func(exports.data[0]);
+//!     wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+//!     wasm_val_vec_t results = WASM_EMPTY_VEC;
+//!     assert(wasm_func_call(run, &arguments, &results) == NULL);
+//!
+//!     wasm_global_t* calls = wasm_extern_as_global(exports.data[1]);
+//!     wasm_val_t calls_value;
+//!     wasm_global_get(calls, &calls_value);
+//!     assert(calls_value.of.i32 == 2);
+//!
+//!     // Free everything.
+//!     wasm_extern_vec_delete(&exports);
+//!     wasm_instance_delete(instance);
+//!     wasm_module_delete(module);
+//!     wasm_byte_vec_delete(&wasm);
+//!     wasm_byte_vec_delete(&wat);
+//!     wasm_store_delete(store);
+//!     wasm_engine_delete(engine);
+//!
+//!     return 0;
+//! }
+//! #    })
+//! #    .success();
+//! # }
+//! ```
+
+use super::super::super::types::{wasm_name_t, wasm_valkind_enum};
+use super::super::super::value::wasm_valkind_t;
+use super::super::parser::operator::wasmer_parser_operator_t;
+use super::wasmer_middleware_t;
+use crate::error::update_last_error;
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::fmt;
+use std::mem;
+use std::str;
+use std::sync::Arc;
+use wasmer_api::wasmparser::Operator;
+use wasmer_api::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::{entity::EntityRef, GlobalIndex, ModuleInfo};
+
+/// Function type of the callback fed with the operators of the
+/// functions, see [`wasmer_custom_middleware_new`].
+///
+/// It must return `false` to abort the compilation of the module.
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub type wasmer_middleware_feed_callback_t = unsafe extern "C" fn(
+    env: *mut c_void,
+    local_function_index: u32,
+    wasm_operator: wasmer_parser_operator_t,
+    state: &mut wasmer_middleware_state_t,
+) -> bool;
+
+type TransformModuleInfoCallback =
+    unsafe extern "C" fn(env: *mut c_void, module_info: &mut wasmer_module_info_t);
+
+/// Holds the environment of the callbacks, and calls its finalizer
+/// once, when the middleware and all its function middlewares are
+/// dropped.
+struct CustomMiddlewareEnv {
+    env: *mut c_void,
+    env_finalizer: Option<unsafe extern "C" fn(env: *mut c_void)>,
+}
+
+// Only relevant when compiling with multiple threads; Synchronization
+// will be done on the C side.
+unsafe impl Send for CustomMiddlewareEnv {}
+unsafe impl Sync for CustomMiddlewareEnv {}
+
+impl Drop for CustomMiddlewareEnv {
+    fn drop(&mut self) {
+        if let Some(env_finalizer) = self.env_finalizer {
+            unsafe { (env_finalizer)(self.env) }
+        }
+    }
+}
+
+struct CustomMiddleware {
+    feed: wasmer_middleware_feed_callback_t,
+    transform_module_info: Option<TransformModuleInfoCallback>,
+    env: Arc<CustomMiddlewareEnv>,
+}
+
+impl fmt::Debug for CustomMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomMiddleware")
+            .field("env", &self.env.env)
+            .finish()
+    }
+}
+
+impl MemoryUsage for CustomMiddleware {
+    fn size_of_val(&self, _tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self)
+    }
+}
+
+impl ModuleMiddleware for CustomMiddleware {
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        Box::new(FunctionCustomMiddleware {
+            feed: self.feed,
+            env: self.env.clone(),
+            local_function_index,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        if let Some(transform_module_info) = self.transform_module_info {
+            let mut module_info = wasmer_module_info_t { inner: module_info };
+
+            unsafe { (transform_module_info)(self.env.env, &mut module_info) }
+        }
+    }
+}
+
+struct FunctionCustomMiddleware {
+    feed: wasmer_middleware_feed_callback_t,
+    env: Arc<CustomMiddlewareEnv>,
+    local_function_index: LocalFunctionIndex,
+}
+
+impl fmt::Debug for FunctionCustomMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCustomMiddleware")
+            .field("env", &self.env.env)
+            .field("local_function_index", &self.local_function_index)
+            .finish()
+    }
+}
+
+impl FunctionMiddleware for FunctionCustomMiddleware {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let wasm_operator = (&operator).into();
+        let mut state = wasmer_middleware_state_t {
+            operator,
+            inner: state,
+        };
+        let succeeded = unsafe {
+            (self.feed)(
+                self.env.env,
+                self.local_function_index.index() as u32,
+                wasm_operator,
+                &mut state,
+            )
+        };
+
+        if !succeeded {
+            return Err(MiddlewareError::new(
+                "CustomMiddleware",
+                format!("the feed callback has failed on `{:?}`", state.operator),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Opaque type representing a custom middleware.
+///
+/// To transform this specific middleware into a generic one, please
+/// see [`wasmer_custom_middleware_as_middleware`].
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_custom_middleware_t {
+    inner: Arc<CustomMiddleware>,
+}
+
+/// Creates a new custom middleware from a _feed_ callback, an
+/// optional _transform module info_ callback, and an environment
+/// passed to both of them.
+///
+/// `env_finalizer`, if not `NULL`, is called with `env` once the
+/// middleware isn't used anymore, i.e. when the engine is deleted.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_custom_middleware_new(
+    feed: wasmer_middleware_feed_callback_t,
+    transform_module_info: Option<
+        unsafe extern "C" fn(env: *mut c_void, module_info: &mut wasmer_module_info_t),
+    >,
+    env: *mut c_void,
+    env_finalizer: Option<unsafe extern "C" fn(env: *mut c_void)>,
+) -> Box<wasmer_custom_middleware_t> {
+    Box::new(wasmer_custom_middleware_t {
+        inner: Arc::new(CustomMiddleware {
+            feed,
+            transform_module_info,
+            env: Arc::new(CustomMiddlewareEnv { env, env_finalizer }),
+        }),
+    })
+}
+
+/// Deletes a [`wasmer_custom_middleware_t`].
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_custom_middleware_delete(
+    _middleware: Option<Box<wasmer_custom_middleware_t>>,
+) {
+}
+
+/// Transforms a [`wasmer_custom_middleware_t`] into a generic
+/// [`wasmer_middleware_t`], to then be pushed in the configuration with
+/// [`wasm_config_push_middleware`][super::wasm_config_push_middleware].
+///
+/// This function takes ownership of `custom_middleware`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_custom_middleware_as_middleware(
+    custom_middleware: Option<Box<wasmer_custom_middleware_t>>,
+) -> Option<Box<wasmer_middleware_t>> {
+    let custom_middleware = custom_middleware?;
+
+    Some(Box::new(wasmer_middleware_t {
+        inner: custom_middleware.inner,
+    }))
+}
+
+/// Opaque type representing the state of the compilation of a
+/// function, given to the _feed_ callback of a custom middleware.
+///
+/// It is only valid during the call of the callback.
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_middleware_state_t<'a, 'b> {
+    operator: Operator<'a>,
+    inner: &'b mut MiddlewareReaderState<'a>,
+}
+
+/// Pushes the operator given to the _feed_ callback, otherwise it is
+/// dropped.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_current(state: &mut wasmer_middleware_state_t) {
+    state.inner.push_operator(state.operator.clone());
+}
+
+/// Pushes an operator without immediate, e.g. `I32Add`.
+///
+/// It returns `false` if the operator needs an immediate, e.g. the
+/// `memarg` of `I32Load` or the value of `F32Const`; the error can be
+/// read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_operator(
+    state: &mut wasmer_middleware_state_t,
+    wasm_operator: wasmer_parser_operator_t,
+) -> bool {
+    match wasm_operator.to_operator() {
+        Some(operator) => {
+            state.inner.push_operator(operator);
+
+            true
+        }
+        None => {
+            update_last_error("the operator can't be built without an immediate");
+
+            false
+        }
+    }
+}
+
+/// Pushes an operator whose only immediate is an index or a depth,
+/// e.g. `Call`, `LocalGet`, `GlobalSet` or `Br`.
+///
+/// It returns `false` if the operator has no such immediate; the
+/// error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_operator_with_index(
+    state: &mut wasmer_middleware_state_t,
+    wasm_operator: wasmer_parser_operator_t,
+    index: u32,
+) -> bool {
+    match wasm_operator.to_operator_with_index(index) {
+        Some(operator) => {
+            state.inner.push_operator(operator);
+
+            true
+        }
+        None => {
+            update_last_error("the operator can't be built from an index");
+
+            false
+        }
+    }
+}
+
+/// Pushes an `I32Const` operator.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_i32_const(
+    state: &mut wasmer_middleware_state_t,
+    value: i32,
+) {
+    state.inner.push_operator(Operator::I32Const { value });
+}
+
+/// Pushes an `I64Const` operator.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_push_i64_const(
+    state: &mut wasmer_middleware_state_t,
+    value: i64,
+) {
+    state.inner.push_operator(Operator::I64Const { value });
+}
+
+/// Reads the index or the depth of the operator given to the _feed_
+/// callback, e.g. the index of the function of a `Call`.
+///
+/// It returns `false` if the operator has no such immediate.
+#[no_mangle]
+pub extern "C" fn wasmer_middleware_state_operator_index(
+    state: &wasmer_middleware_state_t,
+    // own
+    index: &mut u32,
+) -> bool {
+    use Operator as O;
+
+    match state.operator {
+        O::Catch { index: value }
+        | O::Throw { index: value }
+        | O::Rethrow {
+            relative_depth: value,
+        }
+        | O::Br {
+            relative_depth: value,
+        }
+        | O::BrIf {
+            relative_depth: value,
+        }
+        | O::Delegate {
+            relative_depth: value,
+        }
+        | O::Call {
+            function_index: value,
+        }
+        | O::ReturnCall {
+            function_index: value,
+        }
+        | O::RefFunc {
+            function_index: value,
+        }
+        | O::LocalGet { local_index: value }
+        | O::LocalSet { local_index: value }
+        | O::LocalTee { local_index: value }
+        | O::GlobalGet {
+            global_index: value,
+        }
+        | O::GlobalSet {
+            global_index: value,
+        }
+        | O::DataDrop { segment: value }
+        | O::ElemDrop { segment: value }
+        | O::MemoryFill { mem: value }
+        | O::TableFill { table: value }
+        | O::TableGet { table: value }
+        | O::TableSet { table: value }
+        | O::TableGrow { table: value }
+        | O::TableSize { table: value } => {
+            *index = value;
+
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Opaque type representing the information about a module being
+/// compiled, given to the _transform module info_ callback of a
+/// custom middleware.
+///
+/// It is only valid during the call of the callback.
+///
+/// # Example
+///
+/// See module's documentation.
+#[allow(non_camel_case_types)]
+pub struct wasmer_module_info_t<'a> {
+    inner: &'a mut ModuleInfo,
+}
+
+/// Declares a new global in the module, of kind `WASM_I32`,
+/// `WASM_I64`, `WASM_F32` or `WASM_F64`, initialized to `value`, and
+/// writes its index to `global_index`.
+///
+/// A `WASM_I32` global is initialized to the low 32 bits of
+/// `value`. A `WASM_F32` or `WASM_F64` global is initialized to the
+/// float whose bit pattern is respectively the low 32 bits of `value`
+/// or `value` itself.
+///
+/// It returns `false` if the kind isn't supported, e.g. a reference;
+/// the error can be read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_module_info_add_global(
+    module_info: &mut wasmer_module_info_t,
+    kind: wasm_valkind_t,
+    mutable: bool,
+    value: i64,
+    // own
+    global_index: &mut u32,
+) -> bool {
+    let (ty, initializer) = match kind.try_into() {
+        Ok(wasm_valkind_enum::WASM_I32) => (Type::I32, GlobalInit::I32Const(value as i32)),
+        Ok(wasm_valkind_enum::WASM_I64) => (Type::I64, GlobalInit::I64Const(value)),
+        Ok(wasm_valkind_enum::WASM_F32) => (
+            Type::F32,
+            GlobalInit::F32Const(f32::from_bits(value as u32)),
+        ),
+        Ok(wasm_valkind_enum::WASM_F64) => (
+            Type::F64,
+            GlobalInit::F64Const(f64::from_bits(value as u64)),
+        ),
+        _ => {
+            update_last_error("only numeric globals can be added");
+
+            return false;
+        }
+    };
+    let mutability = if mutable {
+        Mutability::Var
+    } else {
+        Mutability::Const
+    };
+
+    let index = module_info
+        .inner
+        .globals
+        .push(GlobalType::new(ty, mutability));
+    module_info.inner.global_initializers.push(initializer);
+    *global_index = index.index() as u32;
+
+    true
+}
+
+/// Exports a global of the module under the given name.
+///
+/// It returns `false` if there is no such global; the error can be
+/// read with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// See module's documentation.
+#[no_mangle]
+pub extern "C" fn wasmer_module_info_export_global(
+    module_info: &mut wasmer_module_info_t,
+    name: &wasm_name_t,
+    global_index: u32,
+) -> bool {
+    let name = c_try!(str::from_utf8(name.as_slice()); otherwise false);
+    let global_index = GlobalIndex::new(global_index as usize);
+
+    if module_info.inner.globals.get(global_index).is_none() {
+        update_last_error("the global doesn't exist");
+
+        return false;
+    }
+
+    module_info
+        .inner
+        .exports
+        .insert(name.to_string(), ExportIndex::Global(global_index));
+
+    true
+}
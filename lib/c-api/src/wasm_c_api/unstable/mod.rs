@@ -1,5 +1,10 @@
+#[cfg(feature = "cache")]
+pub mod cache;
 pub mod engine;
 pub mod features;
+pub mod frame;
+pub mod function;
+pub mod instance;
 #[cfg(feature = "middlewares")]
 pub mod middlewares;
 pub mod module;
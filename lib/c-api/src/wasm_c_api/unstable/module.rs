@@ -1,10 +1,16 @@
 //! Unstable non-standard Wasmer-specific extensions to the Wasm C API.
 
 use super::super::module::wasm_module_t;
-use super::super::types::wasm_name_t;
+use super::super::store::wasm_store_t;
+use super::super::types::{wasm_byte_vec_t, wasm_name_t};
+use crate::error::update_last_error;
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::ptr;
 use std::str;
 use std::sync::Arc;
+use wasmer_api::Module;
+use wasmer_types::FunctionIndex;
 
 /// Unstable non-standard Wasmer-specific API to get the module's
 /// name, otherwise `out->size` is set to `0` and `out->data` to
@@ -154,3 +160,382 @@ pub unsafe extern "C" fn wasmer_module_set_name(
         None => false,
     }
 }
+
+/// Unstable non-standard Wasmer-specific API to get the name of a
+/// function from the name section of the module, given its index in
+/// the function index space (imported functions first), otherwise
+/// `out->size` is set to `0` and `out->data` to `NULL`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(
+///         &wat,
+///         "(module\n"
+///         "  (func $first)\n"
+///         "  (func))"
+///     );
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///
+///     // The first function has a name.
+///     wasm_name_t name;
+///     wasmer_module_function_name(module, 0, &name);
+///     wasmer_assert_name(&name, "first");
+///     wasm_byte_vec_delete(&name);
+///
+///     // The second one has no name.
+///     wasmer_module_function_name(module, 1, &name);
+///     assert(name.size == 0);
+///
+///     // Free everything.
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_function_name(
+    module: &wasm_module_t,
+    function_index: u32,
+    // own
+    out: &mut wasm_name_t,
+) {
+    let function_index = FunctionIndex::from_u32(function_index);
+
+    match module.inner.info().function_names.get(&function_index) {
+        Some(name) => out.set_buffer(name.as_bytes().to_vec()),
+        None => {
+            out.data = ptr::null_mut();
+            out.size = 0;
+        }
+    }
+}
+
+/// Unstable non-standard Wasmer-specific type representing the
+/// payload of a custom section, as returned by
+/// [`wasmer_module_custom_sections`] in a
+/// `wasmer_custom_section_vec_t`.
+///
+/// The vector owns the sections: it must be deleted with
+/// `wasmer_custom_section_vec_delete`, which deletes the sections
+/// too.
+#[allow(non_camel_case_types)]
+pub type wasmer_custom_section_t = wasm_byte_vec_t;
+
+wasm_declare_vec!(custom_section, wasmer);
+
+/// `cbindgen` doesn't see the symbols generated by
+/// `wasm_declare_vec!`, see the same module in `unstable/wasi.rs`.
+#[doc(hidden)]
+#[cfg(__cbindgen_hack__ = "yes")]
+mod __cbindgen_hack__ {
+    use super::*;
+
+    #[repr(C)]
+    pub struct wasmer_custom_section_vec_t {
+        pub size: usize,
+        pub data: *mut wasmer_custom_section_t,
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_custom_section_vec_new(
+        out: *mut wasmer_custom_section_vec_t,
+        length: usize,
+        init: *const wasmer_custom_section_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_custom_section_vec_new_uninitialized(
+        out: *mut wasmer_custom_section_vec_t,
+        length: usize,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_custom_section_vec_copy(
+        out_ptr: &mut wasmer_custom_section_vec_t,
+        in_ptr: &wasmer_custom_section_vec_t,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_custom_section_vec_delete(
+        ptr: Option<&mut wasmer_custom_section_vec_t>,
+    ) {
+        unimplemented!()
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn wasmer_custom_section_vec_new_empty(
+        out: *mut wasmer_custom_section_vec_t,
+    ) {
+        unimplemented!()
+    }
+}
+
+/// Unstable non-standard Wasmer-specific API to get the payloads of
+/// the custom sections of the module with the given name, in the
+/// order they appear in the module.
+///
+/// `out` is empty if there is no such section. It must be deleted
+/// with [`wasmer_custom_section_vec_delete`].
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // A module with a custom section named `hello`, containing
+///     // the bytes `world`.
+///     wasm_byte_vec_t wasm;
+///     wasmer_byte_vec_new_from_string(
+///         &wasm,
+///         "\x00asm\x01\x00\x00\x00"
+///         "\x00\x0b\x05hello" "world"
+///     );
+///
+///     // Create the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Read the custom sections named `hello`.
+///     wasm_name_t name;
+///     wasmer_byte_vec_new_from_string(&name, "hello");
+///
+///     wasmer_custom_section_vec_t sections;
+///     wasmer_module_custom_sections(module, &name, &sections);
+///
+///     assert(sections.size == 1);
+///     wasmer_assert_name(&sections.data[0], "world");
+///
+///     // Free everything.
+///     wasmer_custom_section_vec_delete(&sections);
+///     wasm_byte_vec_delete(&name);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_custom_sections(
+    module: &wasm_module_t,
+    name: &wasm_name_t,
+    // own
+    out: &mut wasmer_custom_section_vec_t,
+) {
+    let sections = match str::from_utf8(name.as_slice()) {
+        Ok(name) => module
+            .inner
+            .custom_sections(name)
+            .map(|section| section.to_vec().into())
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    out.set_buffer(sections);
+}
+
+/// Unstable non-standard Wasmer-specific API to serialize a module
+/// to a file, which can be deserialized later with
+/// [`wasmer_module_deserialize_from_file`], even by a headless
+/// engine.
+///
+/// `path` is a null-terminated UTF-8 string. The file is created if
+/// it doesn't exist, and truncated otherwise.
+///
+/// It returns `false` if `path` is `NULL` or isn't valid UTF-8, or if
+/// the module can't be serialized or written; the error can be read
+/// with `wasmer_last_error_message`.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"f\")))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///
+///     // Serialize it to a file.
+///     assert(wasmer_module_serialize_to_file(module, "target/c-api-module-serialize.wasmu"));
+///
+///     // A `NULL` path is an error.
+///     assert(!wasmer_module_serialize_to_file(module, NULL));
+///     assert(wasmer_last_error_length() > 0);
+///
+///     // Free everything.
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_serialize_to_file(
+    module: &wasm_module_t,
+    path: *const c_char,
+) -> bool {
+    if path.is_null() {
+        update_last_error("`path` must not be `NULL`");
+
+        return false;
+    }
+
+    let path = c_try!(CStr::from_ptr(path).to_str(); otherwise false);
+    c_try!(module.inner.serialize_to_file(path); otherwise false);
+
+    true
+}
+
+/// Unstable non-standard Wasmer-specific API to deserialize a module
+/// from a file written by [`wasmer_module_serialize_to_file`] or
+/// `wasmer_cache_store`. The file is memory-mapped when the engine
+/// supports it, rather than read in memory.
+///
+/// `path` is a null-terminated UTF-8 string. The file must have been
+/// written for the same engine and target as the store's.
+///
+/// It returns `NULL` if `path` is `NULL` or isn't valid UTF-8, or if
+/// the file can't be read or deserialized; the error can be read with
+/// `wasmer_last_error_message`.
+///
+/// # Safety
+///
+/// The module is deserialized without being validated: the file must
+/// be trusted.
+///
+/// # Example
+///
+/// ```rust
+/// # use inline_c::assert_c;
+/// # fn main() {
+/// #    (assert_c! {
+/// # #include "tests/wasmer.h"
+/// #
+/// int main() {
+///     // Create the engine and the store.
+///     wasm_engine_t* engine = wasm_engine_new();
+///     wasm_store_t* store = wasm_store_new(engine);
+///
+///     // Create a WebAssembly module from a WAT definition.
+///     wasm_byte_vec_t wat;
+///     wasmer_byte_vec_new_from_string(&wat, "(module (func (export \"f\")))");
+///     wasm_byte_vec_t wasm;
+///     wat2wasm(&wat, &wasm);
+///
+///     // Create the module, and serialize it to a file.
+///     wasm_module_t* module = wasm_module_new(store, &wasm);
+///     assert(module);
+///     assert(wasmer_module_serialize_to_file(module, "target/c-api-module-deserialize.wasmu"));
+///
+///     // Deserialize it back from the file.
+///     wasm_module_t* deserialized_module = wasmer_module_deserialize_from_file(
+///         store,
+///         "target/c-api-module-deserialize.wasmu"
+///     );
+///     assert(deserialized_module);
+///
+///     wasm_exporttype_vec_t export_types;
+///     wasm_module_exports(deserialized_module, &export_types);
+///     assert(export_types.size == 1);
+///
+///     // A missing file, or a `NULL` path, is an error.
+///     assert(!wasmer_module_deserialize_from_file(store, "target/c-api-module-missing.wasmu"));
+///     assert(wasmer_last_error_length() > 0);
+///     assert(!wasmer_module_deserialize_from_file(store, NULL));
+///     assert(wasmer_last_error_length() > 0);
+///
+///     // Free everything.
+///     wasm_exporttype_vec_delete(&export_types);
+///     wasm_module_delete(deserialized_module);
+///     wasm_module_delete(module);
+///     wasm_byte_vec_delete(&wasm);
+///     wasm_byte_vec_delete(&wat);
+///     wasm_store_delete(store);
+///     wasm_engine_delete(engine);
+///
+///     return 0;
+/// }
+/// #    })
+/// #    .success();
+/// # }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn wasmer_module_deserialize_from_file(
+    store: &wasm_store_t,
+    path: *const c_char,
+) -> Option<Box<wasm_module_t>> {
+    if path.is_null() {
+        update_last_error("`path` must not be `NULL`");
+
+        return None;
+    }
+
+    let path = c_try!(CStr::from_ptr(path).to_str());
+    let module = c_try!(Module::deserialize_from_file(&store.inner, path));
+
+    Some(Box::new(wasm_module_t {
+        inner: Arc::new(module),
+    }))
+}
@@ -1039,3 +1039,408 @@ impl<'a> From<&Operator<'a>> for wasmer_parser_operator_t {
         }
     }
 }
+
+impl wasmer_parser_operator_t {
+    /// Builds the operator, if it has no immediate.
+    pub(crate) fn to_operator(&self) -> Option<Operator<'static>> {
+        use Operator as O;
+
+        Some(match self {
+            Self::Unreachable => O::Unreachable,
+            Self::Nop => O::Nop,
+            Self::Else => O::Else,
+            Self::CatchAll => O::CatchAll,
+            Self::Unwind => O::Unwind,
+            Self::End => O::End,
+            Self::Return => O::Return,
+            Self::Drop => O::Drop,
+            Self::Select => O::Select,
+            Self::RefIsNull => O::RefIsNull,
+            Self::I32Eqz => O::I32Eqz,
+            Self::I32Eq => O::I32Eq,
+            Self::I32Ne => O::I32Ne,
+            Self::I32LtS => O::I32LtS,
+            Self::I32LtU => O::I32LtU,
+            Self::I32GtS => O::I32GtS,
+            Self::I32GtU => O::I32GtU,
+            Self::I32LeS => O::I32LeS,
+            Self::I32LeU => O::I32LeU,
+            Self::I32GeS => O::I32GeS,
+            Self::I32GeU => O::I32GeU,
+            Self::I64Eqz => O::I64Eqz,
+            Self::I64Eq => O::I64Eq,
+            Self::I64Ne => O::I64Ne,
+            Self::I64LtS => O::I64LtS,
+            Self::I64LtU => O::I64LtU,
+            Self::I64GtS => O::I64GtS,
+            Self::I64GtU => O::I64GtU,
+            Self::I64LeS => O::I64LeS,
+            Self::I64LeU => O::I64LeU,
+            Self::I64GeS => O::I64GeS,
+            Self::I64GeU => O::I64GeU,
+            Self::F32Eq => O::F32Eq,
+            Self::F32Ne => O::F32Ne,
+            Self::F32Lt => O::F32Lt,
+            Self::F32Gt => O::F32Gt,
+            Self::F32Le => O::F32Le,
+            Self::F32Ge => O::F32Ge,
+            Self::F64Eq => O::F64Eq,
+            Self::F64Ne => O::F64Ne,
+            Self::F64Lt => O::F64Lt,
+            Self::F64Gt => O::F64Gt,
+            Self::F64Le => O::F64Le,
+            Self::F64Ge => O::F64Ge,
+            Self::I32Clz => O::I32Clz,
+            Self::I32Ctz => O::I32Ctz,
+            Self::I32Popcnt => O::I32Popcnt,
+            Self::I32Add => O::I32Add,
+            Self::I32Sub => O::I32Sub,
+            Self::I32Mul => O::I32Mul,
+            Self::I32DivS => O::I32DivS,
+            Self::I32DivU => O::I32DivU,
+            Self::I32RemS => O::I32RemS,
+            Self::I32RemU => O::I32RemU,
+            Self::I32And => O::I32And,
+            Self::I32Or => O::I32Or,
+            Self::I32Xor => O::I32Xor,
+            Self::I32Shl => O::I32Shl,
+            Self::I32ShrS => O::I32ShrS,
+            Self::I32ShrU => O::I32ShrU,
+            Self::I32Rotl => O::I32Rotl,
+            Self::I32Rotr => O::I32Rotr,
+            Self::I64Clz => O::I64Clz,
+            Self::I64Ctz => O::I64Ctz,
+            Self::I64Popcnt => O::I64Popcnt,
+            Self::I64Add => O::I64Add,
+            Self::I64Sub => O::I64Sub,
+            Self::I64Mul => O::I64Mul,
+            Self::I64DivS => O::I64DivS,
+            Self::I64DivU => O::I64DivU,
+            Self::I64RemS => O::I64RemS,
+            Self::I64RemU => O::I64RemU,
+            Self::I64And => O::I64And,
+            Self::I64Or => O::I64Or,
+            Self::I64Xor => O::I64Xor,
+            Self::I64Shl => O::I64Shl,
+            Self::I64ShrS => O::I64ShrS,
+            Self::I64ShrU => O::I64ShrU,
+            Self::I64Rotl => O::I64Rotl,
+            Self::I64Rotr => O::I64Rotr,
+            Self::F32Abs => O::F32Abs,
+            Self::F32Neg => O::F32Neg,
+            Self::F32Ceil => O::F32Ceil,
+            Self::F32Floor => O::F32Floor,
+            Self::F32Trunc => O::F32Trunc,
+            Self::F32Nearest => O::F32Nearest,
+            Self::F32Sqrt => O::F32Sqrt,
+            Self::F32Add => O::F32Add,
+            Self::F32Sub => O::F32Sub,
+            Self::F32Mul => O::F32Mul,
+            Self::F32Div => O::F32Div,
+            Self::F32Min => O::F32Min,
+            Self::F32Max => O::F32Max,
+            Self::F32Copysign => O::F32Copysign,
+            Self::F64Abs => O::F64Abs,
+            Self::F64Neg => O::F64Neg,
+            Self::F64Ceil => O::F64Ceil,
+            Self::F64Floor => O::F64Floor,
+            Self::F64Trunc => O::F64Trunc,
+            Self::F64Nearest => O::F64Nearest,
+            Self::F64Sqrt => O::F64Sqrt,
+            Self::F64Add => O::F64Add,
+            Self::F64Sub => O::F64Sub,
+            Self::F64Mul => O::F64Mul,
+            Self::F64Div => O::F64Div,
+            Self::F64Min => O::F64Min,
+            Self::F64Max => O::F64Max,
+            Self::F64Copysign => O::F64Copysign,
+            Self::I32WrapI64 => O::I32WrapI64,
+            Self::I32TruncF32S => O::I32TruncF32S,
+            Self::I32TruncF32U => O::I32TruncF32U,
+            Self::I32TruncF64S => O::I32TruncF64S,
+            Self::I32TruncF64U => O::I32TruncF64U,
+            Self::I64ExtendI32S => O::I64ExtendI32S,
+            Self::I64ExtendI32U => O::I64ExtendI32U,
+            Self::I64TruncF32S => O::I64TruncF32S,
+            Self::I64TruncF32U => O::I64TruncF32U,
+            Self::I64TruncF64S => O::I64TruncF64S,
+            Self::I64TruncF64U => O::I64TruncF64U,
+            Self::F32ConvertI32S => O::F32ConvertI32S,
+            Self::F32ConvertI32U => O::F32ConvertI32U,
+            Self::F32ConvertI64S => O::F32ConvertI64S,
+            Self::F32ConvertI64U => O::F32ConvertI64U,
+            Self::F32DemoteF64 => O::F32DemoteF64,
+            Self::F64ConvertI32S => O::F64ConvertI32S,
+            Self::F64ConvertI32U => O::F64ConvertI32U,
+            Self::F64ConvertI64S => O::F64ConvertI64S,
+            Self::F64ConvertI64U => O::F64ConvertI64U,
+            Self::F64PromoteF32 => O::F64PromoteF32,
+            Self::I32ReinterpretF32 => O::I32ReinterpretF32,
+            Self::I64ReinterpretF64 => O::I64ReinterpretF64,
+            Self::F32ReinterpretI32 => O::F32ReinterpretI32,
+            Self::F64ReinterpretI64 => O::F64ReinterpretI64,
+            Self::I32Extend8S => O::I32Extend8S,
+            Self::I32Extend16S => O::I32Extend16S,
+            Self::I64Extend8S => O::I64Extend8S,
+            Self::I64Extend16S => O::I64Extend16S,
+            Self::I64Extend32S => O::I64Extend32S,
+            Self::I32TruncSatF32S => O::I32TruncSatF32S,
+            Self::I32TruncSatF32U => O::I32TruncSatF32U,
+            Self::I32TruncSatF64S => O::I32TruncSatF64S,
+            Self::I32TruncSatF64U => O::I32TruncSatF64U,
+            Self::I64TruncSatF32S => O::I64TruncSatF32S,
+            Self::I64TruncSatF32U => O::I64TruncSatF32U,
+            Self::I64TruncSatF64S => O::I64TruncSatF64S,
+            Self::I64TruncSatF64U => O::I64TruncSatF64U,
+            Self::I8x16Splat => O::I8x16Splat,
+            Self::I16x8Splat => O::I16x8Splat,
+            Self::I32x4Splat => O::I32x4Splat,
+            Self::I64x2Splat => O::I64x2Splat,
+            Self::F32x4Splat => O::F32x4Splat,
+            Self::F64x2Splat => O::F64x2Splat,
+            Self::I8x16Eq => O::I8x16Eq,
+            Self::I8x16Ne => O::I8x16Ne,
+            Self::I8x16LtS => O::I8x16LtS,
+            Self::I8x16LtU => O::I8x16LtU,
+            Self::I8x16GtS => O::I8x16GtS,
+            Self::I8x16GtU => O::I8x16GtU,
+            Self::I8x16LeS => O::I8x16LeS,
+            Self::I8x16LeU => O::I8x16LeU,
+            Self::I8x16GeS => O::I8x16GeS,
+            Self::I8x16GeU => O::I8x16GeU,
+            Self::I16x8Eq => O::I16x8Eq,
+            Self::I16x8Ne => O::I16x8Ne,
+            Self::I16x8LtS => O::I16x8LtS,
+            Self::I16x8LtU => O::I16x8LtU,
+            Self::I16x8GtS => O::I16x8GtS,
+            Self::I16x8GtU => O::I16x8GtU,
+            Self::I16x8LeS => O::I16x8LeS,
+            Self::I16x8LeU => O::I16x8LeU,
+            Self::I16x8GeS => O::I16x8GeS,
+            Self::I16x8GeU => O::I16x8GeU,
+            Self::I32x4Eq => O::I32x4Eq,
+            Self::I32x4Ne => O::I32x4Ne,
+            Self::I32x4LtS => O::I32x4LtS,
+            Self::I32x4LtU => O::I32x4LtU,
+            Self::I32x4GtS => O::I32x4GtS,
+            Self::I32x4GtU => O::I32x4GtU,
+            Self::I32x4LeS => O::I32x4LeS,
+            Self::I32x4LeU => O::I32x4LeU,
+            Self::I32x4GeS => O::I32x4GeS,
+            Self::I32x4GeU => O::I32x4GeU,
+            Self::I64x2Eq => O::I64x2Eq,
+            Self::I64x2Ne => O::I64x2Ne,
+            Self::I64x2LtS => O::I64x2LtS,
+            Self::I64x2GtS => O::I64x2GtS,
+            Self::I64x2LeS => O::I64x2LeS,
+            Self::I64x2GeS => O::I64x2GeS,
+            Self::F32x4Eq => O::F32x4Eq,
+            Self::F32x4Ne => O::F32x4Ne,
+            Self::F32x4Lt => O::F32x4Lt,
+            Self::F32x4Gt => O::F32x4Gt,
+            Self::F32x4Le => O::F32x4Le,
+            Self::F32x4Ge => O::F32x4Ge,
+            Self::F64x2Eq => O::F64x2Eq,
+            Self::F64x2Ne => O::F64x2Ne,
+            Self::F64x2Lt => O::F64x2Lt,
+            Self::F64x2Gt => O::F64x2Gt,
+            Self::F64x2Le => O::F64x2Le,
+            Self::F64x2Ge => O::F64x2Ge,
+            Self::V128Not => O::V128Not,
+            Self::V128And => O::V128And,
+            Self::V128AndNot => O::V128AndNot,
+            Self::V128Or => O::V128Or,
+            Self::V128Xor => O::V128Xor,
+            Self::V128Bitselect => O::V128Bitselect,
+            Self::V128AnyTrue => O::V128AnyTrue,
+            Self::I8x16Popcnt => O::I8x16Popcnt,
+            Self::I8x16Abs => O::I8x16Abs,
+            Self::I8x16Neg => O::I8x16Neg,
+            Self::I8x16AllTrue => O::I8x16AllTrue,
+            Self::I8x16Bitmask => O::I8x16Bitmask,
+            Self::I8x16Shl => O::I8x16Shl,
+            Self::I8x16ShrS => O::I8x16ShrS,
+            Self::I8x16ShrU => O::I8x16ShrU,
+            Self::I8x16Add => O::I8x16Add,
+            Self::I8x16AddSatS => O::I8x16AddSatS,
+            Self::I8x16AddSatU => O::I8x16AddSatU,
+            Self::I8x16Sub => O::I8x16Sub,
+            Self::I8x16SubSatS => O::I8x16SubSatS,
+            Self::I8x16SubSatU => O::I8x16SubSatU,
+            Self::I8x16MinS => O::I8x16MinS,
+            Self::I8x16MinU => O::I8x16MinU,
+            Self::I8x16MaxS => O::I8x16MaxS,
+            Self::I8x16MaxU => O::I8x16MaxU,
+            Self::I16x8Abs => O::I16x8Abs,
+            Self::I16x8Neg => O::I16x8Neg,
+            Self::I16x8AllTrue => O::I16x8AllTrue,
+            Self::I16x8Bitmask => O::I16x8Bitmask,
+            Self::I16x8Shl => O::I16x8Shl,
+            Self::I16x8ShrS => O::I16x8ShrS,
+            Self::I16x8ShrU => O::I16x8ShrU,
+            Self::I16x8Add => O::I16x8Add,
+            Self::I16x8AddSatS => O::I16x8AddSatS,
+            Self::I16x8AddSatU => O::I16x8AddSatU,
+            Self::I16x8Sub => O::I16x8Sub,
+            Self::I16x8SubSatS => O::I16x8SubSatS,
+            Self::I16x8SubSatU => O::I16x8SubSatU,
+            Self::I16x8Mul => O::I16x8Mul,
+            Self::I16x8MinS => O::I16x8MinS,
+            Self::I16x8MinU => O::I16x8MinU,
+            Self::I16x8MaxS => O::I16x8MaxS,
+            Self::I16x8MaxU => O::I16x8MaxU,
+            Self::I16x8ExtAddPairwiseI8x16S => O::I16x8ExtAddPairwiseI8x16S,
+            Self::I16x8ExtAddPairwiseI8x16U => O::I16x8ExtAddPairwiseI8x16U,
+            Self::I32x4Abs => O::I32x4Abs,
+            Self::I32x4Neg => O::I32x4Neg,
+            Self::I32x4AllTrue => O::I32x4AllTrue,
+            Self::I32x4Bitmask => O::I32x4Bitmask,
+            Self::I32x4Shl => O::I32x4Shl,
+            Self::I32x4ShrS => O::I32x4ShrS,
+            Self::I32x4ShrU => O::I32x4ShrU,
+            Self::I32x4Add => O::I32x4Add,
+            Self::I32x4Sub => O::I32x4Sub,
+            Self::I32x4Mul => O::I32x4Mul,
+            Self::I32x4MinS => O::I32x4MinS,
+            Self::I32x4MinU => O::I32x4MinU,
+            Self::I32x4MaxS => O::I32x4MaxS,
+            Self::I32x4MaxU => O::I32x4MaxU,
+            Self::I32x4DotI16x8S => O::I32x4DotI16x8S,
+            Self::I32x4ExtAddPairwiseI16x8S => O::I32x4ExtAddPairwiseI16x8S,
+            Self::I32x4ExtAddPairwiseI16x8U => O::I32x4ExtAddPairwiseI16x8U,
+            Self::I64x2Abs => O::I64x2Abs,
+            Self::I64x2Neg => O::I64x2Neg,
+            Self::I64x2AllTrue => O::I64x2AllTrue,
+            Self::I64x2Bitmask => O::I64x2Bitmask,
+            Self::I64x2Shl => O::I64x2Shl,
+            Self::I64x2ShrS => O::I64x2ShrS,
+            Self::I64x2ShrU => O::I64x2ShrU,
+            Self::I64x2Add => O::I64x2Add,
+            Self::I64x2Sub => O::I64x2Sub,
+            Self::I64x2Mul => O::I64x2Mul,
+            Self::F32x4Ceil => O::F32x4Ceil,
+            Self::F32x4Floor => O::F32x4Floor,
+            Self::F32x4Trunc => O::F32x4Trunc,
+            Self::F32x4Nearest => O::F32x4Nearest,
+            Self::F64x2Ceil => O::F64x2Ceil,
+            Self::F64x2Floor => O::F64x2Floor,
+            Self::F64x2Trunc => O::F64x2Trunc,
+            Self::F64x2Nearest => O::F64x2Nearest,
+            Self::F32x4Abs => O::F32x4Abs,
+            Self::F32x4Neg => O::F32x4Neg,
+            Self::F32x4Sqrt => O::F32x4Sqrt,
+            Self::F32x4Add => O::F32x4Add,
+            Self::F32x4Sub => O::F32x4Sub,
+            Self::F32x4Mul => O::F32x4Mul,
+            Self::F32x4Div => O::F32x4Div,
+            Self::F32x4Min => O::F32x4Min,
+            Self::F32x4Max => O::F32x4Max,
+            Self::F32x4PMin => O::F32x4PMin,
+            Self::F32x4PMax => O::F32x4PMax,
+            Self::F64x2Abs => O::F64x2Abs,
+            Self::F64x2Neg => O::F64x2Neg,
+            Self::F64x2Sqrt => O::F64x2Sqrt,
+            Self::F64x2Add => O::F64x2Add,
+            Self::F64x2Sub => O::F64x2Sub,
+            Self::F64x2Mul => O::F64x2Mul,
+            Self::F64x2Div => O::F64x2Div,
+            Self::F64x2Min => O::F64x2Min,
+            Self::F64x2Max => O::F64x2Max,
+            Self::F64x2PMin => O::F64x2PMin,
+            Self::F64x2PMax => O::F64x2PMax,
+            Self::I32x4TruncSatF32x4S => O::I32x4TruncSatF32x4S,
+            Self::I32x4TruncSatF32x4U => O::I32x4TruncSatF32x4U,
+            Self::F32x4ConvertI32x4S => O::F32x4ConvertI32x4S,
+            Self::F32x4ConvertI32x4U => O::F32x4ConvertI32x4U,
+            Self::I8x16Swizzle => O::I8x16Swizzle,
+            Self::I8x16NarrowI16x8S => O::I8x16NarrowI16x8S,
+            Self::I8x16NarrowI16x8U => O::I8x16NarrowI16x8U,
+            Self::I16x8NarrowI32x4S => O::I16x8NarrowI32x4S,
+            Self::I16x8NarrowI32x4U => O::I16x8NarrowI32x4U,
+            Self::I16x8ExtendLowI8x16S => O::I16x8ExtendLowI8x16S,
+            Self::I16x8ExtendHighI8x16S => O::I16x8ExtendHighI8x16S,
+            Self::I16x8ExtendLowI8x16U => O::I16x8ExtendLowI8x16U,
+            Self::I16x8ExtendHighI8x16U => O::I16x8ExtendHighI8x16U,
+            Self::I32x4ExtendLowI16x8S => O::I32x4ExtendLowI16x8S,
+            Self::I32x4ExtendHighI16x8S => O::I32x4ExtendHighI16x8S,
+            Self::I32x4ExtendLowI16x8U => O::I32x4ExtendLowI16x8U,
+            Self::I32x4ExtendHighI16x8U => O::I32x4ExtendHighI16x8U,
+            Self::I64x2ExtendLowI32x4S => O::I64x2ExtendLowI32x4S,
+            Self::I64x2ExtendHighI32x4S => O::I64x2ExtendHighI32x4S,
+            Self::I64x2ExtendLowI32x4U => O::I64x2ExtendLowI32x4U,
+            Self::I64x2ExtendHighI32x4U => O::I64x2ExtendHighI32x4U,
+            Self::I16x8ExtMulLowI8x16S => O::I16x8ExtMulLowI8x16S,
+            Self::I16x8ExtMulHighI8x16S => O::I16x8ExtMulHighI8x16S,
+            Self::I16x8ExtMulLowI8x16U => O::I16x8ExtMulLowI8x16U,
+            Self::I16x8ExtMulHighI8x16U => O::I16x8ExtMulHighI8x16U,
+            Self::I32x4ExtMulLowI16x8S => O::I32x4ExtMulLowI16x8S,
+            Self::I32x4ExtMulHighI16x8S => O::I32x4ExtMulHighI16x8S,
+            Self::I32x4ExtMulLowI16x8U => O::I32x4ExtMulLowI16x8U,
+            Self::I32x4ExtMulHighI16x8U => O::I32x4ExtMulHighI16x8U,
+            Self::I64x2ExtMulLowI32x4S => O::I64x2ExtMulLowI32x4S,
+            Self::I64x2ExtMulHighI32x4S => O::I64x2ExtMulHighI32x4S,
+            Self::I64x2ExtMulLowI32x4U => O::I64x2ExtMulLowI32x4U,
+            Self::I64x2ExtMulHighI32x4U => O::I64x2ExtMulHighI32x4U,
+            Self::I8x16RoundingAverageU => O::I8x16RoundingAverageU,
+            Self::I16x8RoundingAverageU => O::I16x8RoundingAverageU,
+            Self::I16x8Q15MulrSatS => O::I16x8Q15MulrSatS,
+            Self::F32x4DemoteF64x2Zero => O::F32x4DemoteF64x2Zero,
+            Self::F64x2PromoteLowF32x4 => O::F64x2PromoteLowF32x4,
+            Self::F64x2ConvertLowI32x4S => O::F64x2ConvertLowI32x4S,
+            Self::F64x2ConvertLowI32x4U => O::F64x2ConvertLowI32x4U,
+            Self::I32x4TruncSatF64x2SZero => O::I32x4TruncSatF64x2SZero,
+            Self::I32x4TruncSatF64x2UZero => O::I32x4TruncSatF64x2UZero,
+            _ => return None,
+        })
+    }
+
+    /// Builds the operator, if its only immediate is a `u32` index
+    /// or depth.
+    pub(crate) fn to_operator_with_index(&self, index: u32) -> Option<Operator<'static>> {
+        use Operator as O;
+
+        Some(match self {
+            Self::Catch => O::Catch { index },
+            Self::Throw => O::Throw { index },
+            Self::Rethrow => O::Rethrow {
+                relative_depth: index,
+            },
+            Self::Br => O::Br {
+                relative_depth: index,
+            },
+            Self::BrIf => O::BrIf {
+                relative_depth: index,
+            },
+            Self::Call => O::Call {
+                function_index: index,
+            },
+            Self::ReturnCall => O::ReturnCall {
+                function_index: index,
+            },
+            Self::Delegate => O::Delegate {
+                relative_depth: index,
+            },
+            Self::LocalGet => O::LocalGet { local_index: index },
+            Self::LocalSet => O::LocalSet { local_index: index },
+            Self::LocalTee => O::LocalTee { local_index: index },
+            Self::GlobalGet => O::GlobalGet {
+                global_index: index,
+            },
+            Self::GlobalSet => O::GlobalSet {
+                global_index: index,
+            },
+            Self::RefFunc => O::RefFunc {
+                function_index: index,
+            },
+            Self::DataDrop => O::DataDrop { segment: index },
+            Self::MemoryFill => O::MemoryFill { mem: index },
+            Self::ElemDrop => O::ElemDrop { segment: index },
+            Self::TableFill => O::TableFill { table: index },
+            Self::TableGet => O::TableGet { table: index },
+            Self::TableSet => O::TableSet { table: index },
+            Self::TableGrow => O::TableGrow { table: index },
+            Self::TableSize => O::TableSize { table: index },
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,239 @@
+//! Implementations of stdin/stdout/stderr for a WASI program backed
+//! by user-defined callbacks.
+
+use super::{wasi_stdio_read_callback_t, wasi_stdio_write_callback_t};
+use std::ffi::c_void;
+use std::fmt;
+use std::io::{self, Read, Seek, Write};
+use wasmer_wasi::{WasiFile, WasiFsError};
+
+/// For stdout/stderr. Every write is forwarded to the callback.
+pub struct OutputCallback {
+    callback: wasi_stdio_write_callback_t,
+    userdata: *mut c_void,
+}
+
+impl OutputCallback {
+    pub fn new(callback: wasi_stdio_write_callback_t, userdata: *mut c_void) -> Self {
+        Self { callback, userdata }
+    }
+}
+
+// The user data is only accessed by the callback, synchronization is
+// left to the C side.
+unsafe impl Send for OutputCallback {}
+
+impl fmt::Debug for OutputCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OutputCallback")
+            .field("userdata", &self.userdata)
+            .finish()
+    }
+}
+
+impl WasiFile for OutputCallback {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        // return an arbitrary amount
+        Ok(1024)
+    }
+}
+
+// fail when reading or Seeking
+impl Read for OutputCallback {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not read from an output callback",
+        ))
+    }
+}
+impl Seek for OutputCallback {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek an output callback",
+        ))
+    }
+}
+impl Write for OutputCallback {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = unsafe { (self.callback)(self.userdata, buf.as_ptr() as _, buf.len()) };
+
+        if written < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the output callback has failed",
+            ));
+        }
+
+        // The callback can't have consumed more than it was given.
+        Ok((written as usize).min(buf.len()))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// For stdin. Every read is forwarded to the callback.
+pub struct InputCallback {
+    callback: wasi_stdio_read_callback_t,
+    userdata: *mut c_void,
+}
+
+impl InputCallback {
+    pub fn new(callback: wasi_stdio_read_callback_t, userdata: *mut c_void) -> Self {
+        Self { callback, userdata }
+    }
+}
+
+// The user data is only accessed by the callback, synchronization is
+// left to the C side.
+unsafe impl Send for InputCallback {}
+
+impl fmt::Debug for InputCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InputCallback")
+            .field("userdata", &self.userdata)
+            .finish()
+    }
+}
+
+impl WasiFile for InputCallback {
+    fn last_accessed(&self) -> u64 {
+        0
+    }
+    fn last_modified(&self) -> u64 {
+        0
+    }
+    fn created_time(&self) -> u64 {
+        0
+    }
+    fn size(&self) -> u64 {
+        0
+    }
+    fn set_len(&mut self, _len: u64) -> Result<(), WasiFsError> {
+        Err(WasiFsError::PermissionDenied)
+    }
+    fn unlink(&mut self) -> Result<(), WasiFsError> {
+        Ok(())
+    }
+    fn bytes_available(&self) -> Result<usize, WasiFsError> {
+        // The callback can't be asked without consuming its input,
+        // so always report some bytes, and let the read block.
+        Ok(1024)
+    }
+}
+
+impl Read for InputCallback {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = unsafe { (self.callback)(self.userdata, buf.as_mut_ptr() as _, buf.len()) };
+
+        if read < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the input callback has failed",
+            ));
+        }
+
+        // Don't trust a count larger than the buffer.
+        Ok((read as usize).min(buf.len()))
+    }
+}
+
+// fail when writing or Seeking
+impl Seek for InputCallback {
+    fn seek(&mut self, _pos: io::SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not seek an input callback",
+        ))
+    }
+}
+impl Write for InputCallback {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "can not write to an input callback",
+        ))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    unsafe extern "C" fn too_large(
+        _userdata: *mut c_void,
+        _data: *const c_char,
+        len: usize,
+    ) -> isize {
+        len as isize + 42
+    }
+
+    unsafe extern "C" fn failing(
+        _userdata: *mut c_void,
+        _data: *const c_char,
+        _len: usize,
+    ) -> isize {
+        -1
+    }
+
+    unsafe extern "C" fn too_large_read(
+        _userdata: *mut c_void,
+        _data: *mut c_char,
+        len: usize,
+    ) -> isize {
+        len as isize + 42
+    }
+
+    unsafe extern "C" fn failing_read(
+        _userdata: *mut c_void,
+        _data: *mut c_char,
+        _len: usize,
+    ) -> isize {
+        -1
+    }
+
+    #[test]
+    fn test_output_callback_count_is_checked() {
+        let mut output = OutputCallback::new(too_large, ptr::null_mut());
+        assert_eq!(output.write(b"hello").unwrap(), 5);
+
+        let mut output = OutputCallback::new(failing, ptr::null_mut());
+        assert!(output.write(b"hello").is_err());
+    }
+
+    #[test]
+    fn test_input_callback_count_is_checked() {
+        let mut buf = [0; 5];
+
+        let mut input = InputCallback::new(too_large_read, ptr::null_mut());
+        assert_eq!(input.read(&mut buf).unwrap(), 5);
+
+        let mut input = InputCallback::new(failing_read, ptr::null_mut());
+        assert!(input.read(&mut buf).is_err());
+    }
+}
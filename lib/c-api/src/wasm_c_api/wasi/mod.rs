@@ -2,6 +2,7 @@
 //!
 //! This API will be superseded by a standard WASI API when/if such a standard is created.
 
+mod callback_files;
 mod capture_files;
 
 pub use super::unstable::wasi::wasi_get_unordered_imports;
@@ -10,14 +11,18 @@ use super::{
     instance::wasm_instance_t,
     module::wasm_module_t,
     store::wasm_store_t,
+    types::wasm_byte_vec_t,
 };
 use crate::error::update_last_error;
 use std::cmp::min;
 use std::convert::TryFrom;
-use std::ffi::CStr;
+use std::ffi::{c_void, CStr};
+use std::io::{Read, Write};
 use std::os::raw::c_char;
+use std::path::Path;
 use std::slice;
 use wasmer_api::{Extern, NamedResolver};
+use wasmer_vfs::{mem_fs, FileSystem};
 use wasmer_wasi::{
     generate_import_object_from_env, get_wasi_version, WasiEnv, WasiFile, WasiState,
     WasiStateBuilder, WasiVersion,
@@ -29,6 +34,9 @@ pub struct wasi_config_t {
     inherit_stdout: bool,
     inherit_stderr: bool,
     inherit_stdin: bool,
+    stdout_callback: Option<callback_files::OutputCallback>,
+    stderr_callback: Option<callback_files::OutputCallback>,
+    stdin_callback: Option<callback_files::InputCallback>,
     state_builder: WasiStateBuilder,
 }
 
@@ -45,6 +53,9 @@ pub unsafe extern "C" fn wasi_config_new(
         inherit_stdout: true,
         inherit_stderr: true,
         inherit_stdin: true,
+        stdout_callback: None,
+        stderr_callback: None,
+        stdin_callback: None,
         state_builder: WasiState::new(prog_name),
     }))
 }
@@ -136,21 +147,25 @@ pub unsafe extern "C" fn wasi_config_mapdir(
 #[no_mangle]
 pub extern "C" fn wasi_config_capture_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = false;
+    config.stdout_callback = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdout(config: &mut wasi_config_t) {
     config.inherit_stdout = true;
+    config.stdout_callback = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_capture_stderr(config: &mut wasi_config_t) {
     config.inherit_stderr = false;
+    config.stderr_callback = None;
 }
 
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stderr(config: &mut wasi_config_t) {
     config.inherit_stderr = true;
+    config.stderr_callback = None;
 }
 
 //#[no_mangle]
@@ -161,6 +176,169 @@ pub extern "C" fn wasi_config_inherit_stderr(config: &mut wasi_config_t) {
 #[no_mangle]
 pub extern "C" fn wasi_config_inherit_stdin(config: &mut wasi_config_t) {
     config.inherit_stdin = true;
+    config.stdin_callback = None;
+}
+
+/// Callback receiving the data written by a WASI program to `stdout`
+/// or `stderr`, as soon as it's written.
+///
+/// It must return the number of bytes it has consumed, or a negative
+/// number if it has failed. A count larger than `data_len` is
+/// clamped to `data_len`.
+#[allow(non_camel_case_types)]
+pub type wasi_stdio_write_callback_t =
+    unsafe extern "C" fn(userdata: *mut c_void, data: *const c_char, data_len: usize) -> isize;
+
+/// Callback providing the data read by a WASI program from `stdin`.
+///
+/// It must write at most `buffer_len` bytes to `buffer`, and return
+/// the number of bytes written, `0` if there is nothing more to read,
+/// or a negative number if it has failed. It can block until some
+/// data is available.
+#[allow(non_camel_case_types)]
+pub type wasi_stdio_read_callback_t =
+    unsafe extern "C" fn(userdata: *mut c_void, buffer: *mut c_char, buffer_len: usize) -> isize;
+
+/// Non-standard function to send the `stdout` of the WASI program to
+/// a callback, called with `userdata` as its first argument.
+///
+/// `userdata` is owned by the caller, and must outlive the
+/// `wasi_env_t` created from this configuration.
+#[no_mangle]
+pub extern "C" fn wasi_config_stdout_callback(
+    config: &mut wasi_config_t,
+    callback: wasi_stdio_write_callback_t,
+    userdata: *mut c_void,
+) {
+    config.stdout_callback = Some(callback_files::OutputCallback::new(callback, userdata));
+}
+
+/// Non-standard function to send the `stderr` of the WASI program to
+/// a callback, called with `userdata` as its first argument.
+///
+/// `userdata` is owned by the caller, and must outlive the
+/// `wasi_env_t` created from this configuration.
+#[no_mangle]
+pub extern "C" fn wasi_config_stderr_callback(
+    config: &mut wasi_config_t,
+    callback: wasi_stdio_write_callback_t,
+    userdata: *mut c_void,
+) {
+    config.stderr_callback = Some(callback_files::OutputCallback::new(callback, userdata));
+}
+
+/// Non-standard function to feed the `stdin` of the WASI program
+/// from a callback, called with `userdata` as its first argument.
+///
+/// `userdata` is owned by the caller, and must outlive the
+/// `wasi_env_t` created from this configuration.
+#[no_mangle]
+pub extern "C" fn wasi_config_stdin_callback(
+    config: &mut wasi_config_t,
+    callback: wasi_stdio_read_callback_t,
+    userdata: *mut c_void,
+) {
+    config.stdin_callback = Some(callback_files::InputCallback::new(callback, userdata));
+}
+
+/// Non-standard type representing an in-memory filesystem, which can
+/// be populated by the host, and then used by a WASI program instead
+/// of the host filesystem.
+///
+/// The filesystem is shared: files written by the WASI program can
+/// be read by the host with [`wasi_filesystem_read_file`].
+#[derive(Debug)]
+#[allow(non_camel_case_types)]
+pub struct wasi_filesystem_t {
+    inner: mem_fs::FileSystem,
+}
+
+/// Creates a new empty in-memory filesystem, with only a root
+/// directory, `/`.
+#[no_mangle]
+pub extern "C" fn wasi_filesystem_new_in_memory() -> Box<wasi_filesystem_t> {
+    Box::new(wasi_filesystem_t {
+        inner: mem_fs::FileSystem::default(),
+    })
+}
+
+/// Delete a [`wasi_filesystem_t`].
+///
+/// The `wasi_env_t` using it keeps its own reference to the files.
+#[no_mangle]
+pub extern "C" fn wasi_filesystem_delete(_filesystem: Option<Box<wasi_filesystem_t>>) {}
+
+/// Creates a directory in the filesystem. Its parent must exist.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_filesystem_create_dir(
+    filesystem: &wasi_filesystem_t,
+    path: *const c_char,
+) -> bool {
+    let path = c_try!(CStr::from_ptr(path).to_str(); otherwise false);
+    c_try!(filesystem.inner.create_dir(Path::new(path)); otherwise false);
+
+    true
+}
+
+/// Creates a file in the filesystem with the given content, or
+/// replaces the content of an existing file. Its parent directory
+/// must exist.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_filesystem_write_file(
+    filesystem: &wasi_filesystem_t,
+    path: *const c_char,
+    content: &wasm_byte_vec_t,
+) -> bool {
+    let path = c_try!(CStr::from_ptr(path).to_str(); otherwise false);
+    let mut file = c_try!(filesystem
+        .inner
+        .new_open_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path); otherwise false);
+    c_try!(file.write_all(content.as_slice()); otherwise false);
+
+    true
+}
+
+/// Reads the whole content of a file of the filesystem into `out`.
+#[no_mangle]
+pub unsafe extern "C" fn wasi_filesystem_read_file(
+    filesystem: &wasi_filesystem_t,
+    path: *const c_char,
+    // own
+    out: &mut wasm_byte_vec_t,
+) -> bool {
+    let path = c_try!(CStr::from_ptr(path).to_str(); otherwise false);
+    let mut file = c_try!(filesystem
+        .inner
+        .new_open_options()
+        .read(true)
+        .open(path); otherwise false);
+    let mut content = Vec::new();
+    c_try!(file.read_to_end(&mut content); otherwise false);
+
+    out.set_buffer(content);
+
+    true
+}
+
+/// Non-standard function to make the WASI program use an in-memory
+/// filesystem instead of the host filesystem.
+///
+/// The directories given to [`wasi_config_preopen_dir`] and
+/// [`wasi_config_mapdir`] are then looked up in this filesystem,
+/// e.g. `wasi_config_preopen_dir(config, "/")` gives access to all of
+/// it.
+#[no_mangle]
+pub extern "C" fn wasi_config_set_filesystem(
+    config: &mut wasi_config_t,
+    filesystem: &wasi_filesystem_t,
+) {
+    config
+        .state_builder
+        .set_fs(Box::new(filesystem.inner.clone()));
 }
 
 #[allow(non_camel_case_types)]
@@ -174,18 +352,26 @@ pub struct wasi_env_t {
 /// It take ownership over the `wasi_config_t`.
 #[no_mangle]
 pub extern "C" fn wasi_env_new(mut config: Box<wasi_config_t>) -> Option<Box<wasi_env_t>> {
-    if !config.inherit_stdout {
+    if let Some(stdout_callback) = config.stdout_callback.take() {
+        config.state_builder.stdout(Box::new(stdout_callback));
+    } else if !config.inherit_stdout {
         config
             .state_builder
             .stdout(Box::new(capture_files::OutputCapturer::new()));
     }
 
-    if !config.inherit_stderr {
+    if let Some(stderr_callback) = config.stderr_callback.take() {
+        config.state_builder.stderr(Box::new(stderr_callback));
+    } else if !config.inherit_stderr {
         config
             .state_builder
             .stderr(Box::new(capture_files::OutputCapturer::new()));
     }
 
+    if let Some(stdin_callback) = config.stdin_callback.take() {
+        config.state_builder.stdin(Box::new(stdin_callback));
+    }
+
     // TODO: impl capturer for stdin
 
     let wasi_state = c_try!(config.state_builder.build());
@@ -474,4 +660,104 @@ mod tests {
         })
         .success();
     }
+
+    #[test]
+    fn test_wasi_filesystem_and_stdout_callback() {
+        (assert_c! {
+            #include "tests/wasmer.h"
+            #include <string.h>
+
+            typedef struct {
+                char data[64];
+                size_t size;
+            } output_t;
+
+            intptr_t on_stdout(void* userdata, const char* data, uintptr_t data_len) {
+                output_t* output = (output_t*) userdata;
+                assert(output->size + data_len <= sizeof(output->data));
+                memcpy(output->data + output->size, data, data_len);
+                output->size += data_len;
+
+                return data_len;
+            }
+
+            int main() {
+                wasm_engine_t* engine = wasm_engine_new();
+                wasm_store_t* store = wasm_store_new(engine);
+
+                // Prints the content of `/dir/hello.txt`, the
+                // preopened `/` being the file descriptor 4.
+                wasm_byte_vec_t wat;
+                wasmer_byte_vec_new_from_string(
+                    &wat,
+                    "(module\n"
+                    "  (import \"wasi_snapshot_preview1\" \"path_open\" (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_read\" (func $fd_read (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (import \"wasi_snapshot_preview1\" \"fd_write\" (func $fd_write (param i32 i32 i32 i32) (result i32)))\n"
+                    "  (memory (export \"memory\") 1)\n"
+                    "  (data (i32.const 0) \"dir/hello.txt\")\n"
+                    "  (func (export \"_start\")\n"
+                    "    (drop (call $path_open (i32.const 4) (i32.const 0) (i32.const 0) (i32.const 13) (i32.const 0) (i64.const 2) (i64.const 0) (i32.const 0) (i32.const 16)))\n"
+                    "    (i32.store (i32.const 32) (i32.const 64))\n"
+                    "    (i32.store (i32.const 36) (i32.const 64))\n"
+                    "    (drop (call $fd_read (i32.load (i32.const 16)) (i32.const 32) (i32.const 1) (i32.const 40)))\n"
+                    "    (i32.store (i32.const 36) (i32.load (i32.const 40)))\n"
+                    "    (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 1) (i32.const 44)))))"
+                );
+                wasm_byte_vec_t wasm;
+                wat2wasm(&wat, &wasm);
+
+                wasm_module_t* module = wasm_module_new(store, &wasm);
+                assert(module);
+
+                wasi_filesystem_t* filesystem = wasi_filesystem_new_in_memory();
+                assert(wasi_filesystem_create_dir(filesystem, "/dir"));
+
+                wasm_byte_vec_t content;
+                wasmer_byte_vec_new_from_string(&content, "Hello, World!");
+                assert(wasi_filesystem_write_file(filesystem, "/dir/hello.txt", &content));
+
+                output_t output = { { 0 }, 0 };
+
+                wasi_config_t* config = wasi_config_new("example_program");
+                wasi_config_set_filesystem(config, filesystem);
+                assert(wasi_config_preopen_dir(config, "/"));
+                wasi_config_stdout_callback(config, on_stdout, &output);
+
+                wasi_env_t* wasi_env = wasi_env_new(config);
+                assert(wasi_env);
+
+                wasm_extern_vec_t imports;
+                assert(wasi_get_imports(store, module, wasi_env, &imports));
+
+                wasm_instance_t* instance = wasm_instance_new(store, module, &imports, NULL);
+                assert(instance);
+
+                wasm_func_t* start = wasi_get_start_function(instance);
+                assert(start);
+
+                wasm_val_vec_t arguments = WASM_EMPTY_VEC;
+                wasm_val_vec_t results = WASM_EMPTY_VEC;
+                assert(wasm_func_call(start, &arguments, &results) == NULL);
+
+                assert(output.size == content.size);
+                assert(memcmp(output.data, content.data, content.size) == 0);
+
+                wasm_func_delete(start);
+                wasm_instance_delete(instance);
+                wasm_extern_vec_delete(&imports);
+                wasi_env_delete(wasi_env);
+                wasm_byte_vec_delete(&content);
+                wasi_filesystem_delete(filesystem);
+                wasm_module_delete(module);
+                wasm_byte_vec_delete(&wasm);
+                wasm_byte_vec_delete(&wat);
+                wasm_store_delete(store);
+                wasm_engine_delete(engine);
+
+                return 0;
+            }
+        })
+        .success();
+    }
 }
@@ -6,6 +6,8 @@ use crate::commands::Binfmt;
 use crate::commands::Compile;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 use crate::commands::CreateExe;
+#[cfg(feature = "compiler")]
+use crate::commands::TraceSummarize;
 #[cfg(feature = "wast")]
 use crate::commands::Wast;
 use crate::commands::{Cache, Config, Inspect, Run, SelfUpdate, Validate};
@@ -64,6 +66,11 @@ enum WasmerCLIOptions {
     #[structopt(name = "inspect")]
     Inspect(Inspect),
 
+    /// Summarize a trace written by the tracing middleware
+    #[cfg(feature = "compiler")]
+    #[structopt(name = "trace")]
+    Trace(TraceSummarize),
+
     /// Run spec testsuite
     #[cfg(feature = "wast")]
     #[structopt(name = "wast")]
@@ -88,6 +95,8 @@ impl WasmerCLIOptions {
             Self::CreateExe(create_exe) => create_exe.execute(),
             Self::Config(config) => config.execute(),
             Self::Inspect(inspect) => inspect.execute(),
+            #[cfg(feature = "compiler")]
+            Self::Trace(trace) => trace.execute(),
             #[cfg(feature = "wast")]
             Self::Wast(wast) => wast.execute(),
             #[cfg(target_os = "linux")]
@@ -117,7 +126,9 @@ pub fn wasmer_main() {
     } else {
         match command.unwrap_or(&"".to_string()).as_ref() {
             "cache" | "compile" | "config" | "create-exe" | "help" | "inspect" | "run"
-            | "self-update" | "validate" | "wast" | "binfmt" => WasmerCLIOptions::from_args(),
+            | "self-update" | "trace" | "validate" | "wast" | "binfmt" => {
+                WasmerCLIOptions::from_args()
+            }
             _ => {
                 WasmerCLIOptions::from_iter_safe(args.iter()).unwrap_or_else(|e| {
                     match e.kind {
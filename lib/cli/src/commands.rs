@@ -10,6 +10,8 @@ mod create_exe;
 mod inspect;
 mod run;
 mod self_update;
+#[cfg(feature = "compiler")]
+mod trace;
 mod validate;
 #[cfg(feature = "wast")]
 mod wast;
@@ -20,6 +22,8 @@ pub use binfmt::*;
 pub use compile::*;
 #[cfg(all(feature = "staticlib", feature = "compiler"))]
 pub use create_exe::*;
+#[cfg(feature = "compiler")]
+pub use trace::*;
 #[cfg(feature = "wast")]
 pub use wast::*;
 pub use {cache::*, config::*, inspect::*, run::*, self_update::*, validate::*};
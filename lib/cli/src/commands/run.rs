@@ -5,11 +5,16 @@ use crate::store::{CompilerType, EngineType, StoreOptions};
 use crate::suggestions::suggest_function_exports;
 use crate::warning;
 use anyhow::{anyhow, Context, Result};
+use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
+#[cfg(feature = "compiler")]
+use std::sync::Arc;
 use wasmer::*;
 #[cfg(feature = "cache")]
 use wasmer_cache::{Cache, FileSystemCache, Hash};
+#[cfg(feature = "compiler")]
+use wasmer_middlewares::Coverage;
 
 use structopt::StructOpt;
 
@@ -56,6 +61,11 @@ pub struct Run {
     #[structopt(flatten)]
     wasi: Wasi,
 
+    /// Write an lcov coverage report of the execution to the given file
+    #[cfg(feature = "compiler")]
+    #[structopt(long = "coverage", name = "LCOV_FILE", parse(from_os_str))]
+    coverage: Option<PathBuf>,
+
     /// Enable non-standard experimental IO devices
     #[cfg(feature = "io-devices")]
     #[structopt(long = "enable-io-devices")]
@@ -96,6 +106,15 @@ impl Run {
     }
 
     fn inner_execute(&self) -> Result<()> {
+        #[cfg(feature = "compiler")]
+        let (module, coverage) = match self.coverage {
+            Some(_) => {
+                let (module, coverage) = self.get_module_with_coverage()?;
+                (module, Some(coverage))
+            }
+            None => (self.get_module()?, None),
+        };
+        #[cfg(not(feature = "compiler"))]
         let module = self.get_module()?;
         #[cfg(feature = "emscripten")]
         {
@@ -127,7 +146,7 @@ impl Run {
                     }
                 };
 
-                run_emscripten_instance(
+                let result = run_emscripten_instance(
                     &mut instance,
                     &mut em_env,
                     &mut emscripten_globals,
@@ -138,7 +157,10 @@ impl Run {
                     },
                     self.args.iter().map(|arg| arg.as_str()).collect(),
                     None, //run.em_entrypoint.clone(),
-                )?;
+                );
+                #[cfg(feature = "compiler")]
+                self.write_coverage(coverage.as_deref(), &instance)?;
+                result?;
                 return Ok(());
             }
         }
@@ -200,7 +222,10 @@ impl Run {
         if let Some(ref invoke) = self.invoke {
             let imports = imports! {};
             let instance = Instance::new(&module, &imports)?;
-            let result = self.invoke_function(&instance, &invoke, &self.args)?;
+            let result = self.invoke_function(&instance, invoke, &self.args);
+            #[cfg(feature = "compiler")]
+            self.write_coverage(coverage.as_deref(), &instance)?;
+            let result = result?;
             println!(
                 "{}",
                 result
@@ -212,6 +237,8 @@ impl Run {
         } else {
             let start: Function = self.try_find_function(&instance, "_start", &[])?;
             let result = start.call(&[]);
+            #[cfg(feature = "compiler")]
+            self.write_coverage(coverage.as_deref(), &instance)?;
             #[cfg(feature = "wasi")]
             self.wasi.handle_result(result)?;
             #[cfg(not(feature = "wasi"))]
@@ -264,6 +291,49 @@ impl Run {
         Ok(module)
     }
 
+    /// Compiles the module with the coverage middleware. The cache is
+    /// bypassed, so that the instrumented module isn't stored.
+    #[cfg(feature = "compiler")]
+    fn get_module_with_coverage(&self) -> Result<(Module, Arc<Coverage>)> {
+        let contents = std::fs::read(self.path.clone())?;
+        if !is_wasm(&contents) && cfg!(not(feature = "wat")) {
+            bail!("`--coverage` requires a WebAssembly file");
+        }
+        #[cfg(feature = "wat")]
+        let contents = wat2wasm(&contents)?.into_owned();
+        let coverage = Arc::new(
+            Coverage::new(&contents)
+                .with_context(|| "failed to compute the basic blocks of the module")?,
+        );
+        let (store, engine_type, compiler_type) = self
+            .store
+            .get_store_with_middlewares(vec![coverage.clone()])?;
+        let mut module = Module::new(&store, &contents).with_context(|| {
+            format!(
+                "module instantiation failed (engine: {}, compiler: {})",
+                engine_type.to_string(),
+                compiler_type.to_string()
+            )
+        })?;
+        module.set_name(&self.path.file_name().unwrap_or_default().to_string_lossy());
+
+        Ok((module, coverage))
+    }
+
+    /// Writes the lcov coverage report, if `--coverage` is enabled.
+    #[cfg(feature = "compiler")]
+    fn write_coverage(&self, coverage: Option<&Coverage>, instance: &Instance) -> Result<()> {
+        if let (Some(coverage), Some(path)) = (coverage, &self.coverage) {
+            let counts = wasmer_middlewares::coverage::get_block_counts(instance);
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+            coverage
+                .write_lcov(&counts, &mut writer)
+                .and_then(|()| writer.flush())
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "cache")]
     fn get_module_from_cache(
         &self,
@@ -1,8 +1,12 @@
-use crate::utils::{parse_envvar, parse_mapdir};
-use anyhow::Result;
+use crate::utils::{parse_envvar, parse_mapdir, parse_mount};
+use anyhow::{Context, Result};
+use colored::*;
 use std::collections::BTreeSet;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use wasmer::{Instance, Module, RuntimeError, Val};
+use wasmer_wasi::types::*;
 use wasmer_wasi::{get_wasi_versions, WasiError, WasiState, WasiVersion};
 
 use structopt::StructOpt;
@@ -30,6 +34,16 @@ pub struct Wasi {
     )]
     mapped_dirs: Vec<(String, PathBuf)>,
 
+    /// Mount a tar archive, possibly zstd-compressed, or a packaged image, read-only, at a guest directory
+    #[structopt(
+        long = "mount",
+        name = "ARCHIVE:GUEST_DIR",
+        multiple = true,
+        parse(try_from_str = parse_mount),
+        number_of_values = 1,
+    )]
+    mounts: Vec<(PathBuf, String)>,
+
     /// Pass custom environment variables
     #[structopt(
         long = "env",
@@ -85,6 +99,26 @@ impl Wasi {
             .preopen_dirs(self.pre_opened_directories.clone())?
             .map_dirs(self.mapped_dirs.clone())?;
 
+        for (archive, guest_dir) in self.mounts.iter() {
+            let image = wasmer_vfs::image_fs::FileSystem::open(archive).with_context(|| {
+                match archive_compression(archive) {
+                    Some("zstd") => format!(
+                        "Could not mount `{}`: invalid zstd-compressed archive",
+                        archive.display()
+                    ),
+                    Some(compression) => format!(
+                        "Could not mount `{}`: {}-compressed archives aren't supported, only zstd-compressed ones are",
+                        archive.display(),
+                        compression
+                    ),
+                    None => format!("Could not mount `{}`", archive.display()),
+                }
+            })?;
+            wasi_state_builder.mount(Box::new(image), |p| {
+                p.directory("/").alias(guest_dir).read(true)
+            })?;
+        }
+
         #[cfg(feature = "experimental-io-devices")]
         {
             if self.enable_experimental_io_devices {
@@ -109,6 +143,16 @@ impl Wasi {
                         // We should exit with the provided exit code
                         std::process::exit(exit_code as _);
                     }
+                    Ok(WasiError::Signal(sig)) => {
+                        // Like a shell, report the signal and exit with 128 + its
+                        // number on the host
+                        eprintln!(
+                            "{}: terminated by signal {}",
+                            "error".red().bold(),
+                            wasi_signal_to_name(sig)
+                        );
+                        std::process::exit(host_signal(sig).map_or(1, |sig| 128 + sig));
+                    }
                     Ok(err) => err.into(),
                     Err(err) => err.into(),
                 };
@@ -130,3 +174,74 @@ impl Wasi {
         })
     }
 }
+
+/// Returns the number of a WASI signal on the host, if it has one: the
+/// WASI numbers only match the Linux ones up to `SIGTERM`.
+#[cfg(unix)]
+fn host_signal(sig: __wasi_signal_t) -> Option<i32> {
+    Some(match sig {
+        __WASI_SIGHUP => libc::SIGHUP,
+        __WASI_SIGINT => libc::SIGINT,
+        __WASI_SIGQUIT => libc::SIGQUIT,
+        __WASI_SIGILL => libc::SIGILL,
+        __WASI_SIGTRAP => libc::SIGTRAP,
+        __WASI_SIGABRT => libc::SIGABRT,
+        __WASI_SIGBUS => libc::SIGBUS,
+        __WASI_SIGFPE => libc::SIGFPE,
+        __WASI_SIGKILL => libc::SIGKILL,
+        __WASI_SIGUSR1 => libc::SIGUSR1,
+        __WASI_SIGSEGV => libc::SIGSEGV,
+        __WASI_SIGUSR2 => libc::SIGUSR2,
+        __WASI_SIGPIPE => libc::SIGPIPE,
+        __WASI_SIGALRM => libc::SIGALRM,
+        __WASI_SIGTERM => libc::SIGTERM,
+        __WASI_SIGCHLD => libc::SIGCHLD,
+        __WASI_SIGCONT => libc::SIGCONT,
+        __WASI_SIGSTOP => libc::SIGSTOP,
+        __WASI_SIGTSTP => libc::SIGTSTP,
+        __WASI_SIGTTIN => libc::SIGTTIN,
+        __WASI_SIGTTOU => libc::SIGTTOU,
+        __WASI_SIGURG => libc::SIGURG,
+        __WASI_SIGXCPU => libc::SIGXCPU,
+        __WASI_SIGXFSZ => libc::SIGXFSZ,
+        __WASI_SIGVTALRM => libc::SIGVTALRM,
+        __WASI_SIGPROF => libc::SIGPROF,
+        __WASI_SIGWINCH => libc::SIGWINCH,
+        // `SIGPOLL` is `SIGIO` on Linux, and only `SIGIO` elsewhere
+        __WASI_SIGPOLL => libc::SIGIO,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        __WASI_SIGPWR => libc::SIGPWR,
+        __WASI_SIGSYS => libc::SIGSYS,
+        _ => return None,
+    })
+}
+
+/// There are no signal numbers on this host.
+#[cfg(not(unix))]
+fn host_signal(_sig: __wasi_signal_t) -> Option<i32> {
+    None
+}
+
+/// Returns the compression of the archive at `path`, if it's a
+/// compressed archive, to explain why it can't be mounted.
+fn archive_compression(path: &Path) -> Option<&'static str> {
+    let mut header = [0; 4];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+
+    wasmer_vfs::image_fs::compression(&header)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_signal() {
+        assert_eq!(host_signal(__WASI_SIGTERM), Some(15));
+        // Linux has `SIGSTKFLT` at 16, which WASI doesn't.
+        assert_eq!(host_signal(__WASI_SIGCHLD), Some(17));
+        assert_eq!(host_signal(__WASI_SIGXCPU), Some(24));
+        assert_eq!(host_signal(__WASI_SIGSYS), Some(31));
+        assert_eq!(host_signal(0), None);
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+use structopt::StructOpt;
+use wasmer_middlewares::tracing::{CallTree, CallTreeNode, Trace, TraceSummary};
+
+#[derive(Debug, StructOpt)]
+/// The options for the `wasmer trace` subcommand
+pub struct TraceSummarize {
+    /// Trace file written by the tracing middleware
+    #[structopt(name = "FILE", parse(from_os_str))]
+    path: PathBuf,
+
+    /// Number of hottest functions to display
+    #[structopt(long = "top", default_value = "10")]
+    top: usize,
+
+    /// Maximum depth of the displayed call tree
+    #[structopt(long = "depth", default_value = "8")]
+    depth: usize,
+}
+
+impl TraceSummarize {
+    /// Runs logic for the `trace` subcommand
+    pub fn execute(&self) -> Result<()> {
+        self.inner_execute()
+            .context(format!("failed to summarize `{}`", self.path.display()))
+    }
+
+    fn inner_execute(&self) -> Result<()> {
+        let trace = Trace::read(File::open(&self.path)?)?;
+        let summary = trace.summary();
+
+        println!("Events: {}", trace.records.len());
+        println!(
+            "Duration: {}",
+            format_duration(
+                trace
+                    .records
+                    .last()
+                    .map(|record| record.time)
+                    .unwrap_or_default()
+            )
+        );
+        println!("Branches: {}", summary.branches);
+        println!(
+            "Memory accesses: {} loads, {} stores",
+            summary.loads, summary.stores
+        );
+
+        println!("Hottest functions:");
+        println!("  {:>10} {:>12} {:>12}  function", "calls", "self", "total");
+        for function in summary.functions.iter().take(self.top) {
+            println!(
+                "  {:>10} {:>12} {:>12}  {}",
+                function.calls,
+                format_duration(function.self_time),
+                format_duration(function.total_time),
+                function_label(&trace, function.function),
+            );
+        }
+
+        println!("Call tree:");
+        self.print_call_tree(&trace, &summary, summary.call_tree.root(), 0);
+
+        Ok(())
+    }
+
+    fn print_call_tree(
+        &self,
+        trace: &Trace,
+        summary: &TraceSummary,
+        node: &CallTreeNode,
+        depth: usize,
+    ) {
+        let tree: &CallTree = &summary.call_tree;
+        for child in tree.children(node) {
+            let function = child.function.unwrap_or_default();
+            println!(
+                "  {}{} ({} calls, {})",
+                "  ".repeat(depth),
+                function_label(trace, function),
+                child.calls,
+                format_duration(child.total_time),
+            );
+            if depth + 1 < self.depth {
+                self.print_call_tree(trace, summary, child, depth + 1);
+            } else if !child.children.is_empty() {
+                println!("  {}  ...", "  ".repeat(depth));
+            }
+        }
+    }
+}
+
+fn function_label(trace: &Trace, function: u32) -> String {
+    match trace.function_name(function) {
+        Some(name) => format!("{} (#{})", name, function),
+        None => format!("<function #{}>", function),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3?}", duration)
+}
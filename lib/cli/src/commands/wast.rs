@@ -1,16 +1,18 @@
 //! Runs a .wast WebAssembly test suites
 use crate::store::StoreOptions;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::BufWriter;
 use std::path::PathBuf;
 use structopt::StructOpt;
-use wasmer_wast::Wast as WastSpectest;
+use wasmer_wast::{DirectiveOutcome, TestReport, WastRunner};
 
 #[derive(Debug, StructOpt)]
 /// The options for the `wasmer wast` subcommand
 pub struct Wast {
-    /// Wast file to run
-    #[structopt(name = "FILE", parse(from_os_str))]
-    path: PathBuf,
+    /// Wast files to run
+    #[structopt(name = "FILE", parse(from_os_str), required = true)]
+    paths: Vec<PathBuf>,
 
     #[structopt(flatten)]
     store: StoreOptions,
@@ -18,20 +20,82 @@ pub struct Wast {
     #[structopt(short, long)]
     /// A flag to indicate wast stop at the first error or continue.
     fail_fast: bool,
+
+    /// Write a JUnit XML report to the given file
+    #[structopt(long = "junit", name = "JUNIT_FILE", parse(from_os_str))]
+    junit: Option<PathBuf>,
+
+    /// Write a JSON report to the given file
+    #[structopt(long = "json", name = "JSON_FILE", parse(from_os_str))]
+    json: Option<PathBuf>,
+
+    /// The number of files run in parallel (defaults to the number of CPUs)
+    #[structopt(short, long)]
+    jobs: Option<usize>,
 }
 
 impl Wast {
-    /// Runs logic for the `validate` subcommand
+    /// Runs logic for the `wast` subcommand
     pub fn execute(&self) -> Result<()> {
         self.inner_execute()
-            .context(format!("failed to test the wast `{}`", self.path.display()))
+            .context("failed to test the wast files")
     }
+
     fn inner_execute(&self) -> Result<()> {
         let (store, _engine_name, _compiler_name) = self.store.get_store()?;
-        let mut wast = WastSpectest::new_with_spectest(store);
-        wast.fail_fast = self.fail_fast;
-        wast.run_file(&self.path).with_context(|| "tests failed")?;
-        eprintln!("Wast tests succeeded for `{}`.", self.path.display());
+        let fail_fast = self.fail_fast;
+        let mut runner = WastRunner::new(move || store.clone())
+            .configure(move |_path, wast| wast.fail_fast = fail_fast);
+        if let Some(jobs) = self.jobs {
+            runner = runner.threads(jobs);
+        }
+        let report = runner.run_files(&self.paths)?;
+
+        self.write_reports(&report)?;
+
+        for file in report.files.iter() {
+            if let Some(error) = &file.error {
+                eprintln!(
+                    "Wast tests errored for `{}`: {}",
+                    file.path.display(),
+                    error
+                );
+            } else if file.is_success() {
+                eprintln!("Wast tests succeeded for `{}`.", file.path.display());
+            } else {
+                eprintln!("Failed directives on {}:", file.path.display());
+                for directive in file.directives.iter() {
+                    if let DirectiveOutcome::Failed { message, .. } = &directive.outcome {
+                        eprintln!("  • {} ({}:{})", message, directive.line, directive.col);
+                    }
+                }
+            }
+        }
+        eprintln!(
+            "{} passed, {} failed, {} skipped in {:.2}s",
+            report.passed(),
+            report.failed(),
+            report.skipped(),
+            report.duration.as_secs_f64()
+        );
+
+        if !report.is_success() {
+            bail!("tests failed");
+        }
+        Ok(())
+    }
+
+    fn write_reports(&self, report: &TestReport) -> Result<()> {
+        if let Some(path) = &self.junit {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create `{}`", path.display()))?;
+            report.write_junit(BufWriter::new(file))?;
+        }
+        if let Some(path) = &self.json {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create `{}`", path.display()))?;
+            report.write_json(BufWriter::new(file))?;
+        }
         Ok(())
     }
 }
@@ -382,7 +382,28 @@ impl StoreOptions {
         &self,
         target: Target,
     ) -> Result<(Store, EngineType, CompilerType)> {
-        let (compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        self.get_store_for_target_with_middlewares(target, vec![])
+    }
+
+    /// Gets the store for the host target, with the given middlewares
+    /// pushed to the compiler
+    pub fn get_store_with_middlewares(
+        &self,
+        middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    ) -> Result<(Store, EngineType, CompilerType)> {
+        let target = Target::default();
+        self.get_store_for_target_with_middlewares(target, middlewares)
+    }
+
+    fn get_store_for_target_with_middlewares(
+        &self,
+        target: Target,
+        middlewares: Vec<Arc<dyn ModuleMiddleware>>,
+    ) -> Result<(Store, EngineType, CompilerType)> {
+        let (mut compiler_config, compiler_type) = self.compiler.get_compiler_config()?;
+        for middleware in middlewares {
+            compiler_config.push_middleware(middleware);
+        }
         let (engine, engine_type) = self.get_engine_with_compiler(target, compiler_config)?;
         let store = Store::new(&*engine);
         Ok((store, engine_type, compiler_type))
@@ -40,6 +40,19 @@ pub fn parse_mapdir(entry: &str) -> Result<(String, PathBuf)> {
     }
 }
 
+/// Parses a mount of an archive, of the form `<archive>:<guest dir>`
+pub fn parse_mount(entry: &str) -> Result<(PathBuf, String)> {
+    match entry.rsplit_once(':') {
+        Some((archive, guest_dir)) if !archive.is_empty() && !guest_dir.is_empty() => {
+            Ok((PathBuf::from(archive), guest_dir.to_string()))
+        }
+        _ => bail!(
+            "Mounts must be of the form `<archive>:<guest dir>`; found `{}`",
+            &entry
+        ),
+    }
+}
+
 /// Parses an environment variable.
 pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
     let entry = entry.trim();
@@ -66,7 +79,7 @@ pub fn parse_envvar(entry: &str) -> Result<(String, String)> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_envvar;
+    use super::{parse_envvar, parse_mount};
 
     #[test]
     fn test_parse_envvar() {
@@ -89,4 +102,24 @@ mod tests {
             ("A".into(), "B=C=D".into())
         );
     }
+
+    #[test]
+    fn test_parse_mount() {
+        assert_eq!(
+            parse_mount("data.tar").unwrap_err().to_string(),
+            "Mounts must be of the form `<archive>:<guest dir>`; found `data.tar`"
+        );
+        assert_eq!(
+            parse_mount("data.tar:").unwrap_err().to_string(),
+            "Mounts must be of the form `<archive>:<guest dir>`; found `data.tar:`"
+        );
+        assert_eq!(
+            parse_mount("data.tar:/data").unwrap(),
+            ("data.tar".into(), "/data".into())
+        );
+        assert_eq!(
+            parse_mount("C:\\data.tar:/data").unwrap(),
+            ("C:\\data.tar".into(), "/data".into())
+        );
+    }
 }
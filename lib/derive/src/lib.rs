@@ -6,6 +6,7 @@ use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, *};
 
 mod parse;
+mod value_type;
 
 use crate::parse::WasmerAttr;
 
@@ -17,6 +18,20 @@ pub fn derive_wasmer_env(input: proc_macro::TokenStream) -> proc_macro::TokenStr
     gen.into()
 }
 
+/// Implements `ValueType` for a `#[repr(C)]` struct, checking at compile
+/// time that its fields are `ValueType`s and that it has no padding.
+///
+/// It also implements `LittleEndianConvert`, and generates `get_*` and
+/// `set_*` accessors for the named fields that convert them from and to
+/// the little-endian byte order of Wasm memory.
+#[proc_macro_error]
+#[proc_macro_derive(ValueType)]
+pub fn derive_value_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let gen = value_type::impl_value_type(&input);
+    gen.into()
+}
+
 fn impl_wasmer_env_for_struct(
     name: &Ident,
     data: &DataStruct,
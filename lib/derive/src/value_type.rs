@@ -0,0 +1,145 @@
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{spanned::Spanned, *};
+
+pub fn impl_value_type(input: &DeriveInput) -> TokenStream {
+    let struct_name = &input.ident;
+
+    let data = match &input.data {
+        Data::Struct(ds) => ds,
+        _ => abort!(input, "ValueType derive only supports structs"),
+    };
+    if !input.generics.params.is_empty() {
+        abort!(
+            input.generics,
+            "ValueType derive doesn't support generic structs"
+        );
+    }
+    check_repr(input);
+
+    let fields: Vec<&Field> = match &data.fields {
+        Fields::Named(fields) => fields.named.iter().collect(),
+        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+        Fields::Unit => vec![],
+    };
+    let field_types: Vec<&Type> = fields.iter().map(|f| &f.ty).collect();
+    let members: Vec<Member> = fields
+        .iter()
+        .enumerate()
+        .map(|(idx, f)| match &f.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(idx)),
+        })
+        .collect();
+
+    // Every field must be a `ValueType`, i.e. valid for all bit patterns.
+    let field_checks = field_types.iter().map(|ty| {
+        quote_spanned! {ty.span()=>
+            assert_value_type::<#ty>();
+        }
+    });
+
+    // The struct must not have any padding, which is left uninitialized
+    // by Rust and couldn't be safely copied to or from Wasm memory.
+    //
+    // The sum isn't seeded with `0`, which would trigger
+    // `clippy::identity_op` in the user's crate.
+    let fields_size = if field_types.is_empty() {
+        quote! { 0 }
+    } else {
+        quote! { #(::core::mem::size_of::<#field_types>())+* }
+    };
+    let padding_check = quote_spanned! {struct_name.span()=>
+        const _: () = {
+            struct ValueTypeHasPadding<const HAS_PADDING: bool>;
+            let _: ValueTypeHasPadding<false> = ValueTypeHasPadding::<{
+                ::core::mem::size_of::<#struct_name>() != #fields_size
+            }>;
+        };
+    };
+
+    let accessors = fields.iter().filter_map(|f| {
+        let name = f.ident.as_ref()?;
+        let ty = &f.ty;
+        let vis = &f.vis;
+        let getter = format_ident!("get_{}", name);
+        let setter = format_ident!("set_{}", name);
+        let getter_doc = format!(
+            "Returns `{}`, converted from the little-endian byte order of Wasm memory.",
+            name
+        );
+        let setter_doc = format!(
+            "Sets `{}`, converted to the little-endian byte order of Wasm memory.",
+            name
+        );
+        Some(quote_spanned! {f.span()=>
+            #[doc = #getter_doc]
+            #[inline]
+            #vis fn #getter(&self) -> #ty {
+                ::wasmer::LittleEndianConvert::little_endian_to_native(self.#name)
+            }
+
+            #[doc = #setter_doc]
+            #[inline]
+            #vis fn #setter(&mut self, value: #ty) {
+                self.#name = ::wasmer::LittleEndianConvert::native_to_little_endian(value);
+            }
+        })
+    });
+
+    quote! {
+        const _: () = {
+            fn assert_value_type<T: ::wasmer::ValueType>() {}
+            #[allow(dead_code)]
+            fn assert_fields() {
+                #(#field_checks)*
+            }
+        };
+
+        #padding_check
+
+        unsafe impl ::wasmer::ValueType for #struct_name {}
+
+        impl ::wasmer::LittleEndianConvert for #struct_name {
+            #[inline]
+            fn little_endian_to_native(self) -> Self {
+                Self {
+                    #(#members: ::wasmer::LittleEndianConvert::little_endian_to_native(self.#members),)*
+                }
+            }
+
+            #[inline]
+            fn native_to_little_endian(self) -> Self {
+                Self {
+                    #(#members: ::wasmer::LittleEndianConvert::native_to_little_endian(self.#members),)*
+                }
+            }
+        }
+
+        #[allow(dead_code)]
+        impl #struct_name {
+            #(#accessors)*
+        }
+    }
+}
+
+/// Aborts unless the struct has a defined layout, with `#[repr(C)]` or
+/// `#[repr(transparent)]`.
+fn check_repr(input: &DeriveInput) {
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("repr")) {
+        if let Ok(Meta::List(list)) = attr.parse_meta() {
+            let has_layout = list.nested.iter().any(|nested| {
+                matches!(nested, NestedMeta::Meta(Meta::Path(path))
+                    if path.is_ident("C") || path.is_ident("transparent"))
+            });
+            if has_layout {
+                return;
+            }
+        }
+    }
+    abort!(
+        input.ident,
+        "ValueType derive requires a `#[repr(C)]` or `#[repr(transparent)]` struct"
+    );
+}
@@ -0,0 +1,11 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+#[repr(C)]
+struct InvalidBitPatterns {
+    a: bool, //~ ERROR the trait bound `bool: ValueType` is not satisfied
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+struct NoRepr { //~ ValueType derive requires a `#[repr(C)]` or `#[repr(transparent)]` struct
+    a: u32,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+extern crate wasmer;
+
+use wasmer::ValueType;
+
+#[derive(ValueType, Clone, Copy)]
+#[repr(C)]
+struct Padded { //~ ERROR mismatched types
+    a: u8,
+    b: u32,
+}
+
+fn main() {}
@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use wasmer::{LittleEndianConvert, Memory, MemoryType, Store, ValueType, WasmPtr};
+
+#[derive(ValueType, Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+struct Point {
+    x: u32,
+    y: f32,
+}
+
+#[derive(ValueType, Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+struct Shape {
+    origin: Point,
+    points: [[u16; 2]; 4],
+    data: WasmPtr<u8>,
+    flags: u32,
+    kind: u64,
+}
+
+#[derive(ValueType, Copy, Clone, Debug, PartialEq)]
+#[repr(transparent)]
+struct Fd(u32);
+
+#[derive(ValueType, Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+struct Empty;
+
+fn impls_value_type<T: ValueType>() -> bool {
+    true
+}
+
+#[test]
+fn test_derive() {
+    assert!(impls_value_type::<Point>());
+    assert!(impls_value_type::<Shape>());
+    assert!(impls_value_type::<Fd>());
+    assert!(impls_value_type::<Empty>());
+}
+
+#[test]
+fn test_accessors() {
+    let mut point = Point { x: 0, y: 0.0 };
+    point.set_x(0x01020304);
+    point.set_y(1.5);
+    assert_eq!(point.x, 0x01020304u32.to_le());
+    assert_eq!(point.get_x(), 0x01020304);
+    assert_eq!(point.get_y().to_bits(), 1.5f32.to_bits());
+
+    let fd = Fd(7).native_to_little_endian();
+    assert_eq!(fd.little_endian_to_native(), Fd(7));
+}
+
+#[test]
+fn test_memory_layout() {
+    let store = Store::default();
+    let memory = Memory::new(&store, MemoryType::new(1, None, false)).unwrap();
+
+    let mut shape = Shape {
+        origin: Point { x: 0, y: 0.0 },
+        points: [[0; 2]; 4],
+        data: WasmPtr::new(0),
+        flags: 0,
+        kind: 0,
+    };
+    shape.set_origin(
+        Point { x: 1, y: 2.0 }
+            .native_to_little_endian()
+            .little_endian_to_native(),
+    );
+    shape.set_points([[1, 2], [3, 4], [5, 6], [7, 0x0102]]);
+    shape.set_data(WasmPtr::new(0x100));
+    shape.set_kind(3);
+
+    let ptr: WasmPtr<Shape> = WasmPtr::new(8);
+    ptr.write(&memory, shape).unwrap();
+
+    // The fields are laid out in order, in little-endian byte order.
+    let mut bytes = [0; 40];
+    memory.read(8, &mut bytes).unwrap();
+    assert_eq!(&bytes[0..4], &[1, 0, 0, 0]);
+    assert_eq!(&bytes[4..8], &2.0f32.to_le_bytes());
+    assert_eq!(&bytes[20..24], &[7, 0, 2, 1]);
+    assert_eq!(&bytes[24..28], &[0, 1, 0, 0]);
+    assert_eq!(&bytes[32..40], &[3, 0, 0, 0, 0, 0, 0, 0]);
+
+    let read = ptr.read(&memory).unwrap();
+    assert_eq!(read, shape);
+    assert_eq!(read.get_origin().get_y().to_bits(), 2.0f32.to_bits());
+    assert_eq!(read.get_points()[3], [7, 0x0102]);
+    assert_eq!(read.get_data().offset(), 0x100);
+}
@@ -243,7 +243,7 @@ pub struct MetadataHeader {
 impl MetadataHeader {
     /// Current ABI version. Increment this any time breaking changes are made
     /// to the format of the serialized data.
-    const CURRENT_VERSION: u32 = 1;
+    const CURRENT_VERSION: u32 = 2;
 
     /// Magic number to identify wasmer metadata.
     const MAGIC: [u8; 8] = *b"WASMER\0\0";
@@ -293,3 +293,29 @@ impl MetadataHeader {
         Ok(header.len as usize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C, align(16))]
+    struct AlignedHeader([u8; MetadataHeader::LEN]);
+
+    #[test]
+    fn parses_current_version() {
+        let header = AlignedHeader(MetadataHeader::new(42));
+        assert_eq!(MetadataHeader::parse(&header.0).unwrap(), 42);
+    }
+
+    #[test]
+    fn rejects_old_versions() {
+        let mut header = AlignedHeader(MetadataHeader::new(42));
+        // Artifacts serialized before `ModuleInfo::code_section_offset`
+        // was added have version 1.
+        header.0[8..12].copy_from_slice(&1u32.to_ne_bytes());
+        assert!(matches!(
+            MetadataHeader::parse(&header.0),
+            Err(DeserializeError::Incompatible(_))
+        ));
+    }
+}
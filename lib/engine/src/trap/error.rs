@@ -219,6 +219,15 @@ impl RuntimeError {
             _ => false,
         }
     }
+
+    /// Returns a reference to the user error if it is of type `T`,
+    /// without consuming the `RuntimeError` like `downcast` does.
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        match &self.inner.source {
+            RuntimeErrorSource::User(err) => err.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for RuntimeError {
@@ -257,6 +266,13 @@ impl fmt::Display for RuntimeError {
                 func_index,
                 frame.module_offset()
             )?;
+            if let (Some(file), Some(line)) = (frame.source_file(), frame.line()) {
+                writeln!(f)?;
+                write!(f, "        at {}:{}", file, line)?;
+                if let Some(column) = frame.column() {
+                    write!(f, ":{}", column)?;
+                }
+            }
         }
         Ok(())
     }
@@ -11,7 +11,9 @@
 //! let module: ModuleInfo = ...;
 //! FRAME_INFO.register(module, compiled_functions);
 //! ```
+use super::line_table::{LineTable, SourceLocation};
 use loupe::MemoryUsage;
+use once_cell::sync::OnceCell;
 use std::cmp;
 use std::collections::BTreeMap;
 use std::sync::{Arc, RwLock};
@@ -66,6 +68,8 @@ struct ModuleInfoFrameInfo {
     functions: BTreeMap<usize, FunctionInfo>,
     module: Arc<ModuleInfo>,
     frame_infos: PrimaryMap<LocalFunctionIndex, CompiledFunctionFrameInfo>,
+    /// The DWARF line table of the module, parsed on first use.
+    line_table: OnceCell<Option<LineTable>>,
 }
 
 impl ModuleInfoFrameInfo {
@@ -73,6 +77,12 @@ impl ModuleInfoFrameInfo {
         &self.frame_infos.get(local_index).unwrap()
     }
 
+    fn line_table(&self) -> Option<&LineTable> {
+        self.line_table
+            .get_or_init(|| LineTable::new(&self.module))
+            .as_ref()
+    }
+
     /// Gets a function given a pc
     fn function_info(&self, pc: usize) -> Option<&FunctionInfo> {
         let (end, func) = self.functions.range(pc..).next()?;
@@ -139,12 +149,17 @@ impl GlobalFrameInfo {
             None => instr_map.start_srcloc,
         };
         let func_index = module.module.func_index(func.local_index);
+        let source_location = module.line_table().and_then(|line_table| {
+            let address = (instr.bits() as usize).checked_sub(module.module.code_section_offset)?;
+            line_table.lookup(address as u64).cloned()
+        });
         Some(FrameInfo {
             module_name: module.module.name(),
             func_index: func_index.index() as u32,
             function_name: module.module.function_names.get(&func_index).cloned(),
             instr,
             func_start: instr_map.start_srcloc,
+            source_location,
         })
     }
 
@@ -244,6 +259,7 @@ pub fn register(
             functions,
             module,
             frame_infos,
+            line_table: OnceCell::new(),
         },
     );
     assert!(prev.is_none());
@@ -265,6 +281,7 @@ pub struct FrameInfo {
     function_name: Option<String>,
     func_start: SourceLoc,
     instr: SourceLoc,
+    source_location: Option<SourceLocation>,
 }
 
 impl FrameInfo {
@@ -325,4 +342,29 @@ impl FrameInfo {
     pub fn func_offset(&self) -> usize {
         (self.instr.bits() - self.func_start.bits()) as usize
     }
+
+    /// Returns the path of the source file this frame's program
+    /// counter comes from, if the module has DWARF line tables.
+    ///
+    /// The path is the one recorded at compile time, relative to the
+    /// compilation directory when the latter is unknown.
+    pub fn source_file(&self) -> Option<&str> {
+        self.source_location
+            .as_ref()
+            .map(|location| &*location.file)
+    }
+
+    /// Returns the 1-based line in [`FrameInfo::source_file`] this
+    /// frame's program counter comes from, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.source_location.as_ref().map(|location| location.line)
+    }
+
+    /// Returns the 1-based column in [`FrameInfo::source_file`] this
+    /// frame's program counter comes from, if known.
+    pub fn column(&self) -> Option<u32> {
+        self.source_location
+            .as_ref()
+            .and_then(|location| location.column)
+    }
 }
@@ -0,0 +1,182 @@
+//! Source-level symbolication of WebAssembly frames, based on the
+//! DWARF line tables found in the `.debug_line` custom section (and
+//! its companion `.debug_*` sections) of a module.
+
+use gimli::{ColumnType, EndianArcSlice, LittleEndian, Reader, SectionId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wasmer_types::ModuleInfo;
+
+type DwarfReader = EndianArcSlice<LittleEndian>;
+
+/// A source location.
+#[derive(Debug, Clone)]
+pub(crate) struct SourceLocation {
+    pub(crate) file: Arc<str>,
+    pub(crate) line: u32,
+    pub(crate) column: Option<u32>,
+}
+
+/// A row of a line table.
+#[derive(Debug)]
+struct Row {
+    /// The DWARF address, relative to the code section contents.
+    address: u64,
+
+    /// The source location of the code starting at `address`, or
+    /// `None` at the end of a sequence.
+    location: Option<SourceLocation>,
+}
+
+/// The line tables of a module, merged and sorted by address.
+#[derive(Debug)]
+pub(crate) struct LineTable {
+    rows: Vec<Row>,
+}
+
+impl LineTable {
+    /// Parses the line tables of `module`. Returns `None` when the
+    /// module has no (valid) DWARF line table.
+    pub(crate) fn new(module: &ModuleInfo) -> Option<Self> {
+        module.custom_sections(".debug_line").next()?;
+
+        let load_section = |id: SectionId| -> Result<DwarfReader, gimli::Error> {
+            let data = module
+                .custom_sections(id.name())
+                .next()
+                .unwrap_or_else(|| Arc::from(&[][..]));
+            Ok(EndianArcSlice::new(data, LittleEndian))
+        };
+        let dwarf = gimli::Dwarf::load(load_section).ok()?;
+
+        let mut rows = Vec::new();
+        let mut units = dwarf.units();
+
+        while let Ok(Some(header)) = units.next() {
+            let unit = match dwarf.unit(header) {
+                Ok(unit) => unit,
+                Err(_) => continue,
+            };
+            let program = match unit.line_program.clone() {
+                Some(program) => program,
+                None => continue,
+            };
+
+            let mut files: HashMap<u64, Arc<str>> = HashMap::new();
+            let mut sequence: Vec<Row> = Vec::new();
+            let mut program_rows = program.rows();
+
+            while let Ok(Some((header, row))) = program_rows.next_row() {
+                if row.end_sequence() {
+                    sequence.push(Row {
+                        address: row.address(),
+                        location: None,
+                    });
+
+                    // Sequences of functions removed by the linker
+                    // start at address 0, and overlap live code.
+                    if sequence[0].address != 0 {
+                        rows.append(&mut sequence);
+                    } else {
+                        sequence.clear();
+                    }
+                    continue;
+                }
+
+                let line = match row.line() {
+                    Some(line) => line.get() as u32,
+                    None => continue,
+                };
+                let column = match row.column() {
+                    ColumnType::LeftEdge => None,
+                    ColumnType::Column(column) => Some(column.get() as u32),
+                };
+                let file = match files.get(&row.file_index()) {
+                    Some(file) => file.clone(),
+                    None => {
+                        let file: Arc<str> = row
+                            .file(header)
+                            .and_then(|file| file_path(&dwarf, &unit, header, file))
+                            .unwrap_or_else(|| "<unknown>".to_string())
+                            .into();
+                        files.insert(row.file_index(), file.clone());
+                        file
+                    }
+                };
+
+                sequence.push(Row {
+                    address: row.address(),
+                    location: Some(SourceLocation { file, line, column }),
+                });
+            }
+        }
+
+        if rows.is_empty() {
+            return None;
+        }
+
+        // Keep the end of a sequence before the start of the next one
+        // at the same address.
+        rows.sort_by_key(|row| (row.address, row.location.is_some()));
+
+        Some(Self { rows })
+    }
+
+    /// Returns the source location of the code at `address`, relative
+    /// to the code section contents.
+    pub(crate) fn lookup(&self, address: u64) -> Option<&SourceLocation> {
+        let index = match self.rows.binary_search_by_key(&address, |row| row.address) {
+            Ok(mut index) => {
+                // Pick the last row at this address.
+                while index + 1 < self.rows.len() && self.rows[index + 1].address == address {
+                    index += 1;
+                }
+                index
+            }
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+
+        self.rows[index].location.as_ref()
+    }
+}
+
+/// Renders the path of a file of a line table, prefixed with its
+/// directory and the compilation directory when they are relative.
+fn file_path(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &gimli::Unit<DwarfReader>,
+    header: &gimli::LineProgramHeader<DwarfReader>,
+    file: &gimli::FileEntry<DwarfReader>,
+) -> Option<String> {
+    let attr_string = |attr| -> Option<String> {
+        let string = dwarf.attr_string(unit, attr).ok()?;
+        Some(string.to_string_lossy().ok()?.into_owned())
+    };
+
+    let mut path = match &unit.comp_dir {
+        Some(comp_dir) => comp_dir.to_string_lossy().ok()?.into_owned(),
+        None => String::new(),
+    };
+
+    if let Some(directory) = file.directory(header).and_then(attr_string) {
+        push_path(&mut path, &directory);
+    }
+
+    push_path(&mut path, &attr_string(file.path_name())?);
+
+    Some(path)
+}
+
+/// Appends `component` to `path`, or replaces `path` when
+/// `component` is absolute.
+fn push_path(path: &mut String, component: &str) {
+    if component.starts_with('/') || path.is_empty() {
+        *path = component.to_string();
+    } else {
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(component);
+    }
+}
@@ -1,5 +1,6 @@
 mod error;
 mod frame_info;
+mod line_table;
 pub use error::RuntimeError;
 pub use frame_info::{
     is_wasm_pc, register as register_frame_info, FrameInfo, FunctionExtent,
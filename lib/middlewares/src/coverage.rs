@@ -0,0 +1,675 @@
+//! `coverage` is a middleware for measuring which parts of a
+//! WebAssembly module are executed. It counts how many times every
+//! basic block is executed, with one global counter per block.
+//!
+//! The counters can be read after the execution with
+//! [`get_block_counts`], and turned into an lcov report with
+//! [`Coverage::write_lcov`]. Source lines are resolved with the DWARF
+//! sections of the module when they are present; otherwise, the
+//! report only contains function coverage, named after the name
+//! section.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::{CompilerConfig, Instance};
+//! use wasmer_middlewares::coverage::{get_block_counts, Coverage};
+//!
+//! fn create_coverage_middleware(
+//!     compiler_config: &mut dyn CompilerConfig,
+//!     wasm_bytes: &[u8],
+//! ) -> Arc<Coverage> {
+//!     let coverage = Arc::new(Coverage::new(wasm_bytes).unwrap());
+//!
+//!     compiler_config.push_middleware(coverage.clone());
+//!
+//!     coverage
+//! }
+//!
+//! fn write_report(coverage: &Coverage, instance: &Instance) -> std::io::Result<()> {
+//!     let counts = get_block_counts(instance);
+//!     let file = std::fs::File::create("coverage.lcov")?;
+//!
+//!     coverage.write_lcov(&counts, file)
+//! }
+//! ```
+
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::ops::Range;
+use std::sync::Mutex;
+use wasmer::wasmparser::{BinaryReaderError, Name, NameSectionReader, Operator, Parser, Payload};
+use wasmer::{
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, Instance, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
+use wasmer_types::{GlobalIndex, ModuleInfo};
+
+/// The prefix of the exported counter globals. The counter of block
+/// `n` is exported as `wasmer_coverage_counter_{n}`.
+const COUNTER_EXPORT_PREFIX: &str = "wasmer_coverage_counter_";
+
+/// A basic block of the module, as laid out in the code section.
+#[derive(Debug, Clone, PartialEq, Eq, MemoryUsage)]
+pub struct CoverageBlock {
+    /// The index of the function containing the block.
+    pub function: u32,
+
+    /// The offset of the first operator of the block, relative to the
+    /// start of the code section (as DWARF addresses are).
+    pub start: u64,
+
+    /// The offset following the last operator of the block, relative
+    /// to the start of the code section.
+    pub end: u64,
+}
+
+/// Tells where basic blocks start. A new block starts at the
+/// beginning of the function body and after every operator for which
+/// `starts_block_after` returns true.
+#[derive(Default)]
+struct BlockBoundaries {
+    /// The current block nesting depth.
+    depth: u32,
+}
+
+impl BlockBoundaries {
+    fn starts_block_after(&mut self, operator: &Operator) -> bool {
+        match operator {
+            Operator::Block { .. } => {
+                self.depth += 1;
+                false
+            }
+            Operator::Loop { .. } | Operator::If { .. } => {
+                self.depth += 1;
+                true
+            }
+            // The end of the function body.
+            Operator::End if self.depth == 0 => false,
+            Operator::End => {
+                self.depth -= 1;
+                true
+            }
+            Operator::Else | Operator::BrIf { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+/// The module-level coverage middleware.
+///
+/// The basic blocks are computed from the module bytes when the
+/// middleware is created, hence it must be pushed before any other
+/// middleware changing the control flow of the functions (like
+/// [`Metering`][crate::Metering]).
+///
+/// # Panic
+///
+/// An instance of `Coverage` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// basic blocks and the global index of their counters. Attempts to
+/// use a `Coverage` instance from multiple modules will result in a
+/// panic.
+pub struct Coverage {
+    /// All the basic blocks, ordered by function.
+    blocks: Vec<CoverageBlock>,
+
+    /// For each local function, the range of its blocks in `blocks`.
+    function_blocks: Vec<Range<usize>>,
+
+    /// The module name from the name section.
+    module_name: Option<String>,
+
+    /// The function names from the name section.
+    function_names: HashMap<u32, String>,
+
+    /// The DWARF sections of the module, by name.
+    debug_sections: HashMap<String, Vec<u8>>,
+
+    /// The global index of the counter of the first block.
+    first_counter: Mutex<Option<GlobalIndex>>,
+}
+
+/// The function-level coverage middleware.
+pub struct FunctionCoverage {
+    /// The global index of the counter of the next block.
+    next_counter: u32,
+
+    /// The global index following the counter of the last block of
+    /// the function.
+    end_counter: u32,
+
+    /// Whether the counter of the first block has been injected.
+    started: bool,
+
+    boundaries: BlockBoundaries,
+}
+
+impl Coverage {
+    /// Creates a `Coverage` middleware for the given module bytes,
+    /// which must be the ones that are compiled.
+    pub fn new(wasm: &[u8]) -> Result<Self, BinaryReaderError> {
+        let mut blocks: Vec<CoverageBlock> = Vec::new();
+        let mut function_blocks = Vec::new();
+        let mut module_name = None;
+        let mut function_names = HashMap::new();
+        let mut debug_sections = HashMap::new();
+        let mut num_imported_functions = 0;
+        let mut code_section_start = 0;
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        if let wasmer::wasmparser::ImportSectionEntryType::Function(_) = import?.ty
+                        {
+                            num_imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => {
+                    code_section_start = range.start as u64;
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let function = num_imported_functions + function_blocks.len() as u32;
+                    let first_block = blocks.len();
+                    let mut operators = body.get_operators_reader()?;
+                    let mut boundaries = BlockBoundaries::default();
+                    let mut new_block = true;
+
+                    while !operators.eof() {
+                        let (operator, offset) = operators.read_with_offset()?;
+                        let offset = offset as u64 - code_section_start;
+
+                        if new_block {
+                            if blocks.len() > first_block {
+                                blocks.last_mut().unwrap().end = offset;
+                            }
+
+                            blocks.push(CoverageBlock {
+                                function,
+                                start: offset,
+                                end: offset,
+                            });
+                        }
+
+                        new_block = boundaries.starts_block_after(&operator);
+                    }
+
+                    let end = operators.original_position() as u64 - code_section_start;
+                    if let Some(block) = blocks.last_mut() {
+                        block.end = end;
+                    }
+
+                    function_blocks.push(first_block..blocks.len());
+                }
+                Payload::CustomSection {
+                    name: "name",
+                    data,
+                    data_offset,
+                    ..
+                } => {
+                    // A malformed name section is ignored, as it is
+                    // by the module translation.
+                    if let Ok(mut names) = NameSectionReader::new(data, data_offset) {
+                        while let Ok(subsection) = names.read() {
+                            match subsection {
+                                Name::Module(module) => {
+                                    module_name = module.get_name().ok().map(ToString::to_string);
+                                }
+                                Name::Function(function_subsection) => {
+                                    if let Ok(mut naming) = function_subsection.get_map() {
+                                        for _ in 0..naming.get_count() {
+                                            if let Ok(naming) = naming.read() {
+                                                function_names
+                                                    .insert(naming.index, naming.name.to_string());
+                                            }
+                                        }
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                Payload::CustomSection { name, data, .. } if name.starts_with(".debug_") => {
+                    debug_sections.insert(name.to_string(), data.to_vec());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            blocks,
+            function_blocks,
+            module_name,
+            function_names,
+            debug_sections,
+            first_counter: Mutex::new(None),
+        })
+    }
+
+    /// Returns the basic blocks of the module. The counter of the
+    /// block `n` is the `n`th value returned by [`get_block_counts`].
+    pub fn blocks(&self) -> &[CoverageBlock] {
+        &self.blocks
+    }
+
+    /// Returns the name of a function, from the name section.
+    pub fn function_name(&self, function: u32) -> Option<&str> {
+        self.function_names.get(&function).map(String::as_str)
+    }
+
+    /// Writes an lcov report from the block counts returned by
+    /// [`get_block_counts`].
+    ///
+    /// Line coverage is reported for every source file referenced by
+    /// the DWARF line tables of the module. A line is reported as
+    /// executed as many times as the most executed block of that
+    /// line. Function coverage is always reported.
+    pub fn write_lcov<W: Write>(&self, counts: &[u64], mut writer: W) -> io::Result<()> {
+        if counts.len() != self.blocks.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "expected {} block counts, got {}",
+                    self.blocks.len(),
+                    counts.len()
+                ),
+            ));
+        }
+
+        // Line counts, by source file.
+        let mut files: BTreeMap<String, BTreeMap<u32, u64>> = BTreeMap::new();
+        // The source location of the first block of each function.
+        let mut function_locations: HashMap<u32, (String, u32)> = HashMap::new();
+
+        if let Some(context) = self.debug_context() {
+            for (block, &count) in self.blocks.iter().zip(counts) {
+                let locations = match context.find_location_range(block.start, block.end) {
+                    Ok(locations) => locations,
+                    Err(_) => continue,
+                };
+
+                for (_, _, location) in locations {
+                    if let (Some(file), Some(line)) = (location.file, location.line) {
+                        let lines = files.entry(file.to_string()).or_default();
+                        let line_count = lines.entry(line).or_default();
+                        *line_count = (*line_count).max(count);
+
+                        function_locations
+                            .entry(block.function)
+                            .or_insert_with(|| (file.to_string(), line));
+                    }
+                }
+            }
+        }
+
+        // Functions without a source location are reported in a
+        // record named after the module.
+        let module_name = self
+            .module_name
+            .clone()
+            .unwrap_or_else(|| "<wasm module>".to_string());
+        let mut functions: BTreeMap<String, Vec<(u32, String, u64)>> = BTreeMap::new();
+
+        for blocks in &self.function_blocks {
+            if blocks.is_empty() {
+                continue;
+            }
+
+            let function = self.blocks[blocks.start].function;
+            let name = self
+                .function_name(function)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| format!("<function #{}>", function));
+            let (file, line) = function_locations
+                .get(&function)
+                .cloned()
+                .unwrap_or_else(|| (module_name.clone(), 0));
+
+            functions
+                .entry(file)
+                .or_default()
+                .push((line, name, counts[blocks.start]));
+        }
+
+        for file in functions.keys() {
+            files.entry(file.clone()).or_default();
+        }
+
+        writeln!(writer, "TN:")?;
+
+        for (file, lines) in &files {
+            writeln!(writer, "SF:{}", file)?;
+
+            let functions = functions.get(file).map(Vec::as_slice).unwrap_or(&[]);
+
+            for (line, name, _) in functions {
+                writeln!(writer, "FN:{},{}", line, name)?;
+            }
+
+            for (_, name, count) in functions {
+                writeln!(writer, "FNDA:{},{}", count, name)?;
+            }
+
+            writeln!(writer, "FNF:{}", functions.len())?;
+            writeln!(
+                writer,
+                "FNH:{}",
+                functions.iter().filter(|(_, _, count)| *count > 0).count()
+            )?;
+
+            for (line, count) in lines {
+                writeln!(writer, "DA:{},{}", line, count)?;
+            }
+
+            writeln!(writer, "LF:{}", lines.len())?;
+            writeln!(
+                writer,
+                "LH:{}",
+                lines.values().filter(|count| **count > 0).count()
+            )?;
+            writeln!(writer, "end_of_record")?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the DWARF sections, if any.
+    fn debug_context(
+        &self,
+    ) -> Option<addr2line::Context<gimli::EndianRcSlice<gimli::LittleEndian>>> {
+        if !self.debug_sections.contains_key(".debug_line") {
+            return None;
+        }
+
+        let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+            let data = self
+                .debug_sections
+                .get(id.name())
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+
+            Ok(gimli::EndianRcSlice::new(data.into(), gimli::LittleEndian))
+        })
+        .ok()?;
+
+        addr2line::Context::from_dwarf(dwarf).ok()
+    }
+}
+
+impl fmt::Debug for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("blocks", &self.blocks.len())
+            .field("first_counter", &self.first_counter)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Coverage {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let first_counter = self.first_counter.lock().unwrap().unwrap().as_u32();
+        let blocks = self.function_blocks[local_function_index.as_u32() as usize].clone();
+
+        Box::new(FunctionCoverage {
+            next_counter: first_counter + blocks.start as u32,
+            end_counter: first_counter + blocks.end as u32,
+            started: false,
+            boundaries: BlockBoundaries::default(),
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut first_counter = self.first_counter.lock().unwrap();
+
+        if first_counter.is_some() {
+            panic!("Coverage::transform_module_info: Attempting to use a `Coverage` middleware from multiple modules.");
+        }
+
+        if self.function_blocks.len()
+            != module_info.functions.len() - module_info.num_imported_functions
+        {
+            panic!("Coverage::transform_module_info: The `Coverage` middleware has been created for another module.");
+        }
+
+        // Append a global for each block counter.
+        let mut counters = Vec::with_capacity(self.blocks.len());
+
+        for block in 0..self.blocks.len() {
+            let counter = module_info
+                .globals
+                .push(GlobalType::new(Type::I64, Mutability::Var));
+
+            module_info
+                .global_initializers
+                .push(GlobalInit::I64Const(0));
+
+            module_info.exports.insert(
+                format!("{}{}", COUNTER_EXPORT_PREFIX, block),
+                ExportIndex::Global(counter),
+            );
+
+            counters.push(counter);
+        }
+
+        // An empty module still needs a valid index.
+        *first_counter = Some(
+            counters
+                .first()
+                .cloned()
+                .unwrap_or_else(|| GlobalIndex::from_u32(module_info.globals.len() as u32)),
+        );
+    }
+}
+
+impl MemoryUsage for Coverage {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        mem::size_of_val(self) + self.blocks.size_of_val(tracker) - mem::size_of_val(&self.blocks)
+            + self.function_blocks.capacity() * mem::size_of::<Range<usize>>()
+            + self.module_name.as_ref().map(String::capacity).unwrap_or(0)
+            + self
+                .function_names
+                .values()
+                .map(|name| mem::size_of::<u32>() + name.capacity())
+                .sum::<usize>()
+            + self
+                .debug_sections
+                .iter()
+                .map(|(name, data)| name.capacity() + data.capacity())
+                .sum::<usize>()
+            + self.first_counter.size_of_val(tracker)
+            - mem::size_of_val(&self.first_counter)
+    }
+}
+
+impl fmt::Debug for FunctionCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionCoverage")
+            .field("next_counter", &self.next_counter)
+            .field("end_counter", &self.end_counter)
+            .finish()
+    }
+}
+
+impl FunctionCoverage {
+    /// Injects the increment of the counter of the next block.
+    fn count_block<'a>(
+        &mut self,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if self.next_counter >= self.end_counter {
+            return Err(MiddlewareError::new(
+                "Coverage",
+                "the function has more blocks than expected; the `Coverage` middleware must be pushed before middlewares changing the control flow",
+            ));
+        }
+
+        let global_index = self.next_counter;
+        self.next_counter += 1;
+
+        state.extend(&[
+            // globals[counter] += 1;
+            Operator::GlobalGet { global_index },
+            Operator::I64Const { value: 1 },
+            Operator::I64Add,
+            Operator::GlobalSet { global_index },
+        ]);
+
+        Ok(())
+    }
+}
+
+impl FunctionMiddleware for FunctionCoverage {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.started {
+            self.started = true;
+            self.count_block(state)?;
+        }
+
+        let starts_block = self.boundaries.starts_block_after(&operator);
+        state.push_operator(operator);
+
+        if starts_block {
+            self.count_block(state)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Get the execution count of every basic block of an
+/// [`Instance`][wasmer::Instance], in the order of
+/// [`Coverage::blocks`].
+///
+/// Note: This can be used in a headless engine after an ahead-of-time
+/// compilation as all required state lives in the instance.
+///
+/// # Panic
+///
+/// The [`Instance`][wasmer::Instance] must have been processed with
+/// the [`Coverage`] middleware at compile time, otherwise the returned
+/// counts are empty.
+pub fn get_block_counts(instance: &Instance) -> Vec<u64> {
+    (0..)
+        .map(|block| {
+            instance
+                .exports
+                .get_global(&format!("{}{}", COUNTER_EXPORT_PREFIX, block))
+                .ok()
+        })
+        .take_while(Option::is_some)
+        .map(|global| {
+            let count: i64 = global
+                .unwrap()
+                .get()
+                .try_into()
+                .expect("`wasmer_coverage_counter` from Instance has wrong type");
+
+            count as u64
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Module, Store, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (func $abs (export "abs") (param $value i32) (result i32)
+                local.get $value
+                i32.const 0
+                i32.lt_s
+                if (result i32)
+                    i32.const 0
+                    local.get $value
+                    i32.sub
+                else
+                    local.get $value
+                end)
+            (func $unused (export "unused")))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn instantiate(coverage: Arc<Coverage>) -> Instance {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(coverage);
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+
+        Instance::new(&module, &imports! {}).unwrap()
+    }
+
+    #[test]
+    fn computes_blocks() {
+        let coverage = Coverage::new(&bytecode()).unwrap();
+        let functions = coverage
+            .blocks()
+            .iter()
+            .map(|block| block.function)
+            .collect::<Vec<_>>();
+
+        // `$abs`: entry, then, else, after `end`; `$unused`: entry.
+        assert_eq!(functions, vec![0, 0, 0, 0, 1]);
+        assert_eq!(coverage.function_name(0), Some("abs"));
+        assert!(coverage
+            .blocks()
+            .windows(2)
+            .all(|blocks| blocks[0].end <= blocks[1].start));
+    }
+
+    #[test]
+    fn get_block_counts_works() {
+        let coverage = Coverage::new(&bytecode()).unwrap();
+        let instance = instantiate(Arc::new(coverage));
+        assert_eq!(get_block_counts(&instance), vec![0, 0, 0, 0, 0]);
+
+        let abs = instance
+            .exports
+            .get_function("abs")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        assert_eq!(abs.call(-3).unwrap(), 3);
+        assert_eq!(abs.call(4).unwrap(), 4);
+        assert_eq!(abs.call(5).unwrap(), 5);
+        assert_eq!(get_block_counts(&instance), vec![3, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn write_lcov_without_debug_info() {
+        let coverage = Coverage::new(&bytecode()).unwrap();
+        let mut lcov = Vec::new();
+        coverage.write_lcov(&[3, 1, 2, 3, 0], &mut lcov).unwrap();
+
+        assert_eq!(
+            String::from_utf8(lcov).unwrap(),
+            "TN:\nSF:<wasm module>\nFN:0,abs\nFN:0,unused\nFNDA:3,abs\nFNDA:0,unused\nFNF:2\nFNH:1\nLF:0\nLH:0\nend_of_record\n"
+        );
+        assert!(coverage.write_lcov(&[], io::sink()).is_err());
+    }
+}
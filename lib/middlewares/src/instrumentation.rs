@@ -0,0 +1,334 @@
+//! Helpers shared by the middlewares that instrument a module with
+//! calls to host hooks, like [`crate::tracing`] and
+//! [`crate::sanitizer`].
+
+use loupe::MemoryUsage;
+use wasmer::wasmparser::{
+    MemoryImmediate, Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType,
+};
+use wasmer::{
+    ExportIndex, FunctionType, GlobalInit, GlobalType, MiddlewareReaderState, Mutability, Type,
+};
+use wasmer_types::entity::{EntityRef, PrimaryMap};
+use wasmer_types::{FunctionIndex, GlobalIndex, ImportIndex, ModuleInfo, SignatureIndex};
+
+/// Flag set in the `flags` argument of a memory access hook when the
+/// access is a store. The lower 8 bits hold the access size.
+pub(crate) const MEMORY_ACCESS_STORE: i32 = 0x100;
+
+/// Converts a value type to its `wasmparser` counterpart.
+fn wp_type(ty: &Type) -> WpType {
+    match ty {
+        Type::I32 => WpType::I32,
+        Type::I64 => WpType::I64,
+        Type::F32 => WpType::F32,
+        Type::F64 => WpType::F64,
+        Type::V128 => WpType::V128,
+        Type::ExternRef => WpType::ExternRef,
+        Type::FuncRef => WpType::FuncRef,
+    }
+}
+
+/// Finds a block type with no parameters that produces the results of
+/// `signature`, so that a function body can be wrapped in a block.
+///
+/// Multi-value block types refer to the type section of the original
+/// module, hence only pre-existing signatures are considered.
+pub(crate) fn wrapper_block_type(
+    module_info: &ModuleInfo,
+    signature: &FunctionType,
+) -> Option<WpTypeOrFuncType> {
+    match signature.results() {
+        [] => Some(WpTypeOrFuncType::Type(WpType::EmptyBlockType)),
+        [ty] => Some(WpTypeOrFuncType::Type(wp_type(ty))),
+        results => module_info
+            .signatures
+            .iter()
+            .find(|(_, ty)| ty.params().is_empty() && ty.results() == results)
+            .map(|(index, _)| WpTypeOrFuncType::FuncType(index.as_u32())),
+    }
+}
+
+/// Host functions imported by an instrumented module.
+///
+/// The hooks are inserted right after the functions imported by the
+/// original module, which shifts the index of every local function.
+#[derive(Clone, Debug, MemoryUsage)]
+pub(crate) struct InjectedHooks {
+    /// The number of functions imported by the original module, which
+    /// is also the index of the first hook.
+    first: u32,
+
+    /// The number of injected hooks.
+    count: u32,
+}
+
+impl InjectedHooks {
+    /// Imports the `hooks`, described by their name and parameters,
+    /// from `namespace`, and shifts every reference to a local
+    /// function in `module_info`.
+    ///
+    /// The signatures of the hooks may be appended to the module
+    /// signatures, so any wrapping block type must be computed before
+    /// calling this function.
+    pub(crate) fn inject(
+        module_info: &mut ModuleInfo,
+        namespace: &str,
+        hooks: &[(&str, Vec<Type>)],
+    ) -> Self {
+        let first = module_info.num_imported_functions;
+        let count = hooks.len();
+        let injected = Self {
+            first: first as u32,
+            count: count as u32,
+        };
+
+        let mut hook_signatures: Vec<SignatureIndex> = Vec::with_capacity(count);
+
+        for (offset, (name, params)) in hooks.iter().enumerate() {
+            let signature = FunctionType::new(params.clone(), vec![]);
+            let signature_index = match module_info
+                .signatures
+                .iter()
+                .find(|(_, ty)| **ty == signature)
+            {
+                Some((index, _)) => index,
+                None => module_info.signatures.push(signature),
+            };
+            hook_signatures.push(signature_index);
+
+            let import_index = module_info.imports.len() as u32;
+            module_info.imports.insert(
+                (namespace.to_string(), name.to_string(), import_index),
+                ImportIndex::Function(FunctionIndex::new(first + offset)),
+            );
+        }
+
+        // Insert the hooks after the imported functions, and shift
+        // every reference to a local function.
+        let mut functions: Vec<SignatureIndex> = module_info.functions.values().cloned().collect();
+        functions.splice(first..first, hook_signatures);
+        module_info.functions = functions.into_iter().collect::<PrimaryMap<_, _>>();
+        module_info.num_imported_functions += count;
+
+        let shift = |index: FunctionIndex| FunctionIndex::new(injected.remap(index.as_u32()) as _);
+
+        for export in module_info.exports.values_mut() {
+            if let ExportIndex::Function(index) = export {
+                *index = shift(*index);
+            }
+        }
+
+        if let Some(start_function) = module_info.start_function.as_mut() {
+            *start_function = shift(*start_function);
+        }
+
+        for initializer in module_info.table_initializers.iter_mut() {
+            for element in initializer.elements.iter_mut() {
+                *element = shift(*element);
+            }
+        }
+
+        for elements in module_info.passive_elements.values_mut() {
+            for element in elements.iter_mut() {
+                *element = shift(*element);
+            }
+        }
+
+        for initializer in module_info.global_initializers.values_mut() {
+            if let GlobalInit::RefFunc(index) = initializer {
+                *index = shift(*index);
+            }
+        }
+
+        module_info.function_names = module_info
+            .function_names
+            .drain()
+            .map(|(index, name)| (shift(index), name))
+            .collect();
+
+        injected
+    }
+
+    /// Returns the function index of the `nth` injected hook.
+    pub(crate) fn hook(&self, nth: usize) -> u32 {
+        debug_assert!((nth as u32) < self.count);
+        self.first + nth as u32
+    }
+
+    /// Maps a function index of the original module to the
+    /// corresponding index in the instrumented module.
+    pub(crate) fn remap(&self, function_index: u32) -> u32 {
+        if function_index >= self.first {
+            function_index + self.count
+        } else {
+            function_index
+        }
+    }
+
+    /// Remaps the function index referenced by `operator`, if any.
+    pub(crate) fn remap_operator<'a>(&self, operator: Operator<'a>) -> Operator<'a> {
+        match operator {
+            Operator::Call { function_index } => Operator::Call {
+                function_index: self.remap(function_index),
+            },
+            Operator::ReturnCall { function_index } => Operator::ReturnCall {
+                function_index: self.remap(function_index),
+            },
+            Operator::RefFunc { function_index } => Operator::RefFunc {
+                function_index: self.remap(function_index),
+            },
+            operator => operator,
+        }
+    }
+}
+
+/// Globals used to temporarily hold the operands of an instrumented
+/// operator while a hook is called.
+#[derive(Clone, Debug, MemoryUsage)]
+pub(crate) struct ScratchGlobals {
+    /// Holds an address, a branch condition or a `br_table` index.
+    pub(crate) address: u32,
+
+    /// Holds the length of a bulk memory operation.
+    pub(crate) length: u32,
+    i32_value: u32,
+    i64_value: u32,
+    f32_value: u32,
+    f64_value: u32,
+}
+
+impl ScratchGlobals {
+    /// Appends the scratch globals to `module_info`.
+    pub(crate) fn push(module_info: &mut ModuleInfo) -> Self {
+        let mut push_global = |ty: Type, init: GlobalInit| -> u32 {
+            let index: GlobalIndex = module_info
+                .globals
+                .push(GlobalType::new(ty, Mutability::Var));
+            module_info.global_initializers.push(init);
+            index.as_u32()
+        };
+
+        Self {
+            address: push_global(Type::I32, GlobalInit::I32Const(0)),
+            length: push_global(Type::I32, GlobalInit::I32Const(0)),
+            i32_value: push_global(Type::I32, GlobalInit::I32Const(0)),
+            i64_value: push_global(Type::I64, GlobalInit::I64Const(0)),
+            f32_value: push_global(Type::F32, GlobalInit::F32Const(0.0)),
+            f64_value: push_global(Type::F64, GlobalInit::F64Const(0.0)),
+        }
+    }
+
+    /// Returns the global holding values of type `ty`.
+    pub(crate) fn value(&self, ty: WpType) -> u32 {
+        match ty {
+            WpType::I32 => self.i32_value,
+            WpType::I64 => self.i64_value,
+            WpType::F32 => self.f32_value,
+            WpType::F64 => self.f64_value,
+            _ => unreachable!("unexpected stored value type"),
+        }
+    }
+}
+
+/// A load or a store.
+#[derive(Debug)]
+pub(crate) struct MemoryAccess {
+    pub(crate) memarg: MemoryImmediate,
+
+    /// The number of accessed bytes.
+    pub(crate) size: i32,
+
+    /// The type of the stored value, for a store.
+    pub(crate) stored: Option<WpType>,
+}
+
+impl MemoryAccess {
+    /// Returns the memory access performed by `operator`, if any.
+    ///
+    /// SIMD and atomic accesses are not supported.
+    pub(crate) fn of(operator: &Operator) -> Option<Self> {
+        let (memarg, size, stored) = match *operator {
+            Operator::I32Load { memarg }
+            | Operator::F32Load { memarg }
+            | Operator::I64Load32S { memarg }
+            | Operator::I64Load32U { memarg } => (memarg, 4, None),
+            Operator::I64Load { memarg } | Operator::F64Load { memarg } => (memarg, 8, None),
+            Operator::I32Load8S { memarg }
+            | Operator::I32Load8U { memarg }
+            | Operator::I64Load8S { memarg }
+            | Operator::I64Load8U { memarg } => (memarg, 1, None),
+            Operator::I32Load16S { memarg }
+            | Operator::I32Load16U { memarg }
+            | Operator::I64Load16S { memarg }
+            | Operator::I64Load16U { memarg } => (memarg, 2, None),
+            Operator::I32Store { memarg } => (memarg, 4, Some(WpType::I32)),
+            Operator::I32Store8 { memarg } => (memarg, 1, Some(WpType::I32)),
+            Operator::I32Store16 { memarg } => (memarg, 2, Some(WpType::I32)),
+            Operator::I64Store { memarg } => (memarg, 8, Some(WpType::I64)),
+            Operator::I64Store8 { memarg } => (memarg, 1, Some(WpType::I64)),
+            Operator::I64Store16 { memarg } => (memarg, 2, Some(WpType::I64)),
+            Operator::I64Store32 { memarg } => (memarg, 4, Some(WpType::I64)),
+            Operator::F32Store { memarg } => (memarg, 4, Some(WpType::F32)),
+            Operator::F64Store { memarg } => (memarg, 8, Some(WpType::F64)),
+            _ => return None,
+        };
+
+        Some(Self {
+            memarg,
+            size,
+            stored,
+        })
+    }
+
+    /// Injects a call to `hook` before the access. The stack holds the
+    /// address, followed by the stored value for a store.
+    ///
+    /// The hook receives the `leading` arguments, then the address,
+    /// the static offset and the flags of the access, combined with
+    /// `extra_flags`.
+    pub(crate) fn inject_hook<'a>(
+        &self,
+        scratch: &ScratchGlobals,
+        leading: &[Operator<'a>],
+        extra_flags: i32,
+        hook: u32,
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let value_global = self.stored.map(|ty| scratch.value(ty));
+
+        if let Some(global_index) = value_global {
+            state.push_operator(Operator::GlobalSet { global_index });
+        }
+
+        state.push_operator(Operator::GlobalSet {
+            global_index: scratch.address,
+        });
+        state.extend(leading);
+        state.extend(&[
+            Operator::GlobalGet {
+                global_index: scratch.address,
+            },
+            Operator::I32Const {
+                value: self.memarg.offset as i32,
+            },
+            Operator::I32Const {
+                value: if self.stored.is_some() {
+                    self.size | MEMORY_ACCESS_STORE | extra_flags
+                } else {
+                    self.size | extra_flags
+                },
+            },
+            Operator::Call {
+                function_index: hook,
+            },
+            Operator::GlobalGet {
+                global_index: scratch.address,
+            },
+        ]);
+
+        if let Some(global_index) = value_global {
+            state.push_operator(Operator::GlobalGet { global_index });
+        }
+    }
+}
@@ -1,6 +1,13 @@
+pub mod coverage;
+mod instrumentation;
 pub mod metering;
+pub mod sanitizer;
+pub mod tracing;
 
 // The most commonly used symbol are exported at top level of the
 // module. Others are available via modules,
 // e.g. `wasmer_middlewares::metering::get_remaining_points`
+pub use coverage::Coverage;
 pub use metering::Metering;
+pub use sanitizer::Sanitizer;
+pub use tracing::Tracing;
@@ -0,0 +1,1459 @@
+//! `sanitizer` is a middleware detecting heap corruption inside the
+//! linear memory of a guest, similarly to what AddressSanitizer does
+//! for native code.
+//!
+//! Bounds checks only catch accesses outside of the linear memory.
+//! The [`Sanitizer`] middleware instruments every load, store and
+//! bulk memory operator with a call to a host hook, and the guest
+//! allocator functions (`malloc`, `calloc`, `realloc` and `free` by
+//! default) with calls on entry and exit. The hooks are implemented
+//! by a [`ShadowMemory`], which keeps track of the heap allocations
+//! and of their initialized bytes, and reports:
+//!
+//! * accesses overflowing a live allocation,
+//! * accesses to a freed allocation,
+//! * reads of bytes of an allocation that have never been written,
+//! * double and invalid frees.
+//!
+//! A violation aborts the execution with a [`RuntimeError`] holding a
+//! [`SanitizerError`], which can be retrieved with
+//! [`SanitizerRuntimeError::sanitizer_error`] (or with
+//! [`RuntimeError::downcast`]). The [`RuntimeError::trace`] points to
+//! the guest function that performed the faulty access.
+//!
+//! Memory outside of the tracked allocations, like the stack or
+//! static data, is never reported.
+//!
+//! [`RuntimeError`]: wasmer::RuntimeError
+//! [`RuntimeError::downcast`]: wasmer::RuntimeError::downcast
+//! [`RuntimeError::trace`]: wasmer::RuntimeError::trace
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::{CompilerConfig, ImportObject, Instance, Module, RuntimeError};
+//! use wasmer_middlewares::sanitizer::{Sanitizer, SanitizerRuntimeError, ShadowMemory, SANITIZER_NAMESPACE};
+//!
+//! fn enable_sanitizer(compiler_config: &mut dyn CompilerConfig) {
+//!     // `strlen` reads whole words, possibly past the end of an allocation.
+//!     let sanitizer = Arc::new(Sanitizer::new().ignore_function("strlen"));
+//!
+//!     compiler_config.push_middleware(sanitizer);
+//! }
+//!
+//! fn run_sanitized(module: &Module) -> Result<(), Box<dyn std::error::Error>> {
+//!     let shadow = ShadowMemory::new();
+//!
+//!     let mut import_object = ImportObject::new();
+//!     import_object.register(SANITIZER_NAMESPACE, shadow.exports(module.store()));
+//!
+//!     let instance = Instance::new(module, &import_object)?;
+//!     let run = instance.exports.get_native_function::<(), ()>("run")?;
+//!
+//!     if let Err(error) = run.call() {
+//!         for frame in error.trace() {
+//!             eprintln!("  at {}", frame.function_name().unwrap_or("<unnamed>"));
+//!         }
+//!
+//!         match error.sanitizer_error() {
+//!             Some(violation) => eprintln!("{}", violation),
+//!             None => return Err(error.into()),
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::instrumentation::{InjectedHooks, MemoryAccess, ScratchGlobals, MEMORY_ACCESS_STORE};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    ExportIndex, Exports, Function, FunctionMiddleware, FunctionType, LocalFunctionIndex,
+    MiddlewareError, MiddlewareReaderState, ModuleMiddleware, RuntimeError, Store, Type, WasmerEnv,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::ModuleInfo;
+
+/// The namespace from which an instrumented module imports the
+/// sanitizer hooks.
+pub const SANITIZER_NAMESPACE: &str = "wasmer_sanitizer";
+
+/// Flag set in the `flags` argument of the memory hooks when the
+/// access happens in an ignored function: it is recorded, but never
+/// reported.
+const MEMORY_ACCESS_UNCHECKED: i32 = 0x200;
+
+/// The number of bytes following a live allocation in which an
+/// access is reported as an overflow, even though those bytes are
+/// not tracked.
+const REDZONE_SIZE: u64 = 8;
+
+/// The indexes of the injected hooks, in declaration order.
+const HOOK_MEMORY_ACCESS: usize = 0;
+const HOOK_MEMORY_COPY: usize = 1;
+const HOOK_MEMORY_FILL: usize = 2;
+const HOOK_ALLOCATOR_ENTER: usize = 3;
+const HOOK_ALLOCATOR_EXIT: usize = 4;
+
+/// A guest function managing the heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, MemoryUsage)]
+#[repr(i32)]
+pub enum AllocatorFunction {
+    /// `malloc(size: i32) -> i32`.
+    Malloc = 0,
+
+    /// `calloc(count: i32, size: i32) -> i32`.
+    Calloc = 1,
+
+    /// `realloc(pointer: i32, size: i32) -> i32`.
+    Realloc = 2,
+
+    /// `free(pointer: i32)`.
+    Free = 3,
+}
+
+impl AllocatorFunction {
+    fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Malloc),
+            1 => Some(Self::Calloc),
+            2 => Some(Self::Realloc),
+            3 => Some(Self::Free),
+            _ => None,
+        }
+    }
+
+    /// The signature the guest function must have to be tracked.
+    fn signature(self) -> FunctionType {
+        match self {
+            Self::Malloc => FunctionType::new(vec![Type::I32], vec![Type::I32]),
+            Self::Calloc | Self::Realloc => {
+                FunctionType::new(vec![Type::I32, Type::I32], vec![Type::I32])
+            }
+            Self::Free => FunctionType::new(vec![Type::I32], vec![]),
+        }
+    }
+
+    /// The block type wrapping the function body.
+    fn wrapper(self) -> WpTypeOrFuncType {
+        match self {
+            Self::Free => WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            _ => WpTypeOrFuncType::Type(WpType::I32),
+        }
+    }
+}
+
+/// How a local function is instrumented.
+#[derive(Clone, Copy, Debug, MemoryUsage)]
+enum FunctionKind {
+    /// Its memory accesses are checked.
+    Checked,
+
+    /// Its memory accesses are recorded but not checked.
+    Ignored,
+
+    /// It is an allocator function: its calls are tracked, and its
+    /// memory accesses are not instrumented.
+    Allocator(AllocatorFunction),
+}
+
+/// Module-specific state computed by `Sanitizer::transform_module_info`.
+#[derive(Debug)]
+struct SanitizerState {
+    /// The hooks imported by the instrumented module.
+    injected: InjectedHooks,
+
+    scratch: ScratchGlobals,
+
+    /// How each local function is instrumented.
+    functions: Vec<FunctionKind>,
+}
+
+/// The module-level sanitizer middleware.
+///
+/// The instrumented module imports its hooks from the
+/// [`SANITIZER_NAMESPACE`] namespace. They are typically provided by
+/// [`ShadowMemory::exports`].
+///
+/// The allocator functions are looked up by name, in the exports and
+/// then in the name section of the module. Allocations made without
+/// calling them are not tracked. SIMD and atomic accesses are not
+/// checked.
+///
+/// # Panic
+///
+/// An instance of `Sanitizer` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// index of the injected hooks. Attempts to use a `Sanitizer`
+/// instance from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::sanitizer::{AllocatorFunction, Sanitizer};
+///
+/// fn create_sanitizer_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // The guest allocator is `dlmalloc` rather than `malloc`.
+///     let sanitizer = Arc::new(Sanitizer::new().allocator_function(AllocatorFunction::Malloc, "dlmalloc"));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(sanitizer);
+/// }
+/// ```
+pub struct Sanitizer {
+    /// The name of each allocator function.
+    allocator_functions: Vec<(AllocatorFunction, String)>,
+
+    /// The names of the functions whose accesses are not checked.
+    ignored_functions: Vec<String>,
+
+    /// The module-specific state.
+    state: Mutex<Option<Arc<SanitizerState>>>,
+}
+
+/// The function-level sanitizer middleware.
+pub struct FunctionSanitizer {
+    /// The module-specific state.
+    state: Arc<SanitizerState>,
+
+    kind: FunctionKind,
+
+    /// Whether the function prologue has been injected.
+    entered: bool,
+
+    /// The current block nesting depth.
+    depth: u32,
+}
+
+impl Sanitizer {
+    /// Creates a `Sanitizer` middleware tracking the `malloc`,
+    /// `calloc`, `realloc` and `free` functions.
+    pub fn new() -> Self {
+        Self {
+            allocator_functions: vec![
+                (AllocatorFunction::Malloc, "malloc".to_string()),
+                (AllocatorFunction::Calloc, "calloc".to_string()),
+                (AllocatorFunction::Realloc, "realloc".to_string()),
+                (AllocatorFunction::Free, "free".to_string()),
+            ],
+            ignored_functions: vec![],
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Sets the name of an allocator function.
+    pub fn allocator_function(mut self, function: AllocatorFunction, name: &str) -> Self {
+        for (allocator_function, allocator_name) in self.allocator_functions.iter_mut() {
+            if *allocator_function == function {
+                *allocator_name = name.to_string();
+            }
+        }
+        self
+    }
+
+    /// Disables the checks of the memory accesses made by the function
+    /// named `name`, like a hand-written `strlen` reading whole words
+    /// past the end of a string. Its writes still initialize memory.
+    pub fn ignore_function(mut self, name: &str) -> Self {
+        self.ignored_functions.push(name.to_string());
+        self
+    }
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sanitizer")
+            .field("allocator_functions", &self.allocator_functions)
+            .field("ignored_functions", &self.ignored_functions)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// Finds the local function named `name`, in the exports or in the
+/// name section of the module.
+fn find_local_function(module_info: &ModuleInfo, name: &str) -> Option<LocalFunctionIndex> {
+    let function_index = match module_info.exports.get(name) {
+        Some(ExportIndex::Function(index)) => Some(*index),
+        _ => module_info
+            .function_names
+            .iter()
+            .find(|(_, function_name)| *function_name == name)
+            .map(|(index, _)| *index),
+    }?;
+
+    module_info.local_func_index(function_index)
+}
+
+impl ModuleMiddleware for Sanitizer {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let kind = state.functions[local_function_index.index()];
+
+        Box::new(FunctionSanitizer {
+            state,
+            kind,
+            entered: false,
+            depth: 0,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("Sanitizer::transform_module_info: Attempting to use a `Sanitizer` middleware from multiple modules.");
+        }
+
+        let mut functions = vec![
+            FunctionKind::Checked;
+            module_info.functions.len() - module_info.num_imported_functions
+        ];
+
+        for name in self.ignored_functions.iter() {
+            if let Some(local_index) = find_local_function(module_info, name) {
+                functions[local_index.index()] = FunctionKind::Ignored;
+            }
+        }
+
+        for (function, name) in self.allocator_functions.iter() {
+            if let Some(local_index) = find_local_function(module_info, name) {
+                let function_index = module_info.func_index(local_index);
+                let signature = &module_info.signatures[module_info.functions[function_index]];
+
+                if *signature == function.signature() {
+                    functions[local_index.index()] = FunctionKind::Allocator(*function);
+                }
+            }
+        }
+
+        let injected = InjectedHooks::inject(
+            module_info,
+            SANITIZER_NAMESPACE,
+            &[
+                ("memory_access", vec![Type::I32; 3]),
+                ("memory_copy", vec![Type::I32; 4]),
+                ("memory_fill", vec![Type::I32; 3]),
+                ("allocator_enter", vec![Type::I32; 3]),
+                ("allocator_exit", vec![Type::I32; 2]),
+            ],
+        );
+        let scratch = ScratchGlobals::push(module_info);
+
+        *state = Some(Arc::new(SanitizerState {
+            injected,
+            scratch,
+            functions,
+        }));
+    }
+}
+
+impl MemoryUsage for Sanitizer {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let state_size = match &*self.state.lock().unwrap() {
+            Some(state) => {
+                mem::size_of::<SanitizerState>()
+                    + state.injected.size_of_val(tracker)
+                    + state.scratch.size_of_val(tracker)
+                    + state.functions.capacity() * mem::size_of::<FunctionKind>()
+            }
+            None => 0,
+        };
+
+        mem::size_of_val(self)
+            + self
+                .allocator_functions
+                .iter()
+                .map(|(_, name)| name.capacity())
+                .sum::<usize>()
+            + self
+                .ignored_functions
+                .iter()
+                .map(|name| name.capacity())
+                .sum::<usize>()
+            + state_size
+    }
+}
+
+impl fmt::Debug for FunctionSanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionSanitizer")
+            .field("kind", &self.kind)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl FunctionSanitizer {
+    /// The flags passed to the memory hooks.
+    fn flags(&self) -> i32 {
+        match self.kind {
+            FunctionKind::Ignored => MEMORY_ACCESS_UNCHECKED,
+            _ => 0,
+        }
+    }
+
+    /// Injects a call to the `allocator_exit` hook. The stack holds
+    /// the result of the allocator function, if any.
+    fn allocator_exit<'a>(
+        &self,
+        function: AllocatorFunction,
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let allocator_exit = self.state.injected.hook(HOOK_ALLOCATOR_EXIT);
+        let address = self.state.scratch.address;
+
+        if function == AllocatorFunction::Free {
+            state.extend(&[
+                Operator::I32Const {
+                    value: function as i32,
+                },
+                Operator::I32Const { value: 0 },
+                Operator::Call {
+                    function_index: allocator_exit,
+                },
+            ]);
+        } else {
+            state.extend(&[
+                Operator::GlobalSet {
+                    global_index: address,
+                },
+                Operator::I32Const {
+                    value: function as i32,
+                },
+                Operator::GlobalGet {
+                    global_index: address,
+                },
+                Operator::Call {
+                    function_index: allocator_exit,
+                },
+                Operator::GlobalGet {
+                    global_index: address,
+                },
+            ]);
+        }
+    }
+
+    /// Injects a call to `hook` before a bulk memory operator taking
+    /// three `i32` operands. The hook receives the operands selected
+    /// by `arguments`, in order, followed by the flags.
+    fn bulk_memory<'a>(
+        &self,
+        hook: usize,
+        arguments: &[usize],
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let scratch = &self.state.scratch;
+        let globals = [scratch.address, scratch.value(WpType::I32), scratch.length];
+
+        for global_index in globals.iter().rev() {
+            state.push_operator(Operator::GlobalSet {
+                global_index: *global_index,
+            });
+        }
+
+        for argument in arguments {
+            state.push_operator(Operator::GlobalGet {
+                global_index: globals[*argument],
+            });
+        }
+
+        state.extend(&[
+            Operator::I32Const {
+                value: self.flags(),
+            },
+            Operator::Call {
+                function_index: self.state.injected.hook(hook),
+            },
+        ]);
+
+        for global_index in globals.iter() {
+            state.push_operator(Operator::GlobalGet {
+                global_index: *global_index,
+            });
+        }
+    }
+}
+
+impl FunctionMiddleware for FunctionSanitizer {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        let allocator = match self.kind {
+            FunctionKind::Allocator(function) => Some(function),
+            _ => None,
+        };
+
+        if !self.entered {
+            self.entered = true;
+
+            if let Some(function) = allocator {
+                state.extend(&[
+                    Operator::I32Const {
+                        value: function as i32,
+                    },
+                    Operator::LocalGet { local_index: 0 },
+                ]);
+                state.push_operator(match function {
+                    AllocatorFunction::Calloc | AllocatorFunction::Realloc => {
+                        Operator::LocalGet { local_index: 1 }
+                    }
+                    _ => Operator::I32Const { value: 0 },
+                });
+                state.extend(&[
+                    Operator::Call {
+                        function_index: self.state.injected.hook(HOOK_ALLOCATOR_ENTER),
+                    },
+                    // Wrap the body in a block, so that branches to
+                    // the function body label reach the exit hook.
+                    Operator::Block {
+                        ty: function.wrapper(),
+                    },
+                ]);
+            }
+        }
+
+        match operator {
+            Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => {
+                self.depth += 1;
+            }
+            Operator::End if self.depth == 0 => {
+                // End of the function body.
+                if let Some(function) = allocator {
+                    state.push_operator(Operator::End);
+                    self.allocator_exit(function, state);
+                }
+            }
+            Operator::End => {
+                self.depth -= 1;
+            }
+            Operator::Return => {
+                if let Some(function) = allocator {
+                    self.allocator_exit(function, state);
+                }
+            }
+            _ if allocator.is_some() => {}
+            Operator::MemoryCopy { .. } => {
+                // destination, source, length
+                self.bulk_memory(HOOK_MEMORY_COPY, &[0, 1, 2], state);
+            }
+            Operator::MemoryFill { .. } | Operator::MemoryInit { .. } => {
+                // destination, value or segment offset, length
+                self.bulk_memory(HOOK_MEMORY_FILL, &[0, 2], state);
+            }
+            ref operator => {
+                if let Some(access) = MemoryAccess::of(operator) {
+                    access.inject_hook(
+                        &self.state.scratch,
+                        &[],
+                        self.flags(),
+                        self.state.injected.hook(HOOK_MEMORY_ACCESS),
+                        state,
+                    );
+                }
+            }
+        }
+
+        state.push_operator(self.state.injected.remap_operator(operator));
+
+        Ok(())
+    }
+}
+
+/// A heap allocation made by the guest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Allocation {
+    /// The address of the first byte of the allocation.
+    pub address: u32,
+
+    /// The size of the allocation, in bytes.
+    pub size: u32,
+}
+
+/// The kind of a memory violation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// An access overflowing an allocation.
+    HeapBufferOverflow,
+
+    /// An access to a freed allocation.
+    UseAfterFree,
+
+    /// A read of bytes that have never been written.
+    UninitializedRead,
+
+    /// A free of an already freed allocation.
+    DoubleFree,
+
+    /// A free of a pointer inside an allocation.
+    InvalidFree,
+}
+
+/// The operation causing a memory violation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// A load, or the source of a `memory.copy`.
+    Read,
+
+    /// A store, or the destination of a bulk memory operator.
+    Write,
+
+    /// A call to the `free` (or `realloc`) allocator function.
+    Free,
+}
+
+/// A memory violation detected by a [`ShadowMemory`].
+///
+/// It is the source of the [`wasmer::RuntimeError`] aborting the
+/// execution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SanitizerError {
+    /// The kind of violation.
+    pub violation: Violation,
+
+    /// The operation causing the violation.
+    pub access: Access,
+
+    /// The effective address of the access, or the freed pointer.
+    pub address: u64,
+
+    /// The number of accessed bytes, or 0 for a free.
+    pub size: u64,
+
+    /// The allocation concerned by the violation.
+    pub allocation: Allocation,
+}
+
+impl fmt::Display for SanitizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let access = match self.access {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Free => "free",
+        };
+        let allocation = format!(
+            "the {}-byte allocation at {:#x}",
+            self.allocation.size, self.allocation.address
+        );
+
+        match self.violation {
+            Violation::HeapBufferOverflow => write!(
+                f,
+                "heap-buffer-overflow: {}-byte {} at {:#x} overflows {}",
+                self.size, access, self.address, allocation
+            ),
+            Violation::UseAfterFree => write!(
+                f,
+                "heap-use-after-free: {}-byte {} at {:#x} in freed {}",
+                self.size,
+                access,
+                self.address,
+                &allocation[4..]
+            ),
+            Violation::UninitializedRead => write!(
+                f,
+                "use-of-uninitialized-value: {}-byte {} at {:#x} in {}",
+                self.size, access, self.address, allocation
+            ),
+            Violation::DoubleFree => write!(
+                f,
+                "double-free: {:#x} is {}, which is already freed",
+                self.address, allocation
+            ),
+            Violation::InvalidFree => write!(
+                f,
+                "bad-free: {:#x} is not the start of {}",
+                self.address, allocation
+            ),
+        }
+    }
+}
+
+impl Error for SanitizerError {}
+
+/// Gives access to the [`SanitizerError`] held by a
+/// [`RuntimeError`], to tell memory violations apart from the other
+/// runtime errors.
+///
+/// [`RuntimeError`]: wasmer::RuntimeError
+pub trait SanitizerRuntimeError {
+    /// Returns the memory violation that aborted the execution, if
+    /// the error has been raised by a [`ShadowMemory`].
+    fn sanitizer_error(&self) -> Option<&SanitizerError>;
+}
+
+impl SanitizerRuntimeError for RuntimeError {
+    fn sanitizer_error(&self) -> Option<&SanitizerError> {
+        self.downcast_ref::<SanitizerError>()
+    }
+}
+
+/// The shadow state of an allocation.
+#[derive(Debug)]
+struct Block {
+    size: u32,
+
+    freed: bool,
+
+    /// One bit per byte, set when the byte has been written. Empty
+    /// once the allocation is freed.
+    initialized: Vec<u8>,
+}
+
+impl Block {
+    fn new(size: u32, initialized: bool) -> Self {
+        Self {
+            size,
+            freed: false,
+            initialized: vec![if initialized { 0xff } else { 0 }; (size as usize + 7) / 8],
+        }
+    }
+
+    fn is_initialized(&self, offset: u32) -> bool {
+        self.initialized[offset as usize / 8] & (1 << (offset % 8)) != 0
+    }
+
+    fn set_initialized(&mut self, offset: u32, initialized: bool) {
+        let mask = 1 << (offset % 8);
+
+        if initialized {
+            self.initialized[offset as usize / 8] |= mask;
+        } else {
+            self.initialized[offset as usize / 8] &= !mask;
+        }
+    }
+}
+
+/// An allocator call in progress.
+#[derive(Debug)]
+struct AllocatorCall {
+    function: AllocatorFunction,
+    arguments: (u32, u32),
+}
+
+#[derive(Debug, Default)]
+struct ShadowState {
+    /// The tracked allocations, by address. Freed allocations are
+    /// kept until their memory is allocated again.
+    ///
+    /// Addresses are 64-bit wide, like the effective addresses of the
+    /// accesses, which may exceed the 32-bit address space when the
+    /// offset of an access is added.
+    blocks: BTreeMap<u64, Block>,
+
+    /// The allocator calls in progress. Memory accesses are not
+    /// checked while the allocator runs, and only the outermost call
+    /// is tracked (`realloc` may call `malloc`, for instance).
+    calls: Vec<AllocatorCall>,
+}
+
+impl ShadowState {
+    /// Returns the block containing `address`, if any.
+    fn block(&self, address: u64) -> Option<(u64, &Block)> {
+        let (start, block) = self.blocks.range(..=address).next_back()?;
+
+        if address < *start + block.size as u64 {
+            Some((*start, block))
+        } else {
+            None
+        }
+    }
+
+    fn block_mut(&mut self, address: u64) -> Option<(u64, &mut Block)> {
+        let (start, block) = self.blocks.range_mut(..=address).next_back()?;
+
+        if address < *start + block.size as u64 {
+            Some((*start, block))
+        } else {
+            None
+        }
+    }
+
+    /// Checks an access of `size` bytes at `address`.
+    fn check(
+        &self,
+        address: u64,
+        size: u64,
+        access: Access,
+        check_initialized: bool,
+    ) -> Result<(), SanitizerError> {
+        // Accesses outside of the linear memory trap anyway.
+        if size == 0 || address + size > u32::MAX as u64 + 1 {
+            return Ok(());
+        }
+
+        let end = address + size;
+        let error = |violation, start: u64, block: &Block| SanitizerError {
+            violation,
+            access,
+            address,
+            size,
+            allocation: Allocation {
+                address: start as u32,
+                size: block.size,
+            },
+        };
+
+        if let Some((&start, block)) = self.blocks.range(..=address).next_back() {
+            let block_end = start + block.size as u64;
+
+            if address < block_end {
+                return if block.freed {
+                    Err(error(Violation::UseAfterFree, start, block))
+                } else if end > block_end {
+                    Err(error(Violation::HeapBufferOverflow, start, block))
+                } else if check_initialized
+                    && !(address..end).all(|byte| block.is_initialized((byte - start) as u32))
+                {
+                    Err(error(Violation::UninitializedRead, start, block))
+                } else {
+                    Ok(())
+                };
+            }
+
+            if !block.freed && address < block_end + REDZONE_SIZE {
+                return Err(error(Violation::HeapBufferOverflow, start, block));
+            }
+        }
+
+        // The access may start before an allocation and end inside it.
+        match self
+            .blocks
+            .range((Bound::Excluded(address), Bound::Unbounded))
+            .next()
+        {
+            Some((&start, block)) if start < end => Err(error(
+                if block.freed {
+                    Violation::UseAfterFree
+                } else {
+                    Violation::HeapBufferOverflow
+                },
+                start,
+                block,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the byte at `address` is initialized. Bytes
+    /// outside of the live allocations are always initialized.
+    fn is_initialized(&self, address: u64) -> bool {
+        match self.block(address) {
+            Some((start, block)) if !block.freed => block.is_initialized((address - start) as u32),
+            _ => true,
+        }
+    }
+
+    /// Sets whether the byte at `address` is initialized, if it
+    /// belongs to a live allocation.
+    fn set_initialized(&mut self, address: u64, initialized: bool) {
+        if let Some((start, block)) = self.block_mut(address) {
+            if !block.freed {
+                block.set_initialized((address - start) as u32, initialized);
+            }
+        }
+    }
+
+    /// Returns whether some tracked allocation overlaps `size` bytes at
+    /// `address`.
+    fn overlaps(&self, address: u64, size: u64) -> bool {
+        size != 0
+            && (self.block(address).is_some()
+                || self
+                    .blocks
+                    .range((Bound::Excluded(address), Bound::Unbounded))
+                    .next()
+                    .map_or(false, |(start, _)| *start < address + size))
+    }
+
+    fn write(&mut self, address: u64, size: u64) {
+        if self.overlaps(address, size) {
+            for byte in address..(address + size).min(u32::MAX as u64 + 1) {
+                self.set_initialized(byte, true);
+            }
+        }
+    }
+
+    fn memory_access(&mut self, address: u64, flags: i32) -> Result<(), SanitizerError> {
+        if !self.calls.is_empty() {
+            return Ok(());
+        }
+
+        let size = (flags & 0xff) as u64;
+        let store = flags & MEMORY_ACCESS_STORE != 0;
+
+        if flags & MEMORY_ACCESS_UNCHECKED == 0 {
+            if store {
+                self.check(address, size, Access::Write, false)?;
+            } else {
+                self.check(address, size, Access::Read, true)?;
+            }
+        }
+
+        if store {
+            self.write(address, size);
+        }
+
+        Ok(())
+    }
+
+    fn memory_copy(
+        &mut self,
+        destination: u64,
+        source: u64,
+        length: u64,
+        flags: i32,
+    ) -> Result<(), SanitizerError> {
+        if !self.calls.is_empty() {
+            return Ok(());
+        }
+
+        if flags & MEMORY_ACCESS_UNCHECKED == 0 {
+            // Copying uninitialized bytes is fine, reading them later
+            // is not.
+            self.check(source, length, Access::Read, false)?;
+            self.check(destination, length, Access::Write, false)?;
+        }
+
+        if self.overlaps(destination, length) {
+            let initialized: Vec<bool> = (source..source + length)
+                .map(|byte| self.is_initialized(byte))
+                .collect();
+
+            for (byte, initialized) in (destination..).zip(initialized) {
+                self.set_initialized(byte, initialized);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn memory_fill(
+        &mut self,
+        destination: u64,
+        length: u64,
+        flags: i32,
+    ) -> Result<(), SanitizerError> {
+        if !self.calls.is_empty() {
+            return Ok(());
+        }
+
+        if flags & MEMORY_ACCESS_UNCHECKED == 0 {
+            self.check(destination, length, Access::Write, false)?;
+        }
+
+        self.write(destination, length);
+
+        Ok(())
+    }
+
+    /// Checks that `pointer` can be freed.
+    fn check_free(&self, pointer: u32) -> Result<(), SanitizerError> {
+        let error = |violation, start: u64, block: &Block| SanitizerError {
+            violation,
+            access: Access::Free,
+            address: pointer as u64,
+            size: 0,
+            allocation: Allocation {
+                address: start as u32,
+                size: block.size,
+            },
+        };
+
+        if let Some(block) = self.blocks.get(&(pointer as u64)) {
+            if block.freed {
+                return Err(error(Violation::DoubleFree, pointer as u64, block));
+            }
+        } else if let Some((start, block)) = self.block(pointer as u64) {
+            return Err(error(Violation::InvalidFree, start, block));
+        }
+
+        // A null pointer, or memory that is not tracked.
+        Ok(())
+    }
+
+    fn allocator_enter(
+        &mut self,
+        function: AllocatorFunction,
+        arguments: (u32, u32),
+    ) -> Result<(), SanitizerError> {
+        if self.calls.is_empty() {
+            match function {
+                AllocatorFunction::Free => self.check_free(arguments.0)?,
+                AllocatorFunction::Realloc if arguments.0 != 0 => self.check_free(arguments.0)?,
+                _ => (),
+            }
+        }
+
+        self.calls.push(AllocatorCall {
+            function,
+            arguments,
+        });
+
+        Ok(())
+    }
+
+    fn allocator_exit(&mut self, function: AllocatorFunction, result: u32) {
+        let call = match self.calls.pop() {
+            Some(call) => call,
+            None => return,
+        };
+
+        if !self.calls.is_empty() || call.function != function {
+            return;
+        }
+
+        match (function, call.arguments) {
+            (AllocatorFunction::Malloc, (size, _)) => self.allocate(result, size, false),
+            (AllocatorFunction::Calloc, (count, size)) => {
+                if let Some(size) = count.checked_mul(size) {
+                    self.allocate(result, size, true);
+                }
+            }
+            (AllocatorFunction::Realloc, (pointer, size)) => {
+                if result == 0 {
+                    return;
+                }
+
+                let previous = match self.blocks.get_mut(&(pointer as u64)) {
+                    Some(block) if pointer != 0 && !block.freed => {
+                        let initialized: Vec<bool> = (0..block.size.min(size))
+                            .map(|offset| block.is_initialized(offset))
+                            .collect();
+                        block.freed = true;
+                        block.initialized = Vec::new();
+                        initialized
+                    }
+                    _ => Vec::new(),
+                };
+
+                self.allocate(result, size, false);
+
+                if let Some(block) = self.blocks.get_mut(&(result as u64)) {
+                    for (offset, initialized) in previous.into_iter().enumerate() {
+                        block.set_initialized(offset as u32, initialized);
+                    }
+                }
+            }
+            (AllocatorFunction::Free, (pointer, _)) => self.free(pointer),
+        }
+    }
+
+    /// Tracks a new allocation, forgetting the previous allocations
+    /// that overlap it.
+    fn allocate(&mut self, address: u32, size: u32, initialized: bool) {
+        if address == 0 {
+            return;
+        }
+
+        let address = address as u64;
+        let end = address + (size as u64).max(1);
+        let mut overlapping: Vec<u64> = self
+            .blocks
+            .range(address..)
+            .take_while(|(start, _)| **start < end)
+            .map(|(start, _)| *start)
+            .collect();
+
+        if let Some((start, _)) = self.block(address) {
+            overlapping.push(start);
+        }
+
+        for start in overlapping {
+            self.blocks.remove(&start);
+        }
+
+        self.blocks.insert(address, Block::new(size, initialized));
+    }
+
+    fn free(&mut self, pointer: u32) {
+        if let Some(block) = self.blocks.get_mut(&(pointer as u64)) {
+            block.freed = true;
+            block.initialized = Vec::new();
+        }
+    }
+}
+
+/// The host side of the [`Sanitizer`] middleware: it implements the
+/// sanitizer hooks, and holds the shadow state of the guest heap.
+///
+/// A `ShadowMemory` is cheap to clone; all the clones share the same
+/// state. A `ShadowMemory` must not be shared among instances.
+#[derive(Clone, Default)]
+pub struct ShadowMemory {
+    inner: Arc<Mutex<ShadowState>>,
+}
+
+impl ShadowMemory {
+    /// Creates a `ShadowMemory` with no tracked allocation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the live allocations, which can be reported as leaks
+    /// once the guest has exited.
+    pub fn live_allocations(&self) -> Vec<Allocation> {
+        self.inner
+            .lock()
+            .unwrap()
+            .blocks
+            .iter()
+            .filter(|(_, block)| !block.freed)
+            // Allocations are made by the guest, in the 32-bit
+            // address space.
+            .map(|(address, block)| Allocation {
+                address: *address as u32,
+                size: block.size,
+            })
+            .collect()
+    }
+
+    /// Creates the hooks imported by a module instrumented with the
+    /// [`Sanitizer`] middleware. They must be registered under the
+    /// [`SANITIZER_NAMESPACE`] namespace.
+    pub fn exports(&self, store: &Store) -> Exports {
+        fn memory_access(
+            shadow: &ShadowMemory,
+            address: i32,
+            offset: i32,
+            flags: i32,
+        ) -> Result<(), SanitizerError> {
+            shadow
+                .inner
+                .lock()
+                .unwrap()
+                .memory_access(address as u32 as u64 + offset as u32 as u64, flags)
+        }
+
+        fn memory_copy(
+            shadow: &ShadowMemory,
+            destination: i32,
+            source: i32,
+            length: i32,
+            flags: i32,
+        ) -> Result<(), SanitizerError> {
+            shadow.inner.lock().unwrap().memory_copy(
+                destination as u32 as u64,
+                source as u32 as u64,
+                length as u32 as u64,
+                flags,
+            )
+        }
+
+        fn memory_fill(
+            shadow: &ShadowMemory,
+            destination: i32,
+            length: i32,
+            flags: i32,
+        ) -> Result<(), SanitizerError> {
+            shadow.inner.lock().unwrap().memory_fill(
+                destination as u32 as u64,
+                length as u32 as u64,
+                flags,
+            )
+        }
+
+        fn allocator_enter(
+            shadow: &ShadowMemory,
+            function: i32,
+            first: i32,
+            second: i32,
+        ) -> Result<(), SanitizerError> {
+            match AllocatorFunction::from_i32(function) {
+                Some(function) => shadow
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .allocator_enter(function, (first as u32, second as u32)),
+                None => Ok(()),
+            }
+        }
+
+        fn allocator_exit(shadow: &ShadowMemory, function: i32, result: i32) {
+            if let Some(function) = AllocatorFunction::from_i32(function) {
+                shadow
+                    .inner
+                    .lock()
+                    .unwrap()
+                    .allocator_exit(function, result as u32);
+            }
+        }
+
+        let mut exports = Exports::new();
+        exports.insert(
+            "memory_access",
+            Function::new_native_with_env(store, self.clone(), memory_access),
+        );
+        exports.insert(
+            "memory_copy",
+            Function::new_native_with_env(store, self.clone(), memory_copy),
+        );
+        exports.insert(
+            "memory_fill",
+            Function::new_native_with_env(store, self.clone(), memory_fill),
+        );
+        exports.insert(
+            "allocator_enter",
+            Function::new_native_with_env(store, self.clone(), allocator_enter),
+        );
+        exports.insert(
+            "allocator_exit",
+            Function::new_native_with_env(store, self.clone(), allocator_exit),
+        );
+
+        exports
+    }
+}
+
+impl WasmerEnv for ShadowMemory {}
+
+impl fmt::Debug for ShadowMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShadowMemory").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{wat2wasm, CompilerConfig, Cranelift, ImportObject, Instance, Module, Universal};
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (memory 1)
+            (global $next (mut i32) (i32.const 1024))
+            ;; A bump allocator leaving 8 bytes between allocations.
+            (func $malloc (export "malloc") (param $size i32) (result i32)
+                (local $pointer i32)
+                global.get $next
+                local.set $pointer
+                global.get $next
+                local.get $size
+                i32.const 15
+                i32.add
+                i32.const -8
+                i32.and
+                i32.add
+                global.set $next
+                local.get $pointer)
+            (func $free (export "free") (param $pointer i32))
+            (func $overflow (export "overflow")
+                i32.const 16
+                call $malloc
+                i32.const 0
+                i32.store offset=16)
+            (func $use_after_free (export "use_after_free")
+                (local $pointer i32)
+                i32.const 16
+                call $malloc
+                local.tee $pointer
+                call $free
+                local.get $pointer
+                i32.load
+                drop)
+            (func $uninitialized_read (export "uninitialized_read") (result i32)
+                (local $pointer i32)
+                i32.const 16
+                call $malloc
+                local.set $pointer
+                local.get $pointer
+                i32.const 42
+                i32.store
+                local.get $pointer
+                i32.load
+                local.get $pointer
+                i32.load offset=4
+                i32.add)
+            (func $initialized_read (export "initialized_read") (result i32)
+                (local $pointer i32)
+                i32.const 16
+                call $malloc
+                local.tee $pointer
+                i32.const 0
+                i32.const 16
+                memory.fill
+                local.get $pointer
+                i32.load offset=12)
+            (func $double_free (export "double_free")
+                (local $pointer i32)
+                i32.const 16
+                call $malloc
+                local.tee $pointer
+                call $free
+                local.get $pointer
+                call $free)
+            (func $static_access (export "static_access") (result i32)
+                i32.const 16
+                i32.load))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn run(sanitizer: Sanitizer, function: &str) -> Result<(), RuntimeError> {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(sanitizer));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode()).unwrap();
+
+        let shadow = ShadowMemory::new();
+        let mut import_object = ImportObject::new();
+        import_object.register(SANITIZER_NAMESPACE, shadow.exports(&store));
+        let instance = Instance::new(&module, &import_object).unwrap();
+
+        instance
+            .exports
+            .get_function(function)
+            .unwrap()
+            .call(&[])
+            .map(|_| ())
+    }
+
+    fn violation(sanitizer: Sanitizer, function: &str) -> SanitizerError {
+        let error = run(sanitizer, function).unwrap_err();
+
+        assert!(error
+            .trace()
+            .iter()
+            .any(|frame| frame.function_name() == Some(function)));
+
+        let violation = error.sanitizer_error().cloned().unwrap();
+        assert_eq!(error.downcast::<SanitizerError>().unwrap(), violation);
+
+        violation
+    }
+
+    #[test]
+    fn detects_violations() {
+        assert_eq!(
+            violation(Sanitizer::new(), "overflow"),
+            SanitizerError {
+                violation: Violation::HeapBufferOverflow,
+                access: Access::Write,
+                address: 1040,
+                size: 4,
+                allocation: Allocation {
+                    address: 1024,
+                    size: 16,
+                },
+            }
+        );
+        assert_eq!(
+            violation(Sanitizer::new(), "use_after_free").violation,
+            Violation::UseAfterFree
+        );
+        assert_eq!(
+            violation(Sanitizer::new(), "uninitialized_read"),
+            SanitizerError {
+                violation: Violation::UninitializedRead,
+                access: Access::Read,
+                address: 1028,
+                size: 4,
+                allocation: Allocation {
+                    address: 1024,
+                    size: 16,
+                },
+            }
+        );
+        assert_eq!(
+            violation(Sanitizer::new(), "double_free").violation,
+            Violation::DoubleFree
+        );
+    }
+
+    #[test]
+    fn accepts_valid_accesses() {
+        assert!(RuntimeError::new("not a violation")
+            .sanitizer_error()
+            .is_none());
+        run(Sanitizer::new(), "initialized_read").unwrap();
+        run(Sanitizer::new(), "static_access").unwrap();
+        run(
+            Sanitizer::new().ignore_function("uninitialized_read"),
+            "uninitialized_read",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn realloc_keeps_initialized_bytes() {
+        let mut state = ShadowState::default();
+
+        state
+            .allocator_enter(AllocatorFunction::Malloc, (8, 0))
+            .unwrap();
+        state.allocator_exit(AllocatorFunction::Malloc, 1024);
+        state.memory_access(1024, 4 | MEMORY_ACCESS_STORE).unwrap();
+
+        state
+            .allocator_enter(AllocatorFunction::Realloc, (1024, 16))
+            .unwrap();
+        // `realloc` calling `malloc` is not tracked.
+        state
+            .allocator_enter(AllocatorFunction::Malloc, (16, 0))
+            .unwrap();
+        state.allocator_exit(AllocatorFunction::Malloc, 2048);
+        state.allocator_exit(AllocatorFunction::Realloc, 2048);
+
+        state.memory_access(2048, 4).unwrap();
+        assert_eq!(
+            state.memory_access(2052, 4).unwrap_err().violation,
+            Violation::UninitializedRead
+        );
+        assert_eq!(
+            state.memory_access(1024, 4).unwrap_err().violation,
+            Violation::UseAfterFree
+        );
+        assert_eq!(state.blocks.len(), 2);
+    }
+
+    #[test]
+    fn wide_addresses_do_not_alias_allocations() {
+        let mut state = ShadowState::default();
+
+        state
+            .allocator_enter(AllocatorFunction::Malloc, (8, 0))
+            .unwrap();
+        state.allocator_exit(AllocatorFunction::Malloc, 1024);
+
+        // The effective address of an access may exceed the 32-bit
+        // address space; it must not be confused with the allocation
+        // at its low 32 bits.
+        let wide = (1 << 32) + 1024;
+        assert!(state.block(wide).is_none());
+        state.memory_access(wide, 4 | MEMORY_ACCESS_STORE).unwrap();
+        state.memory_copy(wide, 1024, 8, 0).unwrap();
+
+        assert_eq!(
+            state.memory_access(1024, 4).unwrap_err().violation,
+            Violation::UninitializedRead
+        );
+    }
+}
@@ -0,0 +1,1473 @@
+//! `tracing` is a middleware for recording what a WebAssembly
+//! instance executes. It injects calls to host hooks on function
+//! entry and exit and, optionally, on branches and memory accesses.
+//!
+//! The hooks are imported by the instrumented module from the
+//! [`TRACING_NAMESPACE`] namespace, and are implemented by a
+//! [`Tracer`], which writes every event to a compact binary trace.
+//! The trace can be read back with [`Trace::read`] and summarized
+//! with [`Trace::summary`] (or with the `wasmer trace` subcommand).
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use wasmer::{CompilerConfig, ImportObject, Instance, Module};
+//! use wasmer_middlewares::tracing::{Tracer, Tracing, TRACING_NAMESPACE};
+//!
+//! fn enable_tracing(compiler_config: &mut dyn CompilerConfig) {
+//!     // Trace function calls and branches, but not memory accesses.
+//!     let tracing = Arc::new(Tracing::new().branches(true));
+//!
+//!     compiler_config.push_middleware(tracing);
+//! }
+//!
+//! fn instantiate_traced(module: &Module) -> Result<(Instance, Tracer), Box<dyn std::error::Error>> {
+//!     let file = std::fs::File::create("guest.trace")?;
+//!     let tracer = Tracer::new(module, file)?;
+//!
+//!     let mut import_object = ImportObject::new();
+//!     import_object.register(TRACING_NAMESPACE, tracer.exports(module.store()));
+//!
+//!     let instance = Instance::new(module, &import_object)?;
+//!
+//!     Ok((instance, tracer))
+//! }
+//! ```
+
+use crate::instrumentation::{
+    wrapper_block_type, InjectedHooks, MemoryAccess, ScratchGlobals, MEMORY_ACCESS_STORE,
+};
+use loupe::{MemoryUsage, MemoryUsageTracker};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufWriter, Read, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use wasmer::wasmparser::{Operator, Type as WpType, TypeOrFuncType as WpTypeOrFuncType};
+use wasmer::{
+    Exports, Function, FunctionMiddleware, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, Module, ModuleMiddleware, Store, Type, WasmerEnv,
+};
+use wasmer_types::entity::EntityRef;
+use wasmer_types::ModuleInfo;
+
+/// The namespace from which an instrumented module imports the
+/// tracing hooks.
+pub const TRACING_NAMESPACE: &str = "wasmer_tracing";
+
+/// The magic header of a binary trace.
+const TRACE_MAGIC: &[u8; 4] = b"\0wtr";
+
+/// The version of the binary trace format.
+const TRACE_VERSION: u8 = 1;
+
+const TAG_ENTER: u8 = 0x01;
+const TAG_EXIT: u8 = 0x02;
+const TAG_BRANCH: u8 = 0x03;
+const TAG_MEMORY_ACCESS: u8 = 0x04;
+
+/// The function indexes of the hooks imported by an instrumented module.
+#[derive(Clone, Debug, MemoryUsage)]
+struct TracingHooks {
+    /// `enter(function: i32)`.
+    enter: u32,
+
+    /// `exit(function: i32)`.
+    exit: u32,
+
+    /// `branch(function: i32, site: i32, value: i32)`.
+    branch: Option<u32>,
+
+    /// `memory_access(function: i32, address: i32, offset: i32, flags: i32)`.
+    memory_access: Option<u32>,
+}
+
+/// Module-specific state computed by `Tracing::transform_module_info`.
+#[derive(Debug)]
+struct TracingState {
+    /// The hooks imported by the instrumented module.
+    injected: InjectedHooks,
+
+    hooks: TracingHooks,
+
+    scratch: Option<ScratchGlobals>,
+
+    /// For each local function, its index in the instrumented module
+    /// and the block type used to wrap its body, if any.
+    functions: Vec<(u32, Option<WpTypeOrFuncType>)>,
+}
+
+/// The module-level tracing middleware.
+///
+/// The instrumented module imports the hooks `enter` and `exit` (and
+/// `branch` or `memory_access` when enabled) from the
+/// [`TRACING_NAMESPACE`] namespace. They are typically provided by
+/// [`Tracer::exports`].
+///
+/// Function exits are traced on `return`, on the end of the function
+/// body and on branches to the function body label, including the
+/// conditional ones (`br_if` and `br_table`). Functions left because
+/// of a trap have no exit event.
+///
+/// # Panic
+///
+/// An instance of `Tracing` should _not_ be shared among different
+/// modules, since it tracks module-specific information like the
+/// index of the injected hooks. Attempts to use a `Tracing` instance
+/// from multiple modules will result in a panic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use wasmer::CompilerConfig;
+/// use wasmer_middlewares::Tracing;
+///
+/// fn create_tracing_middleware(compiler_config: &mut dyn CompilerConfig) {
+///     // Let's trace function calls and memory accesses.
+///     let tracing = Arc::new(Tracing::new().memory_accesses(true));
+///
+///     // Finally, let's push the middleware.
+///     compiler_config.push_middleware(tracing);
+/// }
+/// ```
+pub struct Tracing {
+    /// Whether branches are traced.
+    branches: bool,
+
+    /// Whether memory accesses are traced.
+    memory_accesses: bool,
+
+    /// The module-specific state.
+    state: Mutex<Option<Arc<TracingState>>>,
+}
+
+/// The function-level tracing middleware.
+pub struct FunctionTracing {
+    /// The module-specific state.
+    state: Arc<TracingState>,
+
+    /// The index of the current function in the instrumented module.
+    function_index: u32,
+
+    /// The block type wrapping the function body, if any.
+    wrapper: Option<WpTypeOrFuncType>,
+
+    /// Whether the `enter` hook has been injected.
+    entered: bool,
+
+    /// The current block nesting depth.
+    depth: u32,
+
+    /// The number of branch sites seen so far.
+    branch_sites: u32,
+}
+
+impl Tracing {
+    /// Creates a `Tracing` middleware that traces function entries
+    /// and exits.
+    pub fn new() -> Self {
+        Self {
+            branches: false,
+            memory_accesses: false,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Enables or disables the tracing of branches (`br`, `br_if`,
+    /// `br_table` and `if`).
+    pub fn branches(mut self, enable: bool) -> Self {
+        self.branches = enable;
+        self
+    }
+
+    /// Enables or disables the tracing of memory loads and stores.
+    pub fn memory_accesses(mut self, enable: bool) -> Self {
+        self.memory_accesses = enable;
+        self
+    }
+}
+
+impl Default for Tracing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Tracing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracing")
+            .field("branches", &self.branches)
+            .field("memory_accesses", &self.memory_accesses)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl ModuleMiddleware for Tracing {
+    /// Generates a `FunctionMiddleware` for a given function.
+    fn generate_function_middleware(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware> {
+        let state = self.state.lock().unwrap().clone().unwrap();
+        let (function_index, wrapper) = state.functions[local_function_index.index()];
+
+        Box::new(FunctionTracing {
+            state,
+            function_index,
+            wrapper,
+            entered: false,
+            depth: 0,
+            branch_sites: 0,
+        })
+    }
+
+    /// Transforms a `ModuleInfo` struct in-place. This is called before application on functions begins.
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_some() {
+            panic!("Tracing::transform_module_info: Attempting to use a `Tracing` middleware from multiple modules.");
+        }
+
+        // Declare the hooks.
+        let mut hook_declarations = vec![("enter", vec![Type::I32]), ("exit", vec![Type::I32])];
+
+        if self.branches {
+            hook_declarations.push(("branch", vec![Type::I32; 3]));
+        }
+
+        if self.memory_accesses {
+            hook_declarations.push(("memory_access", vec![Type::I32; 4]));
+        }
+
+        // Compute the wrapping block types before adding the hook
+        // signatures, which are unknown to the function translator.
+        let mut local_functions: Vec<(u32, Option<WpTypeOrFuncType>)> = module_info
+            .functions
+            .iter()
+            .skip(module_info.num_imported_functions)
+            .map(|(function_index, signature_index)| {
+                (
+                    function_index.as_u32(),
+                    wrapper_block_type(module_info, &module_info.signatures[*signature_index]),
+                )
+            })
+            .collect();
+
+        let injected = InjectedHooks::inject(module_info, TRACING_NAMESPACE, &hook_declarations);
+
+        for (function_index, _) in local_functions.iter_mut() {
+            *function_index = injected.remap(*function_index);
+        }
+
+        // Append the scratch globals used to instrument operators
+        // whose operands are needed by the hooks, and to trace the
+        // conditional exits of functions whose body can't be wrapped.
+        let unwrapped_functions = local_functions.iter().any(|(_, wrapper)| wrapper.is_none());
+        let scratch = if self.branches || self.memory_accesses || unwrapped_functions {
+            Some(ScratchGlobals::push(module_info))
+        } else {
+            None
+        };
+
+        let hook = |name: &str| -> Option<u32> {
+            hook_declarations
+                .iter()
+                .position(|(hook_name, _)| *hook_name == name)
+                .map(|nth| injected.hook(nth))
+        };
+
+        *state = Some(Arc::new(TracingState {
+            hooks: TracingHooks {
+                enter: hook("enter").unwrap(),
+                exit: hook("exit").unwrap(),
+                branch: hook("branch"),
+                memory_access: hook("memory_access"),
+            },
+            injected,
+            scratch,
+            functions: local_functions,
+        }));
+    }
+}
+
+impl MemoryUsage for Tracing {
+    fn size_of_val(&self, tracker: &mut dyn MemoryUsageTracker) -> usize {
+        let state_size = match &*self.state.lock().unwrap() {
+            Some(state) => {
+                mem::size_of::<TracingState>()
+                    + state.hooks.size_of_val(tracker)
+                    + state.scratch.size_of_val(tracker)
+                    + state.functions.capacity() * mem::size_of::<(u32, Option<WpTypeOrFuncType>)>()
+            }
+            None => 0,
+        };
+
+        mem::size_of_val(self) + state_size
+    }
+}
+
+impl fmt::Debug for FunctionTracing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionTracing")
+            .field("function_index", &self.function_index)
+            .field("wrapper", &self.wrapper)
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl FunctionTracing {
+    /// Injects a call to the `exit` hook.
+    fn exit<'a>(&self, state: &mut MiddlewareReaderState<'a>) {
+        state.extend(&[
+            Operator::I32Const {
+                value: self.function_index as i32,
+            },
+            Operator::Call {
+                function_index: self.state.hooks.exit,
+            },
+        ]);
+    }
+
+    /// Injects a call to the `exit` hook, for a `br_if` or a
+    /// `br_table` whose condition or index is on top of the stack,
+    /// taken when `exits` holds for the condition or index.
+    ///
+    /// This is only needed when the function body isn't wrapped in a
+    /// block, otherwise such branches reach the `exit` hook at the end
+    /// of the wrapper block.
+    fn conditional_exit<'a>(
+        &self,
+        exits: &[Operator<'static>],
+        state: &mut MiddlewareReaderState<'a>,
+    ) {
+        let scratch = self
+            .state
+            .scratch
+            .as_ref()
+            .expect("scratch globals are pushed for functions without a wrapper");
+
+        state.extend(&[Operator::GlobalSet {
+            global_index: scratch.address,
+        }]);
+        state.extend(exits);
+        state.extend(&[
+            Operator::If {
+                ty: WpTypeOrFuncType::Type(WpType::EmptyBlockType),
+            },
+            Operator::I32Const {
+                value: self.function_index as i32,
+            },
+            Operator::Call {
+                function_index: self.state.hooks.exit,
+            },
+            Operator::End,
+            Operator::GlobalGet {
+                global_index: scratch.address,
+            },
+        ]);
+    }
+
+    /// Injects a call to the `branch` hook, if enabled, for a branch
+    /// operator whose condition or index is on top of the stack (when
+    /// `has_value` is true).
+    fn branch<'a>(&mut self, has_value: bool, state: &mut MiddlewareReaderState<'a>) {
+        let (branch, scratch) = match (self.state.hooks.branch, &self.state.scratch) {
+            (Some(branch), Some(scratch)) => (branch, scratch),
+            _ => return,
+        };
+        let site = self.branch_sites;
+        self.branch_sites += 1;
+
+        if has_value {
+            state.extend(&[
+                Operator::GlobalSet {
+                    global_index: scratch.address,
+                },
+                Operator::I32Const {
+                    value: self.function_index as i32,
+                },
+                Operator::I32Const { value: site as i32 },
+                Operator::GlobalGet {
+                    global_index: scratch.address,
+                },
+                Operator::Call {
+                    function_index: branch,
+                },
+                Operator::GlobalGet {
+                    global_index: scratch.address,
+                },
+            ]);
+        } else {
+            state.extend(&[
+                Operator::I32Const {
+                    value: self.function_index as i32,
+                },
+                Operator::I32Const { value: site as i32 },
+                Operator::I32Const { value: 0 },
+                Operator::Call {
+                    function_index: branch,
+                },
+            ]);
+        }
+    }
+
+    /// Injects a call to the `memory_access` hook, if enabled.
+    fn memory_access<'a>(&self, access: &MemoryAccess, state: &mut MiddlewareReaderState<'a>) {
+        if let (Some(memory_access), Some(scratch)) =
+            (self.state.hooks.memory_access, &self.state.scratch)
+        {
+            access.inject_hook(
+                scratch,
+                &[Operator::I32Const {
+                    value: self.function_index as i32,
+                }],
+                0,
+                memory_access,
+                state,
+            );
+        }
+    }
+}
+
+impl FunctionMiddleware for FunctionTracing {
+    fn feed<'a>(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+
+            state.extend(&[
+                Operator::I32Const {
+                    value: self.function_index as i32,
+                },
+                Operator::Call {
+                    function_index: self.state.hooks.enter,
+                },
+            ]);
+
+            // Wrap the body in a block, so that branches to the
+            // function body label reach the `exit` hook.
+            if let Some(ty) = self.wrapper {
+                state.push_operator(Operator::Block { ty });
+            }
+        }
+
+        let operator = match operator {
+            Operator::Block { .. } | Operator::Loop { .. } => {
+                self.depth += 1;
+                operator
+            }
+            Operator::If { .. } => {
+                self.branch(true, state);
+                self.depth += 1;
+                operator
+            }
+            Operator::End if self.depth == 0 => {
+                // End of the function body.
+                if self.wrapper.is_some() {
+                    state.push_operator(Operator::End);
+                }
+                self.exit(state);
+                operator
+            }
+            Operator::End => {
+                self.depth -= 1;
+                operator
+            }
+            Operator::Return => {
+                self.exit(state);
+                operator
+            }
+            Operator::Br { relative_depth } => {
+                self.branch(false, state);
+                if self.wrapper.is_none() && relative_depth == self.depth {
+                    self.exit(state);
+                }
+                operator
+            }
+            Operator::BrIf { relative_depth } => {
+                self.branch(true, state);
+                if self.wrapper.is_none() && relative_depth == self.depth {
+                    let scratch = self.state.scratch.as_ref().unwrap();
+
+                    // The branch is taken when the condition isn't 0.
+                    self.conditional_exit(
+                        &[Operator::GlobalGet {
+                            global_index: scratch.address,
+                        }],
+                        state,
+                    );
+                }
+                operator
+            }
+            Operator::BrTable { ref table } => {
+                self.branch(true, state);
+                if self.wrapper.is_none() {
+                    let scratch = self.state.scratch.as_ref().unwrap();
+                    let index = Operator::GlobalGet {
+                        global_index: scratch.address,
+                    };
+                    let mut exits = Vec::new();
+
+                    // The branch is taken when the index selects the
+                    // function body label, or when the index is out
+                    // of the table and the default target is the
+                    // function body label.
+                    for (nth, target) in table.targets().enumerate() {
+                        let (target, is_default) = target
+                            .map_err(|error| MiddlewareError::new("Tracing", error.to_string()))?;
+
+                        if target != self.depth {
+                            continue;
+                        }
+
+                        let or = !exits.is_empty();
+                        exits.push(index.clone());
+                        exits.push(Operator::I32Const { value: nth as i32 });
+                        exits.push(if is_default {
+                            Operator::I32GeU
+                        } else {
+                            Operator::I32Eq
+                        });
+
+                        if or {
+                            exits.push(Operator::I32Or);
+                        }
+                    }
+
+                    if !exits.is_empty() {
+                        self.conditional_exit(&exits, state);
+                    }
+                }
+                operator
+            }
+            Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } => {
+                self.exit(state);
+                operator
+            }
+            operator => {
+                if let Some(access) = MemoryAccess::of(&operator) {
+                    self.memory_access(&access, state);
+                }
+                operator
+            }
+        };
+        state.push_operator(self.state.injected.remap_operator(operator));
+
+        Ok(())
+    }
+}
+
+/// A single event of a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// A function has been entered.
+    Enter {
+        /// The function index, in the instrumented module.
+        function: u32,
+    },
+
+    /// A function has been exited.
+    Exit {
+        /// The function index, in the instrumented module.
+        function: u32,
+    },
+
+    /// A branch operator has been executed.
+    Branch {
+        /// The function index, in the instrumented module.
+        function: u32,
+
+        /// The index of the branch operator within the function.
+        site: u32,
+
+        /// The condition of a `br_if` or an `if`, the index of a
+        /// `br_table`, or 0 for a `br`.
+        value: u32,
+    },
+
+    /// A memory load or store has been executed.
+    MemoryAccess {
+        /// The function index, in the instrumented module.
+        function: u32,
+
+        /// The effective address of the access.
+        address: u64,
+
+        /// The size of the access, in bytes.
+        size: u8,
+
+        /// Whether the access is a store.
+        store: bool,
+    },
+}
+
+/// A [`TraceEvent`] with the time elapsed since the beginning of the
+/// trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecord {
+    /// The time elapsed since the beginning of the trace.
+    pub time: Duration,
+
+    /// The event.
+    pub event: TraceEvent,
+}
+
+fn write_varint(writer: &mut dyn Write, mut value: u64) -> io::Result<()> {
+    let mut buffer = [0u8; 10];
+    let mut length = 0;
+
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buffer[length] = byte;
+            length += 1;
+            break;
+        }
+
+        buffer[length] = byte | 0x80;
+        length += 1;
+    }
+
+    writer.write_all(&buffer[..length])
+}
+
+fn read_varint(reader: &mut dyn Read) -> io::Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+
+        if shift >= 64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "varint is too long",
+            ));
+        }
+
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        shift += 7;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn read_u32(reader: &mut dyn Read) -> io::Result<u32> {
+    let value = read_varint(reader)?;
+
+    if value > u32::MAX as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "value does not fit in a u32",
+        ));
+    }
+
+    Ok(value as u32)
+}
+
+struct TracerInner {
+    /// Where the trace is written.
+    writer: BufWriter<Box<dyn Write + Send>>,
+
+    /// When the trace started.
+    start: Instant,
+
+    /// The time of the previous event, relative to `start`.
+    last: Duration,
+
+    /// The first error that happened while writing an event. Hooks
+    /// can't fail, so it is reported by `Tracer::finish`.
+    error: Option<io::Error>,
+}
+
+impl TracerInner {
+    fn record(&mut self, event: TraceEvent) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let time = self.start.elapsed();
+        let delta = time.saturating_sub(self.last);
+        self.last = time;
+
+        if let Err(error) = Self::write_event(&mut self.writer, delta, &event) {
+            self.error = Some(error);
+        }
+    }
+
+    fn write_event(writer: &mut dyn Write, delta: Duration, event: &TraceEvent) -> io::Result<()> {
+        match *event {
+            TraceEvent::Enter { function } => {
+                writer.write_all(&[TAG_ENTER])?;
+                write_varint(writer, function as u64)?;
+            }
+            TraceEvent::Exit { function } => {
+                writer.write_all(&[TAG_EXIT])?;
+                write_varint(writer, function as u64)?;
+            }
+            TraceEvent::Branch {
+                function,
+                site,
+                value,
+            } => {
+                writer.write_all(&[TAG_BRANCH])?;
+                write_varint(writer, function as u64)?;
+                write_varint(writer, site as u64)?;
+                write_varint(writer, value as u64)?;
+            }
+            TraceEvent::MemoryAccess {
+                function,
+                address,
+                size,
+                store,
+            } => {
+                writer.write_all(&[TAG_MEMORY_ACCESS])?;
+                write_varint(writer, function as u64)?;
+                write_varint(writer, address)?;
+                write_varint(writer, size as u64 | if store { 0x100 } else { 0 })?;
+            }
+        }
+
+        write_varint(writer, delta.as_nanos() as u64)
+    }
+}
+
+/// The host side of the [`Tracing`] middleware: it implements the
+/// tracing hooks and writes the events to a binary trace.
+///
+/// A `Tracer` is cheap to clone; all the clones write to the same
+/// trace.
+#[derive(Clone)]
+pub struct Tracer {
+    inner: Arc<Mutex<TracerInner>>,
+}
+
+impl Tracer {
+    /// Creates a `Tracer` for a module instrumented with the
+    /// [`Tracing`] middleware, and writes the trace header (including
+    /// the function names of `module`) to `writer`.
+    pub fn new<W: Write + Send + 'static>(module: &Module, writer: W) -> io::Result<Self> {
+        let mut writer = BufWriter::new(Box::new(writer) as Box<dyn Write + Send>);
+        let module_info = module.info();
+
+        let mut names: Vec<(u32, String)> = module
+            .imports()
+            .functions()
+            .enumerate()
+            .map(|(index, import)| {
+                (
+                    index as u32,
+                    format!("{}.{}", import.module(), import.name()),
+                )
+            })
+            .collect();
+        names.extend(
+            module_info
+                .function_names
+                .iter()
+                .map(|(index, name)| (index.as_u32(), name.clone())),
+        );
+        names.sort();
+        names.dedup_by_key(|(index, _)| *index);
+
+        writer.write_all(TRACE_MAGIC)?;
+        writer.write_all(&[TRACE_VERSION])?;
+        write_varint(&mut writer, names.len() as u64)?;
+
+        for (index, name) in names {
+            write_varint(&mut writer, index as u64)?;
+            write_varint(&mut writer, name.len() as u64)?;
+            writer.write_all(name.as_bytes())?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(TracerInner {
+                writer,
+                start: Instant::now(),
+                last: Duration::default(),
+                error: None,
+            })),
+        })
+    }
+
+    /// Records an event in the trace.
+    pub fn record(&self, event: TraceEvent) {
+        self.inner.lock().unwrap().record(event);
+    }
+
+    /// Flushes the trace, and reports the first error that happened
+    /// while writing it, if any.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if let Some(error) = inner.error.take() {
+            return Err(error);
+        }
+
+        inner.writer.flush()
+    }
+
+    /// Creates the hooks imported by a module instrumented with the
+    /// [`Tracing`] middleware. They must be registered under the
+    /// [`TRACING_NAMESPACE`] namespace.
+    pub fn exports(&self, store: &Store) -> Exports {
+        fn enter(tracer: &Tracer, function: i32) {
+            tracer.record(TraceEvent::Enter {
+                function: function as u32,
+            });
+        }
+
+        fn exit(tracer: &Tracer, function: i32) {
+            tracer.record(TraceEvent::Exit {
+                function: function as u32,
+            });
+        }
+
+        fn branch(tracer: &Tracer, function: i32, site: i32, value: i32) {
+            tracer.record(TraceEvent::Branch {
+                function: function as u32,
+                site: site as u32,
+                value: value as u32,
+            });
+        }
+
+        fn memory_access(tracer: &Tracer, function: i32, address: i32, offset: i32, flags: i32) {
+            tracer.record(TraceEvent::MemoryAccess {
+                function: function as u32,
+                address: address as u32 as u64 + offset as u32 as u64,
+                size: (flags & 0xff) as u8,
+                store: flags & MEMORY_ACCESS_STORE != 0,
+            });
+        }
+
+        let mut exports = Exports::new();
+        exports.insert(
+            "enter",
+            Function::new_native_with_env(store, self.clone(), enter),
+        );
+        exports.insert(
+            "exit",
+            Function::new_native_with_env(store, self.clone(), exit),
+        );
+        exports.insert(
+            "branch",
+            Function::new_native_with_env(store, self.clone(), branch),
+        );
+        exports.insert(
+            "memory_access",
+            Function::new_native_with_env(store, self.clone(), memory_access),
+        );
+
+        exports
+    }
+}
+
+impl WasmerEnv for Tracer {}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer").finish()
+    }
+}
+
+/// A binary trace, as written by a [`Tracer`].
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    /// The names of the functions of the traced module, by function
+    /// index.
+    pub function_names: HashMap<u32, String>,
+
+    /// The recorded events, in order.
+    pub records: Vec<TraceRecord>,
+}
+
+impl Trace {
+    /// Reads a binary trace.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = io::BufReader::new(reader);
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != TRACE_MAGIC {
+            return Err(invalid("not a wasmer trace"));
+        }
+
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+
+        if version[0] != TRACE_VERSION {
+            return Err(invalid("unsupported trace version"));
+        }
+
+        let mut function_names = HashMap::new();
+
+        for _ in 0..read_varint(&mut reader)? {
+            let index = read_u32(&mut reader)?;
+            let length = read_varint(&mut reader)?;
+            // Don't trust `length` to allocate the name, the trace may
+            // be truncated or corrupted.
+            let mut name = Vec::new();
+            (&mut reader).take(length).read_to_end(&mut name)?;
+
+            if name.len() as u64 != length {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated function name",
+                ));
+            }
+
+            let name = String::from_utf8(name).map_err(|_| invalid("invalid function name"))?;
+
+            function_names.insert(index, name);
+        }
+
+        let mut records = Vec::new();
+        let mut time = Duration::default();
+
+        loop {
+            let mut tag = [0u8; 1];
+
+            if reader.read(&mut tag)? == 0 {
+                break;
+            }
+
+            let event = match tag[0] {
+                TAG_ENTER => TraceEvent::Enter {
+                    function: read_u32(&mut reader)?,
+                },
+                TAG_EXIT => TraceEvent::Exit {
+                    function: read_u32(&mut reader)?,
+                },
+                TAG_BRANCH => TraceEvent::Branch {
+                    function: read_u32(&mut reader)?,
+                    site: read_u32(&mut reader)?,
+                    value: read_u32(&mut reader)?,
+                },
+                TAG_MEMORY_ACCESS => {
+                    let function = read_u32(&mut reader)?;
+                    let address = read_varint(&mut reader)?;
+                    let flags = read_varint(&mut reader)?;
+
+                    TraceEvent::MemoryAccess {
+                        function,
+                        address,
+                        size: (flags & 0xff) as u8,
+                        store: flags & 0x100 != 0,
+                    }
+                }
+                _ => return Err(invalid("unknown trace event")),
+            };
+
+            time += Duration::from_nanos(read_varint(&mut reader)?);
+            records.push(TraceRecord { time, event });
+        }
+
+        Ok(Self {
+            function_names,
+            records,
+        })
+    }
+
+    /// Returns the name of a function, if known.
+    pub fn function_name(&self, function: u32) -> Option<&str> {
+        self.function_names.get(&function).map(String::as_str)
+    }
+
+    /// Summarizes the trace: call counts and times per function, call
+    /// tree, and number of branches and memory accesses.
+    ///
+    /// Functions still active at the end of the trace (because of a
+    /// trap for example) are considered exited at the last event.
+    pub fn summary(&self) -> TraceSummary {
+        let mut builder = SummaryBuilder {
+            trace: self,
+            nodes: vec![CallTreeNode::default()],
+            stack: Vec::new(),
+            functions: HashMap::new(),
+        };
+        let mut summary = TraceSummary::default();
+
+        for record in &self.records {
+            match record.event {
+                TraceEvent::Enter { function } => builder.enter(function, record.time),
+                TraceEvent::Exit { function } => {
+                    // Frames above the exited function have been left
+                    // without an exit event, e.g. by a trap caught by
+                    // the host.
+                    if builder.stack.iter().any(|frame| frame.function == function) {
+                        while let Some(frame) = builder.stack.last() {
+                            let done = frame.function == function;
+                            builder.exit(record.time);
+
+                            if done {
+                                break;
+                            }
+                        }
+                    }
+                }
+                TraceEvent::Branch { .. } => summary.branches += 1,
+                TraceEvent::MemoryAccess { store: false, .. } => summary.loads += 1,
+                TraceEvent::MemoryAccess { store: true, .. } => summary.stores += 1,
+            }
+        }
+
+        let end = self
+            .records
+            .last()
+            .map(|record| record.time)
+            .unwrap_or_default();
+
+        while !builder.stack.is_empty() {
+            builder.exit(end);
+        }
+
+        let mut functions: Vec<FunctionSummary> =
+            builder.functions.into_iter().map(|(_, f)| f).collect();
+        functions.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then(b.calls.cmp(&a.calls))
+                .then(a.function.cmp(&b.function))
+        });
+
+        summary.functions = functions;
+        summary.call_tree = CallTree {
+            nodes: builder.nodes,
+        };
+        summary
+    }
+}
+
+/// An active function call, while building a [`TraceSummary`].
+struct Frame {
+    function: u32,
+
+    /// The call tree node of this call.
+    node: usize,
+
+    /// When the function has been entered.
+    entered: Duration,
+
+    /// The time spent in the callees so far.
+    children_time: Duration,
+}
+
+/// Replays the calls of a [`Trace`] to build a [`TraceSummary`].
+struct SummaryBuilder<'a> {
+    trace: &'a Trace,
+    nodes: Vec<CallTreeNode>,
+    stack: Vec<Frame>,
+    functions: HashMap<u32, FunctionSummary>,
+}
+
+impl<'a> SummaryBuilder<'a> {
+    fn function(&mut self, function: u32) -> &mut FunctionSummary {
+        let trace = self.trace;
+
+        self.functions
+            .entry(function)
+            .or_insert_with(|| FunctionSummary {
+                function,
+                name: trace.function_name(function).map(ToString::to_string),
+                ..FunctionSummary::default()
+            })
+    }
+
+    fn enter(&mut self, function: u32, time: Duration) {
+        let parent = self.stack.last().map(|frame| frame.node).unwrap_or(0);
+        let existing = self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].function == Some(function));
+        let node = match existing {
+            Some(child) => child,
+            None => {
+                self.nodes.push(CallTreeNode {
+                    function: Some(function),
+                    ..CallTreeNode::default()
+                });
+                let child = self.nodes.len() - 1;
+                self.nodes[parent].children.push(child);
+                child
+            }
+        };
+
+        self.nodes[node].calls += 1;
+        self.function(function).calls += 1;
+        self.stack.push(Frame {
+            function,
+            node,
+            entered: time,
+            children_time: Duration::default(),
+        });
+    }
+
+    fn exit(&mut self, time: Duration) {
+        let frame = self.stack.pop().unwrap();
+        let total_time = time.saturating_sub(frame.entered);
+
+        let function = self.function(frame.function);
+        function.total_time += total_time;
+        function.self_time += total_time.saturating_sub(frame.children_time);
+        self.nodes[frame.node].total_time += total_time;
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children_time += total_time;
+        }
+    }
+}
+
+/// Statistics about a function in a [`TraceSummary`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionSummary {
+    /// The function index, in the instrumented module.
+    pub function: u32,
+
+    /// The function name, if known.
+    pub name: Option<String>,
+
+    /// The number of calls.
+    pub calls: u64,
+
+    /// The time spent in the function, including its callees.
+    pub total_time: Duration,
+
+    /// The time spent in the function, excluding its callees.
+    pub self_time: Duration,
+}
+
+/// A node of a [`CallTree`]: a function called from a given call path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallTreeNode {
+    /// The function, or `None` for the root of the tree.
+    pub function: Option<u32>,
+
+    /// The number of calls from this call path.
+    pub calls: u64,
+
+    /// The time spent in the function from this call path, including
+    /// its callees.
+    pub total_time: Duration,
+
+    /// The indexes of the callee nodes in [`CallTree::nodes`].
+    pub children: Vec<usize>,
+}
+
+/// The call tree of a trace, where calls from the same call path are
+/// merged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallTree {
+    /// The nodes of the tree. The first node is the root.
+    pub nodes: Vec<CallTreeNode>,
+}
+
+impl CallTree {
+    /// Returns the root of the tree, whose children are the functions
+    /// called by the host.
+    pub fn root(&self) -> &CallTreeNode {
+        &self.nodes[0]
+    }
+
+    /// Returns the callees of a node.
+    pub fn children<'a>(
+        &'a self,
+        node: &'a CallTreeNode,
+    ) -> impl Iterator<Item = &'a CallTreeNode> + 'a {
+        node.children.iter().map(move |&child| &self.nodes[child])
+    }
+}
+
+/// A summary of a [`Trace`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceSummary {
+    /// Statistics per function, the hottest (by self time) first.
+    pub functions: Vec<FunctionSummary>,
+
+    /// The call tree.
+    pub call_tree: CallTree,
+
+    /// The number of executed branch operators.
+    pub branches: u64,
+
+    /// The number of executed memory loads.
+    pub loads: u64,
+
+    /// The number of executed memory stores.
+    pub stores: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use wasmer::{imports, wat2wasm, CompilerConfig, Cranelift, Instance, Universal};
+
+    /// A writer whose content can be read after it has been given to a
+    /// `Tracer`.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn bytecode() -> Vec<u8> {
+        wat2wasm(
+            br#"
+            (module
+            (import "env" "host" (func $host))
+            (memory 1)
+            (func $add_one (param $value i32) (result i32)
+                local.get $value
+                i32.const 1
+                i32.add)
+            (func $run (param $value i32) (result i32)
+                i32.const 16
+                local.get $value
+                i32.store
+                i32.const 16
+                i32.load
+                call $add_one
+                local.get $value
+                br_if 0
+                drop
+                call $host
+                i32.const 0)
+            (export "run" (func $run)))
+            "#,
+        )
+        .unwrap()
+        .into()
+    }
+
+    fn run(tracing: Tracing, value: i32) -> Trace {
+        run_bytecode(bytecode(), tracing, value)
+    }
+
+    fn run_bytecode(bytecode: Vec<u8>, tracing: Tracing, value: i32) -> Trace {
+        let mut compiler_config = Cranelift::default();
+        compiler_config.push_middleware(Arc::new(tracing));
+        let store = Store::new(&Universal::new(compiler_config).engine());
+        let module = Module::new(&store, bytecode).unwrap();
+
+        let buffer = SharedBuffer::default();
+        let tracer = Tracer::new(&module, buffer.clone()).unwrap();
+        let mut import_object = imports! {
+            "env" => {
+                "host" => Function::new_native(&store, || {}),
+            },
+        };
+        import_object.register(TRACING_NAMESPACE, tracer.exports(&store));
+
+        let instance = Instance::new(&module, &import_object).unwrap();
+        let run = instance
+            .exports
+            .get_function("run")
+            .unwrap()
+            .native::<i32, i32>()
+            .unwrap();
+        run.call(value).unwrap();
+        tracer.finish().unwrap();
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        Trace::read(&bytes[..]).unwrap()
+    }
+
+    fn events(trace: &Trace) -> Vec<TraceEvent> {
+        trace
+            .records
+            .iter()
+            .map(|record| record.event.clone())
+            .collect()
+    }
+
+    #[test]
+    fn traces_calls() {
+        let trace = run(Tracing::new(), 0);
+
+        // The `env.host` import keeps index 0, `$add_one` and `$run`
+        // are shifted after the 2 hooks.
+        assert_eq!(
+            events(&trace),
+            vec![
+                TraceEvent::Enter { function: 4 },
+                TraceEvent::Enter { function: 3 },
+                TraceEvent::Exit { function: 3 },
+                TraceEvent::Exit { function: 4 },
+            ]
+        );
+        assert_eq!(trace.function_name(0), Some("env.host"));
+        assert_eq!(trace.function_name(3), Some("add_one"));
+        assert_eq!(trace.function_name(4), Some("run"));
+    }
+
+    #[test]
+    fn traces_branches_to_the_function_label() {
+        // `br_if 0` is taken, the exit is still traced.
+        let trace = run(Tracing::new().branches(true), 1);
+
+        assert_eq!(
+            events(&trace),
+            vec![
+                TraceEvent::Enter { function: 5 },
+                TraceEvent::Enter { function: 4 },
+                TraceEvent::Exit { function: 4 },
+                TraceEvent::Branch {
+                    function: 5,
+                    site: 0,
+                    value: 1,
+                },
+                TraceEvent::Exit { function: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn traces_conditional_exits_of_unwrapped_functions() {
+        // No type of the module produces `i32 i64` without parameters,
+        // so the bodies of `$cond` and `$pick` can't be wrapped in a
+        // block.
+        let bytecode = wat2wasm(
+            br#"
+            (module
+            (type $t (func (param i32) (result i32 i64)))
+            (func $cond (param $value i32) (result i32 i64)
+                i32.const 1
+                i64.const 2
+                local.get $value
+                br_if 0
+                drop
+                drop
+                i32.const 3
+                i64.const 4)
+            (func $pick (param $value i32) (result i32 i64)
+                i32.const 1
+                block (type $t)
+                    i64.const 2
+                    local.get $value
+                    br_table 0 1
+                end
+                drop
+                drop
+                i32.const 3
+                i64.const 4)
+            (func $run (param $value i32) (result i32)
+                local.get $value
+                call $cond
+                drop
+                drop
+                local.get $value
+                call $pick
+                drop)
+            (export "run" (func $run)))
+            "#,
+        )
+        .unwrap()
+        .into_owned();
+        let expected = vec![
+            TraceEvent::Enter { function: 4 },
+            TraceEvent::Enter { function: 2 },
+            TraceEvent::Exit { function: 2 },
+            TraceEvent::Enter { function: 3 },
+            TraceEvent::Exit { function: 3 },
+            TraceEvent::Exit { function: 4 },
+        ];
+
+        // The branches are not taken, the functions reach their end.
+        let trace = run_bytecode(bytecode.clone(), Tracing::new(), 0);
+        assert_eq!(events(&trace), expected);
+
+        // `br_if 0` and the default target of `br_table` leave the
+        // functions.
+        let trace = run_bytecode(bytecode, Tracing::new(), 5);
+        assert_eq!(events(&trace), expected);
+    }
+
+    #[test]
+    fn rejects_truncated_traces() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TRACE_MAGIC);
+        bytes.push(TRACE_VERSION);
+        // One function name, of index 0, whose length is way larger
+        // than the remaining input.
+        write_varint(&mut bytes, 1).unwrap();
+        write_varint(&mut bytes, 0).unwrap();
+        write_varint(&mut bytes, u64::MAX >> 1).unwrap();
+        bytes.extend_from_slice(b"run");
+
+        let error = Trace::read(&bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn traces_memory_accesses() {
+        let trace = run(Tracing::new().memory_accesses(true), 0);
+
+        assert_eq!(
+            events(&trace),
+            vec![
+                TraceEvent::Enter { function: 5 },
+                TraceEvent::MemoryAccess {
+                    function: 5,
+                    address: 16,
+                    size: 4,
+                    store: true,
+                },
+                TraceEvent::MemoryAccess {
+                    function: 5,
+                    address: 16,
+                    size: 4,
+                    store: false,
+                },
+                TraceEvent::Enter { function: 4 },
+                TraceEvent::Exit { function: 4 },
+                TraceEvent::Exit { function: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_works() {
+        let trace = run(Tracing::new().branches(true).memory_accesses(true), 0);
+        let summary = trace.summary();
+
+        assert_eq!(summary.branches, 1);
+        assert_eq!(summary.loads, 1);
+        assert_eq!(summary.stores, 1);
+        assert_eq!(summary.functions.len(), 2);
+        assert!(summary.functions.iter().all(|function| function.calls == 1));
+
+        let root = summary.call_tree.root();
+        let run = summary.call_tree.children(root).collect::<Vec<_>>();
+        assert_eq!(run.len(), 1);
+        assert_eq!(run[0].function, Some(6));
+
+        let add_one = summary.call_tree.children(run[0]).collect::<Vec<_>>();
+        assert_eq!(add_one.len(), 1);
+        assert_eq!(add_one[0].function, Some(5));
+        assert_eq!(add_one[0].calls, 1);
+    }
+}
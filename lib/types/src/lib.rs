@@ -81,7 +81,7 @@ pub use crate::initializers::{
 };
 pub use crate::memory_view::{Atomically, MemoryView};
 pub use crate::module::{ExportsIterator, ImportsIterator, ModuleInfo};
-pub use crate::native::{NativeWasmType, ValueType};
+pub use crate::native::{LittleEndianConvert, NativeWasmType, ValueType};
 pub use crate::units::{
     Bytes, PageCountOutOfRange, Pages, WASM_MAX_PAGES, WASM_MIN_PAGES, WASM_PAGE_SIZE,
 };
@@ -71,11 +71,11 @@ where
     /// Creates a subarray view from this `MemoryView`.
     pub fn subarray(&self, start: u32, end: u32) -> Self {
         assert!(
-            (start as usize) < self.length,
+            (start as usize) <= self.length,
             "The range start is bigger than current length"
         );
         assert!(
-            (end as usize) < self.length,
+            (end as usize) <= self.length,
             "The range end is bigger than current length"
         );
 
@@ -102,6 +102,14 @@ where
             *self.ptr.offset(i as isize) = *byte;
         }
     }
+
+    /// Copy the contents of this `MemoryView` into the destination
+    /// slice, which must be at least as long as the view.
+    pub fn copy_to(&self, dst: &mut [T]) {
+        for (i, value) in dst[..self.length].iter_mut().enumerate() {
+            *value = unsafe { *self.ptr.add(i) };
+        }
+    }
 }
 
 impl<'a, T: Atomic> MemoryView<'a, T> {
@@ -121,6 +121,12 @@ pub struct ModuleInfo {
     /// The data for each CustomSection in the module.
     pub custom_sections_data: PrimaryMap<CustomSectionIndex, Arc<[u8]>>,
 
+    /// The offset of the code section contents in the wasm file.
+    ///
+    /// Code addresses in DWARF debugging information are relative to
+    /// this offset.
+    pub code_section_offset: usize,
+
     /// Number of imported functions in the module.
     pub num_imported_functions: usize,
 
@@ -154,6 +160,7 @@ pub struct ArchivableModuleInfo {
     globals: PrimaryMap<GlobalIndex, GlobalType>,
     custom_sections: ArchivableIndexMap<String, CustomSectionIndex>,
     custom_sections_data: PrimaryMap<CustomSectionIndex, Arc<[u8]>>,
+    code_section_offset: usize,
     num_imported_functions: usize,
     num_imported_tables: usize,
     num_imported_memories: usize,
@@ -180,6 +187,7 @@ impl From<ModuleInfo> for ArchivableModuleInfo {
             globals: it.globals,
             custom_sections: ArchivableIndexMap::from(it.custom_sections),
             custom_sections_data: it.custom_sections_data,
+            code_section_offset: it.code_section_offset,
             num_imported_functions: it.num_imported_functions,
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
@@ -209,6 +217,7 @@ impl From<ArchivableModuleInfo> for ModuleInfo {
             globals: it.globals,
             custom_sections: it.custom_sections.into(),
             custom_sections_data: it.custom_sections_data,
+            code_section_offset: it.code_section_offset,
             num_imported_functions: it.num_imported_functions,
             num_imported_tables: it.num_imported_tables,
             num_imported_memories: it.num_imported_memories,
@@ -273,6 +282,7 @@ impl PartialEq for ModuleInfo {
             && self.globals == other.globals
             && self.custom_sections == other.custom_sections
             && self.custom_sections_data == other.custom_sections_data
+            && self.code_section_offset == other.code_section_offset
             && self.num_imported_functions == other.num_imported_functions
             && self.num_imported_tables == other.num_imported_tables
             && self.num_imported_memories == other.num_imported_memories
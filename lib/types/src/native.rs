@@ -268,3 +268,83 @@ macro_rules! impl_value_type_for {
 }
 
 impl_value_type_for!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+// Arrays have no padding between their elements, so they are valid for
+// all bit patterns as long as their elements are.
+unsafe impl<T: ValueType, const N: usize> ValueType for [T; N] {}
+
+/// Conversions of a value from and to the little-endian byte order of
+/// the WebAssembly linear memory.
+///
+/// On little-endian hosts these conversions are no-ops. They can be
+/// implemented for structs with `#[derive(ValueType)]`, which also
+/// generates accessors doing the conversions.
+pub trait LittleEndianConvert: Copy {
+    /// Converts a value read from Wasm memory to the host byte order.
+    fn little_endian_to_native(self) -> Self;
+
+    /// Converts a value to the byte order of Wasm memory.
+    fn native_to_little_endian(self) -> Self;
+}
+
+macro_rules! impl_little_endian_convert_for {
+    ( $($type:ty),* ) => {
+        $(
+            impl LittleEndianConvert for $type {
+                #[inline]
+                fn little_endian_to_native(self) -> Self {
+                    <$type>::from_le(self)
+                }
+
+                #[inline]
+                fn native_to_little_endian(self) -> Self {
+                    self.to_le()
+                }
+            }
+        )*
+    };
+}
+
+impl_little_endian_convert_for!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl LittleEndianConvert for f32 {
+    #[inline]
+    fn little_endian_to_native(self) -> Self {
+        Self::from_bits(self.to_bits().little_endian_to_native())
+    }
+
+    #[inline]
+    fn native_to_little_endian(self) -> Self {
+        Self::from_bits(self.to_bits().native_to_little_endian())
+    }
+}
+
+impl LittleEndianConvert for f64 {
+    #[inline]
+    fn little_endian_to_native(self) -> Self {
+        Self::from_bits(self.to_bits().little_endian_to_native())
+    }
+
+    #[inline]
+    fn native_to_little_endian(self) -> Self {
+        Self::from_bits(self.to_bits().native_to_little_endian())
+    }
+}
+
+impl<T: LittleEndianConvert, const N: usize> LittleEndianConvert for [T; N] {
+    #[inline]
+    fn little_endian_to_native(mut self) -> Self {
+        for elem in self.iter_mut() {
+            *elem = elem.little_endian_to_native();
+        }
+        self
+    }
+
+    #[inline]
+    fn native_to_little_endian(mut self) -> Self {
+        for elem in self.iter_mut() {
+            *elem = elem.native_to_little_endian();
+        }
+        self
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     DirEntry, FileDescriptor, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
-    Result, VirtualFile,
+    Readiness, Result, VirtualFile,
 };
 #[cfg(feature = "enable-serde")]
 use serde::{de, Deserialize, Serialize};
@@ -113,6 +113,46 @@ impl crate::FileSystem for FileSystem {
             .and_then(TryInto::try_into)
             .map_err(Into::into)
     }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        fs::symlink_metadata(path)
+            .and_then(TryInto::try_into)
+            .map_err(Into::into)
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(original, link).map_err(Into::into)
+        }
+
+        #[cfg(windows)]
+        {
+            // Windows needs to know whether the symlink points to a
+            // directory. `original` is relative to the symlink.
+            let target = link
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(original);
+
+            if target.is_dir() {
+                std::os::windows::fs::symlink_dir(original, link).map_err(Into::into)
+            } else {
+                std::os::windows::fs::symlink_file(original, link).map_err(Into::into)
+            }
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let _ = (original, link);
+
+            Err(FsError::PermissionDenied)
+        }
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        fs::read_link(path).map_err(Into::into)
+    }
 }
 
 impl TryInto<Metadata> for fs::Metadata {
@@ -418,6 +458,10 @@ impl VirtualFile for File {
     fn bytes_available(&self) -> Result<usize> {
         host_file_bytes_available(self.inner.try_into_filedescriptor()?)
     }
+
+    fn poll_readiness(&self) -> Result<Readiness> {
+        host_file_readiness(self.inner.try_into_filedescriptor()?)
+    }
 }
 
 #[cfg(unix)]
@@ -440,6 +484,52 @@ fn host_file_bytes_available(_host_fd: FileDescriptor) -> Result<usize> {
     unimplemented!("host_file_bytes_available not yet implemented for non-Unix-like targets.  This probably means the program tried to use wasi::poll_oneoff")
 }
 
+#[cfg(unix)]
+fn host_file_readiness(host_fd: FileDescriptor) -> Result<Readiness> {
+    let fd: RawFd = host_fd.try_into()?;
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN | libc::POLLOUT,
+        revents: 0,
+    };
+    // a timeout of 0 makes `poll` return immediately
+    let result = unsafe { libc::poll(&mut poll_fd, 1, 0) };
+
+    if result < 0 {
+        return Err(FsError::IOError);
+    }
+    if poll_fd.revents & libc::POLLNVAL != 0 {
+        return Err(FsError::InvalidFd);
+    }
+
+    // `FIONREAD` isn't supported by all kinds of files
+    let bytes_available = host_file_bytes_available(FileDescriptor(fd as usize)).unwrap_or(0);
+
+    Ok(Readiness {
+        read: if poll_fd.revents & libc::POLLIN != 0 {
+            Some(bytes_available)
+        } else {
+            None
+        },
+        write: if poll_fd.revents & libc::POLLOUT != 0 {
+            Some(bytes_available)
+        } else {
+            None
+        },
+        hangup: poll_fd.revents & libc::POLLHUP != 0,
+    })
+}
+
+#[cfg(not(unix))]
+fn host_file_readiness(_host_fd: FileDescriptor) -> Result<Readiness> {
+    // TODO: poll the handle; until then, host files are always ready
+    Ok(Readiness {
+        read: Some(0),
+        write: Some(0),
+        hangup: false,
+    })
+}
+
 /// A wrapper type around Stdout that implements `VirtualFile` and
 /// `Serialize` + `Deserialize`.
 #[derive(Debug, Default)]
@@ -534,6 +624,10 @@ impl VirtualFile for Stdout {
     fn get_fd(&self) -> Option<FileDescriptor> {
         io::stdout().try_into_filedescriptor().ok()
     }
+
+    fn poll_readiness(&self) -> Result<Readiness> {
+        host_file_readiness(io::stdout().try_into_filedescriptor()?)
+    }
 }
 
 /// A wrapper type around Stderr that implements `VirtualFile` and
@@ -630,6 +724,10 @@ impl VirtualFile for Stderr {
     fn get_fd(&self) -> Option<FileDescriptor> {
         io::stderr().try_into_filedescriptor().ok()
     }
+
+    fn poll_readiness(&self) -> Result<Readiness> {
+        host_file_readiness(io::stderr().try_into_filedescriptor()?)
+    }
 }
 
 /// A wrapper type around Stdin that implements `VirtualFile` and
@@ -725,4 +823,8 @@ impl VirtualFile for Stdin {
     fn get_fd(&self) -> Option<FileDescriptor> {
         io::stdin().try_into_filedescriptor().ok()
     }
+
+    fn poll_readiness(&self) -> Result<Readiness> {
+        host_file_readiness(io::stdin().try_into_filedescriptor()?)
+    }
 }
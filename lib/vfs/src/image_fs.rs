@@ -0,0 +1,976 @@
+//! A read-only file system backed by a packaged image: a tar archive,
+//! possibly zstd-compressed, or a flat indexed image as written by
+//! [`pack`].
+//!
+//! Only the index of the image is built when it's opened, nothing is
+//! extracted: files are read lazily, straight from the image, which
+//! is memory-mapped when opened from a path. Many instances can share
+//! the same image, [`FileSystem`] is cheap to clone.
+//!
+//! A zstd-compressed image can't be read lazily though: it's decoded
+//! in memory when it's opened, then indexed like an uncompressed one.
+//! Other compressions, like gzip, are rejected with
+//! [`FsError::InvalidData`], [`compression`] tells such archives apart
+//! from invalid ones.
+//!
+//! # The indexed image format
+//!
+//! All integers are little-endian, offsets are relative to the start
+//! of the image.
+//!
+//! ```text
+//! magic    8 bytes   b"WVFSIMG\0"
+//! version  u32       1
+//! count    u32       number of entries
+//! entries  count times:
+//!     kind      u8    0 = directory, 1 = file, 2 = symlink
+//!     modified  u64   nanoseconds since the UNIX epoch
+//!     offset    u64   offset of the file contents or symlink target
+//!     len       u64   length of the file contents or symlink target
+//!     path_len  u16
+//!     path      path_len bytes, absolute and UTF-8 encoded
+//! data
+//! ```
+
+use crate::{
+    DirEntry, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result,
+    VirtualFile,
+};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Deref;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// The magic bytes starting an indexed image.
+pub const IMAGE_MAGIC: &[u8; 8] = b"WVFSIMG\0";
+const IMAGE_VERSION: u32 = 1;
+
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Returns the name of the compression of `data`, `"zstd"` or
+/// `"gzip"`, if it starts like a compressed archive. [`FileSystem`]
+/// only decodes the zstd ones.
+pub fn compression(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(ZSTD_MAGIC) {
+        Some("zstd")
+    } else if data.starts_with(GZIP_MAGIC) {
+        Some("gzip")
+    } else {
+        None
+    }
+}
+
+/// Decodes all the zstd frames of `data`.
+fn decode_zstd(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+
+    while !data.is_empty() {
+        let mut decoder =
+            ruzstd::StreamingDecoder::new(&mut data).map_err(|_| FsError::InvalidData)?;
+        decoder
+            .read_to_end(&mut decoded)
+            .map_err(|_| FsError::InvalidData)?;
+
+        let decoder = decoder.inner();
+        if let (Some(expected), Some(actual)) = (
+            decoder.get_checksum_from_data(),
+            decoder.get_calculated_checksum(),
+        ) {
+            if expected != actual {
+                return Err(FsError::InvalidData);
+            }
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// The maximum number of symlinks followed to resolve a path, like
+/// Linux.
+const MAX_SYMLINKS: u32 = 40;
+
+/// The read-only image file system.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    image: Arc<Image>,
+}
+
+impl FileSystem {
+    /// Opens the image or the tar archive at `path`, it's
+    /// memory-mapped, unless it's zstd-compressed.
+    ///
+    /// The file must not be modified while the file system is in use.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // Mapping an empty file fails on some platforms.
+        if file.metadata()?.len() == 0 {
+            return Self::from_bytes(Vec::new());
+        }
+
+        // SAFETY: The mapping is read-only, and the file is documented
+        // to not be modified while the file system is in use.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+        Self::from_data(Data::Mmap(mmap))
+    }
+
+    /// Loads an image or a tar archive, possibly zstd-compressed, from
+    /// memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_data(Data::Bytes(bytes))
+    }
+
+    fn from_data(data: Data) -> Result<Self> {
+        let nodes = if data.starts_with(IMAGE_MAGIC) {
+            index_image(&data)?
+        } else if data.starts_with(ZSTD_MAGIC) {
+            return Self::from_data(Data::Bytes(decode_zstd(&data)?));
+        } else if compression(&data).is_some() {
+            return Err(FsError::InvalidData);
+        } else {
+            index_tar(&data)?
+        };
+
+        Ok(Self {
+            image: Arc::new(Image { data, nodes }),
+        })
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let (path, node) = self.image.node(path, true)?;
+
+        match node {
+            Node::Directory { children, .. } => Ok(ReadDir::new(
+                children
+                    .iter()
+                    .map(|name| {
+                        let path = path.join(name);
+                        let metadata = self.image.nodes[&path].metadata().clone();
+
+                        DirEntry {
+                            path,
+                            metadata: Ok(metadata),
+                        }
+                    })
+                    .collect(),
+            )),
+            _ => Err(FsError::InvalidInput),
+        }
+    }
+
+    fn create_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn remove_dir(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        Ok(self.image.node(path, true)?.1.metadata().clone())
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        Ok(self.image.node(path, false)?.1.metadata().clone())
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        match self.image.node(path, false)?.1 {
+            Node::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(FsError::InvalidInput),
+        }
+    }
+
+    fn remove_file(&self, _path: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            image: self.image.clone(),
+        }))
+    }
+}
+
+/// The type that is responsible to open a file.
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    image: Arc<Image>,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(&mut self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        // WASI asks for `write` whenever the rights of the directory
+        // allow it, so opening with it is accepted, but writing fails.
+        if conf.append() || conf.truncate() || conf.create() || conf.create_new() {
+            return Err(FsError::PermissionDenied);
+        }
+
+        match self.image.node(path, true)?.1 {
+            Node::File {
+                offset,
+                len,
+                metadata,
+            } => Ok(Box::new(File {
+                image: self.image.clone(),
+                offset: *offset,
+                len: *len,
+                cursor: 0,
+                metadata: metadata.clone(),
+            })),
+            _ => Err(FsError::NotAFile),
+        }
+    }
+}
+
+/// A file of an image, its contents are read from the image directly.
+#[derive(Debug)]
+pub struct File {
+    image: Arc<Image>,
+    offset: usize,
+    len: usize,
+    cursor: usize,
+    metadata: Metadata,
+}
+
+impl File {
+    fn contents(&self) -> &[u8] {
+        &self.image.data[self.offset..self.offset + self.len]
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = &self.contents()[self.cursor.min(self.len)..];
+        let read = remaining.len().min(buf.len());
+        buf[..read].copy_from_slice(&remaining[..read]);
+        self.cursor += read;
+
+        Ok(read)
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, position: io::SeekFrom) -> io::Result<u64> {
+        let cursor = match position {
+            io::SeekFrom::Start(offset) => Some(offset as i64),
+            io::SeekFrom::End(offset) => (self.len as i64).checked_add(offset),
+            io::SeekFrom::Current(offset) => (self.cursor as i64).checked_add(offset),
+        };
+
+        match cursor {
+            Some(cursor) if cursor >= 0 => {
+                self.cursor = cursor as usize;
+
+                Ok(cursor as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seeking before the beginning of the file",
+            )),
+        }
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the file is read-only",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl VirtualFile for File {
+    fn last_accessed(&self) -> u64 {
+        self.metadata.accessed
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.metadata.modified
+    }
+
+    fn created_time(&self) -> u64 {
+        self.metadata.created
+    }
+
+    fn size(&self) -> u64 {
+        self.len as u64
+    }
+
+    fn set_len(&mut self, _new_size: u64) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+
+    fn bytes_available(&self) -> Result<usize> {
+        Ok(self.len.saturating_sub(self.cursor))
+    }
+}
+
+/// Packs the tree rooted at `root` in `fs` into an indexed image,
+/// written to `out`.
+pub fn pack(fs: &dyn crate::FileSystem, root: &Path, out: &mut dyn Write) -> Result<()> {
+    let mut entries = Vec::new();
+    collect(fs, root, PathBuf::from("/"), &mut entries)?;
+
+    let index_len = entries
+        .iter()
+        .map(|(path, ..)| 1 + 8 + 8 + 8 + 2 + path.len())
+        .sum::<usize>();
+    let mut offset = (IMAGE_MAGIC.len() + 4 + 4 + index_len) as u64;
+
+    out.write_all(IMAGE_MAGIC)?;
+    out.write_all(&IMAGE_VERSION.to_le_bytes())?;
+    out.write_all(&(entries.len() as u32).to_le_bytes())?;
+
+    for (path, kind, modified, contents) in &entries {
+        out.write_all(&[*kind])?;
+        out.write_all(&modified.to_le_bytes())?;
+        out.write_all(&offset.to_le_bytes())?;
+        out.write_all(&(contents.len() as u64).to_le_bytes())?;
+        out.write_all(&(path.len() as u16).to_le_bytes())?;
+        out.write_all(path.as_bytes())?;
+
+        offset += contents.len() as u64;
+    }
+
+    for (.., contents) in &entries {
+        out.write_all(contents)?;
+    }
+
+    Ok(())
+}
+
+/// An entry of an indexed image: its path, kind, modification time
+/// and contents.
+type PackedEntry = (String, u8, u64, Vec<u8>);
+
+fn collect(
+    fs: &dyn crate::FileSystem,
+    host_path: &Path,
+    image_path: PathBuf,
+    entries: &mut Vec<PackedEntry>,
+) -> Result<()> {
+    let metadata = fs.symlink_metadata(host_path)?;
+    let path = image_path
+        .to_str()
+        .ok_or(FsError::InvalidInput)?
+        .to_string();
+
+    if metadata.file_type().is_symlink() {
+        let target = fs.read_link(host_path)?;
+        let target = target.to_str().ok_or(FsError::InvalidInput)?;
+
+        entries.push((path, 2, metadata.modified, target.as_bytes().to_vec()));
+    } else if metadata.is_dir() {
+        entries.push((path, 0, metadata.modified, Vec::new()));
+
+        for entry in fs.read_dir(host_path)? {
+            let name = entry?.file_name();
+
+            collect(fs, &host_path.join(&name), image_path.join(&name), entries)?;
+        }
+    } else {
+        let mut contents = Vec::with_capacity(metadata.len as usize);
+        fs.new_open_options()
+            .read(true)
+            .open(host_path)?
+            .read_to_end(&mut contents)?;
+
+        entries.push((path, 1, metadata.modified, contents));
+    }
+
+    Ok(())
+}
+
+/// The bytes of an image.
+enum Data {
+    Mmap(memmap2::Mmap),
+    Bytes(Vec<u8>),
+}
+
+impl Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mmap(mmap) => mmap,
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Directory {
+        children: Vec<OsString>,
+        metadata: Metadata,
+    },
+    File {
+        offset: usize,
+        len: usize,
+        metadata: Metadata,
+    },
+    Symlink {
+        target: PathBuf,
+        metadata: Metadata,
+    },
+}
+
+impl Node {
+    fn directory(modified: u64) -> Self {
+        Self::Directory {
+            children: Vec::new(),
+            metadata: metadata(
+                FileType {
+                    dir: true,
+                    ..Default::default()
+                },
+                modified,
+                0,
+            ),
+        }
+    }
+
+    fn metadata(&self) -> &Metadata {
+        match self {
+            Self::Directory { metadata, .. } => metadata,
+            Self::File { metadata, .. } => metadata,
+            Self::Symlink { metadata, .. } => metadata,
+        }
+    }
+}
+
+fn metadata(ft: FileType, modified: u64, len: u64) -> Metadata {
+    Metadata {
+        ft,
+        accessed: modified,
+        created: modified,
+        modified,
+        len,
+    }
+}
+
+struct Image {
+    data: Data,
+    nodes: HashMap<PathBuf, Node>,
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Image")
+            .field("size", &self.data.len())
+            .field("entries", &self.nodes.len())
+            .finish()
+    }
+}
+
+impl Image {
+    /// Returns the node designated by `path`, along with its path
+    /// once the symlinks are resolved. The last component is followed
+    /// only if `follow_last` is true.
+    fn node(&self, path: &Path, follow_last: bool) -> Result<(PathBuf, &Node)> {
+        let path = self.resolve(path, follow_last, 0)?;
+        let node = self.nodes.get(&path).ok_or(FsError::EntityNotFound)?;
+
+        Ok((path, node))
+    }
+
+    fn resolve(&self, path: &Path, follow_last: bool, symlinks: u32) -> Result<PathBuf> {
+        let mut components = path.components().peekable();
+
+        match components.next() {
+            Some(Component::RootDir) => {}
+            _ => return Err(FsError::InvalidInput),
+        }
+
+        let mut resolved = PathBuf::from("/");
+
+        while let Some(component) = components.next() {
+            let is_last = components.peek().is_none();
+
+            match component {
+                Component::CurDir => (),
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(name) => {
+                    match self.nodes.get(&resolved) {
+                        Some(Node::Directory { .. }) => (),
+                        Some(_) => return Err(FsError::BaseNotDirectory),
+                        None => return Err(FsError::EntityNotFound),
+                    }
+
+                    resolved.push(name);
+
+                    if let Some(Node::Symlink { target, .. }) = self.nodes.get(&resolved) {
+                        if follow_last || !is_last {
+                            if symlinks >= MAX_SYMLINKS {
+                                return Err(FsError::TooManySymlinks);
+                            }
+
+                            // A relative target is relative to the
+                            // directory containing the symlink, an
+                            // absolute target replaces the path.
+                            resolved.pop();
+                            let target = resolved.join(target);
+                            resolved = self.resolve(&target, true, symlinks + 1)?;
+                        }
+                    }
+                }
+                _ => return Err(FsError::InvalidInput),
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+/// Inserts `node` at `path`, creating the missing parent directories.
+fn insert(nodes: &mut HashMap<PathBuf, Node>, path: PathBuf, node: Node) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) => parent.to_path_buf(),
+        // That's the root, which always exists.
+        None => return Ok(()),
+    };
+
+    if !nodes.contains_key(&parent) {
+        insert(nodes, parent.clone(), Node::directory(0))?;
+    }
+
+    match nodes.get_mut(&parent) {
+        Some(Node::Directory { children, .. }) => {
+            let name = path.file_name().ok_or(FsError::InvalidData)?;
+
+            if !children.iter().any(|child| child == name) {
+                children.push(name.to_os_string());
+            }
+        }
+        _ => return Err(FsError::InvalidData),
+    }
+
+    // An entry repeated in an archive replaces the previous one, but
+    // a directory keeps its children.
+    match (nodes.get_mut(&path), node) {
+        (
+            Some(Node::Directory { metadata, .. }),
+            Node::Directory {
+                metadata: new_metadata,
+                ..
+            },
+        ) => *metadata = new_metadata,
+        (_, node) => {
+            nodes.insert(path, node);
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns a path of an archive into an absolute path. Paths escaping
+/// the root are rejected.
+fn archive_path(path: &Path) -> Result<PathBuf> {
+    let mut absolute = PathBuf::from("/");
+
+    for component in path.components() {
+        match component {
+            Component::RootDir | Component::CurDir => (),
+            Component::Normal(name) => absolute.push(name),
+            _ => return Err(FsError::InvalidData),
+        }
+    }
+
+    Ok(absolute)
+}
+
+fn index_tar(data: &[u8]) -> Result<HashMap<PathBuf, Node>> {
+    let mut nodes = HashMap::new();
+    nodes.insert(PathBuf::from("/"), Node::directory(0));
+    let mut hard_links = Vec::new();
+
+    let mut archive = tar::Archive::new(data);
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let path = archive_path(&entry.path()?)?;
+        let modified = header.mtime().unwrap_or(0).saturating_mul(1_000_000_000);
+        let entry_type = header.entry_type();
+
+        let node = if entry_type.is_dir() {
+            Node::directory(modified)
+        } else if entry_type.is_file() || entry_type.is_contiguous() {
+            Node::File {
+                offset: entry.raw_file_position() as usize,
+                len: entry.size() as usize,
+                metadata: metadata(
+                    FileType {
+                        file: true,
+                        ..Default::default()
+                    },
+                    modified,
+                    entry.size(),
+                ),
+            }
+        } else if entry_type.is_symlink() {
+            let target = entry.link_name()?.ok_or(FsError::InvalidData)?;
+
+            Node::Symlink {
+                metadata: metadata(
+                    FileType {
+                        symlink: true,
+                        ..Default::default()
+                    },
+                    modified,
+                    target.as_os_str().len() as u64,
+                ),
+                target: target.into_owned(),
+            }
+        } else if entry_type.is_hard_link() {
+            let target = entry.link_name()?.ok_or(FsError::InvalidData)?;
+            hard_links.push((path, archive_path(&target)?));
+
+            continue;
+        } else if entry_type.is_gnu_sparse() {
+            // The contents of sparse files aren't contiguous.
+            return Err(FsError::InvalidData);
+        } else {
+            // Devices and FIFOs can't be represented.
+            continue;
+        };
+
+        if path.parent().is_some() {
+            insert(&mut nodes, path, node)?;
+        }
+    }
+
+    // Hard links share the contents of their target.
+    for (path, target) in hard_links {
+        let node = nodes.get(&target).ok_or(FsError::InvalidData)?.clone();
+        insert(&mut nodes, path, node)?;
+    }
+
+    Ok(nodes)
+}
+
+fn index_image(data: &[u8]) -> Result<HashMap<PathBuf, Node>> {
+    let mut reader = ImageReader {
+        data,
+        position: IMAGE_MAGIC.len(),
+    };
+
+    if reader.u32()? != IMAGE_VERSION {
+        return Err(FsError::InvalidData);
+    }
+
+    let count = reader.u32()?;
+    let mut nodes = HashMap::new();
+    nodes.insert(PathBuf::from("/"), Node::directory(0));
+
+    for _ in 0..count {
+        let kind = reader.bytes(1)?[0];
+        let modified = reader.u64()?;
+        let offset = reader.u64()? as usize;
+        let len = reader.u64()? as usize;
+        let path_len = u16::from_le_bytes(reader.bytes(2)?.try_into().unwrap()) as usize;
+        let path =
+            std::str::from_utf8(reader.bytes(path_len)?).map_err(|_| FsError::InvalidData)?;
+        let path = archive_path(Path::new(path))?;
+
+        let contents = offset
+            .checked_add(len)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(FsError::InvalidData)?;
+
+        let node = match kind {
+            0 => Node::directory(modified),
+            1 => Node::File {
+                offset,
+                len,
+                metadata: metadata(
+                    FileType {
+                        file: true,
+                        ..Default::default()
+                    },
+                    modified,
+                    len as u64,
+                ),
+            },
+            2 => Node::Symlink {
+                target: PathBuf::from(
+                    std::str::from_utf8(contents).map_err(|_| FsError::InvalidData)?,
+                ),
+                metadata: metadata(
+                    FileType {
+                        symlink: true,
+                        ..Default::default()
+                    },
+                    modified,
+                    len as u64,
+                ),
+            },
+            _ => return Err(FsError::InvalidData),
+        };
+
+        if path.parent().is_some() {
+            insert(&mut nodes, path, node)?;
+        } else if let Node::Directory { .. } = node {
+            nodes.insert(path, node);
+        }
+    }
+
+    Ok(nodes)
+}
+
+struct ImageReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ImageReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + len)
+            .ok_or(FsError::InvalidData)?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test_image_fs {
+    use super::{compression, pack, FileSystem, IMAGE_MAGIC};
+    use crate::{FileSystem as FS, FsError};
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+
+    macro_rules! path {
+        ($path:expr) => {
+            std::path::Path::new($path)
+        };
+    }
+
+    fn archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mtime(42);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "data/hello.txt", &b"hello"[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "data/link", "hello.txt")
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "copy.txt", "data/hello.txt")
+            .unwrap();
+
+        builder.into_inner().unwrap()
+    }
+
+    fn read(fs: &FileSystem, path: &std::path::Path) -> Vec<u8> {
+        let mut contents = Vec::new();
+        fs.new_open_options()
+            .read(true)
+            .open(path)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+
+        contents
+    }
+
+    #[test]
+    fn test_tar() {
+        let fs = FileSystem::from_bytes(archive()).unwrap();
+
+        assert!(fs.metadata(path!("/data")).unwrap().is_dir());
+        assert_eq!(read(&fs, path!("/data/hello.txt")), b"hello");
+        assert_eq!(read(&fs, path!("/data/link")), b"hello");
+        assert_eq!(read(&fs, path!("/copy.txt")), b"hello");
+        assert_eq!(
+            fs.metadata(path!("/data/hello.txt")).unwrap().modified(),
+            42_000_000_000,
+        );
+        assert_eq!(
+            fs.read_link(path!("/data/link")),
+            Ok(PathBuf::from("hello.txt"))
+        );
+        assert!(fs
+            .symlink_metadata(path!("/data/link"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        let mut names = fs
+            .read_dir(path!("/"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![PathBuf::from("/copy.txt"), PathBuf::from("/data")]
+        );
+
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .open(path!("/data/hello.txt"))
+            .unwrap();
+        assert_eq!(file.seek(SeekFrom::End(-2)).unwrap(), 3);
+        let mut rest = String::new();
+        file.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "lo");
+
+        assert_eq!(
+            fs.metadata(path!("/missing")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+    }
+
+    #[test]
+    fn test_read_only() {
+        let fs = FileSystem::from_bytes(archive()).unwrap();
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .truncate(true)
+                .open(path!("/data/hello.txt"))
+                .map(|_| ()),
+            Err(FsError::PermissionDenied),
+        );
+        let mut file = fs
+            .new_open_options()
+            .read(true)
+            .write(true)
+            .open(path!("/data/hello.txt"))
+            .unwrap();
+        assert_eq!(
+            file.write(b"bye").unwrap_err().kind(),
+            std::io::ErrorKind::PermissionDenied,
+        );
+        assert_eq!(fs.create_dir(path!("/tmp")), Err(FsError::PermissionDenied));
+        assert_eq!(
+            fs.remove_file(path!("/copy.txt")),
+            Err(FsError::PermissionDenied)
+        );
+    }
+
+    /// Wraps `data` in a zstd frame made of a single uncompressed block.
+    fn zstd_frame(data: &[u8]) -> Vec<u8> {
+        // Single segment, with a 4-byte content size.
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0xa0];
+        frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        // The last block, uncompressed.
+        let block_header = (data.len() as u32) << 3 | 1;
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(data);
+
+        frame
+    }
+
+    #[test]
+    fn test_zstd() {
+        let archive = archive();
+        let (head, tail) = archive.split_at(1000);
+        let mut compressed = zstd_frame(head);
+        // The frames are concatenated.
+        compressed.extend(zstd_frame(tail));
+
+        let fs = FileSystem::from_bytes(compressed.clone()).unwrap();
+        assert_eq!(read(&fs, path!("/data/hello.txt")), b"hello");
+        assert_eq!(read(&fs, path!("/data/link")), b"hello");
+        assert_eq!(read(&fs, path!("/copy.txt")), b"hello");
+
+        // A compressed indexed image.
+        let mut image = Vec::new();
+        pack(&fs, path!("/"), &mut image).unwrap();
+        let fs = FileSystem::from_bytes(zstd_frame(&image)).unwrap();
+        assert_eq!(read(&fs, path!("/data/hello.txt")), b"hello");
+
+        assert_eq!(
+            FileSystem::from_bytes(compressed[..compressed.len() - 1].to_vec()).map(|_| ()),
+            Err(FsError::InvalidData),
+            "truncated archives are rejected",
+        );
+        assert_eq!(
+            FileSystem::from_bytes(vec![0x1f, 0x8b, 0, 0]).map(|_| ()),
+            Err(FsError::InvalidData),
+            "gzip-compressed archives are rejected",
+        );
+    }
+
+    #[test]
+    fn test_compression() {
+        assert_eq!(compression(&[0x28, 0xb5, 0x2f, 0xfd, 0, 0]), Some("zstd"));
+        assert_eq!(compression(&[0x1f, 0x8b, 0, 0]), Some("gzip"));
+        assert_eq!(compression(IMAGE_MAGIC), None);
+        assert_eq!(compression(&archive()), None);
+        assert_eq!(compression(&[]), None);
+    }
+
+    #[test]
+    fn test_pack() {
+        let tar = FileSystem::from_bytes(archive()).unwrap();
+        let mut image = Vec::new();
+        pack(&tar, path!("/"), &mut image).unwrap();
+
+        let path = std::env::temp_dir().join(format!("wasmer-vfs-image-{}", std::process::id()));
+        std::fs::write(&path, &image).unwrap();
+        let fs = FileSystem::open(&path).unwrap();
+
+        assert_eq!(read(&fs, path!("/data/hello.txt")), b"hello");
+        assert_eq!(read(&fs, path!("/data/link")), b"hello");
+        assert_eq!(read(&fs, path!("/copy.txt")), b"hello");
+        assert_eq!(
+            fs.read_link(path!("/data/link")),
+            Ok(PathBuf::from("hello.txt"))
+        );
+
+        drop(fs);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
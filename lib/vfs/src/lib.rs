@@ -11,10 +11,23 @@ compile_error!("At least the `host-fs` or the `mem-fs` feature must be enabled.
 #[cfg(all(feature = "mem-fs", feature = "enable-serde"))]
 compile_error!("`mem-fs` does not support `enable-serde` for the moment.");
 
+#[cfg(all(feature = "image-fs", feature = "enable-serde"))]
+compile_error!("`image-fs` does not support `enable-serde` for the moment.");
+
+#[cfg(all(feature = "quota-fs", feature = "enable-serde"))]
+compile_error!("`quota-fs` does not support `enable-serde` for the moment.");
+
 #[cfg(feature = "host-fs")]
 pub mod host_fs;
+#[cfg(feature = "image-fs")]
+pub mod image_fs;
 #[cfg(feature = "mem-fs")]
 pub mod mem_fs;
+pub mod mount_fs;
+#[cfg(feature = "mem-fs")]
+pub mod overlay_fs;
+#[cfg(feature = "quota-fs")]
+pub mod quota_fs;
 
 pub type Result<T> = std::result::Result<T, FsError>;
 
@@ -28,12 +41,25 @@ pub trait FileSystem: fmt::Debug + Send + Sync + 'static + Upcastable {
     fn remove_dir(&self, path: &Path) -> Result<()>;
     fn rename(&self, from: &Path, to: &Path) -> Result<()>;
     fn metadata(&self, path: &Path) -> Result<Metadata>;
-    /// This method gets metadata without following symlinks in the path.
-    /// Currently identical to `metadata` because symlinks aren't implemented
-    /// yet.
+    /// This method gets metadata without following `path` if it's a
+    /// symlink. The default implementation is identical to `metadata`,
+    /// for file systems without symlinks.
     fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
         self.metadata(path)
     }
+    /// Creates a symlink at `link` whose value is `original`. The
+    /// default implementation fails with `FsError::PermissionDenied`,
+    /// for file systems without symlinks.
+    fn symlink(&self, _original: &Path, _link: &Path) -> Result<()> {
+        Err(FsError::PermissionDenied)
+    }
+    /// Reads the value of the symlink at `path`. The default
+    /// implementation fails with `FsError::InvalidInput`, as if `path`
+    /// wasn't a symlink.
+    fn read_link(&self, _path: &Path) -> Result<PathBuf> {
+        Err(FsError::InvalidInput)
+    }
+    /// Removes a file, or a symlink without following it.
     fn remove_file(&self, path: &Path) -> Result<()>;
 
     fn new_open_options(&self) -> OpenOptions;
@@ -166,6 +192,13 @@ pub trait VirtualFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcas
     /// the extra bytes will be allocated and zeroed
     fn set_len(&mut self, new_size: u64) -> Result<()>;
 
+    /// Change the last accessed and the last modified times of the file,
+    /// in nanoseconds as UNIX timestamps, `None` leaves a time unchanged.
+    /// Default implementation returns `Ok(())`, the times are only kept by the caller
+    fn set_times(&mut self, _accessed: Option<u64>, _modified: Option<u64>) -> Result<()> {
+        Ok(())
+    }
+
     /// Request deletion of the file
     fn unlink(&mut self) -> Result<()>;
 
@@ -184,6 +217,36 @@ pub trait VirtualFile: fmt::Debug + Send + Write + Read + Seek + 'static + Upcas
     fn get_fd(&self) -> Option<FileDescriptor> {
         None
     }
+
+    /// Returns whether the file can be read from or written to
+    /// without blocking. This function must not block.
+    ///
+    /// The default implementation is always ready, like a regular
+    /// file, with `bytes_available` bytes.
+    fn poll_readiness(&self) -> Result<Readiness> {
+        let bytes_available = self.bytes_available()?;
+
+        Ok(Readiness {
+            read: Some(bytes_available),
+            write: Some(bytes_available),
+            hangup: false,
+        })
+    }
+}
+
+/// The readiness of a file, as returned by
+/// [`VirtualFile::poll_readiness`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Readiness {
+    /// `Some(n)` if the file can be read from without blocking, with
+    /// `n` the number of bytes available to read.
+    pub read: Option<usize>,
+    /// `Some(n)` if the file can be written to without blocking, with
+    /// `n` the number of bytes available in the file, or `0` if it's
+    /// unknown.
+    pub write: Option<usize>,
+    /// The other end of the file has been closed.
+    pub hangup: bool,
 }
 
 // Implementation of `Upcastable` taken from https://users.rust-lang.org/t/why-does-downcasting-not-work-for-subtraits/33286/7 .
@@ -296,6 +359,17 @@ pub enum FsError {
     /// Directory not Empty
     #[error("directory not empty")]
     DirectoryNotEmpty,
+    /// Too many symlinks were followed while resolving a path, there is
+    /// probably a loop
+    #[error("too many levels of symbolic links")]
+    TooManySymlinks,
+    /// A quota of bytes or inodes has been exceeded
+    #[error("quota exceeded")]
+    QuotaExceeded,
+    /// The operation spans two different file systems, e.g. a rename
+    /// across mount points
+    #[error("cross-device link")]
+    CrossDevice,
     /// Some other unhandled error. If you see this, it's probably a bug.
     #[error("unknown error found")]
     UnknownError,
@@ -303,6 +377,15 @@ pub enum FsError {
 
 impl From<io::Error> for FsError {
     fn from(io_error: io::Error) -> Self {
+        // A `FsError` may have been carried through an `io::Error`,
+        // e.g. by `Write::write`.
+        if let Some(fs_error) = io_error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<FsError>())
+        {
+            return *fs_error;
+        }
+
         match io_error.kind() {
             io::ErrorKind::AddrInUse => FsError::AddressInUse,
             io::ErrorKind::AddrNotAvailable => FsError::AddressNotAvailable,
@@ -371,7 +454,7 @@ impl DirEntry {
 }
 
 #[allow(clippy::len_without_is_empty)] // Clippy thinks it's an iterator.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 // TODO: review this, proper solution would probably use a trait object internally
 pub struct Metadata {
     pub ft: FileType,
@@ -411,7 +494,7 @@ impl Metadata {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 // TODO: review this, proper solution would probably use a trait object internally
 pub struct FileType {
     pub dir: bool,
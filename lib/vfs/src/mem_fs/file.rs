@@ -111,6 +111,7 @@ impl VirtualFile for FileHandle {
                 file.buffer
                     .resize(new_size.try_into().map_err(|_| FsError::UnknownError)?, 0);
                 metadata.len = new_size;
+                metadata.modified = time();
             }
             _ => return Err(FsError::NotAFile),
         }
@@ -118,6 +119,30 @@ impl VirtualFile for FileHandle {
         Ok(())
     }
 
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) -> Result<()> {
+        let mut fs = self
+            .filesystem
+            .inner
+            .try_write()
+            .map_err(|_| FsError::Lock)?;
+
+        let metadata = fs
+            .storage
+            .get_mut(self.inode)
+            .ok_or(FsError::NotAFile)?
+            .metadata_mut();
+
+        if let Some(accessed) = accessed {
+            metadata.accessed = accessed;
+        }
+
+        if let Some(modified) = modified {
+            metadata.modified = modified;
+        }
+
+        Ok(())
+    }
+
     fn unlink(&mut self) -> Result<()> {
         let (inode_of_parent, position, inode_of_file) = {
             // Read lock.
@@ -300,6 +325,50 @@ mod test_virtual_file {
         assert_eq!(file.size(), 7, "file has a new length");
     }
 
+    #[test]
+    fn test_set_times() {
+        let fs = FileSystem::default();
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo.txt"))
+            .expect("failed to create a new file");
+        let created_time = file.created_time();
+
+        assert!(
+            matches!(file.set_times(Some(1), None), Ok(())),
+            "setting the last accessed time",
+        );
+        assert_eq!(file.last_accessed(), 1, "last accessed time is set");
+        assert!(file.last_modified() > 1, "last modified time is unchanged");
+
+        assert!(
+            matches!(file.set_times(None, Some(2)), Ok(())),
+            "setting the last modified time",
+        );
+        assert_eq!(file.last_accessed(), 1, "last accessed time is unchanged");
+        assert_eq!(file.last_modified(), 2, "last modified time is set");
+
+        assert!(matches!(file.set_len(7), Ok(())), "setting a new length");
+        assert!(
+            file.last_modified() >= created_time,
+            "setting a new length updates the last modified time",
+        );
+        assert_eq!(
+            fs.metadata(path!("/foo.txt"))
+                .map(|metadata| metadata.accessed),
+            Ok(1),
+            "the times are kept by the file system",
+        );
+        assert_eq!(
+            file.created_time(),
+            created_time,
+            "created time is unchanged"
+        );
+    }
+
     #[test]
     fn test_unlink() {
         let fs = FileSystem::default();
@@ -865,6 +934,14 @@ impl File {
     pub(super) fn len(&self) -> usize {
         self.buffer.len()
     }
+
+    pub(super) fn from_bytes(buffer: Vec<u8>) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
 }
 
 impl Read for File {
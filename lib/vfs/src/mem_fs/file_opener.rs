@@ -43,6 +43,9 @@ impl crate::FileOpener for FileOpener {
                 .try_read()
                 .map_err(|_| FsError::Lock)?;
 
+            // Follow the symlinks, the file may not exist yet.
+            let (path, _) = fs.resolve(path, true, 0)?;
+
             // Check the path has a parent.
             let parent_of_path = path.parent().ok_or(FsError::BaseNotDirectory)?;
 
@@ -236,6 +236,98 @@ impl crate::FileSystem for FileSystem {
             .clone())
     }
 
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        // Read lock.
+        let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
+
+        Ok(fs
+            .storage
+            .get(fs.inode_of_symlink(path)?)
+            .ok_or(FsError::UnknownError)?
+            .metadata()
+            .clone())
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        let (inode_of_parent, name_of_link) = {
+            // Read lock.
+            let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
+
+            // Canonicalize the path without checking the path exists,
+            // because it's about to be created.
+            let path = fs.canonicalize_without_inode(link)?;
+
+            // Check the path has a parent.
+            let parent_of_path = path.parent().ok_or(FsError::BaseNotDirectory)?;
+
+            // Check the symlink name.
+            let name_of_link = path
+                .file_name()
+                .ok_or(FsError::InvalidInput)?
+                .to_os_string();
+
+            // Find the parent inode.
+            let inode_of_parent = fs.inode_of_parent(parent_of_path)?;
+
+            // Check nothing exists with the same name.
+            if fs
+                .from_parent_get_position_and_inode(inode_of_parent, &name_of_link)?
+                .is_some()
+            {
+                return Err(FsError::AlreadyExists);
+            }
+
+            (inode_of_parent, name_of_link)
+        };
+
+        {
+            // Write lock.
+            let mut fs = self.inner.try_write().map_err(|_| FsError::Lock)?;
+
+            // Creating the symlink in the storage.
+            let inode_of_link = fs.storage.vacant_entry().key();
+            let real_inode_of_link = fs.storage.insert(Node::Symlink {
+                inode: inode_of_link,
+                name: name_of_link,
+                target: original.to_path_buf(),
+                metadata: {
+                    let time = time();
+
+                    Metadata {
+                        ft: FileType {
+                            symlink: true,
+                            ..Default::default()
+                        },
+                        accessed: time,
+                        created: time,
+                        modified: time,
+                        len: original.as_os_str().len() as u64,
+                    }
+                },
+            });
+
+            assert_eq!(
+                inode_of_link, real_inode_of_link,
+                "new symlink inode should have been correctly calculated",
+            );
+
+            // Adding the new symlink to its parent.
+            fs.add_child_to_node(inode_of_parent, inode_of_link)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        // Read lock.
+        let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
+
+        match fs.storage.get(fs.inode_of_symlink(path)?) {
+            Some(Node::Symlink { target, .. }) => Ok(target.clone()),
+            _ => Err(FsError::InvalidInput),
+        }
+    }
+
     fn remove_file(&self, path: &Path) -> Result<()> {
         let (inode_of_parent, position, inode_of_file) = {
             // Read lock.
@@ -302,35 +394,90 @@ pub(super) struct FileSystemInner {
 }
 
 impl FileSystemInner {
-    /// Get the inode associated to a path if it exists.
+    /// Get the inode associated to a path if it exists, following the
+    /// symlinks.
     pub(super) fn inode_of(&self, path: &Path) -> Result<Inode> {
+        self.resolve(path, true, 0)?.1.ok_or(FsError::NotAFile)
+    }
+
+    /// Like `Self::inode_of` but if the path is a symlink, it isn't
+    /// followed: the inode of the symlink itself is returned.
+    pub(super) fn inode_of_symlink(&self, path: &Path) -> Result<Inode> {
+        self.resolve(path, false, 0)?.1.ok_or(FsError::NotAFile)
+    }
+
+    /// Resolve the symlinks of a path, i.e. returns the path of the
+    /// node it designates, along with its inode if it exists. Only
+    /// the last component of the path may not exist.
+    ///
+    /// The last component is followed only if `follow_last` is
+    /// true. `symlinks` is the number of symlinks followed so far, to
+    /// detect loops.
+    pub(super) fn resolve(
+        &self,
+        path: &Path,
+        follow_last: bool,
+        symlinks: u32,
+    ) -> Result<(PathBuf, Option<Inode>)> {
         // SAFETY: The root node always exists, so it's safe to unwrap here.
         let mut node = self.storage.get(ROOT_INODE).unwrap();
-        let mut components = path.components();
+        let mut resolved_path = PathBuf::from("/");
+        let mut components = path.components().peekable();
 
         match components.next() {
             Some(Component::RootDir) => {}
             _ => return Err(FsError::BaseNotDirectory),
         }
 
-        for component in components {
-            node = match node {
+        while let Some(component) = components.next() {
+            let is_last = components.peek().is_none();
+            let child = match node {
                 Node::Directory { children, .. } => children
                     .iter()
                     .filter_map(|inode| self.storage.get(*inode))
-                    .find_map(|node| {
-                        if node.name() == component.as_os_str() {
-                            Some(node)
-                        } else {
-                            None
-                        }
-                    })
-                    .ok_or(FsError::NotAFile)?,
+                    .find(|node| node.name() == component.as_os_str()),
                 _ => return Err(FsError::BaseNotDirectory),
             };
+
+            node = match child {
+                Some(Node::Symlink { target, .. }) if follow_last || !is_last => {
+                    if symlinks >= MAX_SYMLINKS {
+                        return Err(FsError::TooManySymlinks);
+                    }
+
+                    // A relative target is relative to the directory
+                    // containing the symlink, an absolute target
+                    // replaces the path.
+                    let target = self.canonicalize_without_inode(&resolved_path.join(target))?;
+
+                    match self.resolve(&target, true, symlinks + 1)? {
+                        (target, Some(inode)) => {
+                            resolved_path = target;
+
+                            self.storage.get(inode).ok_or(FsError::UnknownError)?
+                        }
+                        (target, None) if is_last => return Ok((target, None)),
+                        (_, None) => return Err(FsError::NotAFile),
+                    }
+                }
+
+                Some(child) => {
+                    resolved_path.push(component);
+
+                    child
+                }
+
+                None if is_last => {
+                    resolved_path.push(component);
+
+                    return Ok((resolved_path, None));
+                }
+
+                None => return Err(FsError::NotAFile),
+            };
         }
 
-        Ok(node.inode())
+        Ok((resolved_path, Some(node.inode())))
     }
 
     /// Get the inode associated to a “parent path”. The returned
@@ -381,7 +528,8 @@ impl FileSystemInner {
     }
 
     /// From the inode of a parent node (so, a directory), returns the
-    /// child index of `name_of_file` along with its inode.
+    /// child index of `name_of_file` along with its inode. A symlink
+    /// is considered as a file.
     pub(super) fn from_parent_get_position_and_inode_of_file(
         &self,
         inode_of_parent: Inode,
@@ -393,7 +541,9 @@ impl FileSystemInner {
                 .enumerate()
                 .filter_map(|(nth, inode)| self.storage.get(*inode).map(|node| (nth, node)))
                 .find_map(|(nth, node)| match node {
-                    Node::File { inode, name, .. } if name.as_os_str() == name_of_file => {
+                    Node::File { inode, name, .. } | Node::Symlink { inode, name, .. }
+                        if name.as_os_str() == name_of_file =>
+                    {
                         Some(Some((nth, *inode)))
                     }
 
@@ -408,7 +558,7 @@ impl FileSystemInner {
 
     /// From the inode of a parent node (so, a directory), returns the
     /// child index of `name_of` along with its inode, whatever the
-    /// type of inode is (directory or file).
+    /// type of inode is (directory, file or symlink).
     fn from_parent_get_position_and_inode(
         &self,
         inode_of_parent: Inode,
@@ -420,7 +570,9 @@ impl FileSystemInner {
                 .enumerate()
                 .filter_map(|(nth, inode)| self.storage.get(*inode).map(|node| (nth, node)))
                 .find_map(|(nth, node)| match node {
-                    Node::File { inode, name, .. } | Node::Directory { inode, name, .. }
+                    Node::File { inode, name, .. }
+                    | Node::Directory { inode, name, .. }
+                    | Node::Symlink { inode, name, .. }
                         if name.as_os_str() == name_of =>
                     {
                         Some(Some((nth, *inode)))
@@ -574,6 +726,7 @@ impl fmt::Debug for FileSystemInner {
                     ty = match node {
                         Node::File { .. } => "file",
                         Node::Directory { .. } => "dir",
+                        Node::Symlink { .. } => "link",
                     },
                     name = node.name().to_string_lossy(),
                     indentation_symbol = " ",
@@ -1061,6 +1214,8 @@ mod test_filesystem {
             }) if accessed == created && created == modified && modified > 0
         ));
 
+        let root_metadata = root_metadata.unwrap();
+
         assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
 
         let foo_metadata = fs.metadata(path!("/foo"));
@@ -1108,8 +1263,8 @@ mod test_filesystem {
                     modified,
                     len: 0
                 }) if
-                    accessed == foo_metadata.accessed &&
-                    created == foo_metadata.created &&
+                    accessed == root_metadata.accessed &&
+                    created == root_metadata.created &&
                     modified > foo_metadata.modified
             ),
             "the modified time of the parent is updated when file is renamed",
@@ -1361,6 +1516,106 @@ mod test_filesystem {
             "canonicalizing a crazily stupid path name",
         );
     }
+
+    #[test]
+    fn test_symlink() {
+        use std::io::{Read, Write};
+
+        let fs = FileSystem::default();
+
+        assert_eq!(fs.create_dir(path!("/foo")), Ok(()));
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/foo/hello.txt"))
+            .unwrap()
+            .write_all(b"hello")
+            .unwrap();
+
+        assert_eq!(
+            fs.symlink(path!("foo/hello.txt"), path!("/relative")),
+            Ok(()),
+            "creating a relative symlink",
+        );
+        assert_eq!(
+            fs.symlink(path!("/foo"), path!("/absolute")),
+            Ok(()),
+            "creating an absolute symlink",
+        );
+        assert_eq!(
+            fs.symlink(path!("/foo"), path!("/absolute")),
+            Err(FsError::AlreadyExists),
+            "creating a symlink that already exists",
+        );
+
+        assert_eq!(
+            fs.read_link(path!("/relative")),
+            Ok(path!(buf "foo/hello.txt")),
+            "reading a symlink",
+        );
+        assert_eq!(
+            fs.read_link(path!("/foo")),
+            Err(FsError::InvalidInput),
+            "reading a directory as a symlink",
+        );
+
+        assert!(
+            matches!(fs.metadata(path!("/relative")), Ok(metadata) if metadata.is_file()),
+            "the metadata of a symlink are the ones of its target",
+        );
+        assert!(
+            matches!(
+                fs.symlink_metadata(path!("/relative")),
+                Ok(metadata) if metadata.file_type().is_symlink() && metadata.len() == 13
+            ),
+            "the symlink metadata of a symlink are its own",
+        );
+
+        let mut content = String::new();
+        fs.new_open_options()
+            .read(true)
+            .open(path!("/absolute/hello.txt"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "hello", "reading a file through a symlink");
+
+        assert!(
+            fs.new_open_options()
+                .write(true)
+                .create(true)
+                .open(path!("/absolute/new.txt"))
+                .is_ok(),
+            "creating a file through a symlink",
+        );
+        assert!(
+            fs.metadata(path!("/foo/new.txt")).is_ok(),
+            "the file has been created in the target",
+        );
+
+        assert_eq!(fs.symlink(path!("loop2"), path!("/loop1")), Ok(()));
+        assert_eq!(fs.symlink(path!("loop1"), path!("/loop2")), Ok(()));
+        assert_eq!(
+            fs.metadata(path!("/loop1")).map(|_| ()),
+            Err(FsError::TooManySymlinks),
+            "detecting symlink loops",
+        );
+
+        assert_eq!(
+            fs.remove_file(path!("/absolute")),
+            Ok(()),
+            "removing a symlink",
+        );
+        assert_eq!(
+            fs.symlink_metadata(path!("/absolute")).map(|_| ()),
+            Err(FsError::NotAFile),
+            "the symlink no longer exists",
+        );
+        assert!(
+            fs.metadata(path!("/foo")).is_ok(),
+            "the target of a removed symlink still exists",
+        );
+    }
 }
 
 #[allow(dead_code)] // The `No` variant.
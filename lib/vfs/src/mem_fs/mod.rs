@@ -1,19 +1,26 @@
 mod file;
 mod file_opener;
 mod filesystem;
+mod snapshot;
 mod stdio;
 
 use file::{File, FileHandle};
 pub use file_opener::FileOpener;
 pub use filesystem::FileSystem;
+pub use snapshot::SNAPSHOT_MAGIC;
 pub use stdio::{Stderr, Stdin, Stdout};
 
 use crate::Metadata;
 use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
 
 type Inode = usize;
 const ROOT_INODE: Inode = 0;
 
+/// The maximum number of symlinks followed to resolve a path, like
+/// Linux.
+const MAX_SYMLINKS: u32 = 40;
+
 #[derive(Debug)]
 enum Node {
     File {
@@ -28,6 +35,12 @@ enum Node {
         children: Vec<Inode>,
         metadata: Metadata,
     },
+    Symlink {
+        inode: Inode,
+        name: OsString,
+        target: PathBuf,
+        metadata: Metadata,
+    },
 }
 
 impl Node {
@@ -35,6 +48,7 @@ impl Node {
         *match self {
             Self::File { inode, .. } => inode,
             Self::Directory { inode, .. } => inode,
+            Self::Symlink { inode, .. } => inode,
         }
     }
 
@@ -42,6 +56,7 @@ impl Node {
         match self {
             Self::File { name, .. } => name.as_os_str(),
             Self::Directory { name, .. } => name.as_os_str(),
+            Self::Symlink { name, .. } => name.as_os_str(),
         }
     }
 
@@ -49,6 +64,7 @@ impl Node {
         match self {
             Self::File { metadata, .. } => metadata,
             Self::Directory { metadata, .. } => metadata,
+            Self::Symlink { metadata, .. } => metadata,
         }
     }
 
@@ -56,6 +72,7 @@ impl Node {
         match self {
             Self::File { metadata, .. } => metadata,
             Self::Directory { metadata, .. } => metadata,
+            Self::Symlink { metadata, .. } => metadata,
         }
     }
 
@@ -63,6 +80,7 @@ impl Node {
         match self {
             Self::File { name, .. } => *name = new_name,
             Self::Directory { name, .. } => *name = new_name,
+            Self::Symlink { name, .. } => *name = new_name,
         }
     }
 }
@@ -75,7 +93,7 @@ fn time() -> u64 {
         std::time::SystemTime::now()
             .duration_since(std::time::SystemTime::UNIX_EPOCH)
             .unwrap()
-            .as_secs()
+            .as_nanos() as u64
     }
 
     #[cfg(feature = "no-time")]
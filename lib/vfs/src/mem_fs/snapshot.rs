@@ -0,0 +1,390 @@
+//! This module contains the snapshots of a [`FileSystem`]: the whole
+//! tree (directories, files and their contents, symlinks and all the
+//! metadata) dumped to bytes, to be restored later, possibly many
+//! times, e.g. to pre-populate a file system once and to load it
+//! instantly for each instance.
+//!
+//! The permissions aren't preserved: the nodes of the in-memory file
+//! system don't have any permission metadata to begin with.
+//!
+//! # The snapshot format
+//!
+//! All integers are little-endian. The nodes are written depth-first
+//! starting from the root, so a parent always comes before its
+//! children, which are in the order of the directory.
+//!
+//! ```text
+//! magic    8 bytes   b"WMEMSNP\0"
+//! version  u32       1
+//! count    u32       number of nodes, including the root
+//! nodes    count times:
+//!     kind      u8    0 = directory, 1 = file, 2 = symlink
+//!     parent    u32   index of the parent node, 0 for the root
+//!     file type u8    bit 0 = char device, 1 = block device,
+//!                     2 = socket, 3 = fifo
+//!     accessed  u64
+//!     created   u64
+//!     modified  u64
+//!     name_len  u16
+//!     name      name_len bytes, UTF-8 encoded
+//!     data_len  u64
+//!     data      data_len bytes, the file contents or the symlink
+//!               target, UTF-8 encoded
+//! ```
+
+use super::filesystem::FileSystemInner;
+use super::*;
+use crate::{FileType, FsError, Result};
+use slab::Slab;
+use std::convert::TryInto;
+use std::sync::{Arc, RwLock};
+
+/// The magic bytes starting a snapshot.
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"WMEMSNP\0";
+const SNAPSHOT_VERSION: u32 = 1;
+
+const KIND_DIRECTORY: u8 = 0;
+const KIND_FILE: u8 = 1;
+const KIND_SYMLINK: u8 = 2;
+
+impl FileSystem {
+    /// Dumps the whole file system to bytes, which can be restored
+    /// with [`Self::from_snapshot`].
+    ///
+    /// The positions of the opened files aren't part of the snapshot.
+    pub fn snapshot(&self) -> Result<Vec<u8>> {
+        // Read lock.
+        let fs = self.inner.try_read().map_err(|_| FsError::Lock)?;
+
+        let mut nodes = Vec::with_capacity(fs.storage.len());
+        let mut stack = vec![(ROOT_INODE, 0)];
+
+        while let Some((inode, parent)) = stack.pop() {
+            let node = fs.storage.get(inode).ok_or(FsError::UnknownError)?;
+            let index = nodes.len() as u32;
+            nodes.push((node, parent));
+
+            if let Node::Directory { children, .. } = node {
+                // Reversed to be popped in the order of the directory.
+                stack.extend(children.iter().rev().map(|child| (*child, index)));
+            }
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(SNAPSHOT_MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(nodes.len() as u32).to_le_bytes());
+
+        for (node, parent) in nodes {
+            let (kind, data) = match node {
+                Node::Directory { .. } => (KIND_DIRECTORY, &[][..]),
+                Node::File { file, .. } => (KIND_FILE, file.as_bytes()),
+                Node::Symlink { target, .. } => (
+                    KIND_SYMLINK,
+                    target.to_str().ok_or(FsError::InvalidInput)?.as_bytes(),
+                ),
+            };
+            let name = node.name().to_str().ok_or(FsError::InvalidInput)?;
+            let metadata = node.metadata();
+
+            out.push(kind);
+            out.extend_from_slice(&parent.to_le_bytes());
+            out.push(
+                metadata.ft.char_device as u8
+                    | (metadata.ft.block_device as u8) << 1
+                    | (metadata.ft.socket as u8) << 2
+                    | (metadata.ft.fifo as u8) << 3,
+            );
+            out.extend_from_slice(&metadata.accessed.to_le_bytes());
+            out.extend_from_slice(&metadata.created.to_le_bytes());
+            out.extend_from_slice(&metadata.modified.to_le_bytes());
+            let name_len: u16 = name.len().try_into().map_err(|_| FsError::InvalidInput)?;
+            out.extend_from_slice(&name_len.to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            out.extend_from_slice(data);
+        }
+
+        Ok(out)
+    }
+
+    /// Restores a file system from a snapshot made by
+    /// [`Self::snapshot`].
+    ///
+    /// Fails with [`FsError::InvalidData`] if the snapshot is
+    /// malformed, e.g. if a directory has two children with the same
+    /// name.
+    pub fn from_snapshot(snapshot: &[u8]) -> Result<Self> {
+        let mut reader = SnapshotReader {
+            data: snapshot,
+            position: 0,
+        };
+
+        if reader.bytes(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC
+            || reader.u32()? != SNAPSHOT_VERSION
+        {
+            return Err(FsError::InvalidData);
+        }
+
+        let count = reader.u32()? as usize;
+        // Don't trust `count` to reserve memory, the snapshot may be
+        // truncated.
+        let mut storage = Slab::with_capacity(count.min(1024));
+
+        for index in 0..count {
+            let kind = reader.u8()?;
+            let parent = reader.u32()? as usize;
+            let flags = reader.u8()?;
+            let accessed = reader.u64()?;
+            let created = reader.u64()?;
+            let modified = reader.u64()?;
+            let name_len = reader.u16()? as usize;
+            let name = OsString::from(reader.str(name_len)?);
+            let data_len = reader.u64()?.try_into().map_err(|_| FsError::InvalidData)?;
+            let data = reader.bytes(data_len)?;
+
+            let mut metadata = Metadata {
+                ft: FileType {
+                    char_device: (flags & 1) != 0,
+                    block_device: (flags & (1 << 1)) != 0,
+                    socket: (flags & (1 << 2)) != 0,
+                    fifo: (flags & (1 << 3)) != 0,
+                    ..Default::default()
+                },
+                accessed,
+                created,
+                modified,
+                len: 0,
+            };
+
+            // Nodes are inserted in order in an empty slab, so the
+            // index of a node is its inode.
+            let inode = index;
+            let node = match kind {
+                KIND_DIRECTORY => {
+                    metadata.ft.dir = true;
+
+                    Node::Directory {
+                        inode,
+                        name,
+                        children: Vec::new(),
+                        metadata,
+                    }
+                }
+
+                KIND_FILE => {
+                    metadata.ft.file = true;
+                    metadata.len = data_len as u64;
+
+                    Node::File {
+                        inode,
+                        name,
+                        file: File::from_bytes(data.to_vec()),
+                        metadata,
+                    }
+                }
+
+                KIND_SYMLINK => {
+                    metadata.ft.symlink = true;
+                    metadata.len = data_len as u64;
+
+                    Node::Symlink {
+                        inode,
+                        name,
+                        target: PathBuf::from(
+                            std::str::from_utf8(data).map_err(|_| FsError::InvalidData)?,
+                        ),
+                        metadata,
+                    }
+                }
+
+                _ => return Err(FsError::InvalidData),
+            };
+
+            if index == ROOT_INODE {
+                if !matches!(node, Node::Directory { .. }) {
+                    return Err(FsError::InvalidData);
+                }
+            } else {
+                // The parent must come first, be a directory, and not
+                // already have a child with the same name.
+                let children = match storage.get(parent) {
+                    Some(Node::Directory { children, .. }) if parent < index => children,
+                    _ => return Err(FsError::InvalidData),
+                };
+
+                if children
+                    .iter()
+                    .any(|child| storage.get(*child).map(Node::name) == Some(node.name()))
+                {
+                    return Err(FsError::InvalidData);
+                }
+
+                if let Some(Node::Directory { children, .. }) = storage.get_mut(parent) {
+                    children.push(inode);
+                }
+            }
+
+            if storage.insert(node) != inode {
+                return Err(FsError::UnknownError);
+            }
+        }
+
+        if storage.is_empty() || reader.position != snapshot.len() {
+            return Err(FsError::InvalidData);
+        }
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(FileSystemInner { storage })),
+        })
+    }
+}
+
+struct SnapshotReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.position..end))
+            .ok_or(FsError::InvalidData)?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn str(&mut self, len: usize) -> Result<&'a str> {
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| FsError::InvalidData)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test_snapshot {
+    use crate::{mem_fs::*, FileSystem as FS, FsError};
+    use std::io::{Read, Write};
+
+    macro_rules! path {
+        ($path:expr) => {
+            std::path::Path::new($path)
+        };
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let fs = FileSystem::default();
+        fs.create_dir(path!("/etc")).unwrap();
+        fs.create_dir(path!("/etc/empty")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/etc/hosts"))
+            .unwrap()
+            .write_all(b"127.0.0.1 localhost")
+            .unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/empty"))
+            .unwrap();
+        fs.symlink(path!("etc/hosts"), path!("/hosts")).unwrap();
+
+        let snapshot = fs.snapshot().unwrap();
+        assert!(snapshot.starts_with(SNAPSHOT_MAGIC));
+
+        let restored = FileSystem::from_snapshot(&snapshot).unwrap();
+
+        for path in &["/", "/etc", "/etc/empty", "/etc/hosts", "/empty", "/hosts"] {
+            assert_eq!(
+                restored.symlink_metadata(path!(path)),
+                fs.symlink_metadata(path!(path)),
+                "the metadata of `{}` is preserved",
+                path,
+            );
+        }
+
+        let mut contents = String::new();
+        restored
+            .new_open_options()
+            .read(true)
+            .open(path!("/hosts"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "127.0.0.1 localhost");
+
+        let names = restored
+            .read_dir(path!("/"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["etc", "empty", "hosts"], "the order is kept");
+
+        // The restored file system is independent, and can be used as
+        // usual.
+        restored.remove_file(path!("/empty")).unwrap();
+        restored.create_dir(path!("/tmp")).unwrap();
+        assert!(fs.metadata(path!("/empty")).is_ok());
+        assert!(fs.metadata(path!("/tmp")).is_err());
+        assert!(FileSystem::from_snapshot(&restored.snapshot().unwrap())
+            .unwrap()
+            .metadata(path!("/tmp"))
+            .unwrap()
+            .is_dir());
+    }
+
+    #[test]
+    fn test_invalid_snapshot() {
+        let snapshot = FileSystem::default().snapshot().unwrap();
+
+        assert!(FileSystem::from_snapshot(&snapshot).is_ok());
+        assert_eq!(
+            FileSystem::from_snapshot(&snapshot[..snapshot.len() - 1]).map(|_| ()),
+            Err(FsError::InvalidData),
+        );
+        assert_eq!(
+            FileSystem::from_snapshot(b"not a snapshot").map(|_| ()),
+            Err(FsError::InvalidData),
+        );
+    }
+
+    #[test]
+    fn test_snapshot_with_duplicate_names() {
+        let fs = FileSystem::default();
+        fs.create_dir(path!("/foo")).unwrap();
+        fs.create_dir(path!("/bar")).unwrap();
+
+        let mut snapshot = fs.snapshot().unwrap();
+        // Rename `/bar` to `/foo`: the names have the same length, and
+        // `bar` only appears once.
+        let position = snapshot
+            .windows(3)
+            .position(|window| window == b"bar")
+            .unwrap();
+        snapshot[position..position + 3].copy_from_slice(b"foo");
+
+        assert_eq!(
+            FileSystem::from_snapshot(&snapshot).map(|_| ()),
+            Err(FsError::InvalidData),
+        );
+    }
+}
@@ -1,7 +1,7 @@
 //! This module contains the standard I/O streams, i.e. “emulated”
 //! `stdin`, `stdout` and `stderr`.
 
-use crate::{FileDescriptor, FsError, Result, VirtualFile};
+use crate::{FileDescriptor, FsError, Readiness, Result, VirtualFile};
 use std::io::{self, Read, Seek, Write};
 
 macro_rules! impl_virtualfile_on_std_streams {
@@ -49,12 +49,24 @@ macro_rules! impl_virtualfile_on_std_streams {
             }
 
             fn bytes_available(&self) -> Result<usize> {
-                unimplemented!();
+                Ok(self.buf.len())
             }
 
             fn get_fd(&self) -> Option<FileDescriptor> {
                 None
             }
+
+            fn poll_readiness(&self) -> Result<Readiness> {
+                Ok(Readiness {
+                    read: if self.is_readable() {
+                        Some(self.buf.len())
+                    } else {
+                        None
+                    },
+                    write: if self.is_writable() { Some(0) } else { None },
+                    hangup: false,
+                })
+            }
         }
 
         impl_virtualfile_on_std_streams!(impl Seek for $name);
@@ -193,7 +205,7 @@ impl_virtualfile_on_std_streams!(Stderr {
 
 #[cfg(test)]
 mod test_read_write_seek {
-    use crate::mem_fs::*;
+    use crate::{mem_fs::*, Readiness, VirtualFile};
     use std::io::{self, Read, Seek, Write};
 
     #[test]
@@ -339,4 +351,33 @@ mod test_read_write_seek {
             "cannot seek `stderr`",
         );
     }
+
+    #[test]
+    fn test_poll_readiness() {
+        let stdin = Stdin {
+            buf: vec![b'f', b'o', b'o'],
+        };
+
+        assert_eq!(
+            stdin.poll_readiness(),
+            Ok(Readiness {
+                read: Some(3),
+                write: None,
+                hangup: false
+            }),
+            "`stdin` is only ready to be read",
+        );
+
+        let stdout = Stdout { buf: vec![] };
+
+        assert_eq!(
+            stdout.poll_readiness(),
+            Ok(Readiness {
+                read: None,
+                write: Some(0),
+                hangup: false
+            }),
+            "`stdout` is only ready to be written",
+        );
+    }
 }
@@ -0,0 +1,472 @@
+//! A mount table: file systems mounted at different paths, e.g. a
+//! `mem_fs` at `/tmp`, a host directory at `/data` and a read-only
+//! image at `/etc`.
+//!
+//! A path is routed to the file system mounted at its longest
+//! matching prefix, and is translated to a path of that file system,
+//! relative to the source directory of the mount. Relative paths are
+//! routed to the file system mounted at `/`, unchanged.
+//!
+//! A mount point shadows what's below it in the parent file system.
+//! The ancestors of the mount points that aren't covered by another
+//! mount are synthetic, empty directories.
+
+use crate::{
+    DirEntry, FileType, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Result,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// The mount table file system.
+///
+/// It can be cloned, it's a light copy sharing the same mount table,
+/// which can be changed at any time.
+#[derive(Debug, Clone, Default)]
+pub struct FileSystem {
+    mounts: Arc<RwLock<BTreeMap<PathBuf, Mount>>>,
+}
+
+#[derive(Clone)]
+struct Mount {
+    fs: Arc<dyn crate::FileSystem>,
+    source: PathBuf,
+}
+
+impl fmt::Debug for Mount {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Mount")
+            .field("fs", &self.fs)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl FileSystem {
+    /// Creates an empty mount table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts the directory `source` of `fs` at `path`, which must be
+    /// absolute.
+    pub fn mount(&self, path: &Path, fs: Arc<dyn crate::FileSystem>, source: &Path) -> Result<()> {
+        let path = mount_point(path)?;
+        let mut mounts = self.mounts.write().map_err(|_| FsError::Lock)?;
+
+        if mounts.contains_key(&path) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        mounts.insert(
+            path,
+            Mount {
+                fs,
+                source: source.to_path_buf(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Unmounts the file system mounted at `path`.
+    pub fn unmount(&self, path: &Path) -> Result<()> {
+        let path = mount_point(path)?;
+        let mut mounts = self.mounts.write().map_err(|_| FsError::Lock)?;
+
+        mounts
+            .remove(&path)
+            .map(|_| ())
+            .ok_or(FsError::EntityNotFound)
+    }
+
+    /// The paths where file systems are mounted.
+    pub fn mount_points(&self) -> Result<Vec<PathBuf>> {
+        let mounts = self.mounts.read().map_err(|_| FsError::Lock)?;
+
+        Ok(mounts.keys().cloned().collect())
+    }
+
+    /// Returns the file system `path` is routed to, along with the
+    /// mount point and the translated path, or `None` if no file
+    /// system covers `path`.
+    fn route(&self, path: &Path) -> Result<Option<(PathBuf, Mount, PathBuf)>> {
+        let mounts = self.mounts.read().map_err(|_| FsError::Lock)?;
+
+        if path.is_relative() {
+            return Ok(mounts
+                .get(Path::new("/"))
+                .map(|mount| (PathBuf::from("/"), mount.clone(), path.to_path_buf())));
+        }
+
+        let path = normalize(path);
+
+        Ok(mounts
+            .iter()
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.components().count())
+            .map(|(mount_point, mount)| {
+                // SAFETY: `path` starts with `mount_point`, it's been
+                // checked above.
+                let rest = path.strip_prefix(mount_point).unwrap();
+                let translated = if rest.as_os_str().is_empty() {
+                    mount.source.clone()
+                } else {
+                    mount.source.join(rest)
+                };
+
+                (mount_point.clone(), mount.clone(), translated)
+            }))
+    }
+
+    /// Like `Self::route` but fails if no file system covers `path`.
+    fn route_or_fail(&self, path: &Path) -> Result<(PathBuf, Mount, PathBuf)> {
+        self.route(path)?.ok_or(FsError::EntityNotFound)
+    }
+
+    /// The paths right below `path` which are mount points, or
+    /// ancestors of mount points.
+    fn child_mount_points(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        if path.is_relative() {
+            return Ok(Vec::new());
+        }
+
+        let path = normalize(path);
+        let mounts = self.mounts.read().map_err(|_| FsError::Lock)?;
+        let mut children = mounts
+            .keys()
+            .filter_map(|mount_point| {
+                let name = mount_point.strip_prefix(&path).ok()?.components().next()?;
+
+                Some(path.join(name))
+            })
+            .collect::<Vec<_>>();
+        children.dedup();
+
+        Ok(children)
+    }
+
+    /// Checks whether `path` is an ancestor of a mount point.
+    fn is_ancestor_of_mount_point(&self, path: &Path) -> Result<bool> {
+        if path.is_relative() {
+            return Ok(false);
+        }
+
+        let path = normalize(path);
+        let mounts = self.mounts.read().map_err(|_| FsError::Lock)?;
+
+        Ok(mounts
+            .keys()
+            .any(|mount_point| mount_point.starts_with(&path)))
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let read_dir = match self.route(path)? {
+            Some((_, mount, translated)) => match mount.fs.read_dir(&translated) {
+                Err(_) if self.is_ancestor_of_mount_point(path)? => None,
+                read_dir => Some(read_dir?),
+            },
+            None if self.is_ancestor_of_mount_point(path)? => None,
+            None => return Err(FsError::EntityNotFound),
+        };
+        let mut entries = match read_dir {
+            Some(read_dir) => read_dir
+                .map(|entry| {
+                    entry.map(|entry| DirEntry {
+                        path: path.join(entry.file_name()),
+                        metadata: entry.metadata,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        // The mount points, and their ancestors, shadow the entries of
+        // the parent file system.
+        for mount_point in self.child_mount_points(path)? {
+            let name = mount_point.file_name().ok_or(FsError::InvalidInput)?;
+            entries.retain(|entry| entry.file_name() != name);
+            entries.push(DirEntry {
+                path: path.join(name),
+                metadata: self.metadata(&mount_point),
+            });
+        }
+
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        if self.is_ancestor_of_mount_point(path)? {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let (_, mount, translated) = self.route_or_fail(path)?;
+        mount.fs.create_dir(&translated)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        if self.is_ancestor_of_mount_point(path)? {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let (_, mount, translated) = self.route_or_fail(path)?;
+        mount.fs.remove_dir(&translated)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        if self.is_ancestor_of_mount_point(from)? || self.is_ancestor_of_mount_point(to)? {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let (from_mount_point, mount, from) = self.route_or_fail(from)?;
+        let (to_mount_point, _, to) = self.route_or_fail(to)?;
+
+        if from_mount_point != to_mount_point {
+            return Err(FsError::CrossDevice);
+        }
+
+        mount.fs.rename(&from, &to)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let metadata = match self.route(path)? {
+            Some((_, mount, translated)) => mount.fs.metadata(&translated),
+            None => Err(FsError::EntityNotFound),
+        };
+
+        match metadata {
+            Err(_) if self.is_ancestor_of_mount_point(path)? => Ok(directory_metadata()),
+            metadata => metadata,
+        }
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        // A mount point, or one of its ancestors, is never a symlink.
+        if self.is_ancestor_of_mount_point(path)? {
+            return self.metadata(path);
+        }
+
+        let (_, mount, translated) = self.route_or_fail(path)?;
+        mount.fs.symlink_metadata(&translated)
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        if self.is_ancestor_of_mount_point(link)? {
+            return Err(FsError::AlreadyExists);
+        }
+
+        // The value of the symlink is stored verbatim, it's resolved
+        // by the caller.
+        let (_, mount, translated) = self.route_or_fail(link)?;
+        mount.fs.symlink(original, &translated)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        if self.is_ancestor_of_mount_point(path)? {
+            return Err(FsError::InvalidInput);
+        }
+
+        let (_, mount, translated) = self.route_or_fail(path)?;
+        mount.fs.read_link(&translated)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        if self.is_ancestor_of_mount_point(path)? {
+            return Err(FsError::PermissionDenied);
+        }
+
+        let (_, mount, translated) = self.route_or_fail(path)?;
+        mount.fs.remove_file(&translated)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The type that is responsible to open a file.
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(
+        &mut self,
+        path: &Path,
+        conf: &OpenOptionsConfig,
+    ) -> Result<Box<dyn crate::VirtualFile>> {
+        let (_, mount, translated) = self.filesystem.route_or_fail(path)?;
+
+        mount
+            .fs
+            .new_open_options()
+            .read(conf.read())
+            .write(conf.write())
+            .append(conf.append())
+            .truncate(conf.truncate())
+            .create(conf.create())
+            .create_new(conf.create_new())
+            .open(&translated)
+    }
+}
+
+fn directory_metadata() -> Metadata {
+    Metadata {
+        ft: FileType {
+            dir: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Checks that `path` can be a mount point, and normalizes it.
+fn mount_point(path: &Path) -> Result<PathBuf> {
+    if !path.is_absolute() {
+        return Err(FsError::InvalidInput);
+    }
+
+    Ok(normalize(path))
+}
+
+/// Removes the `.` and `..` components of an absolute path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                // The root is its own parent.
+                if normalized.parent().is_some() {
+                    normalized.pop();
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod test_mount_fs {
+    use super::FileSystem;
+    use crate::{mem_fs, FileSystem as FS, FsError};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    macro_rules! path {
+        ($path:expr) => {
+            std::path::Path::new($path)
+        };
+    }
+
+    fn names(fs: &dyn FS, path: &Path) -> Vec<PathBuf> {
+        let mut names = fs
+            .read_dir(path)
+            .unwrap()
+            .map(|entry| PathBuf::from(entry.unwrap().file_name()))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn test_routing() {
+        let root = mem_fs::FileSystem::default();
+        root.create_dir(path!("/home")).unwrap();
+        let tmp = mem_fs::FileSystem::default();
+        let data = mem_fs::FileSystem::default();
+        data.create_dir(path!("/volumes")).unwrap();
+        data.create_dir(path!("/volumes/data")).unwrap();
+
+        let fs = FileSystem::new();
+        fs.mount(path!("/"), Arc::new(root.clone()), path!("/"))
+            .unwrap();
+        fs.mount(path!("/tmp"), Arc::new(tmp.clone()), path!("/"))
+            .unwrap();
+        fs.mount(
+            path!("/mnt/data"),
+            Arc::new(data.clone()),
+            path!("/volumes/data"),
+        )
+        .unwrap();
+        assert_eq!(
+            fs.mount(path!("/tmp"), Arc::new(tmp.clone()), path!("/")),
+            Err(FsError::AlreadyExists),
+        );
+
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open(path!("/tmp/scratch"))
+            .unwrap()
+            .write_all(b"scratch")
+            .unwrap();
+        assert!(tmp.metadata(path!("/scratch")).is_ok());
+        assert!(root.metadata(path!("/tmp/scratch")).is_err());
+
+        assert_eq!(fs.create_dir(path!("/mnt/data/dir")), Ok(()));
+        assert!(data.metadata(path!("/volumes/data/dir")).unwrap().is_dir());
+
+        assert_eq!(
+            names(&fs, path!("/")),
+            vec![
+                PathBuf::from("home"),
+                PathBuf::from("mnt"),
+                PathBuf::from("tmp")
+            ],
+            "the mount points are listed",
+        );
+        assert!(
+            fs.metadata(path!("/mnt")).unwrap().is_dir(),
+            "the ancestors of mount points are directories",
+        );
+        assert_eq!(names(&fs, path!("/mnt")), vec![PathBuf::from("data")]);
+
+        let mut contents = String::new();
+        fs.new_open_options()
+            .read(true)
+            .open(path!("/tmp/../tmp/scratch"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "scratch");
+
+        assert_eq!(
+            fs.rename(path!("/tmp/scratch"), path!("/home/scratch")),
+            Err(FsError::CrossDevice),
+        );
+        assert_eq!(fs.remove_dir(path!("/tmp")), Err(FsError::PermissionDenied));
+    }
+
+    #[test]
+    fn test_unmount() {
+        let fs = FileSystem::new();
+        fs.mount(
+            path!("/tmp"),
+            Arc::new(mem_fs::FileSystem::default()),
+            path!("/"),
+        )
+        .unwrap();
+
+        assert_eq!(fs.mount_points(), Ok(vec![PathBuf::from("/tmp")]));
+        assert!(fs.metadata(path!("/")).unwrap().is_dir());
+        assert_eq!(fs.unmount(path!("/tmp")), Ok(()));
+        assert_eq!(fs.unmount(path!("/tmp")), Err(FsError::EntityNotFound));
+        assert_eq!(
+            fs.metadata(path!("/tmp")).map(|_| ()),
+            Err(FsError::EntityNotFound)
+        );
+    }
+}
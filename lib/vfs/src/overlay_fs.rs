@@ -0,0 +1,559 @@
+//! An overlay file system: a read-only lower layer, which can be
+//! shared by many instances, covered by a private, writable `mem_fs`
+//! upper layer.
+//!
+//! Reads go to the upper layer first, then to the lower one. Writes
+//! always go to the upper layer: a file of the lower layer is copied
+//! up before being opened for writing, and its parent directories are
+//! created in the upper layer. Removing an entry of the lower layer
+//! records a _whiteout_, which hides it and everything below it.
+//!
+//! Symlinks are resolved within each layer, not across layers.
+
+use crate::{
+    mem_fs, DirEntry, FileSystem as _, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
+    Result, VirtualFile,
+};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// The overlay file system.
+///
+/// It can be cloned, it's a light copy sharing the same layers and
+/// whiteouts.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    lower: Arc<dyn crate::FileSystem>,
+    upper: mem_fs::FileSystem,
+    whiteouts: Arc<RwLock<HashSet<PathBuf>>>,
+}
+
+impl FileSystem {
+    /// Creates an overlay on top of `lower`, with an empty upper
+    /// layer. `lower` is never modified.
+    pub fn new(lower: Arc<dyn crate::FileSystem>) -> Self {
+        Self {
+            lower,
+            upper: mem_fs::FileSystem::default(),
+            whiteouts: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// The read-only lower layer.
+    pub fn lower(&self) -> &Arc<dyn crate::FileSystem> {
+        &self.lower
+    }
+
+    /// The writable upper layer, holding everything written so far.
+    pub fn upper(&self) -> &mem_fs::FileSystem {
+        &self.upper
+    }
+
+    /// The paths of the lower layer that have been removed.
+    pub fn whiteouts(&self) -> Result<Vec<PathBuf>> {
+        let whiteouts = self.whiteouts.read().map_err(|_| FsError::Lock)?;
+
+        Ok(whiteouts.iter().cloned().collect())
+    }
+
+    /// Checks whether `path`, or one of its ancestors, has been
+    /// removed from the lower layer.
+    fn is_whited_out(&self, path: &Path) -> Result<bool> {
+        let path = normalize(path);
+        let whiteouts = self.whiteouts.read().map_err(|_| FsError::Lock)?;
+
+        Ok(whiteouts.iter().any(|whiteout| path.starts_with(whiteout)))
+    }
+
+    /// Hides `path` and everything below it in the lower layer.
+    fn whiteout(&self, path: &Path) -> Result<()> {
+        let path = normalize(path);
+        let mut whiteouts = self.whiteouts.write().map_err(|_| FsError::Lock)?;
+
+        // The new whiteout covers the ones below it.
+        whiteouts.retain(|whiteout| !whiteout.starts_with(&path));
+        whiteouts.insert(path);
+
+        Ok(())
+    }
+
+    /// Runs `f` on the lower layer, unless `path` is whited out.
+    fn on_lower<T>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&dyn crate::FileSystem) -> Result<T>,
+    ) -> Result<T> {
+        if self.is_whited_out(path)? {
+            return Err(FsError::EntityNotFound);
+        }
+
+        f(self.lower.as_ref())
+    }
+
+    fn in_upper(&self, path: &Path) -> bool {
+        self.upper.symlink_metadata(path).is_ok()
+    }
+
+    fn in_lower(&self, path: &Path) -> Result<bool> {
+        Ok(self
+            .on_lower(path, |lower| lower.symlink_metadata(path))
+            .is_ok())
+    }
+
+    /// Creates the parent directories of `path` in the upper layer,
+    /// they must exist in the overlay.
+    fn copy_up_parents(&self, path: &Path) -> Result<()> {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let mut ancestors = parent.ancestors().collect::<Vec<_>>();
+        ancestors.reverse();
+
+        for ancestor in ancestors {
+            if self.in_upper(ancestor) {
+                continue;
+            }
+
+            if !self.symlink_metadata(ancestor)?.is_dir() {
+                return Err(FsError::BaseNotDirectory);
+            }
+
+            self.upper.create_dir(ancestor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies `path` from the lower layer to the upper layer, if it's
+    /// not there already. The children of a directory aren't copied.
+    fn copy_up(&self, path: &Path) -> Result<()> {
+        if self.in_upper(path) {
+            return Ok(());
+        }
+
+        self.copy_up_parents(path)?;
+
+        let metadata = self.on_lower(path, |lower| lower.symlink_metadata(path))?;
+
+        if metadata.file_type().is_symlink() {
+            let target = self.lower.read_link(path)?;
+            self.upper.symlink(&target, path)
+        } else if metadata.is_dir() {
+            self.upper.create_dir(path)
+        } else {
+            let mut contents = Vec::with_capacity(metadata.len() as usize);
+            self.lower
+                .new_open_options()
+                .read(true)
+                .open(path)?
+                .read_to_end(&mut contents)?;
+
+            self.upper
+                .new_open_options()
+                .write(true)
+                .create_new(true)
+                .open(path)?
+                .write_all(&contents)?;
+
+            Ok(())
+        }
+    }
+
+    /// Like `Self::copy_up` but copies the children of a directory
+    /// too, recursively.
+    fn copy_up_tree(&self, path: &Path) -> Result<()> {
+        self.copy_up(path)?;
+
+        if self.upper.symlink_metadata(path)?.is_dir() {
+            for entry in self.read_dir(path)? {
+                self.copy_up_tree(&path.join(entry?.file_name()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let in_upper = self.in_upper(path);
+        let mut entries = if in_upper {
+            self.upper
+                .read_dir(path)?
+                .collect::<Result<Vec<DirEntry>>>()?
+        } else {
+            Vec::new()
+        };
+
+        // Merge the entries of the lower layer that are neither
+        // shadowed by the upper layer nor whited out.
+        match self.on_lower(path, |lower| lower.read_dir(path)) {
+            Ok(lower_entries) => {
+                for entry in lower_entries {
+                    let entry = entry?;
+                    let name = entry.file_name();
+
+                    if entries.iter().any(|entry| entry.file_name() == name)
+                        || self.is_whited_out(&path.join(&name))?
+                    {
+                        continue;
+                    }
+
+                    entries.push(entry);
+                }
+            }
+            Err(error) if !in_upper => return Err(error),
+            Err(_) => (),
+        }
+
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        if self.symlink_metadata(path).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        self.copy_up_parents(path)?;
+
+        // A whiteout at `path` is kept: it makes the new directory
+        // opaque, the old children of the lower layer stay hidden.
+        self.upper.create_dir(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        if !self.symlink_metadata(path)?.is_dir() {
+            return Err(FsError::BaseNotDirectory);
+        }
+
+        if self.read_dir(path)?.next().is_some() {
+            return Err(FsError::DirectoryNotEmpty);
+        }
+
+        let in_lower = self.in_lower(path)?;
+
+        if self.in_upper(path) {
+            self.upper.remove_dir(path)?;
+        }
+
+        if in_lower {
+            self.whiteout(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let metadata = self.symlink_metadata(from)?;
+
+        if let Ok(to_metadata) = self.symlink_metadata(to) {
+            if to_metadata.is_dir() && self.read_dir(to)?.next().is_some() {
+                return Err(FsError::DirectoryNotEmpty);
+            }
+        }
+
+        let from_in_lower = self.in_lower(from)?;
+        let to_in_lower = self.in_lower(to)?;
+
+        if metadata.is_dir() {
+            self.copy_up_tree(from)?;
+        } else {
+            self.copy_up(from)?;
+        }
+        self.copy_up_parents(to)?;
+
+        self.upper.rename(from, to)?;
+
+        if from_in_lower {
+            self.whiteout(from)?;
+        }
+
+        // What was at `to` in the lower layer has been replaced.
+        if to_in_lower {
+            self.whiteout(to)?;
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        if self.in_upper(path) {
+            return self.upper.metadata(path);
+        }
+
+        self.on_lower(path, |lower| lower.metadata(path))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        if self.in_upper(path) {
+            return self.upper.symlink_metadata(path);
+        }
+
+        self.on_lower(path, |lower| lower.symlink_metadata(path))
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        if self.symlink_metadata(link).is_ok() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        self.copy_up_parents(link)?;
+        self.upper.symlink(original, link)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        if self.in_upper(path) {
+            return self.upper.read_link(path);
+        }
+
+        self.on_lower(path, |lower| lower.read_link(path))
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        if self.symlink_metadata(path)?.is_dir() {
+            return Err(FsError::NotAFile);
+        }
+
+        let in_lower = self.in_lower(path)?;
+
+        if self.in_upper(path) {
+            self.upper.remove_file(path)?;
+        }
+
+        if in_lower {
+            self.whiteout(path)?;
+        }
+
+        Ok(())
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The type that is responsible to open a file.
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(&mut self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        let fs = &self.filesystem;
+        let writing =
+            conf.write() || conf.append() || conf.truncate() || conf.create() || conf.create_new();
+
+        // Reading a file of the lower layer doesn't need to copy it.
+        if !writing && !fs.in_upper(path) {
+            return fs.on_lower(path, |lower| {
+                lower.new_open_options().read(conf.read()).open(path)
+            });
+        }
+
+        match fs.symlink_metadata(path) {
+            Ok(_) if conf.create_new() => return Err(FsError::AlreadyExists),
+            Ok(metadata) if metadata.is_dir() => return Err(FsError::NotAFile),
+            Ok(_) => fs.copy_up(path)?,
+            Err(_) if conf.create() || conf.create_new() => fs.copy_up_parents(path)?,
+            Err(error) => return Err(error),
+        }
+
+        fs.upper
+            .new_open_options()
+            .read(conf.read())
+            .write(conf.write())
+            .append(conf.append())
+            .truncate(conf.truncate())
+            .create(conf.create())
+            .create_new(conf.create_new())
+            .open(path)
+    }
+}
+
+/// Removes the `.` and `..` components of a path, so that whiteouts
+/// can be compared.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod test_overlay_fs {
+    use super::FileSystem;
+    use crate::{mem_fs, FileSystem as FS, FsError};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    macro_rules! path {
+        ($path:expr) => {
+            std::path::Path::new($path)
+        };
+    }
+
+    fn write(fs: &dyn FS, path: &Path, contents: &[u8]) {
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap()
+            .write_all(contents)
+            .unwrap();
+    }
+
+    fn read(fs: &dyn FS, path: &Path) -> Vec<u8> {
+        let mut contents = Vec::new();
+        fs.new_open_options()
+            .read(true)
+            .open(path)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+
+        contents
+    }
+
+    fn names(fs: &dyn FS, path: &Path) -> Vec<PathBuf> {
+        let mut names = fs
+            .read_dir(path)
+            .unwrap()
+            .map(|entry| PathBuf::from(entry.unwrap().file_name()))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        names
+    }
+
+    fn lower() -> mem_fs::FileSystem {
+        let lower = mem_fs::FileSystem::default();
+        lower.create_dir(path!("/etc")).unwrap();
+        lower.create_dir(path!("/etc/conf.d")).unwrap();
+        write(&lower, path!("/etc/hosts"), b"localhost");
+        write(&lower, path!("/etc/conf.d/a"), b"a");
+
+        lower
+    }
+
+    #[test]
+    fn test_copy_up_on_write() {
+        let lower = lower();
+        let fs = FileSystem::new(Arc::new(lower.clone()));
+
+        assert_eq!(read(&fs, path!("/etc/hosts")), b"localhost");
+
+        let mut file = fs
+            .new_open_options()
+            .append(true)
+            .open(path!("/etc/hosts"))
+            .unwrap();
+        file.write_all(b" example").unwrap();
+        drop(file);
+
+        assert_eq!(read(&fs, path!("/etc/hosts")), b"localhost example");
+        assert_eq!(
+            read(&lower, path!("/etc/hosts")),
+            b"localhost",
+            "the lower layer is left untouched",
+        );
+        assert!(fs.upper().metadata(path!("/etc/hosts")).is_ok());
+
+        write(&fs, path!("/etc/conf.d/b"), b"b");
+        assert_eq!(
+            names(&fs, path!("/etc/conf.d")),
+            vec![PathBuf::from("a"), PathBuf::from("b")],
+            "the directories of both layers are merged",
+        );
+        assert!(lower.metadata(path!("/etc/conf.d/b")).is_err());
+
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create_new(true)
+                .open(path!("/etc/hosts"))
+                .map(|_| ()),
+            Err(FsError::AlreadyExists),
+        );
+    }
+
+    #[test]
+    fn test_whiteouts() {
+        let lower = lower();
+        let fs = FileSystem::new(Arc::new(lower.clone()));
+
+        assert_eq!(fs.remove_file(path!("/etc/hosts")), Ok(()));
+        assert_eq!(
+            fs.metadata(path!("/etc/hosts")).map(|_| ()),
+            Err(FsError::EntityNotFound),
+        );
+        assert_eq!(names(&fs, path!("/etc")), vec![PathBuf::from("conf.d")]);
+        assert!(lower.metadata(path!("/etc/hosts")).is_ok());
+
+        assert_eq!(
+            fs.remove_dir(path!("/etc/conf.d")),
+            Err(FsError::DirectoryNotEmpty),
+        );
+        assert_eq!(fs.remove_file(path!("/etc/conf.d/a")), Ok(()));
+        assert_eq!(fs.remove_dir(path!("/etc/conf.d")), Ok(()));
+        let mut whiteouts = fs.whiteouts().unwrap();
+        whiteouts.sort();
+        assert_eq!(
+            whiteouts,
+            vec![PathBuf::from("/etc/conf.d"), PathBuf::from("/etc/hosts")],
+            "the whiteout of a directory covers its children",
+        );
+
+        assert_eq!(fs.create_dir(path!("/etc/conf.d")), Ok(()));
+        assert!(
+            names(&fs, path!("/etc/conf.d")).is_empty(),
+            "a directory created over a whiteout is opaque",
+        );
+
+        write(&fs, path!("/etc/hosts"), b"example");
+        assert_eq!(read(&fs, path!("/etc/hosts")), b"example");
+    }
+
+    #[test]
+    fn test_rename() {
+        let lower = lower();
+        let fs = FileSystem::new(Arc::new(lower.clone()));
+
+        assert_eq!(fs.rename(path!("/etc"), path!("/config")), Ok(()));
+        assert!(fs.metadata(path!("/etc")).is_err());
+        assert_eq!(read(&fs, path!("/config/hosts")), b"localhost");
+        assert_eq!(read(&fs, path!("/config/conf.d/a")), b"a");
+        assert!(lower.metadata(path!("/etc/hosts")).is_ok());
+        assert!(lower.metadata(path!("/config")).is_err());
+    }
+
+    #[test]
+    fn test_shared_lower_layer() {
+        let lower: Arc<dyn FS> = Arc::new(lower());
+        let first = FileSystem::new(lower.clone());
+        let second = FileSystem::new(lower);
+
+        write(&first, path!("/etc/hosts"), b"first");
+        assert_eq!(first.remove_file(path!("/etc/conf.d/a")), Ok(()));
+
+        assert_eq!(read(&first, path!("/etc/hosts")), b"first");
+        assert_eq!(read(&second, path!("/etc/hosts")), b"localhost");
+        assert!(second.metadata(path!("/etc/conf.d/a")).is_ok());
+    }
+}
@@ -0,0 +1,743 @@
+//! A file system enforcing quotas on another one, and auditing the
+//! accesses to it.
+//!
+//! It wraps any file system, and the files opened through it. The
+//! number of bytes written and the number of inodes (files,
+//! directories and symlinks) created through it are capped by a
+//! [`Quota`]; going over fails with [`FsError::QuotaExceeded`]. The
+//! usage starts with what the wrapped file system already holds, so
+//! that removing its files doesn't make room over the quota; only the
+//! changes made through this file system are accounted for after
+//! that.
+//!
+//! An [`AuditEvent`] is emitted for every open, read, write, unlink
+//! (of a file or a directory) and rename, with the paths as seen by
+//! this file system.
+
+use crate::{
+    FileDescriptor, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir, Readiness, Result,
+    VirtualFile,
+};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The limits of a [`FileSystem`]. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// The maximum number of bytes of file contents.
+    pub max_bytes: Option<u64>,
+    /// The maximum number of files, directories and symlinks.
+    pub max_inodes: Option<u64>,
+}
+
+/// The resources used through a [`FileSystem`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub bytes: u64,
+    pub inodes: u64,
+}
+
+/// An access to the file system, along with its outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditEvent {
+    Open {
+        path: PathBuf,
+        read: bool,
+        write: bool,
+        create: bool,
+        result: Result<()>,
+    },
+    /// The number of bytes read, on success.
+    Read {
+        path: PathBuf,
+        result: Result<usize>,
+    },
+    /// The number of bytes written, on success.
+    Write {
+        path: PathBuf,
+        result: Result<usize>,
+    },
+    Unlink {
+        path: PathBuf,
+        result: Result<()>,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+        result: Result<()>,
+    },
+}
+
+/// A callback receiving the audit events.
+pub type AuditHandler = Arc<dyn Fn(&AuditEvent) + Send + Sync>;
+
+/// The file system wrapper.
+///
+/// It can be cloned, it's a light copy sharing the same usage.
+#[derive(Clone)]
+pub struct FileSystem {
+    inner: Arc<dyn crate::FileSystem>,
+    quota: Quota,
+    usage: Arc<Mutex<Usage>>,
+    audit: Option<AuditHandler>,
+}
+
+impl fmt::Debug for FileSystem {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("quota_fs::FileSystem")
+            .field("inner", &self.inner)
+            .field("quota", &self.quota)
+            .field("usage", &self.usage)
+            .field("audit", &self.audit.is_some())
+            .finish()
+    }
+}
+
+impl FileSystem {
+    /// Wraps `inner`, capped by `quota`.
+    ///
+    /// The whole tree of `inner` is walked from `/` to compute the
+    /// initial usage; the entries which can't be read aren't counted.
+    pub fn new(inner: Arc<dyn crate::FileSystem>, quota: Quota) -> Self {
+        let usage = usage_of(&*inner);
+
+        Self {
+            inner,
+            quota,
+            usage: Arc::new(Mutex::new(usage)),
+            audit: None,
+        }
+    }
+
+    /// Sets the callback receiving the audit events.
+    pub fn with_audit(mut self, audit: AuditHandler) -> Self {
+        self.audit = Some(audit);
+
+        self
+    }
+
+    /// The wrapped file system.
+    pub fn inner(&self) -> &Arc<dyn crate::FileSystem> {
+        &self.inner
+    }
+
+    pub fn quota(&self) -> Quota {
+        self.quota
+    }
+
+    /// The resources used so far.
+    pub fn usage(&self) -> Result<Usage> {
+        Ok(*self.usage.lock().map_err(|_| FsError::Lock)?)
+    }
+
+    /// Reserves `bytes` and `inodes`, or fails if it would go over the
+    /// quota.
+    fn reserve(&self, bytes: u64, inodes: u64) -> Result<()> {
+        let mut usage = self.usage.lock().map_err(|_| FsError::Lock)?;
+        let new_bytes = usage.bytes.saturating_add(bytes);
+        let new_inodes = usage.inodes.saturating_add(inodes);
+
+        if matches!(self.quota.max_bytes, Some(max) if new_bytes > max)
+            || matches!(self.quota.max_inodes, Some(max) if new_inodes > max)
+        {
+            return Err(FsError::QuotaExceeded);
+        }
+
+        usage.bytes = new_bytes;
+        usage.inodes = new_inodes;
+
+        Ok(())
+    }
+
+    /// Accounts for `bytes` already written, even over the quota.
+    fn charge(&self, bytes: u64) {
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.bytes = usage.bytes.saturating_add(bytes);
+        }
+    }
+
+    /// Gives back `bytes` and `inodes`.
+    fn release(&self, bytes: u64, inodes: u64) {
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.bytes = usage.bytes.saturating_sub(bytes);
+            usage.inodes = usage.inodes.saturating_sub(inodes);
+        }
+    }
+
+    fn audit(&self, event: AuditEvent) {
+        if let Some(audit) = &self.audit {
+            audit(&event);
+        }
+    }
+
+    /// Reserves an inode, and runs `create`, which creates it.
+    fn create_inode<F>(&self, create: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        self.reserve(0, 1)?;
+
+        create().map_err(|error| {
+            self.release(0, 1);
+
+            error
+        })
+    }
+}
+
+/// The number of bytes used by a node.
+fn bytes_of(metadata: &Metadata) -> u64 {
+    if metadata.is_file() {
+        metadata.len
+    } else {
+        0
+    }
+}
+
+/// The resources already used in `fs`, without following the
+/// symlinks.
+fn usage_of(fs: &dyn crate::FileSystem) -> Usage {
+    let mut usage = Usage::default();
+    let mut directories = vec![PathBuf::from("/")];
+
+    while let Some(directory) = directories.pop() {
+        let entries = match fs.read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let metadata = match fs.symlink_metadata(&entry.path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            usage.bytes = usage.bytes.saturating_add(bytes_of(&metadata));
+            usage.inodes = usage.inodes.saturating_add(1);
+
+            if metadata.is_dir() {
+                directories.push(entry.path);
+            }
+        }
+    }
+
+    usage
+}
+
+impl crate::FileSystem for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        self.inner.read_dir(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        self.create_inode(|| self.inner.create_dir(path))
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let result = self.inner.remove_dir(path);
+
+        if result.is_ok() {
+            self.release(0, 1);
+        }
+
+        self.audit(AuditEvent::Unlink {
+            path: path.to_path_buf(),
+            result,
+        });
+
+        result
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        // The entry at `to`, if any, is replaced.
+        let replaced = self.inner.symlink_metadata(to).ok();
+        let result = self.inner.rename(from, to);
+
+        if let (Ok(()), Some(replaced)) = (&result, replaced) {
+            self.release(bytes_of(&replaced), 1);
+        }
+
+        self.audit(AuditEvent::Rename {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+            result,
+        });
+
+        result
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        self.inner.symlink_metadata(path)
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        self.create_inode(|| self.inner.symlink(original, link))
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        self.inner.read_link(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let removed = self.inner.symlink_metadata(path).ok();
+        let result = self.inner.remove_file(path);
+
+        if let (Ok(()), Some(removed)) = (&result, removed) {
+            self.release(bytes_of(&removed), 1);
+        }
+
+        self.audit(AuditEvent::Unlink {
+            path: path.to_path_buf(),
+            result,
+        });
+
+        result
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(FileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// The type that is responsible to open a file.
+#[derive(Debug, Clone)]
+pub struct FileOpener {
+    filesystem: FileSystem,
+}
+
+impl FileOpener {
+    fn open_inner(&self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        let filesystem = &self.filesystem;
+        let existing = filesystem.inner.metadata(path).ok();
+        let creates = existing.is_none() && (conf.create() || conf.create_new());
+
+        if creates {
+            filesystem.reserve(0, 1)?;
+        }
+
+        let file = filesystem
+            .inner
+            .new_open_options()
+            .read(conf.read())
+            .write(conf.write())
+            .append(conf.append())
+            .truncate(conf.truncate())
+            .create(conf.create())
+            .create_new(conf.create_new())
+            .open(path);
+
+        match (&file, existing) {
+            (Err(_), _) if creates => filesystem.release(0, 1),
+            (Ok(_), Some(existing)) if conf.truncate() => {
+                filesystem.release(bytes_of(&existing), 0)
+            }
+            _ => (),
+        }
+
+        Ok(Box::new(File {
+            inner: file?,
+            path: path.to_path_buf(),
+            append: conf.append(),
+            filesystem: filesystem.clone(),
+        }))
+    }
+}
+
+impl crate::FileOpener for FileOpener {
+    fn open(&mut self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        let file = self.open_inner(path, conf);
+
+        self.filesystem.audit(AuditEvent::Open {
+            path: path.to_path_buf(),
+            read: conf.read(),
+            write: conf.write(),
+            create: conf.create() || conf.create_new(),
+            result: file.as_ref().map(|_| ()).map_err(|error| *error),
+        });
+
+        file
+    }
+}
+
+/// A file opened through a [`FileSystem`].
+#[derive(Debug)]
+pub struct File {
+    inner: Box<dyn VirtualFile>,
+    path: PathBuf,
+    append: bool,
+    filesystem: FileSystem,
+}
+
+impl File {
+    /// The offset where the next write happens.
+    fn write_position(&mut self) -> u64 {
+        if self.append {
+            return self.inner.size();
+        }
+
+        self.inner
+            .seek(SeekFrom::Current(0))
+            .unwrap_or_else(|_| self.inner.size())
+    }
+
+    fn write_inner(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.inner.size();
+        let end = self.write_position() + buf.len() as u64;
+        let reserved = end.saturating_sub(size);
+
+        self.filesystem
+            .reserve(reserved, 0)
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+
+        let written = self.inner.write(buf);
+
+        // Settle the reservation with what the file really grew by.
+        let grown = self.inner.size().saturating_sub(size);
+        if grown < reserved {
+            self.filesystem.release(reserved - grown, 0);
+        } else {
+            self.filesystem.charge(grown - reserved);
+        }
+
+        written
+    }
+}
+
+impl VirtualFile for File {
+    fn last_accessed(&self) -> u64 {
+        self.inner.last_accessed()
+    }
+
+    fn last_modified(&self) -> u64 {
+        self.inner.last_modified()
+    }
+
+    fn created_time(&self) -> u64 {
+        self.inner.created_time()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+
+    fn set_len(&mut self, new_size: u64) -> Result<()> {
+        let size = self.inner.size();
+
+        if new_size > size {
+            self.filesystem.reserve(new_size - size, 0)?;
+        }
+
+        match self.inner.set_len(new_size) {
+            Ok(()) if new_size < size => {
+                self.filesystem.release(size - new_size, 0);
+
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+            Err(error) => {
+                if new_size > size {
+                    self.filesystem.release(new_size - size, 0);
+                }
+
+                Err(error)
+            }
+        }
+    }
+
+    fn set_times(&mut self, accessed: Option<u64>, modified: Option<u64>) -> Result<()> {
+        self.inner.set_times(accessed, modified)
+    }
+
+    fn unlink(&mut self) -> Result<()> {
+        let size = self.inner.size();
+        let result = self.inner.unlink();
+
+        if result.is_ok() {
+            self.filesystem.release(size, 1);
+        }
+
+        self.filesystem.audit(AuditEvent::Unlink {
+            path: self.path.clone(),
+            result,
+        });
+
+        result
+    }
+
+    fn sync_to_disk(&self) -> Result<()> {
+        self.inner.sync_to_disk()
+    }
+
+    fn bytes_available(&self) -> Result<usize> {
+        self.inner.bytes_available()
+    }
+
+    fn get_fd(&self) -> Option<FileDescriptor> {
+        self.inner.get_fd()
+    }
+
+    fn poll_readiness(&self) -> Result<Readiness> {
+        self.inner.poll_readiness()
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+
+        self.filesystem.audit(AuditEvent::Read {
+            path: self.path.clone(),
+            result: match &result {
+                Ok(read) => Ok(*read),
+                Err(error) => Err(fs_error_of(error)),
+            },
+        });
+
+        result
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.write_inner(buf);
+
+        self.filesystem.audit(AuditEvent::Write {
+            path: self.path.clone(),
+            result: match &result {
+                Ok(written) => Ok(*written),
+                Err(error) => Err(fs_error_of(error)),
+            },
+        });
+
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(position)
+    }
+}
+
+/// Like `FsError::from` but without consuming the error.
+fn fs_error_of(error: &io::Error) -> FsError {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<FsError>())
+        .copied()
+        .unwrap_or_else(|| FsError::from(io::Error::from(error.kind())))
+}
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod test_quota_fs {
+    use super::{AuditEvent, FileSystem, Quota, Usage};
+    use crate::{mem_fs, FileSystem as FS, FsError};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    macro_rules! path {
+        ($path:expr) => {
+            std::path::Path::new($path)
+        };
+    }
+
+    fn quota_fs(quota: Quota) -> FileSystem {
+        FileSystem::new(Arc::new(mem_fs::FileSystem::default()), quota)
+    }
+
+    #[test]
+    fn test_byte_quota() {
+        let fs = quota_fs(Quota {
+            max_bytes: Some(10),
+            max_inodes: None,
+        });
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open(path!("/file"))
+            .unwrap();
+        file.write_all(b"0123456789").unwrap();
+        assert_eq!(
+            fs.usage(),
+            Ok(Usage {
+                bytes: 10,
+                inodes: 1
+            })
+        );
+
+        let error = file.write(b"more").unwrap_err();
+        assert_eq!(FsError::from(error), FsError::QuotaExceeded);
+        assert_eq!(file.set_len(11), Err(FsError::QuotaExceeded));
+
+        // Shrinking, and removing, frees space.
+        file.set_len(4).unwrap();
+        assert_eq!(fs.usage().unwrap().bytes, 4);
+        drop(file);
+        fs.remove_file(path!("/file")).unwrap();
+        assert_eq!(fs.usage(), Ok(Usage::default()));
+    }
+
+    #[test]
+    fn test_inode_quota() {
+        let fs = quota_fs(Quota {
+            max_bytes: None,
+            max_inodes: Some(2),
+        });
+
+        fs.create_dir(path!("/dir")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/dir/file"))
+            .unwrap();
+        assert_eq!(fs.create_dir(path!("/other")), Err(FsError::QuotaExceeded));
+        assert_eq!(
+            fs.new_open_options()
+                .write(true)
+                .create(true)
+                .open(path!("/other"))
+                .map(|_| ()),
+            Err(FsError::QuotaExceeded)
+        );
+
+        // Opening an existing file doesn't create an inode.
+        assert!(fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open(path!("/dir/file"))
+            .is_ok());
+
+        fs.remove_file(path!("/dir/file")).unwrap();
+        assert_eq!(fs.symlink(path!("/dir"), path!("/link")), Ok(()));
+        assert_eq!(fs.usage().unwrap().inodes, 2);
+    }
+
+    #[test]
+    fn test_existing_files_are_accounted_for() {
+        let inner = mem_fs::FileSystem::default();
+        inner.create_dir(path!("/dir")).unwrap();
+        inner
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(path!("/dir/file"))
+            .unwrap()
+            .write_all(b"0123456789")
+            .unwrap();
+        inner.symlink(path!("dir"), path!("/link")).unwrap();
+
+        let fs = FileSystem::new(
+            Arc::new(inner),
+            Quota {
+                max_bytes: Some(10),
+                max_inodes: None,
+            },
+        );
+        assert_eq!(
+            fs.usage(),
+            Ok(Usage {
+                bytes: 10,
+                inodes: 3
+            })
+        );
+
+        // Removing a file that was there before doesn't make room over
+        // the quota.
+        fs.remove_file(path!("/dir/file")).unwrap();
+        fs.remove_dir(path!("/dir")).unwrap();
+        assert_eq!(
+            fs.usage(),
+            Ok(Usage {
+                bytes: 0,
+                inodes: 1
+            })
+        );
+
+        let mut file = fs
+            .new_open_options()
+            .write(true)
+            .create(true)
+            .open(path!("/file"))
+            .unwrap();
+        file.write_all(b"0123456789").unwrap();
+        assert_eq!(
+            FsError::from(file.write(b"more").unwrap_err()),
+            FsError::QuotaExceeded
+        );
+    }
+
+    #[test]
+    fn test_audit() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let fs = quota_fs(Quota::default()).with_audit({
+            let events = events.clone();
+
+            Arc::new(move |event: &AuditEvent| events.lock().unwrap().push(event.clone()))
+        });
+
+        fs.new_open_options()
+            .write(true)
+            .create(true)
+            .open(path!("/a"))
+            .unwrap()
+            .write_all(b"hello")
+            .unwrap();
+        fs.rename(path!("/a"), path!("/b")).unwrap();
+        let error = fs.remove_file(path!("/a")).unwrap_err();
+        fs.create_dir(path!("/dir")).unwrap();
+        fs.remove_dir(path!("/dir")).unwrap();
+        let dir_error = fs.remove_dir(path!("/dir")).unwrap_err();
+
+        let path = |path: &str| PathBuf::from(path);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                AuditEvent::Open {
+                    path: path("/a"),
+                    read: false,
+                    write: true,
+                    create: true,
+                    result: Ok(()),
+                },
+                AuditEvent::Write {
+                    path: path("/a"),
+                    result: Ok(5),
+                },
+                AuditEvent::Rename {
+                    from: path("/a"),
+                    to: path("/b"),
+                    result: Ok(()),
+                },
+                AuditEvent::Unlink {
+                    path: path("/a"),
+                    result: Err(error),
+                },
+                AuditEvent::Unlink {
+                    path: path("/dir"),
+                    result: Ok(()),
+                },
+                AuditEvent::Unlink {
+                    path: path("/dir"),
+                    result: Err(dir_error),
+                },
+            ]
+        );
+    }
+}
@@ -29,3 +29,39 @@ pub const __WASI_SIGWINCH: u8 = 27;
 pub const __WASI_SIGPOLL: u8 = 28;
 pub const __WASI_SIGPWR: u8 = 29;
 pub const __WASI_SIGSYS: u8 = 30;
+
+pub fn wasi_signal_to_name(sig: __wasi_signal_t) -> &'static str {
+    match sig {
+        __WASI_SIGHUP => "SIGHUP",
+        __WASI_SIGINT => "SIGINT",
+        __WASI_SIGQUIT => "SIGQUIT",
+        __WASI_SIGILL => "SIGILL",
+        __WASI_SIGTRAP => "SIGTRAP",
+        __WASI_SIGABRT => "SIGABRT",
+        __WASI_SIGBUS => "SIGBUS",
+        __WASI_SIGFPE => "SIGFPE",
+        __WASI_SIGKILL => "SIGKILL",
+        __WASI_SIGUSR1 => "SIGUSR1",
+        __WASI_SIGSEGV => "SIGSEGV",
+        __WASI_SIGUSR2 => "SIGUSR2",
+        __WASI_SIGPIPE => "SIGPIPE",
+        __WASI_SIGALRM => "SIGALRM",
+        __WASI_SIGTERM => "SIGTERM",
+        __WASI_SIGCHLD => "SIGCHLD",
+        __WASI_SIGCONT => "SIGCONT",
+        __WASI_SIGSTOP => "SIGSTOP",
+        __WASI_SIGTSTP => "SIGTSTP",
+        __WASI_SIGTTIN => "SIGTTIN",
+        __WASI_SIGTTOU => "SIGTTOU",
+        __WASI_SIGURG => "SIGURG",
+        __WASI_SIGXCPU => "SIGXCPU",
+        __WASI_SIGXFSZ => "SIGXFSZ",
+        __WASI_SIGVTALRM => "SIGVTALRM",
+        __WASI_SIGPROF => "SIGPROF",
+        __WASI_SIGWINCH => "SIGWINCH",
+        __WASI_SIGPOLL => "SIGPOLL",
+        __WASI_SIGPWR => "SIGPWR",
+        __WASI_SIGSYS => "SIGSYS",
+        _ => "Invalid",
+    }
+}
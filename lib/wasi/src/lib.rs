@@ -43,8 +43,9 @@ mod utils;
 use crate::syscalls::*;
 
 pub use crate::state::{
-    Fd, Pipe, Stderr, Stdin, Stdout, WasiFs, WasiState, WasiStateBuilder, WasiStateCreationError,
-    ALL_RIGHTS, VIRTUAL_ROOT_FD,
+    Fd, MockClock, Pipe, SeededRandom, SignalDisposition, SignalHandler, Stderr, Stdin, Stdout,
+    SystemClock, SystemRandom, WasiClock, WasiFs, WasiRandom, WasiSignals, WasiState,
+    WasiStateBuilder, WasiStateCreationError, ALL_RIGHTS, VIRTUAL_ROOT_FD,
 };
 pub use crate::syscalls::types;
 pub use crate::utils::{get_wasi_version, get_wasi_versions, is_wasi_module, WasiVersion};
@@ -68,6 +69,8 @@ use std::sync::{Arc, Mutex, MutexGuard};
 pub enum WasiError {
     #[error("WASI exited with code: {0}")]
     Exit(syscalls::types::__wasi_exitcode_t),
+    #[error("WASI was terminated by signal: {0}")]
+    Signal(syscalls::types::__wasi_signal_t),
     #[error("The WASI version could not be determined")]
     UnknownWasiVersion,
 }
@@ -132,6 +135,30 @@ impl WasiEnv {
         self.state.lock().unwrap()
     }
 
+    /// Mount a file system while the instance is running, see
+    /// [`WasiStateBuilder::mount`].
+    ///
+    /// Returns the file descriptor of the new preopened directory. The
+    /// WASI module usually only looks up the preopened directories
+    /// when it starts, but it can reach the new one through the
+    /// virtual root, with an absolute path.
+    pub fn mount<F>(
+        &self,
+        fs: Box<dyn wasmer_vfs::FileSystem>,
+        inner: F,
+    ) -> Result<syscalls::types::__wasi_fd_t, WasiStateCreationError>
+    where
+        F: Fn(&mut state::PreopenDirBuilder) -> &mut state::PreopenDirBuilder,
+    {
+        let mut pdb = state::PreopenDirBuilder::new();
+        let po_dir = inner(&mut pdb).build()?;
+
+        self.state()
+            .fs
+            .mount(Arc::from(fs), &po_dir)
+            .map_err(WasiStateCreationError::WasiFsCreationError)
+    }
+
     /// Get a reference to the memory
     pub fn memory(&self) -> &Memory {
         self.memory_ref()
@@ -1,9 +1,15 @@
 //! Builder system for configuring a [`WasiState`] and creating it.
 
-use crate::state::{default_fs_backing, WasiFs, WasiState};
-use crate::syscalls::types::{__WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO};
+use crate::state::{
+    default_clock, default_fs_backing, default_random, SignalHandler, WasiClock, WasiFs,
+    WasiRandom, WasiSignals, WasiState,
+};
+use crate::syscalls::types::{
+    __wasi_signal_t, __WASI_STDERR_FILENO, __WASI_STDIN_FILENO, __WASI_STDOUT_FILENO,
+};
 use crate::WasiEnv;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 use wasmer_vfs::{FsError, VirtualFile};
 
@@ -39,12 +45,16 @@ pub struct WasiStateBuilder {
     envs: Vec<(Vec<u8>, Vec<u8>)>,
     preopens: Vec<PreopenedDir>,
     vfs_preopens: Vec<String>,
+    mounts: Vec<(Arc<dyn wasmer_vfs::FileSystem>, PreopenedDir)>,
     #[allow(clippy::type_complexity)]
     setup_fs_fn: Option<Box<dyn Fn(&mut WasiFs) -> Result<(), String> + Send>>,
     stdout_override: Option<Box<dyn VirtualFile>>,
     stderr_override: Option<Box<dyn VirtualFile>>,
     stdin_override: Option<Box<dyn VirtualFile>>,
     fs_override: Option<Box<dyn wasmer_vfs::FileSystem>>,
+    clock_override: Option<Box<dyn WasiClock>>,
+    random_override: Option<Box<dyn WasiRandom>>,
+    signal_handlers: Vec<(__wasi_signal_t, SignalHandler)>,
 }
 
 impl std::fmt::Debug for WasiStateBuilder {
@@ -54,10 +64,21 @@ impl std::fmt::Debug for WasiStateBuilder {
             .field("args", &self.args)
             .field("envs", &self.envs)
             .field("preopens", &self.preopens)
+            .field("mounts", &self.mounts)
             .field("setup_fs_fn exists", &self.setup_fs_fn.is_some())
             .field("stdout_override exists", &self.stdout_override.is_some())
             .field("stderr_override exists", &self.stderr_override.is_some())
             .field("stdin_override exists", &self.stdin_override.is_some())
+            .field("clock_override", &self.clock_override)
+            .field("random_override", &self.random_override)
+            .field(
+                "signal_handlers",
+                &self
+                    .signal_handlers
+                    .iter()
+                    .map(|(sig, _)| sig)
+                    .collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
@@ -204,6 +225,37 @@ impl WasiStateBuilder {
         Ok(self)
     }
 
+    /// Mount a file system and preopen one of its directories.
+    ///
+    /// The directory of `fs` set with
+    /// [`PreopenDirBuilder::directory`] is mounted in the virtual
+    /// root, at the path set with [`PreopenDirBuilder::alias`], with
+    /// its own permissions. It lets a WASI module see several file
+    /// systems at once, e.g. a host directory next to an in-memory
+    /// scratch space. The mounted file system can only be reached
+    /// through its own preopened directory: it doesn't shadow the
+    /// directory at the same path in the file system set with
+    /// [`Self::set_fs`], which the other preopened directories still
+    /// see.
+    ///
+    /// File systems can also be mounted while the instance is running
+    /// with [`WasiEnv::mount`].
+    pub fn mount<F>(
+        &mut self,
+        fs: Box<dyn wasmer_vfs::FileSystem>,
+        inner: F,
+    ) -> Result<&mut Self, WasiStateCreationError>
+    where
+        F: Fn(&mut PreopenDirBuilder) -> &mut PreopenDirBuilder,
+    {
+        let mut pdb = PreopenDirBuilder::new();
+        let po_dir = inner(&mut pdb).build()?;
+
+        self.mounts.push((Arc::from(fs), po_dir));
+
+        Ok(self)
+    }
+
     /// Preopen a directory.
     ///
     /// This opens the given directory at the virtual root, `/`, and allows
@@ -308,6 +360,39 @@ impl WasiStateBuilder {
         self
     }
 
+    /// Sets the clocks to be used with this WASI instance.
+    ///
+    /// By default, the clocks of the host are used. A
+    /// [`MockClock`][crate::MockClock] makes the time deterministic.
+    pub fn set_clock(&mut self, clock: Box<dyn WasiClock>) -> &mut Self {
+        self.clock_override = Some(clock);
+
+        self
+    }
+
+    /// Sets the source of randomness to be used with this WASI instance.
+    ///
+    /// By default, the random number generator of the operating system
+    /// is used. A [`SeededRandom`][crate::SeededRandom] makes the
+    /// random bytes deterministic.
+    pub fn set_random(&mut self, random: Box<dyn WasiRandom>) -> &mut Self {
+        self.random_override = Some(random);
+
+        self
+    }
+
+    /// Registers a host callback for a signal raised by the guest with
+    /// `proc_raise`.
+    ///
+    /// The callback decides whether the instance is terminated or keeps
+    /// running. Signals without a callback get their
+    /// [default disposition][crate::SignalDisposition::default_for].
+    pub fn signal_handler(&mut self, sig: __wasi_signal_t, handler: SignalHandler) -> &mut Self {
+        self.signal_handlers.push((sig, handler));
+
+        self
+    }
+
     /// Configure the WASI filesystem before running.
     // TODO: improve ergonomics on this function
     pub fn setup_fs(
@@ -331,6 +416,9 @@ impl WasiStateBuilder {
     /// reset to their defaults:
     ///
     /// * [Self::set_fs],
+    /// * [Self::set_clock],
+    /// * [Self::set_random],
+    /// * [Self::signal_handler],
     /// * [Self::stdin],
     /// * [Self::stdout],
     /// * [Self::stderr].
@@ -410,6 +498,12 @@ impl WasiStateBuilder {
         let mut wasi_fs = WasiFs::new_with_preopen(&self.preopens, &self.vfs_preopens, fs_backing)
             .map_err(WasiStateCreationError::WasiFsCreationError)?;
 
+        for (fs, preopen) in self.mounts.iter() {
+            wasi_fs
+                .mount(fs.clone(), preopen)
+                .map_err(WasiStateCreationError::WasiFsCreationError)?;
+        }
+
         // set up the file system, overriding base files and calling the setup function
         if let Some(stdin_override) = self.stdin_override.take() {
             wasi_fs
@@ -448,6 +542,20 @@ impl WasiStateBuilder {
                     env
                 })
                 .collect(),
+            clock: self
+                .clock_override
+                .take()
+                .map(Arc::from)
+                .unwrap_or_else(default_clock),
+            random: self.random_override.take().unwrap_or_else(default_random),
+            signals: {
+                let mut signals = WasiSignals::default();
+                for (sig, handler) in self.signal_handlers.drain(..) {
+                    signals.set_handler(sig, handler);
+                }
+
+                signals
+            },
         })
     }
 
@@ -479,7 +587,7 @@ pub struct PreopenDirBuilder {
 }
 
 /// The built version of `PreopenDirBuilder`
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub(crate) struct PreopenedDir {
     pub(crate) path: PathBuf,
     pub(crate) alias: Option<String>,
@@ -632,4 +740,121 @@ mod test {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn deterministic_clock_and_random() {
+        use crate::state::{MockClock, SeededRandom};
+        use crate::syscalls::types::__WASI_CLOCK_REALTIME;
+
+        let clock = MockClock::new(42);
+        let mut state = create_wasi_state("test_prog")
+            .set_clock(Box::new(clock.clone()))
+            .set_random(Box::new(SeededRandom::new(7)))
+            .build()
+            .unwrap();
+
+        assert_eq!(state.clock.time(__WASI_CLOCK_REALTIME, 0), Ok(42));
+        clock.advance(8);
+        assert_eq!(state.clock.time(__WASI_CLOCK_REALTIME, 0), Ok(50));
+
+        let mut bytes = [0; 11];
+        let mut expected = [0; 11];
+        state.random.fill(&mut bytes).unwrap();
+        SeededRandom::new(7).fill(&mut expected).unwrap();
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn signal_handlers() {
+        use crate::state::SignalDisposition;
+        use crate::syscalls::types::{__WASI_SIGABRT, __WASI_SIGCHLD, __WASI_SIGUSR1};
+
+        let mut state = create_wasi_state("test_prog")
+            .signal_handler(__WASI_SIGUSR1, Box::new(|_| SignalDisposition::Ignore))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            state.signals.raise(__WASI_SIGUSR1),
+            SignalDisposition::Ignore
+        );
+        assert_eq!(
+            state.signals.raise(__WASI_SIGABRT),
+            SignalDisposition::Terminate
+        );
+        assert_eq!(
+            state.signals.raise(__WASI_SIGCHLD),
+            SignalDisposition::Ignore
+        );
+    }
+
+    #[test]
+    fn signal_handlers_can_use_the_state() {
+        use crate::state::SignalDisposition;
+        use crate::syscalls::proc_raise;
+        use crate::syscalls::types::{__WASI_ESUCCESS, __WASI_SIGTERM, __WASI_SIGUSR1};
+        use crate::WasiError;
+
+        let env = create_wasi_state("test_prog").finalize().unwrap();
+        let handler_env = env.clone();
+        env.state().signals.set_handler(
+            __WASI_SIGUSR1,
+            Box::new(move |_| {
+                if handler_env.state().args.is_empty() {
+                    SignalDisposition::Terminate
+                } else {
+                    SignalDisposition::Ignore
+                }
+            }),
+        );
+
+        assert_eq!(proc_raise(&env, __WASI_SIGUSR1).ok(), Some(__WASI_ESUCCESS));
+        // the handler is still registered
+        assert_eq!(proc_raise(&env, __WASI_SIGUSR1).ok(), Some(__WASI_ESUCCESS));
+        assert!(matches!(
+            proc_raise(&env, __WASI_SIGTERM),
+            Err(WasiError::Signal(_))
+        ));
+    }
+
+    #[cfg(feature = "host-fs")]
+    #[test]
+    fn mounts() {
+        use crate::state::Kind;
+        use wasmer_vfs::host_fs;
+
+        let mut state = create_wasi_state("test_prog")
+            .mount(Box::new(host_fs::FileSystem::default()), |p| {
+                p.directory("src").alias("/sources").read(true)
+            })
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let fd = *state.fs.preopen_fds.last().unwrap();
+        let inode = state.fs.get_inode_at_path(fd, "lib.rs", true).unwrap();
+        assert!(matches!(state.fs.inodes[inode].kind, Kind::File { .. }));
+
+        let env = WasiEnv::new(state);
+        let fd = env
+            .mount(Box::new(host_fs::FileSystem::default()), |p| {
+                p.directory("src/state").alias("state").read(true)
+            })
+            .unwrap();
+        {
+            let state = env.state();
+            assert!(state.fs.preopen_fds.contains(&fd));
+            assert!(matches!(
+                state.fs.inodes[state.fs.get_fd(fd).unwrap().inode].kind,
+                Kind::Dir { ref path, .. } if *path == crate::state::mounts::mount_point("state")
+            ));
+        }
+        assert!(
+            env.mount(Box::new(host_fs::FileSystem::default()), |p| {
+                p.directory("src").alias("state").read(true)
+            })
+            .is_err(),
+            "a path can't be mounted twice"
+        );
+    }
 }
@@ -0,0 +1,148 @@
+//! Clocks used by the WASI syscalls, to get the time and to wait.
+
+use crate::syscalls::{platform_clock_res, platform_clock_time, types::*};
+use std::fmt;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// The clocks of a WASI instance, used by `clock_res_get`,
+/// `clock_time_get` and `poll_oneoff`.
+///
+/// Implement it to control the time seen by the guest, e.g. to run it
+/// reproducibly; see [`MockClock`].
+pub trait WasiClock: fmt::Debug + Send + Sync + 'static {
+    /// Returns the resolution of the clock `clock_id`, in nanoseconds.
+    fn resolution(&self, clock_id: __wasi_clockid_t) -> Result<__wasi_timestamp_t, __wasi_errno_t>;
+
+    /// Returns the time of the clock `clock_id`, in nanoseconds. The
+    /// `precision` is the maximum lag that the guest accepts.
+    fn time(
+        &self,
+        clock_id: __wasi_clockid_t,
+        precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t>;
+
+    /// Waits for `duration` nanoseconds, when `poll_oneoff` has
+    /// nothing to report yet.
+    ///
+    /// A clock which can't wait returns `__WASI_EAGAIN`: `poll_oneoff`
+    /// then reports its clock subscription which is due first right
+    /// away, or fails with `__WASI_EAGAIN` if it has none.
+    fn sleep(&self, duration: __wasi_timestamp_t) -> Result<(), __wasi_errno_t>;
+}
+
+/// The clocks of the host, this is the default.
+#[derive(Debug, Default, Clone)]
+pub struct SystemClock;
+
+impl WasiClock for SystemClock {
+    fn resolution(&self, clock_id: __wasi_clockid_t) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        platform_clock_res(clock_id)
+    }
+
+    fn time(
+        &self,
+        clock_id: __wasi_clockid_t,
+        precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        platform_clock_time(clock_id, precision)
+    }
+
+    #[cfg(not(feature = "js"))]
+    fn sleep(&self, duration: __wasi_timestamp_t) -> Result<(), __wasi_errno_t> {
+        std::thread::sleep(std::time::Duration::from_nanos(duration));
+
+        Ok(())
+    }
+
+    /// The main thread of a browser can't be blocked, so it fails
+    /// with `__WASI_EAGAIN`: `poll_oneoff` doesn't wait for its clock
+    /// subscriptions, and fails when it waits for files which aren't
+    /// ready.
+    #[cfg(feature = "js")]
+    fn sleep(&self, _duration: __wasi_timestamp_t) -> Result<(), __wasi_errno_t> {
+        Err(__WASI_EAGAIN)
+    }
+}
+
+/// A deterministic clock, for tests and replays.
+///
+/// All the clocks read the same time, which only moves forward when the
+/// guest sleeps, or when the host calls [`MockClock::advance`] or
+/// [`MockClock::set`]. Clones share their time, so the host can keep one
+/// to drive the clock of the guest.
+///
+/// Sleeping doesn't block: while `poll_oneoff` waits for a file which
+/// isn't ready, it advances the time and checks the file again in a
+/// loop. The thread yields at every iteration so that other threads
+/// can make the file ready, but the loop keeps a core busy, and never
+/// ends if nothing else feeds the file.
+///
+/// ```
+/// # use wasmer_wasi::{MockClock, WasiClock, types::__WASI_CLOCK_MONOTONIC};
+/// let clock = MockClock::new(1_000);
+/// assert_eq!(clock.time(__WASI_CLOCK_MONOTONIC, 0), Ok(1_000));
+///
+/// clock.sleep(500).unwrap();
+/// assert_eq!(clock.time(__WASI_CLOCK_MONOTONIC, 0), Ok(1_500));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Creates a clock whose time is `now`, in nanoseconds.
+    pub fn new(now: __wasi_timestamp_t) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(now)),
+        }
+    }
+
+    /// Sets the time, in nanoseconds.
+    pub fn set(&self, now: __wasi_timestamp_t) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// Moves the time forward by `duration` nanoseconds.
+    pub fn advance(&self, duration: __wasi_timestamp_t) {
+        self.now.fetch_add(duration, Ordering::SeqCst);
+    }
+}
+
+impl WasiClock for MockClock {
+    fn resolution(&self, clock_id: __wasi_clockid_t) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        match clock_id {
+            __WASI_CLOCK_REALTIME
+            | __WASI_CLOCK_MONOTONIC
+            | __WASI_CLOCK_PROCESS_CPUTIME_ID
+            | __WASI_CLOCK_THREAD_CPUTIME_ID => Ok(1),
+            _ => Err(__WASI_EINVAL),
+        }
+    }
+
+    fn time(
+        &self,
+        clock_id: __wasi_clockid_t,
+        _precision: __wasi_timestamp_t,
+    ) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+        self.resolution(clock_id)?;
+
+        Ok(self.now.load(Ordering::SeqCst))
+    }
+
+    fn sleep(&self, duration: __wasi_timestamp_t) -> Result<(), __wasi_errno_t> {
+        self.advance(duration);
+        // let the other threads feed the files being polled
+        std::thread::yield_now();
+
+        Ok(())
+    }
+}
+
+/// Returns the default clock
+pub(crate) fn default_clock() -> Arc<dyn WasiClock> {
+    Arc::new(SystemClock)
+}
@@ -16,9 +16,16 @@
 #![allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
 
 mod builder;
+mod clock;
+mod mounts;
+mod random;
+mod signal;
 mod types;
 
 pub use self::builder::*;
+pub use self::clock::*;
+pub use self::random::*;
+pub use self::signal::*;
 pub use self::types::*;
 use crate::syscalls::types::*;
 use generational_arena::Arena;
@@ -30,7 +37,8 @@ use std::{
     borrow::Borrow,
     cell::Cell,
     io::Write,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
 };
 use tracing::debug;
 
@@ -112,7 +120,9 @@ pub enum Kind {
         base_po_dir: __wasi_fd_t,
         /// The path to the symlink from the `base_po_dir`
         path_to_symlink: PathBuf,
-        /// the value of the symlink as a relative path
+        /// the value of the symlink, as read by `path_readlink`: either
+        /// relative to the directory containing the symlink, or an
+        /// absolute path that must be in a preopened directory
         relative_path: PathBuf,
     },
     Buffer {
@@ -213,6 +223,12 @@ impl FileSystem for FallbackFileSystem {
     fn symlink_metadata(&self, _path: &Path) -> Result<wasmer_vfs::Metadata, FsError> {
         Self::fail();
     }
+    fn symlink(&self, _original: &Path, _link: &Path) -> Result<(), FsError> {
+        Self::fail();
+    }
+    fn read_link(&self, _path: &Path) -> Result<PathBuf, FsError> {
+        Self::fail();
+    }
     fn remove_file(&self, _path: &Path) -> Result<(), FsError> {
         Self::fail();
     }
@@ -274,127 +290,141 @@ impl WasiFs {
             wasi_fs.preopen_fds.push(fd);
         }
 
-        for PreopenedDir {
+        for preopen in preopens {
+            wasi_fs.create_preopen(root_inode, preopen, preopen.path.clone())?;
+        }
+
+        Ok(wasi_fs)
+    }
+
+    /// Creates the inode and the file descriptor of a preopened
+    /// directory, and adds it to the virtual root. `path_in_fs` is the
+    /// path of the directory in `fs_backing`.
+    fn create_preopen(
+        &mut self,
+        root_inode: Inode,
+        preopen: &PreopenedDir,
+        path_in_fs: PathBuf,
+    ) -> Result<__wasi_fd_t, String> {
+        let PreopenedDir {
             path,
             alias,
             read,
             write,
             create,
-        } in preopens
-        {
-            debug!(
-                "Attempting to preopen {} with alias {:?}",
-                &path.to_string_lossy(),
-                &alias
-            );
-            let cur_dir_metadata = wasi_fs.fs_backing.metadata(path).map_err(|e| {
-                format!(
-                    "Could not get metadata for file {:?}: {}",
-                    path,
-                    e.to_string()
-                )
-            })?;
-
-            let kind = if cur_dir_metadata.is_dir() {
-                Kind::Dir {
-                    parent: Some(root_inode),
-                    path: path.clone(),
-                    entries: Default::default(),
-                }
-            } else {
-                return Err(format!(
-                    "WASI only supports pre-opened directories right now; found \"{}\"",
-                    &path.to_string_lossy()
-                ));
-            };
+        } = preopen;
 
-            let rights = {
-                // TODO: review tell' and fd_readwrite
-                let mut rights =
-                    __WASI_RIGHT_FD_ADVISE | __WASI_RIGHT_FD_TELL | __WASI_RIGHT_FD_SEEK;
-                if *read {
-                    rights |= __WASI_RIGHT_FD_READ
-                        | __WASI_RIGHT_PATH_OPEN
-                        | __WASI_RIGHT_FD_READDIR
-                        | __WASI_RIGHT_PATH_READLINK
-                        | __WASI_RIGHT_PATH_FILESTAT_GET
-                        | __WASI_RIGHT_FD_FILESTAT_GET
-                        | __WASI_RIGHT_PATH_LINK_SOURCE
-                        | __WASI_RIGHT_PATH_RENAME_SOURCE
-                        | __WASI_RIGHT_POLL_FD_READWRITE
-                        | __WASI_RIGHT_SOCK_SHUTDOWN;
-                }
-                if *write {
-                    rights |= __WASI_RIGHT_FD_DATASYNC
-                        | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
-                        | __WASI_RIGHT_FD_WRITE
-                        | __WASI_RIGHT_FD_SYNC
-                        | __WASI_RIGHT_FD_ALLOCATE
-                        | __WASI_RIGHT_PATH_OPEN
-                        | __WASI_RIGHT_PATH_RENAME_TARGET
-                        | __WASI_RIGHT_PATH_FILESTAT_SET_SIZE
-                        | __WASI_RIGHT_PATH_FILESTAT_SET_TIMES
-                        | __WASI_RIGHT_FD_FILESTAT_SET_SIZE
-                        | __WASI_RIGHT_FD_FILESTAT_SET_TIMES
-                        | __WASI_RIGHT_PATH_REMOVE_DIRECTORY
-                        | __WASI_RIGHT_PATH_UNLINK_FILE
-                        | __WASI_RIGHT_POLL_FD_READWRITE
-                        | __WASI_RIGHT_SOCK_SHUTDOWN;
-                }
-                if *create {
-                    rights |= __WASI_RIGHT_PATH_CREATE_DIRECTORY
-                        | __WASI_RIGHT_PATH_CREATE_FILE
-                        | __WASI_RIGHT_PATH_LINK_TARGET
-                        | __WASI_RIGHT_PATH_OPEN
-                        | __WASI_RIGHT_PATH_RENAME_TARGET;
-                }
+        debug!(
+            "Attempting to preopen {} with alias {:?}",
+            &path.to_string_lossy(),
+            &alias
+        );
+        let cur_dir_metadata = self.fs_backing.metadata(&path_in_fs).map_err(|e| {
+            format!(
+                "Could not get metadata for file {:?}: {}",
+                path,
+                e.to_string()
+            )
+        })?;
+
+        let kind = if cur_dir_metadata.is_dir() {
+            Kind::Dir {
+                parent: Some(root_inode),
+                path: path_in_fs,
+                entries: Default::default(),
+            }
+        } else {
+            return Err(format!(
+                "WASI only supports pre-opened directories right now; found \"{}\"",
+                &path.to_string_lossy()
+            ));
+        };
 
-                rights
-            };
-            let inode = if let Some(alias) = &alias {
-                wasi_fs.create_inode(kind, true, alias.clone())
-            } else {
-                wasi_fs.create_inode(kind, true, path.to_string_lossy().into_owned())
+        let rights = {
+            // TODO: review tell' and fd_readwrite
+            let mut rights = __WASI_RIGHT_FD_ADVISE | __WASI_RIGHT_FD_TELL | __WASI_RIGHT_FD_SEEK;
+            if *read {
+                rights |= __WASI_RIGHT_FD_READ
+                    | __WASI_RIGHT_PATH_OPEN
+                    | __WASI_RIGHT_FD_READDIR
+                    | __WASI_RIGHT_PATH_READLINK
+                    | __WASI_RIGHT_PATH_FILESTAT_GET
+                    | __WASI_RIGHT_FD_FILESTAT_GET
+                    | __WASI_RIGHT_PATH_LINK_SOURCE
+                    | __WASI_RIGHT_PATH_RENAME_SOURCE
+                    | __WASI_RIGHT_POLL_FD_READWRITE
+                    | __WASI_RIGHT_SOCK_SHUTDOWN;
             }
-            .map_err(|e| {
-                format!(
-                    "Failed to create inode for preopened dir: WASI error code: {}",
-                    e
-                )
-            })?;
-            let fd_flags = {
-                let mut fd_flags = 0;
-                if *read {
-                    fd_flags |= Fd::READ;
-                }
-                if *write {
-                    // TODO: introduce API for finer grained control
-                    fd_flags |= Fd::WRITE | Fd::APPEND | Fd::TRUNCATE;
-                }
-                if *create {
-                    fd_flags |= Fd::CREATE;
-                }
-                fd_flags
+            if *write {
+                rights |= __WASI_RIGHT_FD_DATASYNC
+                    | __WASI_RIGHT_FD_FDSTAT_SET_FLAGS
+                    | __WASI_RIGHT_FD_WRITE
+                    | __WASI_RIGHT_FD_SYNC
+                    | __WASI_RIGHT_FD_ALLOCATE
+                    | __WASI_RIGHT_PATH_OPEN
+                    | __WASI_RIGHT_PATH_RENAME_TARGET
+                    | __WASI_RIGHT_PATH_FILESTAT_SET_SIZE
+                    | __WASI_RIGHT_PATH_FILESTAT_SET_TIMES
+                    | __WASI_RIGHT_FD_FILESTAT_SET_SIZE
+                    | __WASI_RIGHT_FD_FILESTAT_SET_TIMES
+                    | __WASI_RIGHT_PATH_REMOVE_DIRECTORY
+                    | __WASI_RIGHT_PATH_UNLINK_FILE
+                    | __WASI_RIGHT_POLL_FD_READWRITE
+                    | __WASI_RIGHT_SOCK_SHUTDOWN;
+            }
+            if *create {
+                rights |= __WASI_RIGHT_PATH_CREATE_DIRECTORY
+                    | __WASI_RIGHT_PATH_CREATE_FILE
+                    | __WASI_RIGHT_PATH_LINK_TARGET
+                    | __WASI_RIGHT_PATH_OPEN
+                    | __WASI_RIGHT_PATH_RENAME_TARGET;
+            }
+
+            rights
+        };
+        let inode = if let Some(alias) = &alias {
+            self.create_inode(kind, true, alias.clone())
+        } else {
+            self.create_inode(kind, true, path.to_string_lossy().into_owned())
+        }
+        .map_err(|e| {
+            format!(
+                "Failed to create inode for preopened dir: WASI error code: {}",
+                e
+            )
+        })?;
+        let fd_flags = {
+            let mut fd_flags = 0;
+            if *read {
+                fd_flags |= Fd::READ;
+            }
+            if *write {
+                // TODO: introduce API for finer grained control
+                fd_flags |= Fd::WRITE | Fd::APPEND | Fd::TRUNCATE;
+            }
+            if *create {
+                fd_flags |= Fd::CREATE;
+            }
+            fd_flags
+        };
+        let fd = self
+            .create_fd(rights, rights, 0, fd_flags, inode)
+            .map_err(|e| format!("Could not open fd for file {:?}: {}", path, e))?;
+        if let Kind::Root { entries } = &mut self.inodes[root_inode].kind {
+            let key = if let Some(alias) = &alias {
+                alias.clone()
+            } else {
+                path.to_string_lossy().into_owned()
             };
-            let fd = wasi_fs
-                .create_fd(rights, rights, 0, fd_flags, inode)
-                .map_err(|e| format!("Could not open fd for file {:?}: {}", path, e))?;
-            if let Kind::Root { entries } = &mut wasi_fs.inodes[root_inode].kind {
-                let key = if let Some(alias) = &alias {
-                    alias.clone()
-                } else {
-                    path.to_string_lossy().into_owned()
-                };
-                let existing_entry = entries.insert(key.clone(), inode);
-                if existing_entry.is_some() {
-                    return Err(format!("Found duplicate entry for alias `{}`", key));
-                }
-                assert!(existing_entry.is_none())
+            let existing_entry = entries.insert(key.clone(), inode);
+            if existing_entry.is_some() {
+                return Err(format!("Found duplicate entry for alias `{}`", key));
             }
-            wasi_fs.preopen_fds.push(fd);
+            assert!(existing_entry.is_none())
         }
+        self.preopen_fds.push(fd);
 
-        Ok(wasi_fs)
+        Ok(fd)
     }
 
     /// Private helper function to init the filesystem, called in `new` and
@@ -447,6 +477,75 @@ impl WasiFs {
         Ok((wasi_fs, root_inode))
     }
 
+    /// Mounts the directory `preopen.path` of `fs` in the virtual
+    /// root, at `/<preopen.alias>`, and preopens it with the rights of
+    /// `preopen`. It can be called while the instance is running.
+    ///
+    /// The first mount wraps `fs_backing` so that the mount points
+    /// live in their own namespace, next to the paths of the previous
+    /// `fs_backing`: a mount only shadows the entry of the virtual root
+    /// with the same name, never a directory of the previous
+    /// `fs_backing`, so it can only be reached through its own
+    /// preopened directory, with its own rights.
+    pub(crate) fn mount(
+        &mut self,
+        fs: Arc<dyn FileSystem>,
+        preopen: &PreopenedDir,
+    ) -> Result<__wasi_fd_t, String> {
+        let name = preopen
+            .alias
+            .clone()
+            .unwrap_or_else(|| preopen.path.to_string_lossy().into_owned());
+        let name = name.trim_matches('/');
+        if name.is_empty() {
+            return Err("Can't mount a file system at the virtual root".to_string());
+        }
+
+        let root_inode = self
+            .get_fd(VIRTUAL_ROOT_FD)
+            .map_err(|e| format!("Could not find the virtual root: WASI error code: {}", e))?
+            .inode;
+        if let Kind::Root { entries } = &self.inodes[root_inode].kind {
+            if entries.contains_key(name) {
+                return Err(format!("Found duplicate entry for alias `{}`", name));
+            }
+        }
+
+        if self
+            .fs_backing
+            .downcast_ref::<mounts::MountedFileSystem>()
+            .is_none()
+        {
+            let fs_backing = std::mem::replace(
+                &mut self.fs_backing,
+                Box::new(FallbackFileSystem::default()),
+            );
+            self.fs_backing = Box::new(mounts::MountedFileSystem::new(fs_backing));
+        }
+
+        // SAFETY: `fs_backing` has been wrapped above.
+        let mounts = self
+            .fs_backing
+            .downcast_ref::<mounts::MountedFileSystem>()
+            .unwrap()
+            .mounts()
+            .clone();
+        let path_in_mounts = Path::new("/").join(name);
+        mounts
+            .mount(&path_in_mounts, fs, &preopen.path)
+            .map_err(|e| format!("Could not mount a file system at `/{}`: {}", name, e))?;
+
+        let preopen = PreopenedDir {
+            alias: Some(name.to_string()),
+            ..preopen.clone()
+        };
+        self.create_preopen(root_inode, &preopen, mounts::mount_point(name))
+            .map_err(|e| {
+                let _ = mounts.unmount(&path_in_mounts);
+                e
+            })
+    }
+
     /// Get the `VirtualFile` object at stdout
     pub fn stdout(&self) -> Result<&Option<Box<dyn VirtualFile>>, FsError> {
         self.std_dev_get(__WASI_STDOUT_FILENO)
@@ -638,6 +737,52 @@ impl WasiFs {
         }
     }
 
+    /// Creates an in-memory file with the given contents in the
+    /// directory specified with the name and flags given, and opens it
+    // dead code because this is an API for external use
+    #[allow(dead_code)]
+    pub fn open_buffer_at(
+        &mut self,
+        base: __wasi_fd_t,
+        buffer: Vec<u8>,
+        open_flags: u16,
+        name: String,
+        rights: __wasi_rights_t,
+        rights_inheriting: __wasi_rights_t,
+        flags: __wasi_fdflags_t,
+    ) -> Result<__wasi_fd_t, FsError> {
+        let base_fd = self.get_fd(base).map_err(fs_error_from_wasi_err)?;
+        let base_inode = base_fd.inode;
+
+        match &self.inodes[base_inode].kind {
+            Kind::Dir { ref entries, .. } | Kind::Root { ref entries } => {
+                if let Some(_entry) = entries.get(&name) {
+                    return Err(FsError::AlreadyExists);
+                }
+
+                let kind = Kind::Buffer { buffer };
+
+                let inode = self
+                    .create_inode(kind, false, name.clone())
+                    .map_err(|_| FsError::IOError)?;
+                // reborrow to insert
+                match &mut self.inodes[base_inode].kind {
+                    Kind::Dir {
+                        ref mut entries, ..
+                    }
+                    | Kind::Root { ref mut entries } => {
+                        entries.insert(name, inode);
+                    }
+                    _ => unreachable!("Dir or Root became not Dir or Root"),
+                }
+
+                self.create_fd(rights, rights_inheriting, flags, open_flags, inode)
+                    .map_err(fs_error_from_wasi_err)
+            }
+            _ => Err(FsError::BaseNotDirectory),
+        }
+    }
+
     /// Change the backing of a given file descriptor
     /// Returns the old backing
     /// TODO: add examples
@@ -690,6 +835,11 @@ impl WasiFs {
                     Err(__WASI_EBADF)
                 }
             }
+            Kind::Buffer { buffer } => {
+                let new_size = buffer.len() as u64;
+                self.inodes[fd.inode].stat.st_size = new_size;
+                Ok(new_size as __wasi_filesize_t)
+            }
             Kind::Dir { .. } | Kind::Root { .. } => Err(__WASI_EISDIR),
             _ => Err(__WASI_EINVAL),
         }
@@ -707,32 +857,39 @@ impl WasiFs {
     /// This is where a lot of the magic happens, be very careful when editing
     /// this code.
     ///
+    /// The path is resolved from `base`, on behalf of the fd `rights_base`
+    /// the guest gave: they differ once a symlink has been followed.
+    ///
     /// TODO: write more tests for this code
     fn get_inode_at_path_inner(
         &mut self,
         base: __wasi_fd_t,
+        rights_base: __wasi_fd_t,
         path: &str,
-        mut symlink_count: u32,
+        symlink_count: u32,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
         if symlink_count > MAX_SYMLINKS {
-            return Err(__WASI_EMLINK);
+            return Err(__WASI_ELOOP);
+        }
+        // a `\0` can't be in a path of the host, and it would reach the
+        // mount points through the other preopened directories
+        if path.contains('\0') {
+            return Err(__WASI_EINVAL);
         }
 
         let base_dir = self.get_fd(base)?;
         let path: &Path = Path::new(path);
 
         let mut cur_inode = base_dir.inode;
-        let n_components = path.components().count();
+        // an absolute symlink can start the resolution in a mount
+        self.check_mount_rights(rights_base, cur_inode)?;
         // TODO: rights checks
-        'path_iter: for (i, component) in path.components().enumerate() {
-            // used to terminate symlink resolution properly
-            let last_component = i + 1 == n_components;
+        'path_iter: for component in path.components() {
             // for each component traverse file structure
             // loading inodes as necessary
-            'symlink_resolution: while symlink_count < MAX_SYMLINKS {
+            'symlink_resolution: loop {
                 match &mut self.inodes[cur_inode].kind {
-                    Kind::Buffer { .. } => unimplemented!("state::get_inode_at_path for buffers"),
                     Kind::Dir {
                         ref mut entries,
                         ref path,
@@ -751,8 +908,6 @@ impl WasiFs {
                             "." => continue 'path_iter,
                             _ => (),
                         }
-                        // used for full resolution of symlinks
-                        let mut loop_for_symlink = false;
                         if let Some(entry) =
                             entries.get(component.as_os_str().to_string_lossy().as_ref())
                         {
@@ -769,12 +924,8 @@ impl WasiFs {
                                 .ok()
                                 .ok_or(__WASI_ENOENT)?;
                             let file_type = metadata.file_type();
-                            // we want to insert newly opened dirs and files, but not transient symlinks
-                            // TODO: explain why (think about this deeply when well rested)
-                            let should_insert;
 
                             let kind = if file_type.is_dir() {
-                                should_insert = true;
                                 // load DIR
                                 Kind::Dir {
                                     parent: Some(cur_inode),
@@ -782,7 +933,6 @@ impl WasiFs {
                                     entries: Default::default(),
                                 }
                             } else if file_type.is_file() {
-                                should_insert = true;
                                 // load file
                                 Kind::File {
                                     handle: None,
@@ -790,20 +940,19 @@ impl WasiFs {
                                     fd: None,
                                 }
                             } else if file_type.is_symlink() {
-                                should_insert = false;
-                                let link_value = file.read_link().ok().ok_or(__WASI_EIO)?;
+                                // load symlink, it's followed if needed
+                                // when processing the next component
+                                let link_value = self
+                                    .fs_backing
+                                    .read_link(&file)
+                                    .map_err(fs_error_into_wasi_err)?;
                                 debug!("attempting to decompose path {:?}", link_value);
 
-                                let (pre_open_dir_fd, relative_path) = if link_value.is_relative() {
-                                    self.path_into_pre_open_and_relative_path(&file)?
-                                } else {
-                                    unimplemented!("Absolute symlinks are not yet supported");
-                                };
-                                loop_for_symlink = true;
-                                symlink_count += 1;
+                                let (pre_open_dir_fd, path_to_symlink) =
+                                    self.path_into_pre_open_and_relative_path(&file)?;
                                 Kind::Symlink {
                                     base_po_dir: pre_open_dir_fd,
-                                    path_to_symlink: relative_path.to_owned(),
+                                    path_to_symlink: path_to_symlink.to_owned(),
                                     relative_path: link_value,
                                 }
                             } else {
@@ -862,23 +1011,16 @@ impl WasiFs {
 
                             let new_inode =
                                 self.create_inode(kind, false, file.to_string_lossy().to_string())?;
-                            if should_insert {
-                                if let Kind::Dir {
-                                    ref mut entries, ..
-                                } = &mut self.inodes[cur_inode].kind
-                                {
-                                    entries.insert(
-                                        component.as_os_str().to_string_lossy().to_string(),
-                                        new_inode,
-                                    );
-                                }
+                            if let Kind::Dir {
+                                ref mut entries, ..
+                            } = &mut self.inodes[cur_inode].kind
+                            {
+                                entries.insert(
+                                    component.as_os_str().to_string_lossy().to_string(),
+                                    new_inode,
+                                );
                             }
                             cur_inode = new_inode;
-
-                            if loop_for_symlink && follow_symlinks {
-                                debug!("Following symlink to {:?}", cur_inode);
-                                continue 'symlink_resolution;
-                            }
                         }
                     }
                     Kind::Root { entries } => {
@@ -897,44 +1039,19 @@ impl WasiFs {
                         } else {
                             return Err(__WASI_ENOENT);
                         }
+                        // `..` reaches the mounts from the other preopened
+                        // directories
+                        self.check_mount_rights(rights_base, cur_inode)?;
                     }
-                    Kind::File { .. } => {
+                    Kind::File { .. } | Kind::Buffer { .. } => {
                         return Err(__WASI_ENOTDIR);
                     }
-                    Kind::Symlink {
-                        base_po_dir,
-                        path_to_symlink,
-                        relative_path,
-                    } => {
-                        let new_base_dir = *base_po_dir;
-                        // allocate to reborrow mutabily to recur
-                        let new_path = {
-                            /*if let Kind::Root { .. } = self.inodes[base_po_dir].kind {
-                                assert!(false, "symlinks should never be relative to the root");
-                            }*/
-                            let mut base = path_to_symlink.clone();
-                            // remove the symlink file itself from the path, leaving just the path from the base
-                            // to the dir containing the symlink
-                            base.pop();
-                            base.push(relative_path);
-                            base.to_string_lossy().to_string()
-                        };
+                    Kind::Symlink { .. } => {
+                        // a symlink in the middle of the path is always
+                        // followed, then the component is looked up in its
+                        // target
                         debug!("Following symlink recursively");
-                        let symlink_inode = self.get_inode_at_path_inner(
-                            new_base_dir,
-                            &new_path,
-                            symlink_count + 1,
-                            follow_symlinks,
-                        )?;
-                        cur_inode = symlink_inode;
-                        // if we're at the very end and we found a file, then we're done
-                        // TODO: figure out if this should also happen for directories?
-                        if let Kind::File { .. } = &self.inodes[cur_inode].kind {
-                            // check if on last step
-                            if last_component {
-                                break 'symlink_resolution;
-                            }
-                        }
+                        cur_inode = self.follow_symlink(cur_inode, rights_base, symlink_count)?;
                         continue 'symlink_resolution;
                     }
                 }
@@ -942,9 +1059,105 @@ impl WasiFs {
             }
         }
 
+        // the last component is only followed if asked to
+        if follow_symlinks {
+            if let Kind::Symlink { .. } = self.inodes[cur_inode].kind {
+                debug!("Following symlink to {:?}", cur_inode);
+                cur_inode = self.follow_symlink(cur_inode, rights_base, symlink_count)?;
+            }
+        }
+
         Ok(cur_inode)
     }
 
+    /// Resolves the symlink `inode` to the inode it points to, following
+    /// the symlinks on the way.
+    ///
+    /// A relative symlink is resolved from the directory containing it,
+    /// through the inodes of this file system, so it can't escape the
+    /// preopened directories. An absolute symlink must point into a
+    /// preopened directory, otherwise `__WASI_EACCES` is returned: it is
+    /// looked up by the names the guest sees, e.g. the alias of a
+    /// mapped directory, then by the paths in the backing file system,
+    /// for the symlinks which haven't been created by the guest.
+    fn follow_symlink(
+        &mut self,
+        inode: Inode,
+        rights_base: __wasi_fd_t,
+        symlink_count: u32,
+    ) -> Result<Inode, __wasi_errno_t> {
+        let (base_po_dir, path_to_symlink, link_value) = match &self.inodes[inode].kind {
+            Kind::Symlink {
+                base_po_dir,
+                path_to_symlink,
+                relative_path,
+            } => (*base_po_dir, path_to_symlink.clone(), relative_path.clone()),
+            _ => return Err(__WASI_EINVAL),
+        };
+
+        let (base, path) = if link_value.is_relative() {
+            // remove the symlink file itself from the path, leaving just the path from the base
+            // to the dir containing the symlink
+            let mut path = path_to_symlink;
+            path.pop();
+            path.push(link_value);
+            (base_po_dir, path)
+        } else if let Some((fd, path)) =
+            self.guest_path_into_pre_open_and_relative_path(&link_value)
+        {
+            (fd, path.to_owned())
+        } else {
+            let (fd, path) = self
+                .path_into_pre_open_and_relative_path(&link_value)
+                .map_err(|_| __WASI_EACCES)?;
+            // the virtual root is only the best match when no preopened
+            // directory contains the path
+            if let Kind::Root { .. } = self.inodes[self.get_fd(fd)?.inode].kind {
+                return Err(__WASI_EACCES);
+            }
+            (fd, path.to_owned())
+        };
+
+        self.get_inode_at_path_inner(
+            base,
+            rights_base,
+            &path.to_string_lossy(),
+            symlink_count + 1,
+            true,
+        )
+    }
+
+    /// Checks that a path resolved on behalf of `rights_base` may enter
+    /// `inode` when it's the root of a mount: `rights_base` can't have
+    /// rights that the preopened directory of the mount doesn't have.
+    fn check_mount_rights(
+        &self,
+        rights_base: __wasi_fd_t,
+        inode: Inode,
+    ) -> Result<(), __wasi_errno_t> {
+        match &self.inodes[inode].kind {
+            Kind::Dir { path, .. } if path.parent() == Some(Path::new(mounts::MOUNTS_PREFIX)) => (),
+            _ => return Ok(()),
+        }
+        let base = self.get_fd(rights_base)?;
+        if base.inode == inode {
+            return Ok(());
+        }
+        let mount = self
+            .preopen_fds
+            .iter()
+            .filter_map(|fd| self.fd_map.get(fd))
+            .find(|fd| fd.inode == inode)
+            .ok_or(__WASI_ENOTCAPABLE)?;
+        if base.rights & !mount.rights != 0
+            || base.rights_inheriting & !mount.rights_inheriting != 0
+        {
+            return Err(__WASI_ENOTCAPABLE);
+        }
+
+        Ok(())
+    }
+
     /// Finds the preopened directory that is the "best match" for the given path and
     /// returns a path relative to this preopened directory.
     ///
@@ -954,7 +1167,7 @@ impl WasiFs {
     /// directory, `a/b` and the relative path `c/file`.
     ///
     /// In the case of a tie, the later preopened fd is preferred.
-    fn path_into_pre_open_and_relative_path<'path>(
+    pub(crate) fn path_into_pre_open_and_relative_path<'path>(
         &self,
         path: &'path Path,
     ) -> Result<(__wasi_fd_t, &'path Path), __wasi_errno_t> {
@@ -1006,31 +1219,127 @@ impl WasiFs {
         }
     }
 
-    /// finds the number of directories between the fd and the inode if they're connected
-    /// expects inode to point to a directory
-    pub(crate) fn path_depth_from_fd(
+    /// Like [`WasiFs::path_into_pre_open_and_relative_path`], but for an
+    /// absolute path as seen by the guest: the preopened directories
+    /// are matched by their name, i.e. their alias if they have one,
+    /// rather than by their path in the backing file system.
+    ///
+    /// The virtual root and the directories preopened with a relative
+    /// name, like `.`, never match.
+    pub(crate) fn guest_path_into_pre_open_and_relative_path<'path>(
         &self,
-        fd: __wasi_fd_t,
-        inode: Inode,
-    ) -> Result<usize, __wasi_errno_t> {
-        let mut counter = 0;
-        let base_fd = self.get_fd(fd)?;
-        let base_inode = base_fd.inode;
-        let mut cur_inode = inode;
+        path: &'path Path,
+    ) -> Option<(__wasi_fd_t, &'path Path)> {
+        let mut best_match: Option<(__wasi_fd_t, &'path Path, usize)> = None;
 
-        while cur_inode != base_inode {
-            counter += 1;
-            match &self.inodes[cur_inode].kind {
-                Kind::Dir { parent, .. } => {
-                    if let Some(p) = parent {
-                        cur_inode = *p;
+        for po_fd in &self.preopen_fds {
+            let po_inode = &self.inodes[self.fd_map[po_fd].inode];
+            if let Kind::Root { .. } = po_inode.kind {
+                continue;
+            }
+            if po_inode.name.starts_with('.') {
+                continue;
+            }
+
+            let po_name = Path::new("/").join(&po_inode.name);
+            if let Ok(rel_path) = path.strip_prefix(&po_name) {
+                let prefix_len = po_name.components().count();
+                // `>=` favors the later preopens, like
+                // `path_into_pre_open_and_relative_path`
+                if best_match.map_or(true, |(_, _, max_seen)| prefix_len >= max_seen) {
+                    best_match = Some((*po_fd, rel_path, prefix_len));
+                }
+            }
+        }
+
+        best_match.map(|(fd, rel_path, _)| (fd, rel_path))
+    }
+
+    /// Whether `link_value`, the value of a symlink at `path_to_symlink`
+    /// relatively to its preopened directory `base_po_dir`, stays in the
+    /// preopened directories, by their names as seen by the guest: an
+    /// absolute value must be in a preopened directory, and so must a
+    /// relative value going above `base_po_dir`, e.g. `../other/file`
+    /// from a mapped directory to another.
+    ///
+    /// The check is lexical, the symlinks on the way aren't followed:
+    /// they are checked when they are created.
+    pub(crate) fn is_symlink_in_pre_opens(
+        &self,
+        base_po_dir: __wasi_fd_t,
+        path_to_symlink: &Path,
+        link_value: &Path,
+    ) -> bool {
+        if link_value.is_absolute() {
+            return self
+                .guest_path_into_pre_open_and_relative_path(link_value)
+                .is_some();
+        }
+
+        // the depth of the directory containing the symlink
+        let mut depth = path_to_symlink
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .count()
+            .saturating_sub(1);
+
+        let stays_in_base_po_dir = link_value.components().all(|component| {
+            match component {
+                Component::ParentDir if depth == 0 => return false,
+                Component::ParentDir => depth -= 1,
+                Component::Normal(_) => depth += 1,
+                _ => (),
+            }
+            true
+        });
+        if stays_in_base_po_dir {
+            return true;
+        }
+
+        // otherwise, resolve the value from the name of `base_po_dir`
+        let po_inode = match self.fd_map.get(&base_po_dir) {
+            Some(fd) => &self.inodes[fd.inode],
+            None => return false,
+        };
+        if let Kind::Root { .. } = po_inode.kind {
+            return false;
+        }
+        if po_inode.name.starts_with('.') {
+            return false;
+        }
+
+        let mut guest_path = Path::new("/").join(&po_inode.name).join(path_to_symlink);
+        guest_path.pop();
+        for component in link_value.components() {
+            match component {
+                Component::ParentDir => {
+                    if !guest_path.pop() {
+                        return false;
                     }
                 }
-                _ => return Err(__WASI_EINVAL),
+                Component::Normal(name) => guest_path.push(name),
+                _ => (),
             }
         }
 
-        Ok(counter)
+        self.guest_path_into_pre_open_and_relative_path(&guest_path)
+            .is_some()
+    }
+
+    /// Returns the path of a symlink in the backing file system, from
+    /// the fields of its `Kind::Symlink`.
+    pub(crate) fn symlink_host_path(
+        &self,
+        base_po_dir: __wasi_fd_t,
+        path_to_symlink: &Path,
+    ) -> Option<PathBuf> {
+        let base_po_inode = self.fd_map.get(&base_po_dir)?.inode;
+        match &self.inodes[base_po_inode].kind {
+            Kind::Root { .. } => Some(path_to_symlink.to_owned()),
+            Kind::Dir { path, .. } => Some(path.join(path_to_symlink)),
+            // if this triggers, there's a bug in the symlink code
+            _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
+        }
     }
 
     /// gets a host file from a base directory and a path
@@ -1045,7 +1354,7 @@ impl WasiFs {
         path: &str,
         follow_symlinks: bool,
     ) -> Result<Inode, __wasi_errno_t> {
-        self.get_inode_at_path_inner(base, path, 0, follow_symlinks)
+        self.get_inode_at_path_inner(base, base, path, 0, follow_symlinks)
     }
 
     /// Returns the parent Dir or Root that the file at a given path is in and the file name
@@ -1064,6 +1373,10 @@ impl WasiFs {
             .as_os_str()
             .to_string_lossy()
             .to_string();
+        // see `get_inode_at_path_inner`
+        if new_entity_name.contains('\0') {
+            return Err(__WASI_EINVAL);
+        }
         for comp in components.rev() {
             parent_dir.push(comp);
         }
@@ -1198,7 +1511,7 @@ impl WasiFs {
                     }
                     // TODO: verify this behavior
                     Kind::Dir { .. } => return Err(__WASI_EISDIR),
-                    Kind::Symlink { .. } => unimplemented!("WasiFs::flush Kind::Symlink"),
+                    Kind::Symlink { .. } => return Err(__WASI_EBADF),
                     Kind::Buffer { .. } => (),
                     _ => return Err(__WASI_EIO),
                 }
@@ -1389,26 +1702,15 @@ impl WasiFs {
                 path_to_symlink,
                 ..
             } => {
-                let base_po_inode = &self.fd_map[base_po_dir].inode;
-                let base_po_inode_v = &self.inodes[*base_po_inode];
-                match &base_po_inode_v.kind {
-                    Kind::Root { .. } => {
-                        self.fs_backing.symlink_metadata(path_to_symlink).ok()?
-                    }
-                    Kind::Dir { path, .. } => {
-                        let mut real_path = path.clone();
-                        // PHASE 1: ignore all possible symlinks in `relative_path`
-                        // TODO: walk the segments of `relative_path` via the entries of the Dir
-                        //       use helper function to avoid duplicating this logic (walking this will require
-                        //       &self to be &mut sel
-                        // TODO: adjust size of symlink, too
-                        //      for all paths adjusted think about this
-                        real_path.push(path_to_symlink);
-                        self.fs_backing.symlink_metadata(&real_path).ok()?
-                    }
-                    // if this triggers, there's a bug in the symlink code
-                    _ => unreachable!("Symlink pointing to something that's not a directory as its base preopened directory"),
-                }
+                let real_path = self.symlink_host_path(*base_po_dir, path_to_symlink)?;
+                self.fs_backing.symlink_metadata(&real_path).ok()?
+            }
+            Kind::Buffer { buffer } => {
+                return Some(__wasi_filestat_t {
+                    st_filetype: __WASI_FILETYPE_REGULAR_FILE,
+                    st_size: buffer.len() as u64,
+                    ..__wasi_filestat_t::default()
+                })
             }
             _ => return None,
         };
@@ -1471,7 +1773,10 @@ impl WasiFs {
                 }
             }
             Kind::Root { .. } => return Err(__WASI_EACCES),
-            Kind::Symlink { .. } | Kind::Buffer { .. } => return Err(__WASI_EINVAL),
+            Kind::Buffer { .. } => {
+                self.fd_map.remove(&fd);
+            }
+            Kind::Symlink { .. } => return Err(__WASI_EINVAL),
         }
 
         Ok(())
@@ -1561,6 +1866,12 @@ pub struct WasiState {
     pub fs: WasiFs,
     pub args: Vec<Vec<u8>>,
     pub envs: Vec<Vec<u8>>,
+    #[cfg_attr(feature = "enable-serde", serde(skip, default = "default_clock"))]
+    pub clock: Arc<dyn WasiClock>,
+    #[cfg_attr(feature = "enable-serde", serde(skip, default = "default_random"))]
+    pub random: Box<dyn WasiRandom>,
+    #[cfg_attr(feature = "enable-serde", serde(skip))]
+    pub signals: WasiSignals,
 }
 
 impl WasiState {
@@ -1596,3 +1907,255 @@ pub fn virtual_file_type_to_wasi_file_type(file_type: wasmer_vfs::FileType) -> _
         __WASI_FILETYPE_UNKNOWN
     }
 }
+
+#[cfg(all(test, feature = "mem-fs"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symlink_resolution() {
+        let fs = wasmer_vfs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/dir")).unwrap();
+        fs.create_dir(Path::new("/dir/sub")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(Path::new("/dir/sub/file"))
+            .unwrap();
+        fs.symlink(Path::new("sub"), Path::new("/dir/rel")).unwrap();
+        fs.symlink(Path::new("/dir/sub"), Path::new("/dir/abs"))
+            .unwrap();
+        fs.symlink(Path::new("/etc"), Path::new("/dir/escape"))
+            .unwrap();
+        fs.symlink(Path::new("loop"), Path::new("/dir/loop"))
+            .unwrap();
+
+        let mut state = WasiState::new("test_prog")
+            .set_fs(Box::new(fs))
+            .preopen_dir("/dir")
+            .unwrap()
+            .build()
+            .unwrap();
+        let wasi_fs = &mut state.fs;
+        let fd = *wasi_fs.preopen_fds.last().unwrap();
+
+        for path in &["rel/file", "abs/file"] {
+            let inode = wasi_fs.get_inode_at_path(fd, path, true).unwrap();
+            assert!(
+                matches!(wasi_fs.inodes[inode].kind, Kind::File { .. }),
+                "`{}` must resolve to a file",
+                path
+            );
+        }
+
+        let inode = wasi_fs.get_inode_at_path(fd, "rel", false).unwrap();
+        assert!(matches!(wasi_fs.inodes[inode].kind, Kind::Symlink { .. }));
+        let inode = wasi_fs.get_inode_at_path(fd, "rel", true).unwrap();
+        assert!(matches!(wasi_fs.inodes[inode].kind, Kind::Dir { .. }));
+
+        assert_eq!(
+            wasi_fs.get_inode_at_path(fd, "escape", true),
+            Err(__WASI_EACCES)
+        );
+        assert_eq!(
+            wasi_fs.get_inode_at_path(fd, "loop", true),
+            Err(__WASI_ELOOP)
+        );
+    }
+    #[test]
+    fn absolute_symlinks_in_mapped_directories() {
+        let fs = wasmer_vfs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/host")).unwrap();
+        fs.create_dir(Path::new("/host/dir")).unwrap();
+        fs.create_dir(Path::new("/host/dir/sub")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(Path::new("/host/dir/sub/file"))
+            .unwrap();
+        // created by the guest, which only knows the alias
+        fs.symlink(Path::new("/app/sub"), Path::new("/host/dir/guest"))
+            .unwrap();
+        // created on the host
+        fs.symlink(Path::new("/host/dir/sub"), Path::new("/host/dir/host"))
+            .unwrap();
+        fs.symlink(Path::new("/host"), Path::new("/host/dir/escape"))
+            .unwrap();
+
+        let mut state = WasiState::new("test_prog")
+            .set_fs(Box::new(fs))
+            .map_dir("/app", "/host/dir")
+            .unwrap()
+            .build()
+            .unwrap();
+        let wasi_fs = &mut state.fs;
+        let fd = *wasi_fs.preopen_fds.last().unwrap();
+
+        for path in &["guest/file", "host/file"] {
+            let inode = wasi_fs.get_inode_at_path(fd, path, true).unwrap();
+            assert!(
+                matches!(wasi_fs.inodes[inode].kind, Kind::File { .. }),
+                "`{}` must resolve to a file",
+                path
+            );
+        }
+        assert_eq!(
+            wasi_fs.get_inode_at_path(fd, "escape", true),
+            Err(__WASI_EACCES)
+        );
+
+        // the values of the symlinks the guest can create
+        let in_pre_opens = |link_value: &str| {
+            wasi_fs.is_symlink_in_pre_opens(fd, Path::new("sub/link"), Path::new(link_value))
+        };
+        assert!(in_pre_opens("/app/sub/file"));
+        assert!(in_pre_opens("file"));
+        assert!(in_pre_opens("../sub/file"));
+        assert!(in_pre_opens("../../app/sub/file"));
+        assert!(!in_pre_opens("../../file"));
+        assert!(!in_pre_opens("../../../app/sub/file"));
+        assert!(!in_pre_opens("/host/dir/sub/file"));
+        assert!(!in_pre_opens("/etc/passwd"));
+    }
+
+    #[test]
+    fn mounts_have_their_own_namespace() {
+        let fs = wasmer_vfs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/tmp")).unwrap();
+        fs.create_dir(Path::new("/tmp/app")).unwrap();
+        fs.new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(Path::new("/tmp/app/host.txt"))
+            .unwrap();
+        fs.symlink(Path::new("/tmp/image.txt"), Path::new("/tmp/app/image"))
+            .unwrap();
+        let image = wasmer_vfs::mem_fs::FileSystem::default();
+        image
+            .new_open_options()
+            .write(true)
+            .create_new(true)
+            .open(Path::new("/image.txt"))
+            .unwrap();
+
+        // like `--mapdir /app:/tmp/app --dir / --mount image:/tmp`
+        let mut state = WasiState::new("test_prog")
+            .set_fs(Box::new(fs))
+            .map_dir("/app", "/tmp/app")
+            .unwrap()
+            .preopen(|p| p.directory("/").alias("host").read(true).write(true))
+            .unwrap()
+            .preopen(|p| p.directory("/tmp").alias("ro").read(true))
+            .unwrap()
+            .mount(Box::new(image), |p| {
+                p.directory("/").alias("/tmp").read(true)
+            })
+            .unwrap()
+            .build()
+            .unwrap();
+        let wasi_fs = &mut state.fs;
+        let fd_of = |wasi_fs: &WasiFs, name: &str| {
+            *wasi_fs
+                .preopen_fds
+                .iter()
+                .find(|fd| wasi_fs.inodes[wasi_fs.fd_map[fd].inode].name == name)
+                .unwrap()
+        };
+        let app = fd_of(wasi_fs, "app");
+        let host = fd_of(wasi_fs, "host");
+        let ro = fd_of(wasi_fs, "ro");
+        let tmp = fd_of(wasi_fs, "tmp");
+
+        // the mapped directory isn't sent into the image mounted at the
+        // parent of its path in the backing file system
+        assert!(wasi_fs.get_inode_at_path(app, "host.txt", true).is_ok());
+        assert_eq!(
+            wasi_fs.get_inode_at_path(app, "image.txt", true),
+            Err(__WASI_ENOENT)
+        );
+
+        // the image can only be reached through its own preopened
+        // directory, with its own rights
+        assert!(wasi_fs.get_inode_at_path(tmp, "image.txt", true).is_ok());
+        assert!(wasi_fs
+            .get_inode_at_path(host, "tmp/app/host.txt", true)
+            .is_ok());
+        assert_eq!(
+            wasi_fs.get_inode_at_path(host, "tmp/image.txt", true),
+            Err(__WASI_ENOENT)
+        );
+        assert_eq!(
+            wasi_fs.get_inode_at_path(host, "\0mounts/tmp/image.txt", true),
+            Err(__WASI_EINVAL)
+        );
+        assert_eq!(
+            wasi_fs
+                .get_parent_inode_at_path(host, Path::new("\0mounts"), true)
+                .map(|_| ()),
+            Err(__WASI_EINVAL)
+        );
+
+        // the other preopened directories reach the image through the
+        // virtual root only if they don't have more rights than it
+        assert_eq!(
+            wasi_fs.get_inode_at_path(host, "../tmp/image.txt", true),
+            Err(__WASI_ENOTCAPABLE)
+        );
+        assert_eq!(
+            wasi_fs.get_inode_at_path(app, "image", true),
+            Err(__WASI_ENOTCAPABLE)
+        );
+        assert!(wasi_fs
+            .get_inode_at_path(ro, "../tmp/image.txt", true)
+            .is_ok());
+        assert!(wasi_fs.get_inode_at_path(ro, "app/image", true).is_ok());
+    }
+
+    #[test]
+    fn buffers() {
+        let fs = wasmer_vfs::mem_fs::FileSystem::default();
+        fs.create_dir(Path::new("/dir")).unwrap();
+
+        let mut state = WasiState::new("test_prog")
+            .set_fs(Box::new(fs))
+            .preopen_dir("/dir")
+            .unwrap()
+            .build()
+            .unwrap();
+        let wasi_fs = &mut state.fs;
+        let fd = *wasi_fs.preopen_fds.last().unwrap();
+
+        let buffer_fd = wasi_fs
+            .open_buffer_at(
+                fd,
+                b"hello".to_vec(),
+                Fd::READ | Fd::WRITE,
+                "buffer".to_string(),
+                ALL_RIGHTS,
+                ALL_RIGHTS,
+                0,
+            )
+            .unwrap();
+        assert_eq!(wasi_fs.filestat_fd(buffer_fd).unwrap().st_size, 5);
+        assert_eq!(
+            wasi_fs.filestat_fd(buffer_fd).unwrap().st_filetype,
+            __WASI_FILETYPE_REGULAR_FILE
+        );
+
+        let inode = wasi_fs.get_inode_at_path(fd, "buffer", true).unwrap();
+        if let Kind::Buffer { buffer } = &mut wasi_fs.inodes[inode].kind {
+            buffer.extend_from_slice(b", world");
+        } else {
+            panic!("`buffer` must resolve to a buffer");
+        }
+        assert_eq!(wasi_fs.filestat_resync_size(buffer_fd), Ok(12));
+
+        assert_eq!(
+            wasi_fs.get_inode_at_path(fd, "buffer/file", true),
+            Err(__WASI_ENOTDIR)
+        );
+
+        assert_eq!(wasi_fs.close_fd(buffer_fd), Ok(()));
+        assert_eq!(wasi_fs.get_fd(buffer_fd).map(|_| ()), Err(__WASI_EBADF));
+    }
+}
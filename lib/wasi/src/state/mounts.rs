@@ -0,0 +1,146 @@
+//! The backing file system of a `WasiFs` with mounted file systems.
+//!
+//! The mount points live in their own namespace, below
+//! [`MOUNTS_PREFIX`], rather than in the namespace of the original
+//! backing: a mount at `/tmp` doesn't shadow the `/tmp` directory of
+//! the original backing. A mount can still be reached from another
+//! preopened directory through the virtual root, with `..` or a
+//! symlink, but only if that preopened directory doesn't have rights
+//! that the mount doesn't have: see `WasiFs::check_mount_rights`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wasmer_vfs::{
+    mount_fs, FileOpener, FileSystem, FsError, Metadata, OpenOptions, OpenOptionsConfig, ReadDir,
+    Result, VirtualFile,
+};
+
+/// The prefix of the paths of the mount points in the backing file
+/// system. Its `\0` can't appear in a path of the original backing,
+/// and the paths given by the guest are rejected if they contain one.
+pub(crate) const MOUNTS_PREFIX: &str = "/\0mounts";
+
+/// Returns the path of the mount point `name` in the backing file
+/// system.
+pub(crate) fn mount_point(name: &str) -> PathBuf {
+    Path::new(MOUNTS_PREFIX).join(name)
+}
+
+/// Routes the paths below [`MOUNTS_PREFIX`] to a mount table, and the
+/// other paths to the original backing.
+#[derive(Debug, Clone)]
+pub(crate) struct MountedFileSystem {
+    fs: Arc<dyn FileSystem>,
+    mounts: mount_fs::FileSystem,
+}
+
+impl MountedFileSystem {
+    pub(crate) fn new(fs: Box<dyn FileSystem>) -> Self {
+        Self {
+            fs: Arc::from(fs),
+            mounts: mount_fs::FileSystem::new(),
+        }
+    }
+
+    /// The mount table, where the mount point `name` is at `/<name>`.
+    pub(crate) fn mounts(&self) -> &mount_fs::FileSystem {
+        &self.mounts
+    }
+
+    /// Returns the file system `path` belongs to, and the path in it.
+    fn route<'a>(&'a self, path: &'a Path) -> (&'a dyn FileSystem, PathBuf) {
+        match path.strip_prefix(MOUNTS_PREFIX) {
+            Ok(path) => (&self.mounts, Path::new("/").join(path)),
+            Err(_) => (&*self.fs, path.to_path_buf()),
+        }
+    }
+}
+
+impl FileSystem for MountedFileSystem {
+    fn read_dir(&self, path: &Path) -> Result<ReadDir> {
+        let (fs, translated) = self.route(path);
+        let entries = fs
+            .read_dir(&translated)?
+            .map(|entry| {
+                entry.map(|mut entry| {
+                    entry.path = path.join(entry.file_name());
+                    entry
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ReadDir::new(entries))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let (fs, path) = self.route(path);
+        fs.create_dir(&path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let (fs, path) = self.route(path);
+        fs.remove_dir(&path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        if from.starts_with(MOUNTS_PREFIX) != to.starts_with(MOUNTS_PREFIX) {
+            return Err(FsError::CrossDevice);
+        }
+
+        let (fs, from) = self.route(from);
+        let (_, to) = self.route(to);
+        fs.rename(&from, &to)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let (fs, path) = self.route(path);
+        fs.metadata(&path)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        let (fs, path) = self.route(path);
+        fs.symlink_metadata(&path)
+    }
+
+    fn symlink(&self, original: &Path, link: &Path) -> Result<()> {
+        let (fs, link) = self.route(link);
+        fs.symlink(original, &link)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        let (fs, path) = self.route(path);
+        fs.read_link(&path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let (fs, path) = self.route(path);
+        fs.remove_file(&path)
+    }
+
+    fn new_open_options(&self) -> OpenOptions {
+        OpenOptions::new(Box::new(MountedFileOpener {
+            filesystem: self.clone(),
+        }))
+    }
+}
+
+/// Opens the files of a [`MountedFileSystem`].
+#[derive(Debug, Clone)]
+struct MountedFileOpener {
+    filesystem: MountedFileSystem,
+}
+
+impl FileOpener for MountedFileOpener {
+    fn open(&mut self, path: &Path, conf: &OpenOptionsConfig) -> Result<Box<dyn VirtualFile>> {
+        let (fs, path) = self.filesystem.route(path);
+
+        fs.new_open_options()
+            .read(conf.read())
+            .write(conf.write())
+            .append(conf.append())
+            .truncate(conf.truncate())
+            .create(conf.create())
+            .create_new(conf.create_new())
+            .open(&path)
+    }
+}
@@ -0,0 +1,74 @@
+//! Sources of randomness used by the `random_get` WASI syscall.
+
+use crate::syscalls::types::*;
+use std::fmt;
+
+/// The source of randomness of a WASI instance, used by `random_get`.
+///
+/// Implement it to control the random bytes seen by the guest, e.g.
+/// to run it reproducibly; see [`SeededRandom`].
+pub trait WasiRandom: fmt::Debug + Send + 'static {
+    /// Fills `buf` with random bytes.
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t>;
+}
+
+/// The random number generator of the operating system, this is the
+/// default.
+#[derive(Debug, Default, Clone)]
+pub struct SystemRandom;
+
+impl WasiRandom for SystemRandom {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t> {
+        getrandom::getrandom(buf).map_err(|_| __WASI_EIO)
+    }
+}
+
+/// A deterministic source of randomness, for tests and replays: the
+/// same seed always produces the same bytes.
+///
+/// It's a SplitMix64 generator, it must not be used for cryptography.
+///
+/// ```
+/// # use wasmer_wasi::{SeededRandom, WasiRandom};
+/// let (mut a, mut b) = ([0; 16], [0; 16]);
+/// SeededRandom::new(42).fill(&mut a).unwrap();
+/// SeededRandom::new(42).fill(&mut b).unwrap();
+/// assert_eq!(a, b);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+}
+
+impl WasiRandom for SeededRandom {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), __wasi_errno_t> {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the default source of randomness
+pub(crate) fn default_random() -> Box<dyn WasiRandom> {
+    Box::new(SystemRandom)
+}
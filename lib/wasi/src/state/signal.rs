@@ -0,0 +1,79 @@
+//! Signal dispositions used by the `proc_raise` WASI syscall.
+
+use crate::syscalls::types::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// What happens to a WASI instance when it raises a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalDisposition {
+    /// The instance is terminated, `proc_raise` returns
+    /// [`WasiError::Signal`][crate::WasiError::Signal].
+    Terminate,
+    /// The signal is discarded and the instance keeps running.
+    Ignore,
+}
+
+impl SignalDisposition {
+    /// The default disposition of a signal, as on POSIX systems.
+    ///
+    /// Job control isn't supported, so the signals which would stop
+    /// or continue a process are ignored.
+    pub fn default_for(sig: __wasi_signal_t) -> Self {
+        match sig {
+            __WASI_SIGCHLD | __WASI_SIGURG | __WASI_SIGWINCH | __WASI_SIGCONT | __WASI_SIGSTOP
+            | __WASI_SIGTSTP | __WASI_SIGTTIN | __WASI_SIGTTOU => Self::Ignore,
+            _ => Self::Terminate,
+        }
+    }
+}
+
+/// A host callback invoked when the guest raises a signal; it decides
+/// what happens to the instance.
+pub type SignalHandler = Box<dyn FnMut(__wasi_signal_t) -> SignalDisposition + Send>;
+
+/// The signal handlers registered by the host for a WASI instance.
+///
+/// Signals without a handler get their
+/// [default disposition][SignalDisposition::default_for].
+#[derive(Default)]
+pub struct WasiSignals {
+    handlers: HashMap<__wasi_signal_t, SignalHandler>,
+}
+
+impl fmt::Debug for WasiSignals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasiSignals")
+            .field("handled signals", &self.handlers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl WasiSignals {
+    /// Registers `handler` for `sig`, replacing any previous one.
+    pub fn set_handler(&mut self, sig: __wasi_signal_t, handler: SignalHandler) {
+        self.handlers.insert(sig, handler);
+    }
+
+    /// Delivers `sig`, calling its handler if there is one, and
+    /// returns what must happen to the instance.
+    pub fn raise(&mut self, sig: __wasi_signal_t) -> SignalDisposition {
+        match self.handlers.get_mut(&sig) {
+            Some(handler) => handler(sig),
+            None => SignalDisposition::default_for(sig),
+        }
+    }
+
+    /// Removes the handler of `sig`, to call it without holding the
+    /// lock of the `WasiState`: a handler may use the state itself.
+    /// It must be given back with [`Self::restore_handler`].
+    pub(crate) fn take_handler(&mut self, sig: __wasi_signal_t) -> Option<SignalHandler> {
+        self.handlers.remove(&sig)
+    }
+
+    /// Gives back a handler removed by [`Self::take_handler`], unless
+    /// another one has been registered for `sig` in the meantime.
+    pub(crate) fn restore_handler(&mut self, sig: __wasi_signal_t, handler: SignalHandler) {
+        self.handlers.entry(sig).or_insert(handler);
+    }
+}
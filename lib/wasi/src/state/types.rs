@@ -2,8 +2,6 @@
 use crate::syscalls::types::*;
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
-#[cfg(unix)]
-use std::convert::TryInto;
 use std::{
     collections::VecDeque,
     io::{self, Read, Seek, Write},
@@ -38,6 +36,9 @@ pub fn fs_error_from_wasi_err(err: __wasi_errno_t) -> FsError {
         __WASI_EAGAIN => FsError::WouldBlock,
         __WASI_ENOSPC => FsError::WriteZero,
         __WASI_ENOTEMPTY => FsError::DirectoryNotEmpty,
+        __WASI_ELOOP => FsError::TooManySymlinks,
+        __WASI_EXDEV => FsError::CrossDevice,
+        __WASI_EDQUOT => FsError::QuotaExceeded,
         _ => FsError::UnknownError,
     }
 }
@@ -67,166 +68,13 @@ pub fn fs_error_into_wasi_err(fs_error: FsError) -> __wasi_errno_t {
         FsError::WouldBlock => __WASI_EAGAIN,
         FsError::WriteZero => __WASI_ENOSPC,
         FsError::DirectoryNotEmpty => __WASI_ENOTEMPTY,
+        FsError::TooManySymlinks => __WASI_ELOOP,
+        FsError::CrossDevice => __WASI_EXDEV,
+        FsError::QuotaExceeded => __WASI_EDQUOT,
         FsError::Lock | FsError::UnknownError => __WASI_EIO,
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum PollEvent {
-    /// Data available to read
-    PollIn = 1,
-    /// Data available to write (will still block if data is greater than space available unless
-    /// the fd is configured to not block)
-    PollOut = 2,
-    /// Something didn't work. ignored as input
-    PollError = 4,
-    /// Connection closed. ignored as input
-    PollHangUp = 8,
-    /// Invalid request. ignored as input
-    PollInvalid = 16,
-}
-
-impl PollEvent {
-    fn from_i16(raw_num: i16) -> Option<PollEvent> {
-        Some(match raw_num {
-            1 => PollEvent::PollIn,
-            2 => PollEvent::PollOut,
-            4 => PollEvent::PollError,
-            8 => PollEvent::PollHangUp,
-            16 => PollEvent::PollInvalid,
-            _ => return None,
-        })
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct PollEventBuilder {
-    inner: PollEventSet,
-}
-
-pub type PollEventSet = i16;
-
-#[derive(Debug)]
-pub struct PollEventIter {
-    pes: PollEventSet,
-    i: usize,
-}
-
-impl Iterator for PollEventIter {
-    type Item = PollEvent;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pes == 0 || self.i > 15 {
-            None
-        } else {
-            while self.i < 16 {
-                let result = PollEvent::from_i16(self.pes & (1 << self.i));
-                self.pes &= !(1 << self.i);
-                self.i += 1;
-                if let Some(r) = result {
-                    return Some(r);
-                }
-            }
-            unreachable!("Internal logic error in PollEventIter");
-        }
-    }
-}
-
-pub fn iterate_poll_events(pes: PollEventSet) -> PollEventIter {
-    PollEventIter { pes, i: 0 }
-}
-
-#[cfg(unix)]
-fn poll_event_set_to_platform_poll_events(mut pes: PollEventSet) -> i16 {
-    let mut out = 0;
-    for i in 0..16 {
-        out |= match PollEvent::from_i16(pes & (1 << i)) {
-            Some(PollEvent::PollIn) => libc::POLLIN,
-            Some(PollEvent::PollOut) => libc::POLLOUT,
-            Some(PollEvent::PollError) => libc::POLLERR,
-            Some(PollEvent::PollHangUp) => libc::POLLHUP,
-            Some(PollEvent::PollInvalid) => libc::POLLNVAL,
-            _ => 0,
-        };
-        pes &= !(1 << i);
-    }
-    out
-}
-
-#[cfg(unix)]
-fn platform_poll_events_to_pollevent_set(mut num: i16) -> PollEventSet {
-    let mut peb = PollEventBuilder::new();
-    for i in 0..16 {
-        peb = match num & (1 << i) {
-            libc::POLLIN => peb.add(PollEvent::PollIn),
-            libc::POLLOUT => peb.add(PollEvent::PollOut),
-            libc::POLLERR => peb.add(PollEvent::PollError),
-            libc::POLLHUP => peb.add(PollEvent::PollHangUp),
-            libc::POLLNVAL => peb.add(PollEvent::PollInvalid),
-            _ => peb,
-        };
-        num &= !(1 << i);
-    }
-    peb.build()
-}
-
-impl PollEventBuilder {
-    pub fn new() -> PollEventBuilder {
-        PollEventBuilder { inner: 0 }
-    }
-
-    pub fn add(mut self, event: PollEvent) -> PollEventBuilder {
-        self.inner |= event as PollEventSet;
-        self
-    }
-
-    pub fn build(self) -> PollEventSet {
-        self.inner
-    }
-}
-
-#[cfg(unix)]
-pub(crate) fn poll(
-    selfs: &[&dyn VirtualFile],
-    events: &[PollEventSet],
-    seen_events: &mut [PollEventSet],
-) -> Result<u32, FsError> {
-    if !(selfs.len() == events.len() && events.len() == seen_events.len()) {
-        return Err(FsError::InvalidInput);
-    }
-    let mut fds = selfs
-        .iter()
-        .enumerate()
-        .filter_map(|(i, s)| s.get_fd().map(|rfd| (i, rfd)))
-        .map(|(i, host_fd)| libc::pollfd {
-            fd: host_fd.try_into().unwrap(),
-            events: poll_event_set_to_platform_poll_events(events[i]),
-            revents: 0,
-        })
-        .collect::<Vec<_>>();
-    let result = unsafe { libc::poll(fds.as_mut_ptr(), selfs.len() as _, 1) };
-
-    if result < 0 {
-        // TODO: check errno and return value
-        return Err(FsError::IOError);
-    }
-    // convert result and write back values
-    for (i, fd) in fds.into_iter().enumerate() {
-        seen_events[i] = platform_poll_events_to_pollevent_set(fd.revents);
-    }
-    // unwrap is safe because we check for negative values above
-    Ok(result.try_into().unwrap())
-}
-
-#[cfg(not(unix))]
-pub(crate) fn poll(
-    _selfs: &[&dyn VirtualFile],
-    _events: &[PollEventSet],
-    _seen_events: &mut [PollEventSet],
-) -> Result<(), FsError> {
-    unimplemented!("VirtualFile::poll is not implemented for non-Unix-like targets yet");
-}
-
 pub trait WasiPath {}
 
 /// For piping stdio. Stores all output / input in a byte-vector.
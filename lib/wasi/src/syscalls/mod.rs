@@ -22,9 +22,8 @@ use self::types::*;
 use crate::{
     ptr::{Array, WasmPtr},
     state::{
-        self, fs_error_into_wasi_err, iterate_poll_events, poll,
-        virtual_file_type_to_wasi_file_type, Fd, Inode, InodeVal, Kind, PollEvent,
-        PollEventBuilder, WasiState, MAX_SYMLINKS,
+        self, fs_error_into_wasi_err, virtual_file_type_to_wasi_file_type, Fd, Inode, InodeVal,
+        Kind, SignalDisposition, WasiState, MAX_SYMLINKS,
     },
     WasiEnv, WasiError,
 };
@@ -33,7 +32,7 @@ use std::convert::{Infallible, TryInto};
 use std::io::{self, Read, Seek, Write};
 use tracing::{debug, trace};
 use wasmer::{Memory, RuntimeError, Value, WasmCell};
-use wasmer_vfs::{FsError, VirtualFile};
+use wasmer_vfs::{FsError, Readiness, VirtualFile};
 
 #[cfg(any(
     target_os = "freebsd",
@@ -60,7 +59,7 @@ fn write_bytes_inner<T: Write>(
         let bytes = WasmPtr::<u8, Array>::new(iov_inner.buf).deref(memory, 0, iov_inner.buf_len)?;
         write_loc
             .write_all(&bytes.iter().map(|b_cell| b_cell.get()).collect::<Vec<u8>>())
-            .map_err(|_| __WASI_EIO)?;
+            .map_err(|e| fs_error_into_wasi_err(e.into()))?;
 
         // TODO: handle failure more accurately
         bytes_written += iov_inner.buf_len;
@@ -146,6 +145,58 @@ fn get_current_time_in_nanos() -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     Ok(duration.as_nanos() as __wasi_timestamp_t)
 }
 
+/// Computes the times to set from the `fst_flags` given to
+/// `fd_filestat_set_times` and `path_filestat_set_times`, `None` meaning
+/// the time is left unchanged
+fn times_to_set(
+    st_atim: __wasi_timestamp_t,
+    st_mtim: __wasi_timestamp_t,
+    fst_flags: __wasi_fstflags_t,
+) -> Result<(Option<__wasi_timestamp_t>, Option<__wasi_timestamp_t>), __wasi_errno_t> {
+    let atim = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
+        Some(st_atim)
+    } else if fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
+        Some(get_current_time_in_nanos()?)
+    } else {
+        None
+    };
+    let mtim = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
+        Some(st_mtim)
+    } else if fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
+        Some(get_current_time_in_nanos()?)
+    } else {
+        None
+    };
+
+    Ok((atim, mtim))
+}
+
+/// Sets the times of an inode, and of its file if it's opened so that
+/// the file system backing it keeps them too
+fn set_inode_times(
+    inode: &mut InodeVal,
+    atim: Option<__wasi_timestamp_t>,
+    mtim: Option<__wasi_timestamp_t>,
+) -> Result<(), __wasi_errno_t> {
+    if let Kind::File {
+        handle: Some(handle),
+        ..
+    } = &mut inode.kind
+    {
+        handle
+            .set_times(atim, mtim)
+            .map_err(fs_error_into_wasi_err)?;
+    }
+    if let Some(atim) = atim {
+        inode.stat.st_atim = atim;
+    }
+    if let Some(mtim) = mtim {
+        inode.stat.st_mtim = mtim;
+    }
+
+    Ok(())
+}
+
 /// ### `args_get()`
 /// Read command-line argument data.
 /// The sizes of the buffers should match that returned by [`args_sizes_get()`](#args_sizes_get).
@@ -225,10 +276,13 @@ pub fn clock_res_get(
     resolution: WasmPtr<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
     debug!("wasi::clock_res_get");
-    let memory = env.memory();
+    let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let out_addr = wasi_try!(resolution.deref(memory));
-    platform_clock_res_get(clock_id, out_addr)
+    let t_out = wasi_try!(state.clock.resolution(clock_id));
+    out_addr.set(t_out);
+
+    __WASI_ESUCCESS
 }
 
 /// ### `clock_time_get()`
@@ -251,16 +305,14 @@ pub fn clock_time_get(
         "wasi::clock_time_get clock_id: {}, precision: {}",
         clock_id, precision
     );
-    let memory = env.memory();
+    let (memory, state) = env.get_memory_and_wasi_state(0);
 
     let out_addr = wasi_try!(time.deref(memory));
-    let result = platform_clock_time_get(clock_id, precision, out_addr);
-    debug!(
-        "time: {} => {}",
-        wasi_try!(time.deref(memory)).get(),
-        result
-    );
-    result
+    let t_out = wasi_try!(state.clock.time(clock_id, precision));
+    out_addr.set(t_out);
+    debug!("time: {}", t_out);
+
+    __WASI_ESUCCESS
 }
 
 /// ### `environ_get()`
@@ -367,20 +419,30 @@ pub fn fd_allocate(
     }
     let new_size = wasi_try!(offset.checked_add(len), __WASI_EINVAL);
 
-    match &mut state.fs.inodes[inode].kind {
+    // the file is only ever grown, never truncated
+    let new_size = match &mut state.fs.inodes[inode].kind {
         Kind::File { handle, .. } => {
             if let Some(handle) = handle {
-                wasi_try!(handle.set_len(new_size).map_err(fs_error_into_wasi_err));
+                let size = handle.size();
+                if new_size > size {
+                    wasi_try!(handle.set_len(new_size).map_err(fs_error_into_wasi_err));
+                    new_size
+                } else {
+                    size
+                }
             } else {
                 return __WASI_EBADF;
             }
         }
         Kind::Buffer { buffer } => {
-            buffer.resize(new_size as usize, 0);
+            if new_size as usize > buffer.len() {
+                buffer.resize(new_size as usize, 0);
+            }
+            buffer.len() as u64
         }
         Kind::Symlink { .. } => return __WASI_EBADF,
         Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-    }
+    };
     state.fs.inodes[inode].stat.st_size = new_size;
     debug!("New file size: {}", new_size);
 
@@ -613,25 +675,8 @@ pub fn fd_filestat_set_times(
     }
 
     let inode_idx = fd_entry.inode;
-    let inode = &mut state.fs.inodes[inode_idx];
-
-    if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 || fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
-            st_atim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_atim = time_to_set;
-    }
-
-    if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 || fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
-            st_mtim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_mtim = time_to_set;
-    }
+    let (atim, mtim) = wasi_try!(times_to_set(st_atim, st_mtim, fst_flags));
+    wasi_try!(set_inode_times(&mut state.fs.inodes[inode_idx], atim, mtim));
 
     __WASI_ESUCCESS
 }
@@ -703,10 +748,12 @@ pub fn fd_pread(
                     }
                 }
                 Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pread"),
-                Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[(offset as usize)..], memory, &iov_cells))
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
+                Kind::Buffer { buffer } => wasi_try!(read_bytes(
+                    buffer.get((offset as usize)..).unwrap_or(&[]),
+                    memory,
+                    &iov_cells
+                )),
             }
         }
     };
@@ -845,7 +892,7 @@ pub fn fd_pwrite(
             let inode_idx = fd_entry.inode;
             let inode = &mut state.fs.inodes[inode_idx];
 
-            match &mut inode.kind {
+            let bytes_written = match &mut inode.kind {
                 Kind::File { handle, .. } => {
                     if let Some(handle) = handle {
                         handle.seek(std::io::SeekFrom::Start(offset as u64));
@@ -858,15 +905,16 @@ pub fn fd_pwrite(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_pwrite"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
-                    wasi_try!(write_bytes(
-                        &mut buffer[(offset as usize)..],
-                        memory,
-                        &iovs_arr_cell
-                    ))
+                    let mut cursor = std::io::Cursor::new(buffer);
+                    cursor.set_position(offset as u64);
+                    wasi_try!(write_bytes(&mut cursor, memory, &iovs_arr_cell))
                 }
-            }
+            };
+            wasi_try!(state.fs.filestat_resync_size(fd));
+
+            bytes_written
         }
     };
 
@@ -936,10 +984,12 @@ pub fn fd_read(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_read"),
-                Kind::Buffer { buffer } => {
-                    wasi_try!(read_bytes(&buffer[offset..], memory, &iovs_arr_cell))
-                }
+                Kind::Symlink { .. } => return __WASI_EBADF,
+                Kind::Buffer { buffer } => wasi_try!(read_bytes(
+                    buffer.get(offset..).unwrap_or(&[]),
+                    memory,
+                    &iovs_arr_cell
+                )),
             };
 
             // reborrow
@@ -1157,17 +1207,16 @@ pub fn fd_seek(
                         return __WASI_EINVAL;
                     }
                 }
-                Kind::Symlink { .. } => {
-                    unimplemented!("wasi::fd_seek not implemented for symlinks")
-                }
-                Kind::Dir { .. } | Kind::Root { .. } => {
+                Kind::Dir { .. } | Kind::Root { .. } | Kind::Symlink { .. } => {
                     // TODO: check this
                     return __WASI_EINVAL;
                 }
-                Kind::Buffer { .. } => {
-                    // seeking buffers probably makes sense
-                    // TODO: implement this
-                    return __WASI_EINVAL;
+                Kind::Buffer { ref buffer } => {
+                    let end = buffer.len();
+
+                    // reborrow
+                    let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
+                    fd_entry.offset = (end as i64 + offset) as u64;
                 }
             }
         }
@@ -1304,7 +1353,8 @@ pub fn fd_write(
                 return __WASI_EACCES;
             }
 
-            let offset = fd_entry.offset as usize;
+            let mut offset = fd_entry.offset as usize;
+            let append = fd_entry.flags & __WASI_FDFLAG_APPEND != 0;
             let inode_idx = fd_entry.inode;
             let inode = &mut state.fs.inodes[inode_idx];
 
@@ -1321,15 +1371,20 @@ pub fn fd_write(
                     // TODO: verify
                     return __WASI_EISDIR;
                 }
-                Kind::Symlink { .. } => unimplemented!("Symlinks in wasi::fd_write"),
+                Kind::Symlink { .. } => return __WASI_EBADF,
                 Kind::Buffer { buffer } => {
-                    wasi_try!(write_bytes(&mut buffer[offset..], memory, &iovs_arr_cell))
+                    if append {
+                        offset = buffer.len();
+                    }
+                    let mut cursor = std::io::Cursor::new(buffer);
+                    cursor.set_position(offset as u64);
+                    wasi_try!(write_bytes(&mut cursor, memory, &iovs_arr_cell))
                 }
             };
 
             // reborrow
             let fd_entry = wasi_try!(state.fs.fd_map.get_mut(&fd).ok_or(__WASI_EBADF));
-            fd_entry.offset += bytes_written as u64;
+            fd_entry.offset = offset as u64 + bytes_written as u64;
             wasi_try!(state.fs.filestat_resync_size(fd));
 
             bytes_written
@@ -1372,6 +1427,10 @@ pub fn path_create_directory(
     }
     let path_string = unsafe { get_input_str!(memory, path, path_len) };
     debug!("=> fd: {}, path: {}", fd, &path_string);
+    // like `WasiFs::get_inode_at_path`, which isn't used here
+    if path_string.contains('\0') {
+        return __WASI_EINVAL;
+    }
 
     let path = std::path::PathBuf::from(&path_string);
     let path_vec = wasi_try!(path
@@ -1527,7 +1586,6 @@ pub fn path_filestat_set_times(
     debug!("wasi::path_filestat_set_times");
     let (memory, mut state) = env.get_memory_and_wasi_state(0);
     let fd_entry = wasi_try!(state.fs.get_fd(fd));
-    let fd_inode = fd_entry.inode;
     if !has_rights(fd_entry.rights, __WASI_RIGHT_PATH_FILESTAT_SET_TIMES) {
         return __WASI_EACCES;
     }
@@ -1546,29 +1604,12 @@ pub fn path_filestat_set_times(
         &path_string,
         flags & __WASI_LOOKUP_SYMLINK_FOLLOW != 0,
     ));
-    let stat = wasi_try!(state
-        .fs
-        .get_stat_for_kind(&state.fs.inodes[file_inode].kind)
-        .ok_or(__WASI_EIO));
-
-    let inode = &mut state.fs.inodes[fd_inode];
-
-    if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 || fst_flags & __WASI_FILESTAT_SET_ATIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_ATIM != 0 {
-            st_atim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_atim = time_to_set;
-    }
-    if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 || fst_flags & __WASI_FILESTAT_SET_MTIM_NOW != 0 {
-        let time_to_set = if fst_flags & __WASI_FILESTAT_SET_MTIM != 0 {
-            st_mtim
-        } else {
-            wasi_try!(get_current_time_in_nanos())
-        };
-        inode.stat.st_mtim = time_to_set;
-    }
+    let (atim, mtim) = wasi_try!(times_to_set(st_atim, st_mtim, fst_flags));
+    wasi_try!(set_inode_times(
+        &mut state.fs.inodes[file_inode],
+        atim,
+        mtim
+    ));
 
     __WASI_ESUCCESS
 }
@@ -1780,23 +1821,40 @@ pub fn path_open(
                     .open(&path)
                     .map_err(fs_error_into_wasi_err)));
             }
-            Kind::Buffer { .. } => unimplemented!("wasi::path_open for Buffer type files"),
+            Kind::Buffer { buffer } => {
+                if o_flags & __WASI_O_DIRECTORY != 0 {
+                    return __WASI_ENOTDIR;
+                }
+                if o_flags & __WASI_O_EXCL != 0 {
+                    return __WASI_EEXIST;
+                }
+                open_flags |= Fd::READ;
+                // truncating requires the permission to write
+                if adjusted_rights & __WASI_RIGHT_FD_WRITE != 0 {
+                    open_flags |= Fd::WRITE;
+                    if o_flags & __WASI_O_TRUNC != 0 {
+                        open_flags |= Fd::TRUNCATE;
+                        buffer.clear();
+                    }
+                }
+            }
             Kind::Dir { .. } | Kind::Root { .. } => {
                 // TODO: adjust these to be correct
                 if o_flags & __WASI_O_EXCL != 0 && path_arg.exists() {
                     return __WASI_EEXIST;
                 }
             }
-            Kind::Symlink {
-                base_po_dir,
-                path_to_symlink,
-                relative_path,
-            } => {
-                // I think this should return an error (because symlinks should be resolved away by the path traversal)
-                // TODO: investigate this
-                unimplemented!("SYMLINKS IN PATH_OPEN");
+            Kind::Symlink { .. } => {
+                // symlinks are resolved away by the path traversal,
+                // unless the last one must not be followed
+                return __WASI_ELOOP;
             }
         }
+        // the buffer may have been truncated
+        let inode_val = &mut state.fs.inodes[inode];
+        if let Kind::Buffer { buffer } = &inode_val.kind {
+            inode_val.stat.st_size = buffer.len() as u64;
+        }
         inode
     } else {
         // less-happy path, we have to try to create the file
@@ -2120,7 +2178,36 @@ pub fn path_rename(
             }
         }
         Kind::Buffer { .. } => {}
-        Kind::Symlink { .. } => {}
+        Kind::Symlink {
+            base_po_dir,
+            path_to_symlink,
+            ..
+        } => {
+            let (base_po_dir, path_to_symlink) = (*base_po_dir, path_to_symlink.clone());
+            let source_host_path = wasi_try!(
+                state.fs.symlink_host_path(base_po_dir, &path_to_symlink),
+                __WASI_EBADF
+            );
+            let (new_base_po_dir, new_path_to_symlink) = wasi_try!(state
+                .fs
+                .path_into_pre_open_and_relative_path(&host_adjusted_target_path));
+            let new_path_to_symlink = new_path_to_symlink.to_owned();
+            if let Err(e) = state.fs_rename(source_host_path, &host_adjusted_target_path) {
+                if let Kind::Dir { entries, .. } = &mut state.fs.inodes[source_parent_inode].kind {
+                    entries.insert(source_entry_name, source_entry);
+                }
+                return e;
+            }
+            if let Kind::Symlink {
+                base_po_dir,
+                path_to_symlink,
+                ..
+            } = &mut state.fs.inodes[source_entry].kind
+            {
+                *base_po_dir = new_base_po_dir;
+                *path_to_symlink = new_path_to_symlink;
+            }
+        }
         Kind::Root { .. } => unreachable!("The root can not be moved"),
     }
 
@@ -2165,48 +2252,55 @@ pub fn path_symlink(
         return __WASI_EACCES;
     }
 
-    // get the depth of the parent + 1 (UNDER INVESTIGATION HMMMMMMMM THINK FISH ^ THINK FISH)
-    let old_path_path = std::path::Path::new(&old_path_str);
-    let (source_inode, _) = wasi_try!(state.fs.get_parent_inode_at_path(fd, old_path_path, true));
-    let depth = wasi_try!(state.fs.path_depth_from_fd(fd, source_inode)) - 1;
-
     let new_path_path = std::path::Path::new(&new_path_str);
     let (target_parent_inode, entry_name) =
         wasi_try!(state.fs.get_parent_inode_at_path(fd, new_path_path, true));
 
     // short circuit if anything is wrong, before we create an inode
-    match &state.fs.inodes[target_parent_inode].kind {
-        Kind::Dir { entries, .. } => {
+    let host_path = match &state.fs.inodes[target_parent_inode].kind {
+        Kind::Dir { entries, path, .. } => {
             if entries.contains_key(&entry_name) {
                 return __WASI_EEXIST;
             }
+            path.join(&entry_name)
         }
         Kind::Root { .. } => return __WASI_ENOTCAPABLE,
         Kind::File { .. } | Kind::Symlink { .. } | Kind::Buffer { .. } => {
             unreachable!("get_parent_inode_at_path returned something other than a Dir or Root")
         }
-    }
+    };
 
-    let mut source_path = std::path::Path::new(&old_path_str);
-    let mut relative_path = std::path::PathBuf::new();
-    for _ in 0..depth {
-        relative_path.push("..");
+    // the value of the symlink is stored as is, it's resolved relatively
+    // to the directory containing the symlink when it's followed, or
+    // from the names of the preopened directories if it's absolute
+    let relative_path = std::path::PathBuf::from(&old_path_str);
+    let (base_po_dir, path_to_symlink) =
+        wasi_try!(state.fs.path_into_pre_open_and_relative_path(&host_path));
+    // don't write symlinks to the backing file system which point out
+    // of the sandbox
+    if !state
+        .fs
+        .is_symlink_in_pre_opens(base_po_dir, path_to_symlink, &relative_path)
+    {
+        return __WASI_ENOTCAPABLE;
     }
-    relative_path.push(source_path);
     debug!(
         "Symlinking {} to {}",
         new_path_str,
         relative_path.to_string_lossy()
     );
+    wasi_try!(state
+        .fs
+        .fs_backing
+        .symlink(&relative_path, &host_path)
+        .map_err(fs_error_into_wasi_err));
 
     let kind = Kind::Symlink {
-        base_po_dir: fd,
-        path_to_symlink: std::path::PathBuf::from(new_path_str),
+        base_po_dir,
+        path_to_symlink: path_to_symlink.to_owned(),
         relative_path,
     };
-    let new_inode = state
-        .fs
-        .create_inode_with_default_stat(kind, false, entry_name.clone());
+    let new_inode = wasi_try!(state.fs.create_inode(kind, false, entry_name.clone()));
 
     if let Kind::Dir {
         ref mut entries, ..
@@ -2280,18 +2374,28 @@ pub fn path_unlink_file(
                 }
             }
             Kind::Dir { .. } | Kind::Root { .. } => return __WASI_EISDIR,
-            Kind::Symlink { .. } => {
-                // TODO: actually delete real symlinks and do nothing for virtual symlinks
+            Kind::Symlink {
+                base_po_dir,
+                path_to_symlink,
+                ..
+            } => {
+                // drop mutable borrow on `path_to_symlink`
+                let (base_po_dir, path_to_symlink) = (*base_po_dir, path_to_symlink.clone());
+                let host_path = wasi_try!(
+                    state.fs.symlink_host_path(base_po_dir, &path_to_symlink),
+                    __WASI_EBADF
+                );
+                wasi_try!(state.fs_remove_file(host_path));
             }
-            _ => unimplemented!("wasi::path_unlink_file for Buffer"),
+            // buffers only live in the WASI FS, there is nothing else to remove
+            Kind::Buffer { .. } => (),
         }
         // TODO: test this on Windows and actually make it portable
         // make the file an orphan fd if the fd is still open
-        let fd_is_orphaned = if let Kind::File { handle, .. } = &state.fs.inodes[removed_inode].kind
-        {
-            handle.is_some()
-        } else {
-            false
+        let fd_is_orphaned = match &state.fs.inodes[removed_inode].kind {
+            Kind::File { handle, .. } => handle.is_some(),
+            Kind::Buffer { .. } => state.fs.fd_map.values().any(|fd| fd.inode == removed_inode),
+            _ => false,
         };
         let removed_inode_val = unsafe { state.fs.remove_inode(removed_inode) };
         assert!(
@@ -2322,7 +2426,9 @@ pub fn path_unlink_file(
 /// Output:
 /// - `u32 nevents`
 ///     The number of events seen
-#[cfg(not(feature = "js"))]
+///
+/// When the clock can't wait, as on `js`, a clock subscription which
+/// isn't due yet is reported early; see `WasiClock::sleep`.
 pub fn poll_oneoff(
     env: &WasiEnv,
     in_: WasmPtr<__wasi_subscription_t, Array>,
@@ -2332,193 +2438,216 @@ pub fn poll_oneoff(
 ) -> __wasi_errno_t {
     debug!("wasi::poll_oneoff");
     debug!("  => nsubscriptions = {}", nsubscriptions);
-    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    let memory = env.memory();
 
     let subscription_array = wasi_try!(in_.deref(memory, 0, nsubscriptions));
     let event_array = wasi_try!(out_.deref(memory, 0, nsubscriptions));
-    let mut events_seen = 0;
     let out_ptr = wasi_try!(nevents.deref(memory));
 
-    let mut fds = vec![];
-    let mut clock_subs = vec![];
-    let mut in_events = vec![];
-    let mut total_ns_slept = 0;
-
-    for sub in subscription_array.iter() {
-        let s: WasiSubscription = wasi_try!(sub.get().try_into());
-        let mut peb = PollEventBuilder::new();
-        let mut ns_to_sleep = 0;
-
-        let fd = match s.event_type {
-            EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ) {
-                            return __WASI_EACCES;
+    if nsubscriptions == 0 {
+        return __WASI_EINVAL;
+    }
+
+    // the subscriptions, with the deadlines of the clocks in their own time
+    let mut subscriptions = Vec::with_capacity(nsubscriptions as usize);
+    let clock = env.state().clock.clone();
+
+    {
+        let state = env.state();
+
+        for sub in subscription_array.iter() {
+            let sub = sub.get();
+            let s: WasiSubscription = wasi_try!(sub.try_into());
+
+            let event_type = match s.event_type {
+                EventType::Read(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    match fd {
+                        __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
+                        _ => {
+                            let fd_entry = wasi_try!(state.fs.get_fd(fd));
+                            if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_READ)
+                                || !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE)
+                            {
+                                return __WASI_EACCES;
+                            }
                         }
                     }
+                    s.event_type
                 }
-                in_events.push(peb.add(PollEvent::PollIn).build());
-                Some(fd)
-            }
-            EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
-                match fd {
-                    __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
-                    _ => {
-                        let fd_entry = wasi_try!(state.fs.get_fd(fd));
-
-                        if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE) {
-                            return __WASI_EACCES;
+                EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
+                    match fd {
+                        __WASI_STDIN_FILENO | __WASI_STDOUT_FILENO | __WASI_STDERR_FILENO => (),
+                        _ => {
+                            let fd_entry = wasi_try!(state.fs.get_fd(fd));
+                            if !has_rights(fd_entry.rights, __WASI_RIGHT_FD_WRITE)
+                                || !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE)
+                            {
+                                return __WASI_EACCES;
+                            }
                         }
                     }
+                    s.event_type
                 }
-                in_events.push(peb.add(PollEvent::PollOut).build());
-                Some(fd)
-            }
-            EventType::Clock(clock_info) => {
-                if clock_info.clock_id == __WASI_CLOCK_REALTIME {
-                    // this is a hack
-                    // TODO: do this properly
-                    ns_to_sleep = clock_info.timeout;
-                    clock_subs.push(clock_info);
-                    None
-                } else {
-                    unimplemented!("Polling not implemented for clocks yet");
+                EventType::Clock(mut clock_info) => {
+                    // relative timeouts are turned into absolute ones
+                    if clock_info.flags & __WASI_SUBSCRIPTION_CLOCK_ABSTIME == 0 {
+                        let now = wasi_try!(clock.time(clock_info.clock_id, clock_info.precision));
+                        clock_info.timeout = now.saturating_add(clock_info.timeout);
+                        clock_info.flags |= __WASI_SUBSCRIPTION_CLOCK_ABSTIME;
+                    }
+                    EventType::Clock(clock_info)
                 }
-            }
-        };
+            };
 
-        if let Some(fd) = fd {
-            let wasi_file_ref: &dyn VirtualFile = match fd {
-                __WASI_STDERR_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stderr().map_err(fs_error_into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDIN_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdin().map_err(fs_error_into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                __WASI_STDOUT_FILENO => wasi_try!(
-                    wasi_try!(state.fs.stdout().map_err(fs_error_into_wasi_err)).as_ref(),
-                    __WASI_EBADF
-                )
-                .as_ref(),
-                _ => {
-                    let fd_entry = wasi_try!(state.fs.get_fd(fd));
-                    let inode = fd_entry.inode;
-                    if !has_rights(fd_entry.rights, __WASI_RIGHT_POLL_FD_READWRITE) {
-                        return __WASI_EACCES;
-                    }
+            subscriptions.push((sub, event_type));
+        }
+    }
+
+    let events = loop {
+        let mut events = vec![];
+        // the time to wait before checking the subscriptions again
+        let mut timeout = None;
+        // the clock subscription which is due first
+        let mut earliest_clock: Option<(__wasi_timestamp_t, &__wasi_subscription_t)> = None;
 
-                    match &state.fs.inodes[inode].kind {
-                        Kind::File { handle, .. } => {
-                            if let Some(h) = handle {
-                                h.as_ref()
-                            } else {
-                                return __WASI_EBADF;
+        {
+            let state = env.state();
+
+            for (sub, event_type) in subscriptions.iter() {
+                let event = match event_type {
+                    EventType::Read(__wasi_subscription_fs_readwrite_t { fd })
+                    | EventType::Write(__wasi_subscription_fs_readwrite_t { fd }) => {
+                        let is_read = matches!(event_type, EventType::Read(_));
+
+                        match poll_fd_readiness(&state, *fd) {
+                            Ok(readiness) => {
+                                let nbytes = if is_read {
+                                    readiness.read
+                                } else {
+                                    readiness.write
+                                };
+                                if nbytes.is_none() && !readiness.hangup {
+                                    // check the file again soon
+                                    timeout = Some(timeout.map_or(POLL_INTERVAL_NS, |t| {
+                                        std::cmp::min(t, POLL_INTERVAL_NS)
+                                    }));
+                                    continue;
+                                }
+                                poll_fd_event(
+                                    sub,
+                                    __WASI_ESUCCESS,
+                                    nbytes.unwrap_or(0),
+                                    if readiness.hangup {
+                                        __WASI_EVENT_FD_READWRITE_HANGUP
+                                    } else {
+                                        0
+                                    },
+                                )
                             }
+                            Err(error) => poll_fd_event(sub, error, 0, 0),
                         }
-                        Kind::Dir { .. }
-                        | Kind::Root { .. }
-                        | Kind::Buffer { .. }
-                        | Kind::Symlink { .. } => {
-                            unimplemented!("polling read on non-files not yet supported")
+                    }
+                    EventType::Clock(clock_info) => {
+                        let now = wasi_try!(clock.time(clock_info.clock_id, clock_info.precision));
+                        if now < clock_info.timeout {
+                            let remaining = clock_info.timeout - now;
+                            timeout =
+                                Some(timeout.map_or(remaining, |t| std::cmp::min(t, remaining)));
+                            if earliest_clock.map_or(true, |(t, _)| remaining < t) {
+                                earliest_clock = Some((remaining, sub));
+                            }
+                            continue;
                         }
+                        poll_clock_event(sub)
                     }
-                }
-            };
-            fds.push(wasi_file_ref);
-        } else {
-            let remaining_ns = ns_to_sleep as i64 - total_ns_slept as i64;
-            if remaining_ns > 0 {
-                debug!("Sleeping for {} nanoseconds", remaining_ns);
-                let duration = std::time::Duration::from_nanos(remaining_ns as u64);
-                std::thread::sleep(duration);
-                total_ns_slept += remaining_ns;
+                };
+                events.push(event);
             }
         }
+
+        if !events.is_empty() {
+            break events;
+        }
+
+        // nothing is ready, the state is unlocked while waiting so that
+        // the host can feed the pipes
+        let timeout = wasi_try!(timeout, __WASI_EINVAL);
+        debug!("Sleeping for {} nanoseconds", timeout);
+        match (clock.sleep(timeout), earliest_clock) {
+            (Ok(()), _) => (),
+            // the clock can't wait, e.g. in the main thread of a browser,
+            // so the first clock subscription to be due fires early
+            (Err(__WASI_EAGAIN), Some((_, sub))) => break vec![poll_clock_event(sub)],
+            (Err(error), _) => return error,
+        }
+    };
+
+    // the events are in the same order as their subscriptions
+    for (i, event) in events.iter().enumerate() {
+        event_array[i].set(*event);
     }
-    let mut seen_events = vec![Default::default(); in_events.len()];
-    wasi_try!(poll(
-        fds.as_slice(),
-        in_events.as_slice(),
-        seen_events.as_mut_slice()
-    )
-    .map_err(fs_error_into_wasi_err));
-
-    for (i, seen_event) in seen_events.into_iter().enumerate() {
-        let mut flags = 0;
-        let mut error = __WASI_EAGAIN;
-        let mut bytes_available = 0;
-        let event_iter = iterate_poll_events(seen_event);
-        for event in event_iter {
-            match event {
-                PollEvent::PollError => error = __WASI_EIO,
-                PollEvent::PollHangUp => flags = __WASI_EVENT_FD_READWRITE_HANGUP,
-                PollEvent::PollInvalid => error = __WASI_EINVAL,
-                PollEvent::PollIn => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(fs_error_into_wasi_err));
-                    error = __WASI_ESUCCESS;
-                }
-                PollEvent::PollOut => {
-                    bytes_available =
-                        wasi_try!(fds[i].bytes_available().map_err(fs_error_into_wasi_err));
-                    error = __WASI_ESUCCESS;
-                }
+    out_ptr.set(events.len() as u32);
+    __WASI_ESUCCESS
+}
+
+/// How often files are checked by `poll_oneoff` while waiting, in
+/// nanoseconds.
+const POLL_INTERVAL_NS: __wasi_timestamp_t = 1_000_000;
+
+/// Returns whether the file behind `fd` can be read from or written to,
+/// for `poll_oneoff`.
+fn poll_fd_readiness(state: &WasiState, fd: __wasi_fd_t) -> Result<Readiness, __wasi_errno_t> {
+    let fd_entry = state.fs.get_fd(fd)?;
+
+    match &state.fs.inodes[fd_entry.inode].kind {
+        Kind::File { handle, .. } => {
+            if let Some(handle) = handle {
+                handle.poll_readiness().map_err(fs_error_into_wasi_err)
+            } else {
+                Err(__WASI_EBADF)
             }
         }
-        let event = __wasi_event_t {
-            userdata: subscription_array[i].get().userdata,
-            error,
-            type_: subscription_array[i].get().type_,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: bytes_available as u64,
-                        flags,
-                    },
-                }
-            },
-        };
-        event_array[events_seen].set(event);
-        events_seen += 1;
+        Kind::Buffer { buffer } => Ok(Readiness {
+            read: Some(buffer.len().saturating_sub(fd_entry.offset as usize)),
+            write: Some(0),
+            hangup: false,
+        }),
+        Kind::Dir { .. } | Kind::Root { .. } => Err(__WASI_EISDIR),
+        Kind::Symlink { .. } => Err(__WASI_EBADF),
     }
-    for clock_info in clock_subs {
-        let event = __wasi_event_t {
-            // TOOD: review userdata value
-            userdata: 0,
-            error: __WASI_ESUCCESS,
-            type_: __WASI_EVENTTYPE_CLOCK,
-            u: unsafe {
-                __wasi_event_u {
-                    fd_readwrite: __wasi_event_fd_readwrite_t {
-                        nbytes: 0,
-                        flags: 0,
-                    },
-                }
+}
+
+fn poll_clock_event(sub: &__wasi_subscription_t) -> __wasi_event_t {
+    __wasi_event_t {
+        userdata: sub.userdata,
+        error: __WASI_ESUCCESS,
+        type_: __WASI_EVENTTYPE_CLOCK,
+        u: __wasi_event_u {
+            fd_readwrite: __wasi_event_fd_readwrite_t {
+                nbytes: 0,
+                flags: 0,
             },
-        };
-        event_array[events_seen].set(event);
-        events_seen += 1;
+        },
     }
-    out_ptr.set(events_seen as u32);
-    __WASI_ESUCCESS
 }
 
-#[cfg(feature = "js")]
-pub fn poll_oneoff(
-    env: &WasiEnv,
-    in_: WasmPtr<__wasi_subscription_t, Array>,
-    out_: WasmPtr<__wasi_event_t, Array>,
-    nsubscriptions: u32,
-    nevents: WasmPtr<u32>,
-) -> __wasi_errno_t {
-    unimplemented!();
+fn poll_fd_event(
+    sub: &__wasi_subscription_t,
+    error: __wasi_errno_t,
+    nbytes: usize,
+    flags: __wasi_eventrwflags_t,
+) -> __wasi_event_t {
+    __wasi_event_t {
+        userdata: sub.userdata,
+        error,
+        type_: sub.type_,
+        u: __wasi_event_u {
+            fd_readwrite: __wasi_event_fd_readwrite_t {
+                nbytes: nbytes as u64,
+                flags,
+            },
+        },
+    }
 }
 
 pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) -> Result<(), WasiError> {
@@ -2526,9 +2655,39 @@ pub fn proc_exit(env: &WasiEnv, code: __wasi_exitcode_t) -> Result<(), WasiError
     Err(WasiError::Exit(code))
 }
 
-pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
-    debug!("wasi::proc_raise");
-    unimplemented!("wasi::proc_raise")
+/// ### `proc_raise()`
+/// Send a signal to the process of the calling thread.
+/// Inputs:
+/// - `__wasi_signal_t sig`
+///     The signal; what happens next is decided by the host callback
+///     registered for it, or by its default disposition
+/// Output:
+/// - `WasiError::Signal` if the signal terminates the process
+pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> Result<__wasi_errno_t, WasiError> {
+    debug!("wasi::proc_raise: {}", sig);
+    if sig > __WASI_SIGSYS {
+        return Ok(__WASI_EINVAL);
+    }
+    // `__WASI_SIGNONE` is reserved, there's nothing to deliver
+    if sig == 0 {
+        return Ok(__WASI_ESUCCESS);
+    }
+
+    // the handler is called without the state locked, since it may
+    // use it; if it raises `sig` again, it gets the default disposition
+    let handler = env.state().signals.take_handler(sig);
+    let disposition = match handler {
+        Some(mut handler) => {
+            let disposition = handler(sig);
+            env.state().signals.restore_handler(sig, handler);
+            disposition
+        }
+        None => SignalDisposition::default_for(sig),
+    };
+    match disposition {
+        SignalDisposition::Terminate => Err(WasiError::Signal(sig)),
+        SignalDisposition::Ignore => Ok(__WASI_ESUCCESS),
+    }
 }
 
 /// ### `random_get()`
@@ -2540,21 +2699,18 @@ pub fn proc_raise(env: &WasiEnv, sig: __wasi_signal_t) -> __wasi_errno_t {
 ///     The number of bytes that will be written
 pub fn random_get(env: &WasiEnv, buf: u32, buf_len: u32) -> __wasi_errno_t {
     debug!("wasi::random_get buf_len: {}", buf_len);
-    let memory = env.memory();
-    let mut u8_buffer = vec![0; buf_len as usize];
-    let res = getrandom::getrandom(&mut u8_buffer);
-    match res {
-        Ok(()) => {
-            unsafe {
-                memory
-                    .uint8view()
-                    .subarray(buf as u32, (buf as u32 + buf_len as u32))
-                    .copy_from(&u8_buffer);
-            }
-            __WASI_ESUCCESS
-        }
-        Err(_) => __WASI_EIO,
+    let (memory, mut state) = env.get_memory_and_wasi_state(0);
+    // check the bounds before allocating the buffer
+    if buf as u64 + buf_len as u64 > memory.data_size() {
+        return __WASI_EFAULT;
     }
+    let mut u8_buffer = vec![0; buf_len as usize];
+    wasi_try!(state.random.fill(&mut u8_buffer));
+
+    wasi_try!(memory
+        .write(buf as u64, &u8_buffer)
+        .map_err(|_| __WASI_EFAULT));
+    __WASI_ESUCCESS
 }
 
 /// ### `sched_yield()`
@@ -10,12 +10,22 @@ pub fn platform_clock_res_get(
     clock_id: __wasi_clockid_t,
     resolution: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    let t_out = wasi_try!(platform_clock_res(clock_id));
+    resolution.set(t_out);
+
+    __WASI_ESUCCESS
+}
+
+/// Reads the resolution of a clock, in nanoseconds.
+pub fn platform_clock_res(
+    clock_id: __wasi_clockid_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     let unix_clock_id = match clock_id {
         __WASI_CLOCK_MONOTONIC => CLOCK_MONOTONIC,
         __WASI_CLOCK_PROCESS_CPUTIME_ID => CLOCK_PROCESS_CPUTIME_ID,
         __WASI_CLOCK_REALTIME => CLOCK_REALTIME,
         __WASI_CLOCK_THREAD_CPUTIME_ID => CLOCK_THREAD_CPUTIME_ID,
-        _ => return __WASI_EINVAL,
+        _ => return Err(__WASI_EINVAL),
     };
 
     let (output, timespec_out) = unsafe {
@@ -27,10 +37,9 @@ pub fn platform_clock_res_get(
     };
 
     let t_out = (timespec_out.tv_sec * 1_000_000_000).wrapping_add(timespec_out.tv_nsec);
-    resolution.set(t_out as __wasi_timestamp_t);
 
     // TODO: map output of clock_getres to __wasi_errno_t
-    __WASI_ESUCCESS
+    Ok(t_out as __wasi_timestamp_t)
 }
 
 pub fn platform_clock_time_get(
@@ -38,12 +47,23 @@ pub fn platform_clock_time_get(
     precision: __wasi_timestamp_t,
     time: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    let t_out = wasi_try!(platform_clock_time(clock_id, precision));
+    time.set(t_out);
+
+    __WASI_ESUCCESS
+}
+
+/// Reads the time of a clock, in nanoseconds.
+pub fn platform_clock_time(
+    clock_id: __wasi_clockid_t,
+    _precision: __wasi_timestamp_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     let unix_clock_id = match clock_id {
         __WASI_CLOCK_MONOTONIC => CLOCK_MONOTONIC,
         __WASI_CLOCK_PROCESS_CPUTIME_ID => CLOCK_PROCESS_CPUTIME_ID,
         __WASI_CLOCK_REALTIME => CLOCK_REALTIME,
         __WASI_CLOCK_THREAD_CPUTIME_ID => CLOCK_THREAD_CPUTIME_ID,
-        _ => return __WASI_EINVAL,
+        _ => return Err(__WASI_EINVAL),
     };
 
     let (output, timespec_out) = unsafe {
@@ -58,8 +78,7 @@ pub fn platform_clock_time_get(
     };
 
     let t_out = (timespec_out.tv_sec * 1_000_000_000).wrapping_add(timespec_out.tv_nsec);
-    time.set(t_out as __wasi_timestamp_t);
 
     // TODO: map output of clock_gettime to __wasi_errno_t
-    __WASI_ESUCCESS
+    Ok(t_out as __wasi_timestamp_t)
 }
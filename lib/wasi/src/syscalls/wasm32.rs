@@ -6,20 +6,38 @@ pub fn platform_clock_res_get(
     clock_id: __wasi_clockid_t,
     resolution: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
-    let t_out = 1 * 1_000_000_000;
-    resolution.set(t_out as __wasi_timestamp_t);
+    let t_out = wasi_try!(platform_clock_res(clock_id));
+    resolution.set(t_out);
 
-    // TODO: map output of clock_getres to __wasi_errno_t
     __WASI_ESUCCESS
 }
 
+/// Reads the resolution of a clock, in nanoseconds.
+pub fn platform_clock_res(
+    clock_id: __wasi_clockid_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+    let t_out = 1 * 1_000_000_000;
+
+    Ok(t_out as __wasi_timestamp_t)
+}
+
 pub fn platform_clock_time_get(
     clock_id: __wasi_clockid_t,
     precision: __wasi_timestamp_t,
     time: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
-    let t_out = 1 * 1_000_000_000;
-    time.set(t_out as __wasi_timestamp_t);
+    let t_out = wasi_try!(platform_clock_time(clock_id, precision));
+    time.set(t_out);
 
     __WASI_ESUCCESS
 }
+
+/// Reads the time of a clock, in nanoseconds.
+pub fn platform_clock_time(
+    clock_id: __wasi_clockid_t,
+    precision: __wasi_timestamp_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
+    let t_out = 1 * 1_000_000_000;
+
+    Ok(t_out as __wasi_timestamp_t)
+}
@@ -6,6 +6,15 @@ pub fn platform_clock_res_get(
     clock_id: __wasi_clockid_t,
     resolution: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    let resolution_val = wasi_try!(platform_clock_res(clock_id));
+    resolution.set(resolution_val);
+    __WASI_ESUCCESS
+}
+
+/// Reads the resolution of a clock, in nanoseconds.
+pub fn platform_clock_res(
+    clock_id: __wasi_clockid_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     let resolution_val = match clock_id {
         // resolution of monotonic clock at 10ms, from:
         // https://docs.microsoft.com/en-us/windows/desktop/api/sysinfoapi/nf-sysinfoapi-gettickcount64
@@ -13,15 +22,14 @@ pub fn platform_clock_res_get(
         // TODO: verify or compute this
         __WASI_CLOCK_REALTIME => 1,
         __WASI_CLOCK_PROCESS_CPUTIME_ID => {
-            return __WASI_EINVAL;
+            return Err(__WASI_EINVAL);
         }
         __WASI_CLOCK_THREAD_CPUTIME_ID => {
-            return __WASI_EINVAL;
+            return Err(__WASI_EINVAL);
         }
-        _ => return __WASI_EINVAL,
+        _ => return Err(__WASI_EINVAL),
     };
-    resolution.set(resolution_val);
-    __WASI_ESUCCESS
+    Ok(resolution_val)
 }
 
 pub fn platform_clock_time_get(
@@ -29,18 +37,28 @@ pub fn platform_clock_time_get(
     precision: __wasi_timestamp_t,
     time: WasmCell<__wasi_timestamp_t>,
 ) -> __wasi_errno_t {
+    let nanos = wasi_try!(platform_clock_time(clock_id, precision));
+    time.set(nanos);
+    __WASI_ESUCCESS
+}
+
+/// Reads the time of a clock, in nanoseconds.
+pub fn platform_clock_time(
+    clock_id: __wasi_clockid_t,
+    _precision: __wasi_timestamp_t,
+) -> Result<__wasi_timestamp_t, __wasi_errno_t> {
     let nanos = match clock_id {
         __WASI_CLOCK_MONOTONIC => {
             let tick_ms = unsafe { winapi::um::sysinfoapi::GetTickCount64() };
             tick_ms * 1_000_000
         }
         __WASI_CLOCK_REALTIME => {
-            let duration = wasi_try!(std::time::SystemTime::now()
+            let duration = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .map_err(|e| {
                     debug!("Error in wasi::platform_clock_time_get: {:?}", e);
                     __WASI_EIO
-                }));
+                })?;
             duration.as_nanos() as u64
         }
         __WASI_CLOCK_PROCESS_CPUTIME_ID => {
@@ -49,8 +67,7 @@ pub fn platform_clock_time_get(
         __WASI_CLOCK_THREAD_CPUTIME_ID => {
             unimplemented!("wasi::platform_clock_time_get(__WASI_CLOCK_THREAD_CPUTIME_ID, ..)")
         }
-        _ => return __WASI_EINVAL,
+        _ => return Err(__WASI_EINVAL),
     };
-    time.set(nanos);
-    __WASI_ESUCCESS
+    Ok(nanos)
 }
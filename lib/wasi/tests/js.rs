@@ -133,3 +133,55 @@ fn test_stdin() {
     // We assure stdin is now empty
     assert_eq!(stdin.buf.len(), 0);
 }
+
+#[wasm_bindgen_test]
+fn test_poll_oneoff_clock() {
+    let store = Store::default();
+    let module = Module::new(&store, br#"
+    (module
+        (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll_oneoff (param i32 i32 i32 i32) (result i32)))
+
+        (memory 1)
+        (export "memory" (memory 0))
+
+        ;; A subscription to the monotonic clock, due in 1 second
+        (data (i32.const 0) "\2a")          ;; userdata
+        (data (i32.const 8) "\00")          ;; tag: clock
+        (data (i32.const 16) "\01")         ;; id: monotonic
+        (data (i32.const 24) "\00\ca\9a\3b") ;; timeout
+
+        (func (export "poll") (result i32)
+            (call $poll_oneoff
+                (i32.const 0)   ;; *in
+                (i32.const 64)  ;; *out
+                (i32.const 1)   ;; nsubscriptions
+                (i32.const 128) ;; *nevents
+            )
+        )
+        (func (export "nevents") (result i32)
+            (i32.load (i32.const 128))
+        )
+        (func (export "userdata") (result i32)
+            (i32.load (i32.const 64))
+        )
+    )
+    "#).unwrap();
+
+    let mut wasi_env = WasiState::new("command-name").finalize().unwrap();
+    let import_object = wasi_env.import_object(&module).unwrap();
+    let instance = Instance::new(&module, &import_object).unwrap();
+
+    // the main thread can't sleep, the clock event is reported early
+    // rather than failing
+    let call = |name: &str| {
+        instance
+            .exports
+            .get_function(name)
+            .unwrap()
+            .call(&[])
+            .unwrap()
+    };
+    assert_eq!(call("poll")[0].unwrap_i32(), 0);
+    assert_eq!(call("nevents")[0].unwrap_i32(), 1);
+    assert_eq!(call("userdata")[0].unwrap_i32(), 42);
+}
@@ -35,3 +35,31 @@ impl fmt::Display for DirectiveErrors {
         Ok(())
     }
 }
+
+/// An assertion whose actual value differs from the expected one.
+///
+/// It is carried by the error of a failing directive, so that the
+/// expected and actual values can be reported separately.
+#[derive(Error, Debug)]
+#[error("{message}")]
+pub(crate) struct AssertionFailure {
+    pub(crate) message: String,
+    pub(crate) expected: String,
+    pub(crate) actual: String,
+}
+
+impl AssertionFailure {
+    /// Creates an `anyhow::Error` holding an `AssertionFailure`.
+    pub(crate) fn error(
+        message: String,
+        expected: impl Into<String>,
+        actual: impl Into<String>,
+    ) -> anyhow::Error {
+        Self {
+            message,
+            expected: expected.into(),
+            actual: actual.into(),
+        }
+        .into()
+    }
+}
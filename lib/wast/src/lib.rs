@@ -19,11 +19,15 @@
 )]
 
 mod error;
+mod report;
+mod runner;
 mod spectest;
 mod wasi_wast;
 mod wast;
 
 pub use crate::error::{DirectiveError, DirectiveErrors};
+pub use crate::report::{DirectiveOutcome, DirectiveResult, FileReport, TestReport};
+pub use crate::runner::WastRunner;
 pub use crate::spectest::spectest_importobject;
 pub use crate::wasi_wast::{WasiFileSystemKind, WasiTest};
 pub use crate::wast::Wast;
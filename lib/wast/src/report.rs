@@ -0,0 +1,374 @@
+//! Structured reports of wast test runs, that can be rendered as
+//! JUnit XML or JSON.
+
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Serializes a duration as a number of seconds.
+fn serialize_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// The outcome of a single directive.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum DirectiveOutcome {
+    /// The directive succeeded.
+    Passed,
+
+    /// The directive failed.
+    Failed {
+        /// The error message.
+        message: String,
+
+        /// The expected value, for a failed assertion.
+        expected: Option<String>,
+
+        /// The actual value, for a failed assertion.
+        actual: Option<String>,
+    },
+
+    /// The directive was not executed.
+    Skipped {
+        /// Why the directive was not executed.
+        reason: String,
+    },
+}
+
+impl DirectiveOutcome {
+    /// Returns whether the directive failed.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::Failed { .. })
+    }
+}
+
+/// The result of a single directive of a wast script.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectiveResult {
+    /// The kind of directive, e.g. `assert_return`.
+    pub kind: String,
+
+    /// The line of the directive.
+    pub line: usize,
+
+    /// The column of the directive.
+    pub col: usize,
+
+    /// The time spent running the directive.
+    #[serde(serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+
+    /// The outcome of the directive.
+    #[serde(flatten)]
+    pub outcome: DirectiveOutcome,
+}
+
+/// The results of the directives of a wast script.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    /// The path of the script.
+    pub path: PathBuf,
+
+    /// The time spent running the script.
+    #[serde(serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+
+    /// The results of the directives, in order.
+    pub directives: Vec<DirectiveResult>,
+
+    /// An error preventing the script from running, e.g. a parse
+    /// error.
+    pub error: Option<String>,
+}
+
+impl FileReport {
+    /// Creates the report of a script that could not be run.
+    pub fn from_error(path: PathBuf, duration: Duration, error: String) -> Self {
+        Self {
+            path,
+            duration,
+            directives: vec![],
+            error: Some(error),
+        }
+    }
+
+    /// The number of passed directives.
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| matches!(outcome, DirectiveOutcome::Passed))
+    }
+
+    /// The number of failed directives.
+    pub fn failed(&self) -> usize {
+        self.count(DirectiveOutcome::is_failure)
+    }
+
+    /// The number of skipped directives.
+    pub fn skipped(&self) -> usize {
+        self.count(|outcome| matches!(outcome, DirectiveOutcome::Skipped { .. }))
+    }
+
+    /// Returns whether the script ran, and none of its directives
+    /// failed.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.failed() == 0
+    }
+
+    fn count(&self, predicate: impl Fn(&DirectiveOutcome) -> bool) -> usize {
+        self.directives
+            .iter()
+            .filter(|directive| predicate(&directive.outcome))
+            .count()
+    }
+}
+
+/// The results of a set of wast scripts.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestReport {
+    /// The reports of the scripts, in order.
+    pub files: Vec<FileReport>,
+
+    /// The total time spent running the scripts.
+    #[serde(serialize_with = "serialize_seconds")]
+    pub duration: Duration,
+}
+
+impl TestReport {
+    /// The number of passed directives.
+    pub fn passed(&self) -> usize {
+        self.files.iter().map(FileReport::passed).sum()
+    }
+
+    /// The number of failed directives, including the scripts that
+    /// could not be run.
+    pub fn failed(&self) -> usize {
+        self.files
+            .iter()
+            .map(|file| file.failed() + file.error.is_some() as usize)
+            .sum()
+    }
+
+    /// The number of skipped directives.
+    pub fn skipped(&self) -> usize {
+        self.files.iter().map(FileReport::skipped).sum()
+    }
+
+    /// Returns whether every script succeeded.
+    pub fn is_success(&self) -> bool {
+        self.files.iter().all(FileReport::is_success)
+    }
+
+    /// Writes the report in the JUnit XML format, with a test suite
+    /// per script and a test case per directive.
+    pub fn write_junit<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuites tests="{}" failures="{}" skipped="{}" time="{:.6}">"#,
+            self.passed() + self.failed() + self.skipped(),
+            self.failed(),
+            self.skipped(),
+            self.duration.as_secs_f64(),
+        )?;
+
+        for file in &self.files {
+            let name = escape_xml(&file.path.display().to_string());
+            let errors = file.error.is_some() as usize;
+            writeln!(
+                out,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.6}">"#,
+                name,
+                file.directives.len() + errors,
+                file.failed(),
+                errors,
+                file.skipped(),
+                file.duration.as_secs_f64(),
+            )?;
+
+            if let Some(error) = &file.error {
+                writeln!(
+                    out,
+                    r#"    <testcase name="{}" classname="{}" time="{:.6}">"#,
+                    name,
+                    name,
+                    file.duration.as_secs_f64(),
+                )?;
+                writeln!(out, r#"      <error message="{}"/>"#, escape_xml(error))?;
+                writeln!(out, "    </testcase>")?;
+            }
+
+            for directive in &file.directives {
+                write!(
+                    out,
+                    r#"    <testcase name="{} ({}:{})" classname="{}" time="{:.6}""#,
+                    directive.kind,
+                    directive.line,
+                    directive.col,
+                    name,
+                    directive.duration.as_secs_f64(),
+                )?;
+                match &directive.outcome {
+                    DirectiveOutcome::Passed => writeln!(out, "/>")?,
+                    DirectiveOutcome::Failed {
+                        message,
+                        expected,
+                        actual,
+                    } => {
+                        writeln!(out, ">")?;
+                        write!(out, r#"      <failure message="{}">"#, escape_xml(message))?;
+                        if let Some(expected) = expected {
+                            writeln!(out, "expected: {}", escape_xml(expected))?;
+                        }
+                        if let Some(actual) = actual {
+                            writeln!(out, "actual: {}", escape_xml(actual))?;
+                        }
+                        writeln!(out, "</failure>")?;
+                        writeln!(out, "    </testcase>")?;
+                    }
+                    DirectiveOutcome::Skipped { reason } => {
+                        writeln!(out, ">")?;
+                        writeln!(out, r#"      <skipped message="{}"/>"#, escape_xml(reason))?;
+                        writeln!(out, "    </testcase>")?;
+                    }
+                }
+            }
+
+            writeln!(out, "  </testsuite>")?;
+        }
+
+        writeln!(out, "</testsuites>")
+    }
+
+    /// Writes the report as JSON.
+    pub fn write_json<W: Write>(&self, out: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, self).map_err(io::Error::from)
+    }
+}
+
+/// Escapes the XML special characters of `text`, so that it can be
+/// used in an attribute or an element.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            // Other C0 control characters, and the `U+FFFE` and
+            // `U+FFFF` noncharacters, are not allowed in XML 1.0,
+            // even as character references.
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => {
+                escaped.push(char::REPLACEMENT_CHARACTER)
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> TestReport {
+        let directive = |kind: &str, line, outcome| DirectiveResult {
+            kind: kind.to_string(),
+            line,
+            col: 1,
+            duration: Duration::from_millis(1),
+            outcome,
+        };
+
+        TestReport {
+            files: vec![
+                FileReport {
+                    path: PathBuf::from("i32.wast"),
+                    duration: Duration::from_millis(3),
+                    directives: vec![
+                        directive("module", 1, DirectiveOutcome::Passed),
+                        directive(
+                            "assert_return",
+                            2,
+                            DirectiveOutcome::Failed {
+                                message: "expected I32(1), got I32(2)".to_string(),
+                                expected: Some("I32(1)".to_string()),
+                                actual: Some("I32(2)".to_string()),
+                            },
+                        ),
+                        directive(
+                            "assert_exception",
+                            3,
+                            DirectiveOutcome::Skipped {
+                                reason: "exceptions are not supported".to_string(),
+                            },
+                        ),
+                    ],
+                    error: None,
+                },
+                FileReport::from_error(
+                    PathBuf::from("<broken>.wast"),
+                    Duration::from_millis(1),
+                    "unexpected token".to_string(),
+                ),
+            ],
+            duration: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn counts() {
+        let report = report();
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.failed(), 2);
+        assert_eq!(report.skipped(), 1);
+        assert!(!report.is_success());
+        assert!(!report.files[0].is_success());
+    }
+
+    #[test]
+    fn junit() {
+        let mut out = Vec::new();
+        report().write_junit(&mut out).unwrap();
+        let xml = String::from_utf8(out).unwrap();
+
+        assert!(xml.contains(r#"<testsuites tests="4" failures="2" skipped="1""#));
+        assert!(xml.contains(r#"<testsuite name="i32.wast" tests="3" failures="1" errors="0""#));
+        assert!(xml.contains(r#"<testcase name="module (1:1)" classname="i32.wast""#));
+        assert!(xml.contains(
+            "<failure message=\"expected I32(1), got I32(2)\">expected: I32(1)\nactual: I32(2)\n</failure>"
+        ));
+        assert!(xml.contains(r#"<skipped message="exceptions are not supported"/>"#));
+        assert!(xml.contains(r#"<testsuite name="&lt;broken&gt;.wast""#));
+        assert!(xml.contains(r#"<error message="unexpected token"/>"#));
+    }
+
+    #[test]
+    fn escapes_invalid_xml_characters() {
+        assert_eq!(
+            escape_xml("a\u{0}b\u{1b}[0m\tc\r\nd\u{ffff}"),
+            "a\u{fffd}b\u{fffd}[0m&#9;c&#13;&#10;d\u{fffd}"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut out = Vec::new();
+        report().write_json(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        let directives = &json["files"][0]["directives"];
+        assert_eq!(directives[0]["status"], "passed");
+        assert_eq!(directives[1]["status"], "failed");
+        assert_eq!(directives[1]["expected"], "I32(1)");
+        assert_eq!(directives[1]["actual"], "I32(2)");
+        assert_eq!(directives[2]["reason"], "exceptions are not supported");
+        assert!((directives[0]["duration"].as_f64().unwrap() - 0.001).abs() < 1e-9);
+        assert_eq!(json["files"][1]["error"], "unexpected token");
+    }
+}
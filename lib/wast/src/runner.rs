@@ -0,0 +1,119 @@
+//! Runs wast scripts, possibly in parallel, and collects their
+//! reports.
+
+use crate::report::{FileReport, TestReport};
+use crate::wast::Wast;
+use anyhow::Result;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use wasmer::Store;
+
+type StoreFactory = dyn Fn() -> Store + Send + Sync;
+type Configure = dyn Fn(&Path, &mut Wast) + Send + Sync;
+
+/// Runs a set of wast scripts, each one with a fresh [`Wast`] context
+/// importing the `spectest` module.
+///
+/// ```no_run
+/// # use std::path::PathBuf;
+/// # use wasmer::Store;
+/// # use wasmer_wast::WastRunner;
+/// # fn main() -> anyhow::Result<()> {
+/// let report = WastRunner::new(Store::default)
+///     .configure(|_path, wast| wast.disable_assert_and_exhaustion())
+///     .threads(4)
+///     .run_files(&[PathBuf::from("i32.wast"), PathBuf::from("i64.wast")])?;
+/// report.write_junit(std::io::stdout())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct WastRunner {
+    store: Box<StoreFactory>,
+    configure: Option<Box<Configure>>,
+    threads: Option<usize>,
+}
+
+impl WastRunner {
+    /// Creates a runner, creating the store of every script with
+    /// `store`.
+    pub fn new(store: impl Fn() -> Store + Send + Sync + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            configure: None,
+            threads: None,
+        }
+    }
+
+    /// Configures the [`Wast`] context of every script before it runs.
+    pub fn configure(
+        mut self,
+        configure: impl Fn(&Path, &mut Wast) + Send + Sync + 'static,
+    ) -> Self {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+
+    /// Sets the number of scripts run in parallel. It defaults to the
+    /// number of CPUs.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Runs a single script.
+    ///
+    /// The directives following a failing one are still run, unless
+    /// `fail_fast` is set by [`WastRunner::configure`]. A panic is
+    /// reported as an error of the script.
+    pub fn run_file(&self, path: &Path) -> FileReport {
+        let start = Instant::now();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut wast = Wast::new_with_spectest((self.store)());
+            wast.fail_fast = false;
+            if let Some(configure) = &self.configure {
+                configure(path, &mut wast);
+            }
+            wast.report_file(path)
+        }));
+
+        match result {
+            Ok(Ok(report)) => report,
+            Ok(Err(error)) => {
+                FileReport::from_error(path.to_path_buf(), start.elapsed(), format!("{:#}", error))
+            }
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                FileReport::from_error(
+                    path.to_path_buf(),
+                    start.elapsed(),
+                    format!("panicked: {}", message),
+                )
+            }
+        }
+    }
+
+    /// Runs `paths` in parallel. The reports are in the same order as
+    /// `paths`.
+    pub fn run_files(&self, paths: &[PathBuf]) -> Result<TestReport> {
+        use rayon::prelude::*;
+
+        let start = Instant::now();
+        let mut pool = rayon::ThreadPoolBuilder::new();
+        if let Some(threads) = self.threads {
+            pool = pool.num_threads(threads);
+        }
+        let files = pool
+            .build()?
+            .install(|| paths.par_iter().map(|path| self.run_file(path)).collect());
+
+        Ok(TestReport {
+            files,
+            duration: start.elapsed(),
+        })
+    }
+}
@@ -1,11 +1,19 @@
-use crate::error::{DirectiveError, DirectiveErrors};
+use crate::error::{AssertionFailure, DirectiveError, DirectiveErrors};
+use crate::report::{DirectiveOutcome, DirectiveResult, FileReport};
 use crate::spectest::spectest_importobject;
 use anyhow::{anyhow, bail, Result};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 use std::str;
+use std::time::Instant;
 use wasmer::*;
 
+/// Whether a directive has been executed.
+enum Execution {
+    Ran,
+    Skipped(&'static str),
+}
+
 /// The wast test script language allows modules to be defined and actions
 /// to be performed on them.
 pub struct Wast {
@@ -123,33 +131,53 @@ impl Wast {
             if self.val_matches(v, e)? {
                 continue;
             }
+            let expected = format!("{:?}", e);
             if let Val::V128(bits) = v {
                 if let wast::AssertExpression::V128(pattern) = e {
-                    bail!(
-                        "expected {:?}, got {:?} (v128 bits: {})",
-                        e,
-                        v128_format(*bits, pattern),
-                        bits
-                    );
+                    let actual = format!("{:?}", v128_format(*bits, pattern));
+                    return Err(AssertionFailure::error(
+                        format!(
+                            "expected {}, got {} (v128 bits: {})",
+                            expected, actual, bits
+                        ),
+                        expected,
+                        actual,
+                    ));
                 }
             }
-            bail!("expected {:?}, got {:?}", e, v)
+            let actual = format!("{:?}", v);
+            return Err(AssertionFailure::error(
+                format!("expected {}, got {}", expected, actual),
+                expected,
+                actual,
+            ));
         }
         Ok(())
     }
 
     fn assert_trap(&self, result: Result<Vec<Val>>, expected: &str) -> Result<()> {
         let actual = match result {
-            Ok(values) => bail!("expected trap, got {:?}", values),
+            Ok(values) => {
+                let actual = format!("{:?}", values);
+                return Err(AssertionFailure::error(
+                    format!("expected trap, got {}", actual),
+                    expected,
+                    actual,
+                ));
+            }
             Err(t) => format!("{}", t),
         };
         if self.matches_message_assert_trap(expected, &actual) {
             return Ok(());
         }
-        bail!("expected '{}', got '{}'", expected, actual)
+        Err(AssertionFailure::error(
+            format!("expected '{}', got '{}'", expected, actual),
+            expected,
+            actual,
+        ))
     }
 
-    fn run_directive(&mut self, test: &Path, directive: wast::WastDirective) -> Result<()> {
+    fn run_directive(&mut self, test: &Path, directive: wast::WastDirective) -> Result<Execution> {
         use wast::WastDirective::*;
 
         match directive {
@@ -180,20 +208,22 @@ impl Wast {
                 exec,
                 message,
             } => {
-                if !self.disable_assert_trap_exhaustion {
-                    let result = self.perform_execute(exec);
-                    self.assert_trap(result, message)?;
+                if self.disable_assert_trap_exhaustion {
+                    return Ok(Execution::Skipped("assert_trap is disabled"));
                 }
+                let result = self.perform_execute(exec);
+                self.assert_trap(result, message)?;
             }
             AssertExhaustion {
                 span: _,
                 call,
                 message,
             } => {
-                if !self.disable_assert_trap_exhaustion {
-                    let result = self.perform_invoke(call);
-                    self.assert_trap(result, message)?;
+                if self.disable_assert_trap_exhaustion {
+                    return Ok(Execution::Skipped("assert_exhaustion is disabled"));
                 }
+                let result = self.perform_invoke(call);
+                self.assert_trap(result, message)?;
             }
             AssertInvalid {
                 span: _,
@@ -210,18 +240,21 @@ impl Wast {
                 };
                 let error_message = format!("{:?}", err);
                 if !Self::matches_message_assert_invalid(&message, &error_message) {
-                    bail!(
-                        "assert_invalid: expected \"{}\", got \"{}\"",
+                    return Err(AssertionFailure::error(
+                        format!(
+                            "assert_invalid: expected \"{}\", got \"{}\"",
+                            message, error_message
+                        ),
                         message,
-                        error_message
-                    )
+                        error_message,
+                    ));
                 }
             }
             QuoteModule { .. } => {
-                // Do nothing
+                return Ok(Execution::Skipped("quoted modules are not supported"));
             }
             AssertException { .. } => {
-                // Do nothing for now
+                return Ok(Execution::Skipped("exceptions are not supported"));
             }
             AssertMalformed {
                 module,
@@ -232,7 +265,9 @@ impl Wast {
                     wast::QuoteModule::Module(m) => m,
                     // This is a `*.wat` parser test which we're not
                     // interested in.
-                    wast::QuoteModule::Quote(_) => return Ok(()),
+                    wast::QuoteModule::Quote(_) => {
+                        return Ok(Execution::Skipped("text format tests are not supported"))
+                    }
                 };
                 let bytes = module.encode()?;
                 if self.module(None, &bytes).is_ok() {
@@ -251,20 +286,27 @@ impl Wast {
                 };
                 let error_message = format!("{:?}", err);
                 if !Self::matches_message_assert_unlinkable(&message, &error_message) {
-                    bail!(
-                        "assert_unlinkable: expected {}, got {}",
+                    return Err(AssertionFailure::error(
+                        format!(
+                            "assert_unlinkable: expected {}, got {}",
+                            message, error_message
+                        ),
                         message,
-                        error_message
-                    )
+                        error_message,
+                    ));
                 }
             }
         }
 
-        Ok(())
+        Ok(Execution::Ran)
     }
 
-    /// Run a wast script from a byte buffer.
-    pub fn run_buffer(&mut self, test: &Path, wast: &[u8]) -> Result<()> {
+    /// Run a wast script from a byte buffer, and report the outcome of
+    /// every directive.
+    ///
+    /// An error is returned only when the script can't be parsed.
+    pub fn report_buffer(&mut self, test: &Path, wast: &[u8]) -> Result<FileReport> {
+        let start = Instant::now();
         let wast = str::from_utf8(wast)?;
         let filename = test.to_str().unwrap();
         let adjust_wast = |mut err: wast::Error| {
@@ -275,34 +317,82 @@ impl Wast {
 
         let buf = wast::parser::ParseBuffer::new(wast).map_err(adjust_wast)?;
         let ast = wast::parser::parse::<wast::Wast>(&buf).map_err(adjust_wast)?;
-        let mut errors = Vec::with_capacity(ast.directives.len());
+        let mut directives = Vec::with_capacity(ast.directives.len());
         for directive in ast.directives {
-            let sp = directive.span();
-            if let Err(e) = self.run_directive(&test, directive) {
-                let message = format!("{}", e);
-                // If depends on an instance that doesn't exist
-                if message.contains("no previous instance found") {
-                    continue;
-                }
-                // We don't compute it, comes from instantiating an instance
-                // that we expected to fail.
-                if self.current.is_none() && self.current_is_allowed_failure {
-                    continue;
-                }
-                let (line, col) = sp.linecol_in(wast);
-                errors.push(DirectiveError {
-                    line: line + 1,
-                    col,
-                    message,
-                });
-                if self.fail_fast {
-                    break;
+            let (line, col) = directive.span().linecol_in(wast);
+            let kind = directive_kind(&directive);
+            let directive_start = Instant::now();
+            let outcome = match self.run_directive(test, directive) {
+                Ok(Execution::Ran) => DirectiveOutcome::Passed,
+                Ok(Execution::Skipped(reason)) => DirectiveOutcome::Skipped {
+                    reason: reason.to_string(),
+                },
+                Err(e) => {
+                    let message = format!("{}", e);
+                    if message.contains("no previous instance found") {
+                        // Depends on an instance that doesn't exist.
+                        DirectiveOutcome::Skipped {
+                            reason: "no previous instance found".to_string(),
+                        }
+                    } else if self.current.is_none() && self.current_is_allowed_failure {
+                        // We don't compute it, comes from instantiating an
+                        // instance that we expected to fail.
+                        DirectiveOutcome::Skipped {
+                            reason: "the instantiation failure is allowed".to_string(),
+                        }
+                    } else {
+                        let (expected, actual) = match e.downcast_ref::<AssertionFailure>() {
+                            Some(failure) => {
+                                (Some(failure.expected.clone()), Some(failure.actual.clone()))
+                            }
+                            None => (None, None),
+                        };
+                        DirectiveOutcome::Failed {
+                            message,
+                            expected,
+                            actual,
+                        }
+                    }
                 }
+            };
+            let failed = outcome.is_failure();
+            directives.push(DirectiveResult {
+                kind: kind.to_string(),
+                line: line + 1,
+                col,
+                duration: directive_start.elapsed(),
+                outcome,
+            });
+            if failed && self.fail_fast {
+                break;
             }
         }
+        Ok(FileReport {
+            path: test.to_path_buf(),
+            duration: start.elapsed(),
+            directives,
+            error: None,
+        })
+    }
+
+    /// Run a wast script from a byte buffer.
+    pub fn run_buffer(&mut self, test: &Path, wast: &[u8]) -> Result<()> {
+        let report = self.report_buffer(test, wast)?;
+        let errors = report
+            .directives
+            .into_iter()
+            .filter_map(|directive| match directive.outcome {
+                DirectiveOutcome::Failed { message, .. } => Some(DirectiveError {
+                    line: directive.line,
+                    col: directive.col,
+                    message,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
         if !errors.is_empty() {
             return Err(DirectiveErrors {
-                filename: filename.to_string(),
+                filename: test.to_str().unwrap().to_string(),
                 errors,
             }
             .into());
@@ -338,6 +428,13 @@ impl Wast {
         let bytes = std::fs::read(path)?;
         self.run_buffer(path, &bytes)
     }
+
+    /// Run a wast script from a file, and report the outcome of every
+    /// directive.
+    pub fn report_file(&mut self, path: &Path) -> Result<FileReport> {
+        let bytes = std::fs::read(path)?;
+        self.report_buffer(path, &bytes)
+    }
 }
 
 // This is the implementation specific to the Runtime
@@ -519,6 +616,25 @@ impl Wast {
     }
 }
 
+/// The name of a directive, as written in the script.
+fn directive_kind(directive: &wast::WastDirective) -> &'static str {
+    use wast::WastDirective::*;
+
+    match directive {
+        Module(_) => "module",
+        QuoteModule { .. } => "module quote",
+        AssertMalformed { .. } => "assert_malformed",
+        AssertInvalid { .. } => "assert_invalid",
+        Register { .. } => "register",
+        Invoke(_) => "invoke",
+        AssertTrap { .. } => "assert_trap",
+        AssertReturn { .. } => "assert_return",
+        AssertExhaustion { .. } => "assert_exhaustion",
+        AssertUnlinkable { .. } => "assert_unlinkable",
+        AssertException { .. } => "assert_exception",
+    }
+}
+
 fn extract_lane_as_i8(bytes: u128, lane: usize) -> i8 {
     (bytes >> (lane * 8)) as i8
 }
@@ -86,7 +86,7 @@ location = {
     "wasmer-middlewares": "middlewares",
     "wasmer-vfs": "vfs",
     "wasmer-cli": "cli",
-    "wasmer-wast": "../tests/lib/wast",
+    "wasmer-wast": "../lib/wast",
 }
 
 no_dry_run = False
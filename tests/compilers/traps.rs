@@ -73,6 +73,87 @@ fn test_trap_trace(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+/// Appends a custom section to a wasm binary. Contents must be
+/// shorter than 128 bytes.
+fn append_custom_section(wasm: &mut Vec<u8>, name: &str, contents: &[u8]) {
+    wasm.push(0);
+    wasm.push((1 + name.len() + contents.len()) as u8);
+    wasm.push(name.len() as u8);
+    wasm.extend_from_slice(name.as_bytes());
+    wasm.extend_from_slice(contents);
+}
+
+#[cfg_attr(target_env = "musl", ignore)]
+#[compiler_test(traps)]
+fn test_trap_trace_source_location(config: crate::Config) -> Result<()> {
+    let store = config.store();
+    let mut wasm = wat2wasm(
+        br#"
+        (module $hello_mod
+            (func $hello (export "run") (unreachable))
+        )
+    "#,
+    )?
+    .into_owned();
+
+    // A compilation unit for `/src/main.c`, whose line table maps the
+    // whole code section to line 10, column 3.
+    append_custom_section(
+        &mut wasm,
+        ".debug_abbrev",
+        &[
+            0x01, 0x11, 0x00, 0x10, 0x17, 0x03, 0x08, 0x1b, 0x08, 0x00, 0x00, 0x00,
+        ],
+    );
+    append_custom_section(
+        &mut wasm,
+        ".debug_info",
+        &[
+            0x18, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x01, 0x00, 0x00,
+            0x00, 0x00, b'm', b'a', b'i', b'n', b'.', b'c', 0x00, b'/', b's', b'r', b'c', 0x00,
+        ],
+    );
+    append_custom_section(
+        &mut wasm,
+        ".debug_line",
+        &[
+            // Header.
+            0x36, 0x00, 0x00, 0x00, 0x04, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0xfb,
+            0x0e, 0x0d, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01,
+            0x00, b'm', b'a', b'i', b'n', b'.', b'c', 0x00, 0x00, 0x00, 0x00, 0x00,
+            // DW_LNE_set_address 1, DW_LNS_advance_line 9, DW_LNS_set_column 3,
+            // DW_LNS_copy, DW_LNS_advance_pc 0x1000, DW_LNE_end_sequence.
+            0x00, 0x05, 0x02, 0x01, 0x00, 0x00, 0x00, 0x03, 0x09, 0x05, 0x03, 0x01, 0x02, 0x80,
+            0x20, 0x00, 0x01, 0x01,
+        ],
+    );
+
+    let module = Module::new(&store, wasm)?;
+    assert_eq!(module.custom_sections(".debug_line").count(), 1);
+
+    let instance = Instance::new(&module, &imports! {})?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    let e = run_func.call(&[]).err().expect("error calling function");
+
+    let trace = e.trace();
+    assert_eq!(trace.len(), 1);
+    assert_eq!(trace[0].function_name(), Some("hello"));
+    assert_eq!(trace[0].source_file(), Some("/src/main.c"));
+    assert_eq!(trace[0].line(), Some(10));
+    assert_eq!(trace[0].column(), Some(3));
+    assert!(
+        e.to_string().contains("\n        at /src/main.c:10:3"),
+        "wrong display: {}",
+        e
+    );
+
+    Ok(())
+}
+
 #[compiler_test(traps)]
 fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     let store = config.store();
@@ -116,6 +197,66 @@ fn test_trap_trace_cb(config: crate::Config) -> Result<()> {
     Ok(())
 }
 
+#[compiler_test(traps)]
+fn test_trap_trace_native_cb(config: crate::Config) -> Result<()> {
+    #[derive(Debug)]
+    struct NativeThrow;
+
+    impl std::fmt::Display for NativeThrow {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "native throw")
+        }
+    }
+
+    impl std::error::Error for NativeThrow {}
+
+    let is_cranelift = config.compiler == crate::Compiler::Cranelift;
+    let store = config.store();
+    let wat = r#"
+        (module $hello_mod
+            (import "" "throw" (func $throw))
+            (func (export "run") (call $hello))
+            (func $hello (call $throw))
+        )
+    "#;
+
+    let fn_func = Function::new_native(&store, || -> Result<(), NativeThrow> { Err(NativeThrow) });
+
+    let module = Module::new(&store, wat)?;
+    let instance = Instance::new(
+        &module,
+        &imports! {
+            "" => {
+                "throw" => fn_func
+            }
+        },
+    )?;
+    let run_func = instance
+        .exports
+        .get_function("run")
+        .expect("expected function export");
+
+    let e = run_func.call(&[]).err().expect("error calling function");
+
+    // The error of a native host function is turned into a
+    // `RuntimeError` before the Wasm frames are unwound, so its trace
+    // holds the guest functions that called it.
+    let trace = e.trace();
+    println!("Trace {:?}", trace);
+    // Only checked with Cranelift, see `test_trap_trace_cb`.
+    if is_cranelift {
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0].module_name(), "hello_mod");
+        assert_eq!(trace[0].func_index(), 2);
+        assert_eq!(trace[1].module_name(), "hello_mod");
+        assert_eq!(trace[1].func_index(), 1);
+    }
+    assert_eq!(e.message(), "native throw");
+    assert!(e.downcast::<NativeThrow>().is_ok());
+
+    Ok(())
+}
+
 #[cfg_attr(target_env = "musl", ignore)]
 #[compiler_test(traps)]
 fn test_trap_stack_overflow(config: crate::Config) -> Result<()> {